@@ -1,59 +1,148 @@
 extern crate bfres;
-extern crate yaz0lib_rust;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde_json;
 
 use bfres::fres::FRES;
-use bfres::util::Importable;
 use std::env;
-use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 
+// Headers of the file, its models and its textures
+#[cfg(feature = "json")]
+fn print_json(input_file: &str) {
+    let (bfres_file, mut bfres_cursor) =
+        FRES::open(input_file).expect("Failed to read Bfres file !");
+    let cursor = &mut bfres_cursor;
+    let mut models = Vec::new();
+    if let Some(ref a) = bfres_file.sub_file_index_groups.model_data {
+        for fmdl_entry in &a.entries {
+            let fmdl = fmdl_entry
+                .get_data(cursor)
+                .expect("Failed to read FMDL data !");
+            let mut vertex_buffers = Vec::new();
+            for fvtx_entry in &fmdl.fvtx_array.entries {
+                let fvtx = fvtx_entry.get_data(cursor).unwrap();
+                let mut attributes = Vec::new();
+                for attribute_entry in &fvtx.attributes_index_group.entries {
+                    attributes.push(json!({
+                        "name": attribute_entry.get_name(cursor).unwrap(),
+                        "attribute": attribute_entry.get_data(cursor).unwrap(),
+                    }));
+                }
+                let mut buffer_infos = Vec::new();
+                for buffer_info_entry in &fvtx.buffer_info_array.entries {
+                    buffer_infos.push(buffer_info_entry.get_data(cursor).unwrap());
+                }
+                vertex_buffers.push(json!({
+                    "header": fvtx.header,
+                    "attributes": attributes,
+                    "buffer_infos": buffer_infos,
+                }));
+            }
+            let mut materials = Vec::new();
+            for fmat_entry in &fmdl.fmat_index_group.entries {
+                materials.push(json!({
+                    "name": fmat_entry.get_name(cursor).unwrap(),
+                    "header": fmat_entry.get_data(cursor).unwrap().header,
+                }));
+            }
+            let mut bones = Vec::new();
+            for bone_entry in &fmdl.fskl.bones.entries {
+                bones.push(json!({
+                    "name": bone_entry.get_name(cursor).unwrap(),
+                    "bone": bone_entry.get_data(cursor).unwrap(),
+                }));
+            }
+            let mut shapes = Vec::new();
+            for fshp_entry in &fmdl.fshp_index_group.entries {
+                let fshp = fshp_entry.get_data(cursor).unwrap();
+                let mut key_shapes = Vec::new();
+                if let Some(ref key_shape_index_group) = fshp.key_shape_index_group {
+                    for key_shape_entry in &key_shape_index_group.entries {
+                        key_shapes.push(key_shape_entry.get_name(cursor).unwrap());
+                    }
+                }
+                shapes.push(json!({
+                    "name": fshp_entry.get_name(cursor).unwrap(),
+                    "header": fshp.header,
+                    "lod_models": fshp.get_lod_models(cursor).unwrap(),
+                    "key_shapes": key_shapes,
+                }));
+            }
+            models.push(json!({
+                "name": fmdl_entry.get_name(cursor).unwrap(),
+                "header": fmdl.header,
+                "vertex_buffers": vertex_buffers,
+                "materials": materials,
+                "skeleton": {
+                    "header": fmdl.fskl.header,
+                    "bones": bones,
+                },
+                "shapes": shapes,
+            }));
+        }
+    }
+    let mut textures = Vec::new();
+    if let Some(ref a) = bfres_file.sub_file_index_groups.texture_data {
+        for ftex_entry in &a.entries {
+            textures.push(json!({
+                "name": ftex_entry.get_name(cursor).unwrap(),
+                "header": ftex_entry.get_data(cursor).unwrap().header,
+            }));
+        }
+    }
+    let json = json!({
+        "header": bfres_file.header,
+        "models": models,
+        "textures": textures,
+    });
+    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+}
+
+#[cfg(not(feature = "json"))]
+fn print_json(_: &str) {
+    eprintln!("JSON output needs the json feature, build with --features json");
+    std::process::exit(1);
+}
+
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let mut args: Vec<_> = env::args().collect();
+    let json = match args.iter().position(|a| a == "--json") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
     if args.len() < 2 {
         let exec_name = args[0].to_string();
         println!(
-            "Usage: ./{} input_file",
+            "Usage: ./{} [--json] input_file",
             Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
         );
     } else if args.len() > 2 {
         println!("Please only give one argument");
+    } else if json {
+        print_json(&args[1]);
     } else {
         let input_file = args[1].to_string();
-        let input_file_reader = File::open(&input_file).expect("Failed to open file for reading");
-        let mut input_file_buf_reader = BufReader::new(input_file_reader);
-        let mut yaz_check_buffer = [0u8; 4];
-        input_file_buf_reader
-            .read_exact(&mut yaz_check_buffer)
-            .expect("Failed to read first Magic Number");
-        input_file_buf_reader
-            .seek(SeekFrom::Start(0))
-            .expect("Failed to re-seek to beginning of the file");
-        let mut bfres_cursor = if yaz_check_buffer == [b'Y', b'a', b'z', b'0'] {
-            Cursor::new(
-                yaz0lib_rust::decompress(&mut input_file_buf_reader).expect("Failed to decompress"),
-            )
-        } else {
-            let mut bfres_data = Vec::new();
-            input_file_buf_reader
-                .read_to_end(&mut bfres_data)
-                .expect("Failed to read all data");
-            Cursor::new(bfres_data)
-        };
-        let bfres_file = FRES::import(&mut bfres_cursor).expect("Failed to read Bfres file !");
+        let (bfres_file, mut bfres_cursor) =
+            FRES::open(&input_file).expect("Failed to read Bfres file !");
         println!("Read File successfully !");
         println!("Version {}", bfres_file.header.version);
         println!("{} sub-files", bfres_file.header.get_total_sub_file_count());
         // FMDL
-        if let Some(a) = bfres_file.sub_file_index_groups.model_data {
+        if let Some(ref a) = bfres_file.sub_file_index_groups.model_data {
             println!("{} FMDL sub-files", a.entries.len());
-            for fmdl_entry in a.entries {
+            for fmdl_entry in &a.entries {
                 println!(
                     "--- {} @ 0x{:x}",
                     fmdl_entry.get_name(&mut bfres_cursor).unwrap(),
                     fmdl_entry.data_pointer.get_abs_pos().unwrap()
                 );
-                let fmdl = fmdl_entry.get_data(&mut bfres_cursor).expect("Failed to read FMDL data !");
+                let fmdl = fmdl_entry
+                    .get_data(&mut bfres_cursor)
+                    .expect("Failed to read FMDL data !");
                 println!("    {} vertices", fmdl.header.total_nb_vertices);
                 // FVTX
                 if !fmdl.fvtx_array.entries.is_empty() {
@@ -127,23 +216,53 @@ fn main() {
                         );
                         let fshp = fshp_entry.get_data(&mut bfres_cursor).unwrap();
                         println!("        Flags: {}", fshp.header.flags);
+                        let radii = fshp.get_bounding_radii(&mut bfres_cursor).unwrap();
+                        let radii: Vec<String> = radii.iter().map(|r| r.to_string()).collect();
+                        println!("        Bounding radius: {}", radii.join(", "));
+                        let boxes = fshp.get_bounding_boxes(&mut bfres_cursor).unwrap();
+                        println!("        {} Bounding Boxes:", boxes.len());
+                        for bounding_box in &boxes {
+                            println!(
+                                "        --- Center: {:?}, Extent: {:?}",
+                                bounding_box.center, bounding_box.extent
+                            );
+                        }
+                        let lod_models = fshp.get_lod_models(&mut bfres_cursor).unwrap();
+                        if let Some(key_shapes) = fshp.key_shape_index_group {
+                            println!("        {} Key Shapes:", key_shapes.entries.len());
+                            for key_shape_entry in key_shapes.entries {
+                                println!(
+                                    "        --- {}",
+                                    key_shape_entry.get_name(&mut bfres_cursor).unwrap()
+                                );
+                            }
+                        }
                         println!("        {} LOD Models:", fshp.header.lod_model_count);
-                        for lod_entry in fshp.lod_model_array.entries {
+                        for (lod_index, (lod_entry, lod)) in fshp
+                            .lod_model_array
+                            .entries
+                            .iter()
+                            .zip(lod_models.iter())
+                            .enumerate()
+                        {
                             println!(
-                                "        --- @ 0x{:x}",
+                                "        --- {} @ 0x{:x}",
+                                lod_index,
                                 lod_entry.data_pointer.get_abs_pos().unwrap()
                             );
-                            let lod = lod_entry.get_data(&mut bfres_cursor).unwrap();
+                            println!("            {:?}", lod.primitive_type);
                             println!("            {} points", lod.nb_points);
+                            println!("            {} triangles", lod.get_nb_triangles());
+                            println!("            {} skipped vertices", lod.skip_vertices);
                             println!("            {} visibility groups", lod.nb_visibility_groups);
                         }
                     }
                 }
             }
         }
-        if let Some(a) = bfres_file.sub_file_index_groups.texture_data {
+        if let Some(ref a) = bfres_file.sub_file_index_groups.texture_data {
             println!("{} FTEX sub-files", a.entries.len());
-            for ftex_entry in a.entries {
+            for ftex_entry in &a.entries {
                 println!(
                     "--- {} @ 0x{:x}",
                     ftex_entry.get_name(&mut bfres_cursor).unwrap(),
@@ -180,6 +299,210 @@ fn main() {
                 println!("    Data Length: {}", ftex.header.data_length);
             }
         }
+        // FSHU
+        for (kind, a) in bfres_file.sub_file_index_groups.get_fshu_groups() {
+            println!("{} FSHU ({}) sub-files", a.entries.len(), kind);
+            for fshu_entry in &a.entries {
+                println!(
+                    "--- {} @ 0x{:x}",
+                    fshu_entry.get_name(&mut bfres_cursor).unwrap(),
+                    fshu_entry.data_pointer.get_abs_pos().unwrap()
+                );
+                let fshu = fshu_entry.get_data(&mut bfres_cursor).unwrap();
+                println!("    {} frames", fshu.header.frame_count);
+                println!("    Looping: {}", fshu.header.flags.looping);
+                println!("    {} curves", fshu.header.curve_count);
+                println!(
+                    "    {} material animations:",
+                    fshu.header.material_animation_count
+                );
+                for material_animation_entry in fshu.material_animation_array.entries {
+                    let material_animation = material_animation_entry
+                        .get_data(&mut bfres_cursor)
+                        .unwrap();
+                    println!(
+                        "    --- {}",
+                        material_animation
+                            .get_material_name(&mut bfres_cursor)
+                            .unwrap()
+                    );
+                    for info_entry in material_animation
+                        .get_parameter_animation_infos(&mut bfres_cursor)
+                        .unwrap()
+                        .entries
+                    {
+                        let info = info_entry.get_data(&mut bfres_cursor).unwrap();
+                        println!(
+                            "        {}: {} curves, {} constants",
+                            info.get_parameter_name(&mut bfres_cursor).unwrap(),
+                            info.float_curve_count + info.int_curve_count,
+                            info.constant_count
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(a) = bfres_file.sub_file_index_groups.texture_pattern_animation {
+            println!("{} FTXP sub-files", a.entries.len());
+            for ftxp_entry in a.entries {
+                println!(
+                    "--- {} @ 0x{:x}",
+                    ftxp_entry.get_name(&mut bfres_cursor).unwrap(),
+                    ftxp_entry.data_pointer.get_abs_pos().unwrap()
+                );
+                let ftxp = ftxp_entry.get_data(&mut bfres_cursor).unwrap();
+                println!("    {} frames", ftxp.header.frame_count);
+                println!("    Looping: {}", ftxp.header.flags.looping);
+                println!(
+                    "    Textures: {}",
+                    ftxp.get_texture_names(&mut bfres_cursor)
+                        .unwrap()
+                        .join(", ")
+                );
+                println!(
+                    "    {} material animations:",
+                    ftxp.header.material_animation_count
+                );
+                for material_animation_entry in ftxp.material_animation_array.entries {
+                    let material_animation = material_animation_entry
+                        .get_data(&mut bfres_cursor)
+                        .unwrap();
+                    println!(
+                        "    --- {}",
+                        material_animation
+                            .get_material_name(&mut bfres_cursor)
+                            .unwrap()
+                    );
+                    for info_entry in material_animation
+                        .get_pattern_animation_infos(&mut bfres_cursor)
+                        .unwrap()
+                        .entries
+                    {
+                        let info = info_entry.get_data(&mut bfres_cursor).unwrap();
+                        println!(
+                            "        {}: curve {}",
+                            info.get_sampler_name(&mut bfres_cursor).unwrap(),
+                            info.curve_index
+                        );
+                    }
+                }
+            }
+        }
+        // FVIS
+        let fvis_groups = vec![
+            bfres_file.sub_file_index_groups.bone_visibility_animation,
+            bfres_file
+                .sub_file_index_groups
+                .material_visibility_animation,
+        ];
+        for a in fvis_groups.into_iter().flatten() {
+            println!("{} FVIS sub-files", a.entries.len());
+            for fvis_entry in a.entries {
+                println!(
+                    "--- {} @ 0x{:x}",
+                    fvis_entry.get_name(&mut bfres_cursor).unwrap(),
+                    fvis_entry.data_pointer.get_abs_pos().unwrap()
+                );
+                let fvis = fvis_entry.get_data(&mut bfres_cursor).unwrap();
+                println!("    Type: {}", fvis.header.flags.visibility_type);
+                println!("    {} frames", fvis.header.frame_count);
+                println!("    Looping: {}", fvis.header.flags.looping);
+                println!("    {} curves", fvis.header.curve_count);
+                println!(
+                    "    Animated: {}",
+                    fvis.get_names(&mut bfres_cursor).unwrap().join(", ")
+                );
+            }
+        }
+        if let Some(a) = bfres_file.sub_file_index_groups.shape_animation {
+            println!("{} FSHA sub-files", a.entries.len());
+            for fsha_entry in a.entries {
+                println!(
+                    "--- {} @ 0x{:x}",
+                    fsha_entry.get_name(&mut bfres_cursor).unwrap(),
+                    fsha_entry.data_pointer.get_abs_pos().unwrap()
+                );
+                let fsha = fsha_entry.get_data(&mut bfres_cursor).unwrap();
+                println!("    {} frames", fsha.header.frame_count);
+                println!("    Looping: {}", fsha.header.flags.looping);
+                println!(
+                    "    {} vertex shape animations:",
+                    fsha.header.vertex_shape_animation_count
+                );
+                for vertex_shape_animation_entry in fsha.vertex_shape_animation_array.entries {
+                    let vertex_shape_animation = vertex_shape_animation_entry
+                        .get_data(&mut bfres_cursor)
+                        .unwrap();
+                    println!(
+                        "    --- {}",
+                        vertex_shape_animation
+                            .get_shape_name(&mut bfres_cursor)
+                            .unwrap()
+                    );
+                    println!("        {} curves", vertex_shape_animation.curve_count);
+                    for (name, weight) in vertex_shape_animation
+                        .get_weights_at(&mut bfres_cursor, 0f32)
+                        .unwrap()
+                    {
+                        println!("        {}: {}", name, weight);
+                    }
+                }
+            }
+        }
+        if let Some(a) = bfres_file.sub_file_index_groups.scene_animation {
+            println!("{} FSCN sub-files", a.entries.len());
+            for fscn_entry in a.entries {
+                println!(
+                    "--- {} @ 0x{:x}",
+                    fscn_entry.get_name(&mut bfres_cursor).unwrap(),
+                    fscn_entry.data_pointer.get_abs_pos().unwrap()
+                );
+                let fscn = fscn_entry.get_data(&mut bfres_cursor).unwrap();
+                if let Some(fcam_index_group) = fscn.fcam_index_group {
+                    println!("    {} FCAM:", fcam_index_group.entries.len());
+                    for fcam_entry in fcam_index_group.entries {
+                        println!(
+                            "    --- {}",
+                            fcam_entry.get_name(&mut bfres_cursor).unwrap()
+                        );
+                        let fcam = fcam_entry.get_data(&mut bfres_cursor).unwrap();
+                        println!("        {} frames", fcam.header.frame_count);
+                        println!("        Projection: {}", fcam.header.projection_mode);
+                        println!("        Rotation: {}", fcam.header.rotation_mode);
+                        println!("        {} curves", fcam.header.curve_count);
+                    }
+                }
+                if let Some(flit_index_group) = fscn.flit_index_group {
+                    println!("    {} FLIT:", flit_index_group.entries.len());
+                    for flit_entry in flit_index_group.entries {
+                        println!(
+                            "    --- {}",
+                            flit_entry.get_name(&mut bfres_cursor).unwrap()
+                        );
+                        let flit = flit_entry.get_data(&mut bfres_cursor).unwrap();
+                        println!("        {} frames", flit.header.frame_count);
+                        if let Some(light_type) =
+                            flit.get_light_type_name(&mut bfres_cursor).unwrap()
+                        {
+                            println!("        Type: {}", light_type);
+                        }
+                        println!("        {} curves", flit.header.curve_count);
+                    }
+                }
+                if let Some(ffog_index_group) = fscn.ffog_index_group {
+                    println!("    {} FFOG:", ffog_index_group.entries.len());
+                    for ffog_entry in ffog_index_group.entries {
+                        println!(
+                            "    --- {}",
+                            ffog_entry.get_name(&mut bfres_cursor).unwrap()
+                        );
+                        let ffog = ffog_entry.get_data(&mut bfres_cursor).unwrap();
+                        println!("        {} frames", ffog.header.frame_count);
+                        println!("        {} curves", ffog.header.curve_count);
+                    }
+                }
+            }
+        }
         if let Some(a) = bfres_file.sub_file_index_groups.embedded_file {
             println!("{} Embedded sub-files", a.entries.len());
             for embedded_entry in a.entries {
@@ -195,6 +518,10 @@ fn main() {
                     embedded.length,
                     if embedded.length == 1 { "" } else { "s" }
                 );
+                println!(
+                    "    Content: {}",
+                    embedded.get_kind(&mut bfres_cursor).unwrap()
+                );
             }
         }
     }
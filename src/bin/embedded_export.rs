@@ -0,0 +1,70 @@
+extern crate bfres;
+
+use bfres::fres::FRES;
+use std::env;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+fn write_raw_buffer(path: String, data: &[u8]) {
+    let file = File::create(path).unwrap();
+    let ref mut w = BufWriter::new(file);
+    w.write_all(data).unwrap();
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 3 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} input_file output_folder",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 3 {
+        println!("Please only give two arguments");
+    } else {
+        // Input the data
+        let input_file = args[1].to_string();
+        let output_folder = args[2].to_string();
+
+        // Decompress if necessary and import the file
+        let (bfres_file, mut bfres_cursor) =
+            FRES::open(&input_file).expect("Failed to read FRES file properly");
+
+        let embedded_files = bfres_file
+            .sub_file_index_groups
+            .get_embedded_files(&mut bfres_cursor)
+            .expect("Failed to read embedded files");
+        if embedded_files.is_empty() {
+            println!("No embedded files in this BFRES!");
+        } else {
+            create_dir_all(&output_folder).expect("Failed to create output folder");
+        }
+        for embedded_file in embedded_files {
+            println!(
+                "\n{}, {} bytes, {}",
+                embedded_file.name,
+                embedded_file.data.len(),
+                embedded_file.kind
+            );
+
+            // Names usually already carry an extension, only add one when missing
+            let file_name = Path::new(&embedded_file.name)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| embedded_file.name.clone());
+            let output_path = if Path::new(&file_name).extension().is_some() {
+                format!("{}/{}", output_folder, file_name)
+            } else {
+                format!(
+                    "{}/{}.{}",
+                    output_folder,
+                    file_name,
+                    embedded_file.kind.get_extension()
+                )
+            };
+            println!("  ->  Output as {}", output_path);
+            write_raw_buffer(output_path, &embedded_file.data);
+        }
+    }
+}
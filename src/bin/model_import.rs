@@ -0,0 +1,104 @@
+extern crate bfres;
+
+use bfres::fres::build::{build_bfres, BuildOptions};
+use bfres::fres::fmdl::fshp::lod_model::IndexFormat;
+use bfres::fres::fmdl::fvtx::attributes::AttributesFormats;
+use bfres::scene::Scene;
+use bfres::yaz0;
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+fn take_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            Some(args.remove(i))
+        }
+        _ => None,
+    }
+}
+
+fn main() {
+    let mut args: Vec<_> = env::args().collect();
+    // Half floats for positions and texture coordinates
+    let compact = take_flag(&mut args, "--compact");
+    // Index size, from the vertex count by default
+    let index_format = if take_flag(&mut args, "--u16") {
+        Some(IndexFormat::U16BigEndian)
+    } else if take_flag(&mut args, "--u32") {
+        Some(IndexFormat::U32BigEndian)
+    } else {
+        None
+    };
+    // Template material for the ones the template does not have
+    let template_material = take_value(&mut args, "--material");
+    if args.len() < 4 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} [--compact] [--u16|--u32] [--material name] template_file input_model output_file",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 4 {
+        println!("Please only give three arguments");
+    } else {
+        let template_file = args[1].to_string();
+        let input_model = args[2].to_string();
+        let output_file = args[3].to_string();
+
+        // Decompress if necessary
+        let mut template_reader =
+            BufReader::new(File::open(&template_file).expect("Failed to open template"));
+        let template = yaz0::read_all(&mut template_reader).expect("Failed to read template");
+
+        let scene = Scene::open(&input_model).expect("Failed to read model");
+        println!(
+            "{} meshes, {} materials, {} bones",
+            scene.meshes.len(),
+            scene.materials.len(),
+            scene.bones.len()
+        );
+
+        // Named after the output file
+        let name = Path::new(&output_file)
+            .file_stem()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let mut options = BuildOptions::new(&name);
+        options.template_material = template_material;
+        options.index_format = index_format;
+        if compact {
+            options.position_format = AttributesFormats::FourF16ToFourF32;
+            options.texture_coordinate_format = AttributesFormats::TwoF16ToTwoF32;
+        }
+
+        match build_bfres(&scene, &template, &options) {
+            Ok(output) => {
+                // Compressed when the name says so
+                let output = if output_file.ends_with(".sbfres") || output_file.ends_with(".szs") {
+                    println!("Compressing...");
+                    yaz0::compress(&output, yaz0::MAX_WINDOW, 6)
+                } else {
+                    output
+                };
+                let file = File::create(&output_file).expect("Failed to create output file");
+                BufWriter::new(file).write_all(&output).unwrap();
+                println!("Output as {}", output_file);
+            }
+            Err(e) => println!("/!\\ Failed to build the model: {}", e),
+        }
+    }
+}
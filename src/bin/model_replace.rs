@@ -0,0 +1,82 @@
+extern crate bfres;
+
+use bfres::fres::replace::{replace_model, replace_shape};
+use bfres::scene::Scene;
+use bfres::yaz0;
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+fn take_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            Some(args.remove(i))
+        }
+        _ => None,
+    }
+}
+
+fn main() {
+    let mut args: Vec<_> = env::args().collect();
+    // First model of the file by default
+    let model_name = take_value(&mut args, "--model");
+    // Only this shape, every shape of the model by default
+    let shape_name = take_value(&mut args, "--shape");
+    if args.len() < 4 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} [--model name] [--shape name] input_file input_model output_file",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 4 {
+        println!("Please only give three arguments");
+    } else {
+        let input_file = args[1].to_string();
+        let input_model = args[2].to_string();
+        let output_file = args[3].to_string();
+
+        // Decompress if necessary
+        let mut input_file_reader =
+            BufReader::new(File::open(&input_file).expect("Failed to open file for reading"));
+        let bfres = yaz0::read_all(&mut input_file_reader).expect("Failed to read file");
+
+        let scene = Scene::open(&input_model).expect("Failed to read model");
+        println!("{} meshes, {} bones", scene.meshes.len(), scene.bones.len());
+
+        let result = match shape_name {
+            Some(ref shape_name) => {
+                // The mesh named like the shape, or the only one there is
+                let mesh_index = match scene.meshes.iter().position(|m| &m.name == shape_name) {
+                    Some(i) => Some(i),
+                    None if scene.meshes.len() == 1 => Some(0),
+                    None => None,
+                };
+                match mesh_index {
+                    Some(i) => replace_shape(&bfres, model_name.as_deref(), shape_name, &scene, i),
+                    None => {
+                        println!("/!\\ No mesh named {} in {}", shape_name, input_model);
+                        return;
+                    }
+                }
+            }
+            None => replace_model(&bfres, model_name.as_deref(), &scene),
+        };
+        match result {
+            Ok(output) => {
+                // Compressed back when the name says so
+                let output = if output_file.ends_with(".sbfres") || output_file.ends_with(".szs") {
+                    println!("Compressing...");
+                    yaz0::compress(&output, yaz0::MAX_WINDOW, 6)
+                } else {
+                    output
+                };
+                let file = File::create(&output_file).expect("Failed to create output file");
+                BufWriter::new(file).write_all(&output).unwrap();
+                println!("Output as {}", output_file);
+            }
+            Err(e) => println!("/!\\ Failed to replace the geometry: {}", e),
+        }
+    }
+}
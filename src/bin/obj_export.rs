@@ -1,18 +1,15 @@
 extern crate bfres;
 extern crate ez_io;
 extern crate half;
-extern crate yaz0lib_rust;
 
-use bfres::fres::FRES;
-use bfres::fres::fmdl::fshp::{lod_model::IndexFormat, lod_model::PrimitiveType};
 use bfres::fres::fmdl::fvtx::attributes::AttributesFormats;
-use bfres::util::Importable;
+use bfres::fres::FRES;
 use ez_io::ReadE;
 use half::f16;
 use std::env;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem::transmute;
 use std::path::Path;
 
@@ -23,37 +20,53 @@ struct OBJFile {
 struct OBJGroup {
     vertices_positions: Vec<[f32; 3]>,
     vertices_texture_coordinates: Vec<[f32; 2]>,
-    faces: Vec<[u16; 3]>,
+    faces: Vec<[u32; 3]>,
     name: String,
 }
 
+// Which LOD Models of each shape get exported
+#[derive(Clone, Copy)]
+enum LODSelection {
+    One(usize),
+    All,
+}
+
 impl OBJFile {
     fn export(&self, file: &mut File) -> Result<(), Box<Error>> {
-        let mut offset: u16 = 0;
+        let mut offset: u32 = 0;
         for group in &self.groups {
             file.write_all(&format!("o {}\n", group.name).into_bytes())?;
             for vertex_position in &group.vertices_positions {
-                file.write_all(&format!(
-                    "v {} {} {}\n",
-                    vertex_position[0], vertex_position[1], vertex_position[2]
-                ).into_bytes())?;
+                file.write_all(
+                    &format!(
+                        "v {} {} {}\n",
+                        vertex_position[0], vertex_position[1], vertex_position[2]
+                    )
+                    .into_bytes(),
+                )?;
             }
             for vertex_texture_coordinates in &group.vertices_texture_coordinates {
-                file.write_all(&format!(
-                    "vt {} {}\n",
-                    vertex_texture_coordinates[0], vertex_texture_coordinates[1]
-                ).into_bytes())?;
+                file.write_all(
+                    &format!(
+                        "vt {} {}\n",
+                        vertex_texture_coordinates[0], vertex_texture_coordinates[1]
+                    )
+                    .into_bytes(),
+                )?;
             }
             file.write_all(&"s 1\n".to_string().into_bytes())?;
             for face in &group.faces {
-                file.write_all(&format!(
-                    "f {one}/{one} {two}/{two} {three}/{three}\n",
-                    one = face[0] + 1 + offset,
-                    two = face[1] + 1 + offset,
-                    three = face[2] + 1 + offset
-                ).into_bytes())?;
+                file.write_all(
+                    &format!(
+                        "f {one}/{one} {two}/{two} {three}/{three}\n",
+                        one = face[0] + 1 + offset,
+                        two = face[1] + 1 + offset,
+                        three = face[2] + 1 + offset
+                    )
+                    .into_bytes(),
+                )?;
             }
-            offset += group.vertices_positions.len() as u16;
+            offset += group.vertices_positions.len() as u32;
         }
         Ok(())
     }
@@ -109,12 +122,46 @@ trait FloatRead: Read {
 
 impl<R: Read> FloatRead for R {}
 
+fn take_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            Some(args.remove(i))
+        }
+        _ => None,
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let mut args: Vec<_> = env::args().collect();
+    // Each key shape becomes one more object, with its morphed positions
+    let export_key_shapes = take_flag(&mut args, "--key-shapes");
+    // The highest quality LOD Model by default, "all" gives one object for each
+    let lod_selection = match take_value(&mut args, "--lod") {
+        None => LODSelection::One(0),
+        Some(ref v) if v == "all" => LODSelection::All,
+        Some(v) => match v.parse() {
+            Ok(i) => LODSelection::One(i),
+            Err(_) => {
+                println!("LOD should be a number or all, not {}", v);
+                return;
+            }
+        },
+    };
     if args.len() < 3 {
         let exec_name = args[0].to_string();
         println!(
-            "Usage: ./{} input_file output_folder",
+            "Usage: ./{} [--lod index|all] [--key-shapes] input_file output_folder",
             Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
         );
     } else if args.len() > 3 {
@@ -123,30 +170,10 @@ fn main() {
         // Input the data
         let input_file = args[1].to_string();
         let output_folder = args[2].to_string();
-        let mut input_file_buf_reader =
-            BufReader::new(File::open(&input_file).expect("Failed to open file for reading"));
-        // Get the first Magic Number to check for compression
-        let mut yaz_check_buffer = [0u8; 4];
-        input_file_buf_reader
-            .read_exact(&mut yaz_check_buffer)
-            .expect("Failed to read first Magic Number");
-        input_file_buf_reader
-            .seek(SeekFrom::Start(0))
-            .expect("Failed to re-seek to beginning of the file");
         // Decompress if necessary and make the cursor
-        let mut bfres_cursor = if yaz_check_buffer == [b'Y', b'a', b'z', b'0'] {
-            Cursor::new(
-                yaz0lib_rust::decompress(&mut input_file_buf_reader).expect("Failed to decompress"),
-            )
-        } else {
-            let mut bfres_data = Vec::new();
-            input_file_buf_reader
-                .read_to_end(&mut bfres_data)
-                .expect("Failed to read all data");
-            Cursor::new(bfres_data)
-        };
+        let (bfres_file, mut bfres_cursor) =
+            FRES::open(&input_file).expect("Failed to read FRES file properly");
         let bfres_cursor_ref = &mut bfres_cursor;
-        let bfres_file = FRES::import(bfres_cursor_ref).expect("Failed to read FRES file properly");
 
         // If some Model sub-file exists
         if let Some(model_data_index_group) = bfres_file.sub_file_index_groups.model_data {
@@ -172,16 +199,19 @@ fn main() {
                 let mut vertices_texture_coordinates_groups =
                     Vec::with_capacity(fmdl.fvtx_array.entries.len());
 
+                // Keep the FVTXes, key shapes read their attributes again
+                let mut fvtxs = Vec::with_capacity(fmdl.fvtx_array.entries.len());
+
                 // Go through all the FVTX data
                 println!("    {} FVTX", fmdl.fvtx_array.entries.len());
-                for fvtx_entry in fmdl.fvtx_array.entries {
+                for fvtx_entry in &fmdl.fvtx_array.entries {
                     // Load the FVTX
                     let fvtx = fvtx_entry
                         .get_data(bfres_cursor_ref)
                         .expect("Failed to read FVTX data");
 
                     // Go through all the attributes
-                    for attributes_entry in fvtx.attributes_index_group.entries {
+                    for attributes_entry in &fvtx.attributes_index_group.entries {
                         fn read_buffer_two<R: Read + Seek>(
                             fmt: &AttributesFormats,
                             stride: u16,
@@ -341,7 +371,8 @@ fn main() {
                                     buffer.stride,
                                     buffer_end,
                                     bfres_cursor_ref,
-                                ).unwrap();
+                                )
+                                .unwrap();
 
                                 // Push the retrieved positions of vertices to the main group
                                 println!(
@@ -352,13 +383,13 @@ fn main() {
                             }
                             "_u0" => {
                                 // Create the vector that stores the texture_coordinates
-                                let vertices_texture_coordinates =
-                                    read_buffer_two(
-                                        &attributes.format,
-                                        buffer.stride,
-                                        buffer_end,
-                                        bfres_cursor_ref,
-                                    ).unwrap();
+                                let vertices_texture_coordinates = read_buffer_two(
+                                    &attributes.format,
+                                    buffer.stride,
+                                    buffer_end,
+                                    bfres_cursor_ref,
+                                )
+                                .unwrap();
 
                                 // Push the new texture_coordinates to the main group
                                 println!(
@@ -371,6 +402,7 @@ fn main() {
                             _ => continue,
                         }
                     }
+                    fvtxs.push(fvtx);
                 }
 
                 // Go through all the FSHP data
@@ -381,23 +413,6 @@ fn main() {
                         .get_data(bfres_cursor_ref)
                         .expect("Failed to read FSHP data");
 
-                    // Load the first LOD Model data (the highest quality one)
-                    let lod_model = fshp.lod_model_array.entries[0]
-                        .get_data(bfres_cursor_ref)
-                        .expect("Failed to read FSHP LOD Model");
-
-                    // Check if the primitive type is a triangle
-                    match lod_model.primitive_type {
-                        PrimitiveType::Triangles => {}
-                        _ => continue,
-                    }
-
-                    // Check if the index type is Big Endian u16
-                    match lod_model.index_format {
-                        IndexFormat::U16BigEndian => {}
-                        _ => continue,
-                    }
-
                     // Get the FSHP name
                     let fshp_name = fshp_entry
                         .get_name(bfres_cursor_ref)
@@ -413,48 +428,95 @@ fn main() {
                     let vertices_texture_coordinates =
                         &vertices_texture_coordinates_groups[fvtx_index];
 
-                    // Make the vector that will hold the faces
-                    let mut faces = Vec::new();
-
-                    // Load the Buffer Info
-                    let buffer_info = lod_model
-                        .get_direct_buffer_info(bfres_cursor_ref)
-                        .expect("Failed to read an FSHP Buffer Info");
-
-                    // Calculate the end of the buffer
-                    let buffer_end = buffer_info
-                        .data_offset
-                        .get_abs_pos()
-                        .expect("Failed to calculate absolute position for buffer")
-                        + u64::from(buffer_info.size);
-
-                    // Go to the beginning of the buffer
-                    buffer_info
-                        .data_offset
-                        .seek_abs_pos(bfres_cursor_ref)
-                        .expect("Failed to seek to FSHP Buffer");
-
-                    // Read the faces
-                    while bfres_cursor_ref.seek(SeekFrom::Current(0)).unwrap() < buffer_end {
-                        faces.push([
-                            bfres_cursor_ref.read_be_to_u16().unwrap(),
-                            bfres_cursor_ref.read_be_to_u16().unwrap(),
-                            bfres_cursor_ref.read_be_to_u16().unwrap(),
-                        ]);
-                    }
-
-                    println!("        {} new faces", faces.len());
+                    // Load the LOD Models, the first one is the highest quality one
+                    let lod_models = fshp
+                        .get_lod_models(bfres_cursor_ref)
+                        .expect("Failed to read FSHP LOD Models");
+
+                    // Load the key shapes when they get exported
+                    let key_shapes = if export_key_shapes {
+                        fshp.get_key_shapes(bfres_cursor_ref)
+                            .expect("Failed to read FSHP Key Shapes")
+                    } else {
+                        Vec::new()
+                    };
 
-                    // Add a new OBJGroup for this model
-                    let obj_group = OBJGroup {
-                        vertices_positions: vertices_positions.clone(),
-                        vertices_texture_coordinates: vertices_texture_coordinates.clone(),
-                        faces,
-                        name: fshp_name,
+                    // Shapes with fewer LODs than asked for give their lowest quality one
+                    let selected_lod = match lod_selection {
+                        LODSelection::One(i) if i >= lod_models.len() && !lod_models.is_empty() => {
+                            println!(
+                                "        /!\\ {} only has {} LODs, exporting LOD {} instead",
+                                fshp_name,
+                                lod_models.len(),
+                                lod_models.len() - 1
+                            );
+                            Some(lod_models.len() - 1)
+                        }
+                        LODSelection::One(i) => Some(i),
+                        LODSelection::All => None,
                     };
 
-                    // Add the new OBJGroup to OBJFile
-                    obj_file.groups.push(obj_group);
+                    for (lod_index, lod_model) in lod_models.iter().enumerate() {
+                        match selected_lod {
+                            Some(i) if i != lod_index => continue,
+                            _ => {}
+                        }
+
+                        // Read the faces
+                        let faces = match lod_model.get_triangles(bfres_cursor_ref) {
+                            Ok(f) => f,
+                            Err(e) => {
+                                println!(
+                                    "        /!\\ Skipped LOD {} of {}: {}",
+                                    lod_index, fshp_name, e
+                                );
+                                continue;
+                            }
+                        };
+
+                        println!("        {} new faces", faces.len());
+
+                        // Lower quality ones are told apart by their index
+                        let name = match lod_selection {
+                            LODSelection::All => format!("{}_lod{}", fshp_name, lod_index),
+                            LODSelection::One(_) => fshp_name.clone(),
+                        };
+
+                        // Add a new OBJGroup for this model
+                        let obj_group = OBJGroup {
+                            vertices_positions: vertices_positions.clone(),
+                            vertices_texture_coordinates: vertices_texture_coordinates.clone(),
+                            faces: faces.clone(),
+                            name: name.clone(),
+                        };
+
+                        // Add the new OBJGroup to OBJFile
+                        obj_file.groups.push(obj_group);
+
+                        // The same faces, moved by each key shape
+                        for (key_shape_name, key_shape) in &key_shapes {
+                            let positions = key_shape
+                                .get_target_positions(
+                                    bfres_cursor_ref,
+                                    &fvtxs[fvtx_index],
+                                    fshp.header.target_attribute_count,
+                                )
+                                .expect("Failed to read Key Shape positions");
+                            match positions {
+                                Some(p) => obj_file.groups.push(OBJGroup {
+                                    vertices_positions: p,
+                                    vertices_texture_coordinates: vertices_texture_coordinates
+                                        .clone(),
+                                    faces: faces.clone(),
+                                    name: format!("{}_{}", name, key_shape_name),
+                                }),
+                                None => println!(
+                                    "        /!\\ Key Shape {} of {} does not move positions",
+                                    key_shape_name, fshp_name
+                                ),
+                            }
+                        }
+                    }
                 }
 
                 // Create the output File object
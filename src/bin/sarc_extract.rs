@@ -0,0 +1,48 @@
+extern crate bfres;
+
+use bfres::sarc::SARC;
+use std::env;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+fn write_raw_buffer(path: &Path, data: &[u8]) {
+    let file = File::create(path).unwrap();
+    let ref mut w = BufWriter::new(file);
+    w.write_all(data).unwrap();
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 3 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} input_file output_folder",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 3 {
+        println!("Please only give two arguments");
+    } else {
+        let input_file = args[1].to_string();
+        let output_folder = args[2].to_string();
+
+        // Decompress if necessary and read the archive
+        let sarc = SARC::open(&input_file).expect("Failed to read SARC file properly");
+        println!("{} files", sarc.files.len());
+
+        for file in &sarc.files {
+            // Names can contain folders
+            let name = match file.name {
+                Some(ref n) => n.clone(),
+                None => format!("0x{:08X}.bin", file.hash),
+            };
+            println!("\n{}, {} bytes", name, file.data.len());
+            let output_path = Path::new(&output_folder).join(&name);
+            if let Some(parent) = output_path.parent() {
+                create_dir_all(parent).expect("Failed to create folder");
+            }
+            println!("  ->  Output as {}", output_path.display());
+            write_raw_buffer(&output_path, &file.data);
+        }
+    }
+}
@@ -1,19 +1,18 @@
-extern crate bcndecode;
 extern crate bfres;
-extern crate ez_io;
 extern crate png;
-extern crate yaz0lib_rust;
 
-use bcndecode::{decode, BcnDecoderFormat, BcnEncoding};
+use bfres::dds::DDS;
+use bfres::fres::ftex::dimension::Dimension;
+use bfres::fres::ftex::FTEX;
 use bfres::fres::FRES;
-use bfres::fres::ftex::format::Format;
-use bfres::swizzle::deswizzle;
+use bfres::gtx::{is_gtx, GTX};
 use bfres::util::Importable;
-use ez_io::ReadE;
+use bfres::yaz0;
 use png::HasParameters;
+use std::cmp::max;
 use std::env;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Cursor, Write};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, Write};
 use std::path::Path;
 
 fn write_new_image(path: String, data: &[u8], width: u32, height: u32) {
@@ -33,12 +32,179 @@ fn write_raw_buffer(path: String, data: &[u8]) {
     w.write_all(data).unwrap();
 }
 
+// In the +X, -X, +Y, -Y, +Z, -Z order the faces are stored in
+const CUBE_FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+fn write_image_or_raw(base: String, data: &[u8], width: u32, height: u32) {
+    let expected_output_len = (width * height * 4) as usize;
+    let real_output_len = data.len();
+    if expected_output_len != real_output_len {
+        println!(
+            "  /!\\ Unexpected output size, writing the raw buffer ({} != {})",
+            expected_output_len, real_output_len
+        );
+        println!("  ->  Output as {}.raw", base);
+        write_raw_buffer(format!("{}.raw", base), data);
+    } else {
+        println!("  ->  Output as {}.png", base);
+        write_new_image(format!("{}.png", base), data, width, height);
+    }
+}
+
+// Horizontal cross, 4 faces wide and 3 faces high, unused parts left transparent
+fn make_cube_cross(faces: &[Vec<u8>], width: usize, height: usize) -> Vec<u8> {
+    let positions = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+    let cross_width = width * 4;
+    let mut out = vec![0u8; cross_width * height * 3 * 4];
+    for (face, &(column, row)) in faces.iter().zip(positions.iter()) {
+        for y in 0..height {
+            let source = y * width * 4;
+            let destination = ((row * height + y) * cross_width + column * width) * 4;
+            out[destination..destination + width * 4]
+                .clone_from_slice(&face[source..source + width * 4]);
+        }
+    }
+    out
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+struct Options {
+    raw_channels: bool,
+    dds: bool,
+    cube_cross: bool,
+    resolve: bool,
+}
+
+fn export_texture<R: Read + Seek>(
+    reader: &mut R,
+    name: &str,
+    ftex: &FTEX,
+    output_folder: &str,
+    options: &Options,
+) {
+    println!("\n{}, Format {}", name, ftex.header.texture_format);
+    if !options.raw_channels {
+        println!("Channels: {}", ftex.header.component_selector);
+    }
+    if ftex.get_sample_count() > 1 {
+        println!("Samples: {}", ftex.get_sample_count());
+    }
+
+    let output_base = format!("{}/{}", output_folder, name);
+    let nb_mipmaps = max(1, ftex.header.nb_mipmaps);
+
+    if options.dds {
+        let output_path = format!("{}.dds", output_base);
+        let dds_file = match DDS::from_ftex(reader, ftex) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("  /!\\ Failed to convert to DDS: {}", e);
+                return;
+            }
+        };
+        let file = File::create(&output_path).unwrap();
+        match dds_file.write(&mut BufWriter::new(file)) {
+            Ok(_) => println!("  ->  Output as {}", output_path),
+            Err(e) => println!("  /!\\ Failed to write DDS: {}", e),
+        }
+        return;
+    }
+
+    let is_cube = match ftex.header.dimension {
+        Dimension::Cube => true,
+        _ => false,
+    };
+    for level in 0..nb_mipmaps {
+        let (width, height) = ftex.get_mip_level_dimensions(level);
+        let nb_slices = ftex.get_slice_count(level);
+        let level_suffix = if level == 0 {
+            String::new()
+        } else {
+            format!("_mip{}", level)
+        };
+
+        let mut faces = Vec::new();
+        for slice in 0..nb_slices {
+            let slice_base = if nb_slices == 1 {
+                format!("{}{}", output_base, level_suffix)
+            } else if is_cube {
+                let face_name = CUBE_FACE_NAMES[slice as usize % 6];
+                format!("{}_{}{}", output_base, face_name, level_suffix)
+            } else {
+                format!("{}_{}{}", output_base, slice, level_suffix)
+            };
+
+            // Decode the buffer
+            let decoded = if options.resolve {
+                ftex.resolve_rgba8(reader, level, slice, !options.raw_channels)
+            } else {
+                ftex.decode_slice_rgba8(reader, level, slice, !options.raw_channels)
+            };
+            let image_data = match decoded {
+                Ok(d) => d,
+                Err(e) => {
+                    println!("  /!\\ Failed to decode: {}, writing the raw buffer", e);
+                    // The raw buffer holds every slice, the next level is tried either way
+                    match ftex.get_mip_level_raw_data(reader, level) {
+                        Ok(raw_data) => {
+                            let output_path = format!("{}.raw", slice_base);
+                            println!("  ->  Output as {}", output_path);
+                            write_raw_buffer(output_path, &raw_data);
+                        }
+                        Err(e) => println!("  /!\\ Failed to read the raw buffer: {}", e),
+                    }
+                    break;
+                }
+            };
+
+            if is_cube && options.cube_cross {
+                faces.push(image_data);
+                if faces.len() == 6 {
+                    let cross_base = if nb_slices == 6 {
+                        format!("{}_cross{}", output_base, level_suffix)
+                    } else {
+                        format!("{}_cross_{}{}", output_base, slice / 6, level_suffix)
+                    };
+                    write_image_or_raw(
+                        cross_base,
+                        &make_cube_cross(&faces, width as usize, height as usize),
+                        width * 4,
+                        height * 3,
+                    );
+                    faces.clear();
+                }
+            } else {
+                write_image_or_raw(slice_base, &image_data, width, height);
+            }
+        }
+    }
+}
+
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let mut args: Vec<_> = env::args().collect();
+    let options = Options {
+        // Channels are shuffled like the GPU would unless asked otherwise
+        raw_channels: take_flag(&mut args, "--raw-channels"),
+        // One DDS with every mip level and slice instead of a PNG per image
+        dds: take_flag(&mut args, "--dds"),
+        // Cube maps as a single cross image instead of six faces
+        cube_cross: take_flag(&mut args, "--cube-cross"),
+        // Multisampled textures averaged into one image instead of only the first sample
+        resolve: take_flag(&mut args, "--resolve"),
+    };
     if args.len() < 3 {
         let exec_name = args[0].to_string();
         println!(
-            "Usage: ./{} input_file output_folder",
+            "Usage: ./{} [--raw-channels] [--dds] [--cube-cross] [--resolve] input_file output_folder",
             Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
         );
     } else if args.len() > 3 {
@@ -47,20 +213,39 @@ fn main() {
         // Input the data
         let input_file = args[1].to_string();
         let output_folder = args[2].to_string();
+
+        // Decompress if necessary
         let mut input_file_reader =
             BufReader::new(File::open(&input_file).expect("Failed to open file for reading"));
+        let data = yaz0::read_all(&mut input_file_reader).expect("Failed to read file");
 
-        // Decompress
-        println!("Decompressing...");
-        let output = yaz0lib_rust::decompress(&mut input_file_reader).unwrap();
-        println!("Decompressed !");
-
-        // Make the cursor
-        let mut bfres_cursor: Cursor<Vec<u8>> = Cursor::new(output);
-        let bfres_cursor_ref = &mut bfres_cursor;
+        // Standalone GTX files only hold textures, named after the file
+        if is_gtx(&data) {
+            let mut gtx_cursor = Cursor::new(data);
+            let gtx_file = GTX::import(&mut gtx_cursor).expect("Failed to read GTX file properly");
+            let stem = Path::new(&input_file)
+                .file_stem()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            let nb_textures = gtx_file.textures.len();
+            for (i, ftex) in gtx_file.textures.iter().enumerate() {
+                let name = if nb_textures == 1 {
+                    stem.clone()
+                } else {
+                    format!("{}_{}", stem, i)
+                };
+                export_texture(&mut gtx_cursor, &name, ftex, &output_folder, &options);
+            }
+            return;
+        }
 
         // Import the file
-        let bfres_file = FRES::import(bfres_cursor_ref).expect("Failed to read FRES file properly");
+        let mut bfres_cursor = Cursor::new(data);
+        let bfres_file =
+            FRES::import(&mut bfres_cursor).expect("Failed to read FRES file properly");
+        let bfres_cursor_ref = &mut bfres_cursor;
 
         // If some FTEX Sub-File exists
         if let Some(ftex_index_group) = bfres_file.sub_file_index_groups.texture_data {
@@ -76,75 +261,13 @@ fn main() {
                     .get_data(bfres_cursor_ref)
                     .expect("Failed to read FTEX");
 
-                println!("\n{}, Format {}", ftex_name, ftex.header.texture_format);
-
-                // Check for depth
-                if ftex.header.texture_depth != 1 {
-                    println!("Depth is not 1, skipping...");
-                    continue;
-                }
-
-                // Get important info
-                let width = ftex.header.texture_width as usize;
-                let height = ftex.header.texture_height as usize;
-                let encoding = match ftex.header.texture_format {
-                    Format::TcsR8G8B8A8Unorm => None,
-                    Format::TBc1Unorm | Format::TBc1Srgb => Some(BcnEncoding::Bc1),
-                    Format::TBc2Unorm | Format::TBc2Srgb => Some(BcnEncoding::Bc2),
-                    Format::TBc3Unorm | Format::TBc3Srgb => Some(BcnEncoding::Bc3),
-                    Format::TBc4Unorm | Format::TBc4Snorm => Some(BcnEncoding::Bc4),
-                    Format::TBc5Unorm | Format::TBc5Snorm => Some(BcnEncoding::Bc5),
-                    _ => {
-                        println!("{} not implemented", ftex.header.texture_format);
-                        continue;
-                    }
-                };
-
-                // Read the whole buffer
-                let mut raw_data = Vec::with_capacity(ftex.header.data_length as usize);
-                ftex.header
-                    .data_offset
-                    .seek_abs_pos(bfres_cursor_ref)
-                    .unwrap();
-                while raw_data.len() < raw_data.capacity() {
-                    raw_data.push(bfres_cursor_ref.read_to_u8().unwrap());
-                }
-
-                let de_swizzled_data = deswizzle(&ftex, &raw_data).expect("Failed to de swizzle");
-
-                // Decode the buffer or not
-                let image_data = match encoding {
-                    Some(enc) => decode(
-                        &de_swizzled_data,
-                        width,
-                        height,
-                        enc,
-                        BcnDecoderFormat::RGBA,
-                    ).unwrap(),
-                    None => de_swizzled_data,
-                };
-
-                let expected_output_len = width * height * 4;
-                let real_output_len = image_data.len();
-
-                // Write that to a file
-                let output_base = format!("{}/{}", output_folder, ftex_name);
-                if expected_output_len != real_output_len {
-                    println!(
-                        "  /!\\ Unexpected output size, writing the raw buffer ({} != {})",
-                        expected_output_len, real_output_len
-                    );
-                    println!("  ->  Output as {}.raw", output_base);
-                    write_raw_buffer(format!("{}.raw", output_base), &image_data);
-                } else {
-                    println!("  ->  Output as {}.png", output_base);
-                    write_new_image(
-                        format!("{}.png", output_base),
-                        &image_data,
-                        width as u32,
-                        height as u32,
-                    );
-                }
+                export_texture(
+                    bfres_cursor_ref,
+                    &ftex_name,
+                    &ftex,
+                    &output_folder,
+                    &options,
+                );
             }
         } else {
             println!("No FTEX in this BFRES!");
@@ -0,0 +1,120 @@
+extern crate bfres;
+extern crate png;
+
+use bfres::dds::DDS;
+use bfres::fres::ftex::bcn::Quality;
+use bfres::fres::inject::{replace_texture, NewTexture};
+use bfres::util::Importable;
+use bfres::yaz0;
+use png::HasParameters;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+// Every color type is expanded to 8 bits RGBA
+fn read_png(path: &str) -> Result<NewTexture, Box<Error>> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    // Palettes and 16 bits channels both come out as 8 bits
+    decoder.set(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buffer = vec![0u8; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+    let data = match info.color_type {
+        png::ColorType::RGBA => buffer,
+        png::ColorType::RGB => buffer
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&l| vec![l, l, l, 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks(2)
+            .flat_map(|p| vec![p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(Box::new(png::DecodingError::Other(
+                "Indexed PNGs are not supported".into(),
+            )))
+        }
+    };
+    Ok(NewTexture::Rgba8 {
+        width: info.width,
+        height: info.height,
+        data,
+    })
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+fn main() {
+    let mut args: Vec<_> = env::args().collect();
+    // Block compression quality, between the two by default
+    let quality = if take_flag(&mut args, "--fast") {
+        Quality::Fast
+    } else if take_flag(&mut args, "--best") {
+        Quality::High
+    } else {
+        Quality::Normal
+    };
+    if args.len() < 5 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} [--fast|--best] input_file texture_name image_file output_file",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 5 {
+        println!("Please only give four arguments");
+    } else {
+        let input_file = args[1].to_string();
+        let texture_name = args[2].to_string();
+        let image_file = args[3].to_string();
+        let output_file = args[4].to_string();
+
+        // Read the new image
+        let is_dds = image_file.to_lowercase().ends_with(".dds");
+        let texture = if is_dds {
+            let mut image_reader =
+                BufReader::new(File::open(&image_file).expect("Failed to open image"));
+            NewTexture::DDS(DDS::import(&mut image_reader).expect("Failed to read DDS"))
+        } else {
+            match read_png(&image_file) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("/!\\ Failed to read {}: {}", image_file, e);
+                    return;
+                }
+            }
+        };
+
+        // Decompress if necessary
+        let mut input_file_reader =
+            BufReader::new(File::open(&input_file).expect("Failed to open file for reading"));
+        let bfres = yaz0::read_all(&mut input_file_reader).expect("Failed to read file");
+
+        // Replace and write the new file
+        match replace_texture(&bfres, &texture_name, &texture, quality) {
+            Ok(output) => {
+                // Compressed back when the name says so
+                let output = if output_file.ends_with(".sbfres") || output_file.ends_with(".szs") {
+                    println!("Compressing...");
+                    yaz0::compress(&output, yaz0::MAX_WINDOW, 6)
+                } else {
+                    output
+                };
+                let file = File::create(&output_file).expect("Failed to create output file");
+                BufWriter::new(file).write_all(&output).unwrap();
+                println!("Replaced {}, output as {}", texture_name, output_file);
+            }
+            Err(e) => println!("/!\\ Failed to replace {}: {}", texture_name, e),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+extern crate bfres;
+extern crate serde_json;
+
+use bfres::fres::validate::validate;
+use bfres::yaz0;
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 2 {
+        let exec_name = args[0].to_string();
+        println!(
+            "Usage: ./{} input_file [output_file]",
+            Path::new(&exec_name).file_name().unwrap().to_str().unwrap()
+        );
+    } else if args.len() > 3 {
+        println!("Please only give one or two arguments");
+    } else {
+        let input_file = args[1].to_string();
+
+        // Decompress if necessary
+        let mut input_file_reader =
+            BufReader::new(File::open(&input_file).expect("Failed to open file for reading"));
+        let bfres = yaz0::read_all(&mut input_file_reader).expect("Failed to read file");
+
+        let report = match validate(&bfres) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("/!\\ Failed to read the models: {}", e);
+                process::exit(2);
+            }
+        };
+
+        // The report goes to the output file, or is printed when there is none
+        let json = serde_json::to_string(&report).unwrap();
+        match args.get(2) {
+            Some(output_file) => {
+                let file = File::create(output_file).expect("Failed to create output file");
+                BufWriter::new(file).write_all(json.as_bytes()).unwrap();
+            }
+            None => println!("{}", json),
+        }
+
+        // So scripts can tell when something is wrong
+        let nb_problems = report.get_nb_problems();
+        if nb_problems > 0 {
+            eprintln!("{} problems found", nb_problems);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use error::{check_magic_number, TextureDataTooShort, UnsupportedFormat};
+use fres::ftex::decode::get_image_size;
+use fres::ftex::dimension::Dimension;
+use fres::ftex::format::Format;
+use fres::ftex::FTEX;
+use std::cmp::max;
+use std::error::Error;
+use std::io::{Read, Seek, Write};
+use util::Importable;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDSD_LINEARSIZE: u32 = 0x8_0000;
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x40_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+// Linear images, mip_levels[level][slice], the way FTEX stores them.
+// depth is the number of layers for arrays, of faces for cube maps, and the depth of 3D textures
+pub struct DDS {
+    pub format: Format,
+    pub dimension: Dimension,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub mip_levels: Vec<Vec<Vec<u8>>>,
+}
+
+pub fn get_dxgi_format(format: Format) -> Option<u32> {
+    Some(match format {
+        Format::TcR32G32B32A32Float => 2,
+        Format::TcR32G32B32A32Uint => 3,
+        Format::TcR32G32B32A32Sint => 4,
+        Format::TcR16G16B16A16Float => 10,
+        Format::TcR16G16B16A16Unorm => 11,
+        Format::TcR16G16B16A16Uint => 12,
+        Format::TcR16G16B16A16Snorm => 13,
+        Format::TcR16G16B16A16Sint => 14,
+        Format::TcR32G32Float => 16,
+        Format::TcR32G32Uint => 17,
+        Format::TcR32G32Sint => 18,
+        Format::TX32G8UintX24 => 20,
+        Format::TcsR10G10B10A2Unorm => 24,
+        Format::TcR10G10B10A2Uint => 25,
+        Format::TcR11G11B10Float => 26,
+        Format::TcsR8G8B8A8Unorm => 28,
+        Format::TcsR8G8B8A8Srgb => 29,
+        Format::TcR8G8B8A8Uint => 30,
+        Format::TcR8G8B8A8Snorm => 31,
+        Format::TcR8G8B8A8Sint => 32,
+        Format::TcR16G16Float => 34,
+        Format::TcR16G16Unorm => 35,
+        Format::TcR16G16Uint => 36,
+        Format::TcR16G16Snorm => 37,
+        Format::TcR16G16Sint => 38,
+        Format::TcdR32Float => 41,
+        Format::TcR32Uint => 42,
+        Format::TcR32Sint => 43,
+        Format::DD24S8Float => 45,
+        Format::TX24G8Uint => 47,
+        Format::TcR8G8Unorm => 49,
+        Format::TcR8G8Uint => 50,
+        Format::TcR8G8Snorm => 51,
+        Format::TcR8G8Sint => 52,
+        Format::TcR16Float => 54,
+        Format::TcdR16Unorm => 56,
+        Format::TcR16Uint => 57,
+        Format::TcR16Snorm => 58,
+        Format::TcR16Sint => 59,
+        Format::TcR8Unorm => 61,
+        Format::TcR8Uint => 62,
+        Format::TcR8Snorm => 63,
+        Format::TcR8Sint => 64,
+        Format::TBc1Unorm => 71,
+        Format::TBc1Srgb => 72,
+        Format::TBc2Unorm => 74,
+        Format::TBc2Srgb => 75,
+        Format::TBc3Unorm => 77,
+        Format::TBc3Srgb => 78,
+        Format::TBc4Unorm => 80,
+        Format::TBc4Snorm => 81,
+        Format::TBc5Unorm => 83,
+        Format::TBc5Snorm => 84,
+        Format::TNv12Unorm => 103,
+        // The packed 16 bits formats and A2B10G10R10 have their channels in an order DXGI lacks
+        _ => return None,
+    })
+}
+
+pub fn get_format_from_dxgi(dxgi_format: u32) -> Option<Format> {
+    Some(match dxgi_format {
+        2 => Format::TcR32G32B32A32Float,
+        3 => Format::TcR32G32B32A32Uint,
+        4 => Format::TcR32G32B32A32Sint,
+        10 => Format::TcR16G16B16A16Float,
+        11 => Format::TcR16G16B16A16Unorm,
+        12 => Format::TcR16G16B16A16Uint,
+        13 => Format::TcR16G16B16A16Snorm,
+        14 => Format::TcR16G16B16A16Sint,
+        16 => Format::TcR32G32Float,
+        17 => Format::TcR32G32Uint,
+        18 => Format::TcR32G32Sint,
+        20 => Format::TX32G8UintX24,
+        24 => Format::TcsR10G10B10A2Unorm,
+        25 => Format::TcR10G10B10A2Uint,
+        26 => Format::TcR11G11B10Float,
+        28 => Format::TcsR8G8B8A8Unorm,
+        29 => Format::TcsR8G8B8A8Srgb,
+        30 => Format::TcR8G8B8A8Uint,
+        31 => Format::TcR8G8B8A8Snorm,
+        32 => Format::TcR8G8B8A8Sint,
+        34 => Format::TcR16G16Float,
+        35 => Format::TcR16G16Unorm,
+        36 => Format::TcR16G16Uint,
+        37 => Format::TcR16G16Snorm,
+        38 => Format::TcR16G16Sint,
+        41 => Format::TcdR32Float,
+        42 => Format::TcR32Uint,
+        43 => Format::TcR32Sint,
+        45 => Format::DD24S8Float,
+        47 => Format::TX24G8Uint,
+        49 => Format::TcR8G8Unorm,
+        50 => Format::TcR8G8Uint,
+        51 => Format::TcR8G8Snorm,
+        52 => Format::TcR8G8Sint,
+        54 => Format::TcR16Float,
+        56 => Format::TcdR16Unorm,
+        57 => Format::TcR16Uint,
+        58 => Format::TcR16Snorm,
+        59 => Format::TcR16Sint,
+        61 => Format::TcR8Unorm,
+        62 => Format::TcR8Uint,
+        63 => Format::TcR8Snorm,
+        64 => Format::TcR8Sint,
+        71 => Format::TBc1Unorm,
+        72 => Format::TBc1Srgb,
+        74 => Format::TBc2Unorm,
+        75 => Format::TBc2Srgb,
+        77 => Format::TBc3Unorm,
+        78 => Format::TBc3Srgb,
+        80 => Format::TBc4Unorm,
+        81 => Format::TBc4Snorm,
+        83 => Format::TBc5Unorm,
+        84 => Format::TBc5Snorm,
+        103 => Format::TNv12Unorm,
+        _ => return None,
+    })
+}
+
+// Formats old readers understand without the DX10 extension
+fn get_legacy_four_cc(format: Format) -> Option<&'static [u8; 4]> {
+    match format {
+        Format::TBc1Unorm => Some(b"DXT1"),
+        Format::TBc2Unorm => Some(b"DXT3"),
+        Format::TBc3Unorm => Some(b"DXT5"),
+        Format::TBc4Unorm => Some(b"ATI1"),
+        Format::TBc5Unorm => Some(b"ATI2"),
+        _ => None,
+    }
+}
+
+fn get_format_from_four_cc(four_cc: &[u8; 4]) -> Option<Format> {
+    match four_cc {
+        b"DXT1" => Some(Format::TBc1Unorm),
+        b"DXT2" | b"DXT3" => Some(Format::TBc2Unorm),
+        b"DXT4" | b"DXT5" => Some(Format::TBc3Unorm),
+        b"ATI1" | b"BC4U" => Some(Format::TBc4Unorm),
+        b"BC4S" => Some(Format::TBc4Snorm),
+        b"ATI2" | b"BC5U" => Some(Format::TBc5Unorm),
+        b"BC5S" => Some(Format::TBc5Snorm),
+        _ => None,
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, Box<Error>> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from(bytes[0])
+        | (u32::from(bytes[1]) << 8)
+        | (u32::from(bytes[2]) << 16)
+        | (u32::from(bytes[3]) << 24))
+}
+
+// Uncompressed formats are described with masks, the BGRA order also needs its channels swapped.
+// Gives the format, if red and blue have to be swapped, and if alpha is missing
+fn get_legacy_format(
+    pixel_format_flags: u32,
+    four_cc: &[u8; 4],
+    rgb_bit_count: u32,
+    masks: [u32; 4],
+) -> Option<(Format, bool, bool)> {
+    if pixel_format_flags & DDPF_FOURCC != 0 {
+        get_format_from_four_cc(four_cc).map(|f| (f, false, false))
+    } else if pixel_format_flags & DDPF_RGB != 0 && rgb_bit_count == 32 {
+        let force_alpha = pixel_format_flags & DDPF_ALPHAPIXELS == 0;
+        match (masks[0], masks[1], masks[2]) {
+            (0x0000_00FF, 0x0000_FF00, 0x00FF_0000) => {
+                Some((Format::TcsR8G8B8A8Unorm, false, force_alpha))
+            }
+            (0x00FF_0000, 0x0000_FF00, 0x0000_00FF) => {
+                Some((Format::TcsR8G8B8A8Unorm, true, force_alpha))
+            }
+            _ => None,
+        }
+    } else if pixel_format_flags & DDPF_LUMINANCE != 0 && rgb_bit_count == 8 {
+        Some((Format::TcR8Unorm, false, false))
+    } else {
+        None
+    }
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<(), Box<Error>> {
+    writer.write_all(&[
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ])?;
+    Ok(())
+}
+
+fn is_three_d(dimension: Dimension) -> bool {
+    match dimension {
+        Dimension::ThreeD => true,
+        _ => false,
+    }
+}
+
+fn is_cube(dimension: Dimension) -> bool {
+    match dimension {
+        Dimension::Cube => true,
+        _ => false,
+    }
+}
+
+impl Importable for DDS {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<DDS, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, *b"DDS ")?;
+        let _header_size = read_u32(reader)?;
+        let flags = read_u32(reader)?;
+        let height = read_u32(reader)?;
+        let width = read_u32(reader)?;
+        let _pitch_or_linear_size = read_u32(reader)?;
+        let header_depth = read_u32(reader)?;
+        let mip_count = read_u32(reader)?;
+        let mut reserved = [0u8; 44];
+        reader.read_exact(&mut reserved)?;
+        // Pixel format
+        let _pixel_format_size = read_u32(reader)?;
+        let pixel_format_flags = read_u32(reader)?;
+        let mut four_cc = [0u8; 4];
+        reader.read_exact(&mut four_cc)?;
+        let rgb_bit_count = read_u32(reader)?;
+        let masks = [
+            read_u32(reader)?,
+            read_u32(reader)?,
+            read_u32(reader)?,
+            read_u32(reader)?,
+        ];
+        let _caps = read_u32(reader)?;
+        let caps2 = read_u32(reader)?;
+        let mut reserved2 = [0u8; 12];
+        reader.read_exact(&mut reserved2)?;
+
+        let mut swizzle = (false, false);
+        let (format, dimension, depth) =
+            if pixel_format_flags & DDPF_FOURCC != 0 && &four_cc == b"DX10" {
+                let dxgi_format = read_u32(reader)?;
+                let resource_dimension = read_u32(reader)?;
+                let misc_flag = read_u32(reader)?;
+                let array_size = max(1, read_u32(reader)?);
+                let _misc_flags2 = read_u32(reader)?;
+                let format = match get_format_from_dxgi(dxgi_format) {
+                    Some(f) => f,
+                    None => {
+                        return Err(Box::new(UnsupportedFormat {
+                            format: dxgi_format,
+                            container: "DDS".to_string(),
+                        }))
+                    }
+                };
+                if resource_dimension == D3D10_RESOURCE_DIMENSION_TEXTURE3D {
+                    (format, Dimension::ThreeD, max(1, header_depth))
+                } else if misc_flag & D3D10_RESOURCE_MISC_TEXTURECUBE != 0 {
+                    (format, Dimension::Cube, array_size * 6)
+                } else if array_size > 1 {
+                    (format, Dimension::TwoDArray, array_size)
+                } else {
+                    (format, Dimension::TwoD, 1)
+                }
+            } else {
+                let format =
+                    match get_legacy_format(pixel_format_flags, &four_cc, rgb_bit_count, masks) {
+                        Some((f, swap_red_blue, force_alpha)) => {
+                            swizzle = (swap_red_blue, force_alpha);
+                            f
+                        }
+                        None => {
+                            return Err(Box::new(UnsupportedFormat {
+                                format: read_u32(&mut &four_cc[..])?,
+                                container: "DDS".to_string(),
+                            }))
+                        }
+                    };
+                if caps2 & DDSCAPS2_CUBEMAP != 0 {
+                    (format, Dimension::Cube, 6)
+                } else if caps2 & DDSCAPS2_VOLUME != 0 && flags & DDSD_DEPTH != 0 {
+                    (format, Dimension::ThreeD, max(1, header_depth))
+                } else {
+                    (format, Dimension::TwoD, 1)
+                }
+            };
+        let nb_mipmaps = if flags & DDSD_MIPMAPCOUNT != 0 {
+            max(1, mip_count)
+        } else {
+            1
+        };
+
+        let mut mip_levels: Vec<Vec<Vec<u8>>> = vec![Vec::new(); nb_mipmaps as usize];
+        let (swap_red_blue, force_alpha) = swizzle;
+        let mut read_image = |level: u32| -> Result<Vec<u8>, Box<Error>> {
+            let size = get_image_size(format, max(1, width >> level), max(1, height >> level));
+            let mut data = vec![0u8; size];
+            reader.read_exact(&mut data)?;
+            if swap_red_blue || force_alpha {
+                for pixel in data.chunks_mut(4) {
+                    if swap_red_blue {
+                        pixel.swap(0, 2);
+                    }
+                    if force_alpha {
+                        pixel[3] = 0xFF;
+                    }
+                }
+            }
+            Ok(data)
+        };
+        // 3D textures are stored level by level, everything else layer by layer
+        if is_three_d(dimension) {
+            for level in 0..nb_mipmaps {
+                for _ in 0..max(1, depth >> level) {
+                    let image = read_image(level)?;
+                    mip_levels[level as usize].push(image);
+                }
+            }
+        } else {
+            for _ in 0..depth {
+                for level in 0..nb_mipmaps {
+                    let image = read_image(level)?;
+                    mip_levels[level as usize].push(image);
+                }
+            }
+        }
+
+        Ok(DDS {
+            format,
+            dimension,
+            width,
+            height,
+            depth,
+            mip_levels,
+        })
+    }
+}
+
+impl DDS {
+    // Every level and slice, deswizzled but still in the FTEX format
+    pub fn from_ftex<R: Read + Seek>(reader: &mut R, ftex: &FTEX) -> Result<DDS, Box<Error>> {
+        let header = &ftex.header;
+        let dimension = match header.dimension {
+            Dimension::OneD | Dimension::TwoD | Dimension::TwoDMSAA => Dimension::TwoD,
+            Dimension::OneDArray | Dimension::TwoDArray | Dimension::TwoDMSAAArray => {
+                Dimension::TwoDArray
+            }
+            d => d,
+        };
+        let mut mip_levels = Vec::new();
+        for level in 0..max(1, header.nb_mipmaps) {
+            mip_levels.push(ftex.get_slices(reader, level)?);
+        }
+        Ok(DDS {
+            format: header.texture_format,
+            dimension,
+            width: header.texture_width,
+            height: header.texture_height,
+            depth: max(1, header.texture_depth),
+            mip_levels,
+        })
+    }
+    pub fn get_nb_mipmaps(&self) -> u32 {
+        self.mip_levels.len() as u32
+    }
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Box<Error>> {
+        let format = self.format;
+        let (width, height) = (self.width, self.height);
+        let dxgi_format = match get_dxgi_format(format) {
+            Some(f) => f,
+            None => {
+                return Err(Box::new(UnsupportedFormat {
+                    format: format as u32,
+                    container: "DDS".to_string(),
+                }))
+            }
+        };
+        let three_d = is_three_d(self.dimension);
+        let cube = is_cube(self.dimension);
+        // Arrays of layers or of cube maps need the DX10 extension
+        let legacy_layout = match self.dimension {
+            Dimension::TwoD | Dimension::ThreeD => true,
+            Dimension::Cube => self.depth == 6,
+            _ => false,
+        };
+        let four_cc = if legacy_layout {
+            get_legacy_four_cc(format)
+        } else {
+            None
+        };
+        let legacy_rgba = legacy_layout
+            && match format {
+                Format::TcsR8G8B8A8Unorm => true,
+                _ => false,
+            };
+        let mip_count = self.get_nb_mipmaps();
+
+        let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+        let pitch_or_linear_size = if format.is_block_compressed() {
+            flags |= DDSD_LINEARSIZE;
+            get_image_size(format, width, height) as u32
+        } else if let Format::TNv12Unorm = format {
+            // The pitch of planar formats is the one of their luma plane
+            flags |= DDSD_PITCH;
+            width
+        } else {
+            flags |= DDSD_PITCH;
+            (width * format.get_bits_per_pixel()).div_ceil(8)
+        };
+        let mut caps = DDSCAPS_TEXTURE;
+        let mut caps2 = 0;
+        if mip_count > 1 {
+            flags |= DDSD_MIPMAPCOUNT;
+            caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+        if cube {
+            caps |= DDSCAPS_COMPLEX;
+            caps2 |= DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALLFACES;
+        }
+        if three_d {
+            flags |= DDSD_DEPTH;
+            caps |= DDSCAPS_COMPLEX;
+            caps2 |= DDSCAPS2_VOLUME;
+        }
+
+        writer.write_all(b"DDS ")?;
+        write_u32(writer, 124)?;
+        write_u32(writer, flags)?;
+        write_u32(writer, height)?;
+        write_u32(writer, width)?;
+        write_u32(writer, pitch_or_linear_size)?;
+        write_u32(writer, if three_d { self.depth } else { 0 })?;
+        write_u32(writer, mip_count)?;
+        writer.write_all(&[0u8; 44])?;
+        // Pixel format
+        write_u32(writer, 32)?;
+        if legacy_rgba {
+            write_u32(writer, DDPF_RGB | DDPF_ALPHAPIXELS)?;
+            write_u32(writer, 0)?;
+            write_u32(writer, 32)?;
+            write_u32(writer, 0x0000_00FF)?;
+            write_u32(writer, 0x0000_FF00)?;
+            write_u32(writer, 0x00FF_0000)?;
+            write_u32(writer, 0xFF00_0000)?;
+        } else {
+            write_u32(writer, DDPF_FOURCC)?;
+            writer.write_all(four_cc.unwrap_or(b"DX10"))?;
+            writer.write_all(&[0u8; 20])?;
+        }
+        write_u32(writer, caps)?;
+        write_u32(writer, caps2)?;
+        writer.write_all(&[0u8; 12])?;
+        if four_cc.is_none() && !legacy_rgba {
+            let (resource_dimension, misc_flag, array_size) = if three_d {
+                (D3D10_RESOURCE_DIMENSION_TEXTURE3D, 0, 1)
+            } else if cube {
+                (
+                    D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+                    D3D10_RESOURCE_MISC_TEXTURECUBE,
+                    max(1, self.depth / 6),
+                )
+            } else {
+                (D3D10_RESOURCE_DIMENSION_TEXTURE2D, 0, self.depth)
+            };
+            write_u32(writer, dxgi_format)?;
+            write_u32(writer, resource_dimension)?;
+            write_u32(writer, misc_flag)?;
+            write_u32(writer, array_size)?;
+            write_u32(writer, 0)?;
+        }
+
+        // Deswizzled levels can carry padding, only write what belongs to the image
+        let write_image = |writer: &mut W, level: usize, slice: usize| -> Result<(), Box<Error>> {
+            let size = get_image_size(format, max(1, width >> level), max(1, height >> level));
+            let data = &self.mip_levels[level][slice];
+            if data.len() < size {
+                return Err(Box::new(TextureDataTooShort {
+                    expected: size,
+                    got: data.len(),
+                }));
+            }
+            writer.write_all(&data[..size])?;
+            Ok(())
+        };
+        if three_d {
+            for (level, slices) in self.mip_levels.iter().enumerate() {
+                for slice in 0..slices.len() {
+                    write_image(writer, level, slice)?;
+                }
+            }
+        } else {
+            for slice in 0..self.depth as usize {
+                for level in 0..self.mip_levels.len() {
+                    write_image(writer, level, slice)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -73,7 +73,7 @@ pub fn check_magic_number<T: PartialEq + Sized + fmt::Debug>(
 
 #[derive(Debug)]
 pub struct IncorrectHeaderLength {
-    pub size: u16,
+    pub size: u32,
 }
 
 impl Error for IncorrectHeaderLength {
@@ -127,3 +127,478 @@ impl fmt::Display for IndexGroupTooLong {
         )
     }
 }
+
+#[derive(Debug)]
+pub struct TextureDataTooShort {
+    pub expected: usize,
+    pub got: usize,
+}
+
+impl Error for TextureDataTooShort {
+    fn description(&self) -> &str {
+        "Not enough texture data to decode the whole image"
+    }
+}
+
+impl fmt::Display for TextureDataTooShort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Expected at least 0x{:X} bytes of texture data, got 0x{:X}",
+            self.expected, self.got
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct MipLevelOutOfRange {
+    pub level: u32,
+    pub nb_mipmaps: u32,
+}
+
+impl Error for MipLevelOutOfRange {
+    fn description(&self) -> &str {
+        "Asked for a mip level that the texture does not have"
+    }
+}
+
+impl fmt::Display for MipLevelOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Mip level {} requested, but there are only {}",
+            self.level, self.nb_mipmaps
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct UnsupportedFormat {
+    pub format: u32,
+    pub container: String,
+}
+
+impl Error for UnsupportedFormat {
+    fn description(&self) -> &str {
+        "This texture format cannot be stored in this kind of file"
+    }
+}
+
+impl fmt::Display for UnsupportedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Format 0x{:X} is not supported by {}",
+            self.format, self.container
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct SliceOutOfRange {
+    pub slice: u32,
+    pub nb_slices: u32,
+}
+
+impl Error for SliceOutOfRange {
+    fn description(&self) -> &str {
+        "Asked for a slice that the texture does not have"
+    }
+}
+
+impl fmt::Display for SliceOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Slice {} requested, but there are only {}",
+            self.slice, self.nb_slices
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct SampleOutOfRange {
+    pub sample: u32,
+    pub nb_samples: u32,
+}
+
+impl Error for SampleOutOfRange {
+    fn description(&self) -> &str {
+        "Asked for a sample that the texture does not have"
+    }
+}
+
+impl fmt::Display for SampleOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Sample {} requested, but there are only {}",
+            self.sample, self.nb_samples
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct TextureNotFound {
+    pub name: String,
+}
+
+impl Error for TextureNotFound {
+    fn description(&self) -> &str {
+        "No texture in the file has this name"
+    }
+}
+
+impl fmt::Display for TextureNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a texture named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct FormatMismatch {
+    pub expected: u32,
+    pub got: u32,
+}
+
+impl Error for FormatMismatch {
+    fn description(&self) -> &str {
+        "The new texture does not have the format of the one it replaces"
+    }
+}
+
+impl fmt::Display for FormatMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Expected format 0x{:X}, got 0x{:X}",
+            self.expected, self.got
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct SliceCountMismatch {
+    pub expected: u32,
+    pub got: u32,
+}
+
+impl Error for SliceCountMismatch {
+    fn description(&self) -> &str {
+        "The new texture does not have as many slices as the one it replaces"
+    }
+}
+
+impl fmt::Display for SliceCountMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected {} slices, got {}", self.expected, self.got)
+    }
+}
+
+#[derive(Debug)]
+pub struct Yaz0DataInvalid {
+    pub position: usize,
+}
+
+impl Error for Yaz0DataInvalid {
+    fn description(&self) -> &str {
+        "The Yaz0 compressed data is truncated or refers to data before its start"
+    }
+}
+
+impl fmt::Display for Yaz0DataInvalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid Yaz0 data at 0x{:X}", self.position)
+    }
+}
+
+#[derive(Debug)]
+pub struct FileNotFound {
+    pub name: String,
+}
+
+impl Error for FileNotFound {
+    fn description(&self) -> &str {
+        "No file in the archive has this name"
+    }
+}
+
+impl fmt::Display for FileNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a file named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct MaterialNotFound {
+    pub name: String,
+}
+
+impl Error for MaterialNotFound {
+    fn description(&self) -> &str {
+        "No material in the template has this name"
+    }
+}
+
+impl fmt::Display for MaterialNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a material named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnsupportedPrimitive {
+    pub mode: String,
+}
+
+impl Error for UnsupportedPrimitive {
+    fn description(&self) -> &str {
+        "Only triangles can be turned into a model"
+    }
+}
+
+impl fmt::Display for UnsupportedPrimitive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unsupported primitive mode {}", self.mode)
+    }
+}
+
+#[derive(Debug)]
+pub struct VertexIndexOverflow {
+    pub index: u32,
+    pub skip_vertices: u32,
+}
+
+impl Error for VertexIndexOverflow {
+    fn description(&self) -> &str {
+        "Index is past the biggest vertex index once skipped vertices are added"
+    }
+}
+
+impl fmt::Display for VertexIndexOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Index {} does not fit with {} skipped vertices",
+            self.index, self.skip_vertices
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjDataInvalid {
+    pub line: usize,
+}
+
+impl Error for ObjDataInvalid {
+    fn description(&self) -> &str {
+        "The OBJ file has a line that could not be understood"
+    }
+}
+
+impl fmt::Display for ObjDataInvalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid OBJ data on line {}", self.line)
+    }
+}
+
+#[derive(Debug)]
+pub struct TooManyVertices {
+    pub count: usize,
+    pub maximum: usize,
+}
+
+impl Error for TooManyVertices {
+    fn description(&self) -> &str {
+        "The mesh has more vertices than its indices can refer to"
+    }
+}
+
+impl fmt::Display for TooManyVertices {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} vertices, at most {} can be used",
+            self.count, self.maximum
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct GltfBufferInvalid {
+    pub index: usize,
+}
+
+impl Error for GltfBufferInvalid {
+    fn description(&self) -> &str {
+        "A buffer of the glTF file is missing or too short"
+    }
+}
+
+impl fmt::Display for GltfBufferInvalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not read glTF buffer {}", self.index)
+    }
+}
+
+#[derive(Debug)]
+pub struct ModelNotFound {
+    pub name: String,
+}
+
+impl Error for ModelNotFound {
+    fn description(&self) -> &str {
+        "No model in the file has this name"
+    }
+}
+
+impl fmt::Display for ModelNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a model named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct ShapeNotFound {
+    pub name: String,
+}
+
+impl Error for ShapeNotFound {
+    fn description(&self) -> &str {
+        "No shape in the model has this name"
+    }
+}
+
+impl fmt::Display for ShapeNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a shape named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct BoneNotFound {
+    pub name: String,
+}
+
+impl Error for BoneNotFound {
+    fn description(&self) -> &str {
+        "No bone in the skeleton has this name"
+    }
+}
+
+impl fmt::Display for BoneNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not find a bone named \"{}\"", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct BoneNotSkinned {
+    pub name: String,
+    pub skin_count: u8,
+}
+
+impl Error for BoneNotSkinned {
+    fn description(&self) -> &str {
+        "The bone has no matrix for the skinning of the shape"
+    }
+}
+
+impl fmt::Display for BoneNotSkinned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Bone \"{}\" has no matrix for vertices skinned to {} bones",
+            self.name, self.skin_count
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct MeshOutOfRange {
+    pub index: usize,
+    pub nb_meshes: usize,
+}
+
+impl Error for MeshOutOfRange {
+    fn description(&self) -> &str {
+        "Asked for a mesh that the scene does not have"
+    }
+}
+
+impl fmt::Display for MeshOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Mesh {} requested, but there are only {}",
+            self.index, self.nb_meshes
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct BoneOutOfRange {
+    pub index: usize,
+    pub nb_bones: usize,
+}
+
+impl Error for BoneOutOfRange {
+    fn description(&self) -> &str {
+        "A vertex refers to a bone that the scene does not have"
+    }
+}
+
+impl fmt::Display for BoneOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Bone {} used, but there are only {}",
+            self.index, self.nb_bones
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct MeshDataTooShort {
+    pub name: String,
+    pub data: String,
+    pub expected: usize,
+    pub got: usize,
+}
+
+impl Error for MeshDataTooShort {
+    fn description(&self) -> &str {
+        "The mesh does not have a value for every vertex"
+    }
+}
+
+impl fmt::Display for MeshDataTooShort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Mesh \"{}\" has {} {}, expected one per vertex ({})",
+            self.name, self.got, self.data, self.expected
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct AttributeNotFound {
+    pub name: String,
+}
+
+impl Error for AttributeNotFound {
+    fn description(&self) -> &str {
+        "The vertices do not have this attribute"
+    }
+}
+
+impl fmt::Display for AttributeNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Could not find a vertex attribute named \"{}\"",
+            self.name
+        )
+    }
+}
@@ -0,0 +1,1521 @@
+use error::{MaterialNotFound, TooManyVertices};
+use fres::fmdl::fmat::material_parameter::MaterialParameter;
+use fres::fmdl::fmat::render_info_parameter::RenderInfoValues;
+use fres::fmdl::fmat::FMAT;
+use fres::fmdl::fshp::lod_model::IndexFormat;
+use fres::fmdl::fshp::visibility_group::BoundingBox;
+use fres::fmdl::fvtx::attributes::AttributesFormats;
+use fres::fmdl::fvtx::encode::encode_attribute;
+use fres::ftex::FTEX;
+use fres::writer::{Label, Target, Writer};
+use fres::{Version, FRES};
+use scene::{invert, transform_normal, transform_point, Matrix, Scene, IDENTITY};
+use std::cmp::max;
+use std::error::Error;
+use std::io::{Cursor, Read, Seek};
+use util::{read_text_entry, Importable};
+
+// How a model gets built, formats are the ones used for every shape
+pub struct BuildOptions {
+    // Name of the file and of the model in it
+    pub name: String,
+    // Material of the template used for scene materials it does not have, the first one if None
+    pub template_material: Option<String>,
+    pub position_format: AttributesFormats,
+    pub normal_format: AttributesFormats,
+    pub texture_coordinate_format: AttributesFormats,
+    pub color_format: AttributesFormats,
+    // Picked from the vertex count if None
+    pub index_format: Option<IndexFormat>,
+}
+
+impl BuildOptions {
+    pub fn new(name: &str) -> BuildOptions {
+        BuildOptions {
+            name: name.to_string(),
+            template_material: None,
+            position_format: AttributesFormats::ThreeF32,
+            normal_format: AttributesFormats::ThreeI10toThreeF32,
+            texture_coordinate_format: AttributesFormats::TwoF32,
+            color_format: AttributesFormats::FourU8ToFourF32,
+            index_format: None,
+        }
+    }
+}
+
+pub const BUFFER_ALIGNMENT: usize = 0x40;
+
+// What is kept from the template file
+struct Template {
+    version: Version,
+    materials: Vec<(String, MaterialTemplate)>,
+    textures: Vec<(String, TextureTemplate)>,
+}
+
+#[derive(Clone)]
+struct MaterialTemplate {
+    flags: u32,
+    render_infos: Vec<(String, RenderInfoTemplate)>,
+    render_state: Option<Vec<u8>>,
+    shader_assign: Option<ShaderAssignTemplate>,
+    texture_names: Vec<String>,
+    samplers: Vec<SamplerTemplate>,
+    parameters: Vec<(String, ParameterTemplate)>,
+    parameter_data: Vec<u8>,
+    raw_parameter_data_length: u16,
+    volatile_parameter_count: u16,
+    volatile_flags: Vec<u8>,
+}
+
+type NamedMaterial = (String, MaterialTemplate);
+
+// Name of a texture in the new file, and the index of the template texture it copies
+type NamedTexture = (String, usize);
+
+#[derive(Clone)]
+enum RenderInfoTemplate {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Text(Vec<String>),
+}
+
+#[derive(Clone)]
+struct ShaderAssignTemplate {
+    shader_archive_name: String,
+    shading_model_name: String,
+    revision: u32,
+    attribute_assigns: Vec<(String, String)>,
+    sampler_assigns: Vec<(String, String)>,
+    shader_options: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+struct SamplerTemplate {
+    gx2_sampler: [u32; 3],
+    handle: u32,
+    name: String,
+    index: u8,
+}
+
+#[derive(Clone)]
+struct ParameterTemplate {
+    parameter_type: u8,
+    data_length: u8,
+    data_offset: u16,
+    uniform_offset: i32,
+    callback_pointer: u32,
+    depended_index: u16,
+    depend_index: u16,
+}
+
+struct TextureTemplate {
+    // Everything before the pointers, which do not mean anything out of the file
+    header: Vec<u8>,
+    path: String,
+    data: Vec<u8>,
+    mipmaps: Vec<u8>,
+    alignment: usize,
+}
+
+// A shape ready to be written, with its buffers encoded
+struct Shape {
+    name: String,
+    material_index: u16,
+    bone_index: u16,
+    skin_count: u8,
+    skin_bone_indices: Vec<u16>,
+    vertex_count: u32,
+    attributes: Vec<(String, AttributesFormats, Vec<u8>)>,
+    index_format: IndexFormat,
+    index_count: u32,
+    indices: Vec<u8>,
+    center: [f32; 3],
+    extent: [f32; 3],
+    radius: f32,
+}
+
+// Gives a new BFRES, uncompressed, holding one model made from the scene. The template gives the
+// version and the materials, which keep their shaders and parameters, and the textures they use.
+pub fn build_bfres(
+    scene: &Scene,
+    template: &[u8],
+    options: &BuildOptions,
+) -> Result<Vec<u8>, Box<Error>> {
+    let template = read_template(template)?;
+    let (materials, textures) = pick_materials(scene, &template, options)?;
+    let bones = get_bones(scene, options);
+    let world_matrices = if scene.bones.is_empty() {
+        vec![IDENTITY]
+    } else {
+        scene.get_bone_world_matrices()
+    };
+    let inverse_bind_matrices: Vec<Matrix> = bones
+        .iter()
+        .zip(world_matrices.iter())
+        .map(|(bone, world)| match bone.inverse_bind_matrix {
+            Some(m) => m,
+            None => invert(world),
+        })
+        .collect();
+
+    // Rigid and smooth skinning each have their own matrices, the smooth ones come first
+    let mut smooth_bones = Vec::new();
+    let mut rigid_bones = Vec::new();
+    for mesh in &scene.meshes {
+        match get_skin_count(scene, mesh) {
+            0 => {}
+            1 => rigid_bones.extend(mesh.bone_indices.iter().map(|b| usize::from(b[0]))),
+            _ => {
+                for (indices, weights) in mesh.bone_indices.iter().zip(mesh.bone_weights.iter()) {
+                    for (&i, &w) in indices.iter().zip(weights.iter()) {
+                        if w > 0f32 {
+                            smooth_bones.push(usize::from(i));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    smooth_bones.sort();
+    smooth_bones.dedup();
+    rigid_bones.sort();
+    rigid_bones.dedup();
+    let mut matrix_indices = vec![(None, None); bones.len()];
+    for (i, &b) in smooth_bones.iter().enumerate() {
+        matrix_indices[b].0 = Some(i as u16);
+    }
+    for (i, &b) in rigid_bones.iter().enumerate() {
+        matrix_indices[b].1 = Some((smooth_bones.len() + i) as u16);
+    }
+
+    let mut shape_names = Vec::new();
+    let mut shapes = Vec::new();
+    for mesh in &scene.meshes {
+        if mesh.indices.is_empty() {
+            continue;
+        }
+        let name = make_unique(&mut shape_names, &mesh.name);
+        shapes.push(make_shape(
+            scene,
+            mesh,
+            name,
+            &inverse_bind_matrices,
+            &matrix_indices,
+            options,
+        )?);
+    }
+
+    let mut writer = Writer::new();
+    let fmdl_label = writer.new_label();
+    let fskl_label = writer.new_label();
+    let fvtx_labels: Vec<Label> = shapes.iter().map(|_| writer.new_label()).collect();
+    let fshp_labels: Vec<Label> = shapes.iter().map(|_| writer.new_label()).collect();
+    let fmat_labels: Vec<Label> = materials.iter().map(|_| writer.new_label()).collect();
+    let ftex_labels: Vec<Label> = textures.iter().map(|_| writer.new_label()).collect();
+    let string_table_label = writer.new_label();
+
+    // FRES header
+    writer.write_bytes(b"FRES");
+    writer.write_bytes(&template.version.numbers);
+    writer.write_u16(0xFEFF);
+    writer.write_u16(0x10);
+    writer.write_u32(0); // File length
+    writer.write_u32(0); // Alignment
+    writer.write_text_pointer(&options.name);
+    writer.write_u32(0); // String table length
+    writer.write_label_pointer(string_table_label);
+    let model_group_label = writer.new_label();
+    let texture_group_label = writer.new_label();
+    writer.write_label_pointer(model_group_label);
+    if textures.is_empty() {
+        writer.write_pointer(Target::Null);
+    } else {
+        writer.write_label_pointer(texture_group_label);
+    }
+    for _ in 2..12 {
+        writer.write_pointer(Target::Null);
+    }
+    writer.write_u16(1);
+    writer.write_u16(textures.len() as u16);
+    for _ in 2..12 {
+        writer.write_u16(0);
+    }
+    writer.write_u32(0); // User pointer
+    writer.place(model_group_label);
+    writer.write_index_group(vec![(options.name.clone(), Target::Label(fmdl_label))]);
+    if !textures.is_empty() {
+        writer.place(texture_group_label);
+        writer.write_index_group(
+            textures
+                .iter()
+                .zip(ftex_labels.iter())
+                .map(|(t, &l)| (t.0.clone(), Target::Label(l)))
+                .collect(),
+        );
+    }
+
+    // FMDL
+    let fvtx_array_label = writer.new_label();
+    let fshp_group_label = writer.new_label();
+    let fmat_group_label = writer.new_label();
+    writer.place(fmdl_label);
+    writer.write_bytes(b"FMDL");
+    writer.write_text_pointer(&options.name);
+    writer.write_text_pointer("");
+    writer.write_label_pointer(fskl_label);
+    writer.write_label_pointer(fvtx_array_label);
+    writer.write_label_pointer(fshp_group_label);
+    writer.write_label_pointer(fmat_group_label);
+    writer.write_pointer(Target::Null);
+    writer.write_u16(shapes.len() as u16);
+    writer.write_u16(shapes.len() as u16);
+    writer.write_u16(materials.len() as u16);
+    writer.write_u16(0);
+    writer.write_u32(shapes.iter().map(|s| s.vertex_count).sum());
+    writer.write_u32(0);
+    writer.place(fshp_group_label);
+    writer.write_index_group(
+        shapes
+            .iter()
+            .zip(fshp_labels.iter())
+            .map(|(s, &l)| (s.name.clone(), Target::Label(l)))
+            .collect(),
+    );
+    writer.place(fmat_group_label);
+    writer.write_index_group(
+        materials
+            .iter()
+            .zip(fmat_labels.iter())
+            .map(|(m, &l)| (m.0.clone(), Target::Label(l)))
+            .collect(),
+    );
+
+    // Vertex buffers are found as an array
+    writer.place(fvtx_array_label);
+    let mut fvtx_contents = Vec::new();
+    for (index, (shape, &label)) in shapes.iter().zip(fvtx_labels.iter()).enumerate() {
+        fvtx_contents.push(write_fvtx_header(&mut writer, shape, index as u16, label));
+    }
+    for (shape, labels) in shapes.iter().zip(fvtx_contents) {
+        write_fvtx_contents(&mut writer, shape, labels);
+    }
+
+    let has_radius_array = template.version.has_radius_array();
+    for (index, (shape, &label)) in shapes.iter().zip(fshp_labels.iter()).enumerate() {
+        write_fshp(
+            &mut writer,
+            shape,
+            index as u16,
+            label,
+            fvtx_labels[index],
+            has_radius_array,
+        );
+    }
+
+    for (index, (material, &label)) in materials.iter().zip(fmat_labels.iter()).enumerate() {
+        let ftex_targets: Vec<Option<Label>> = material
+            .1
+            .texture_names
+            .iter()
+            .map(|n| {
+                textures
+                    .iter()
+                    .position(|t| &t.0 == n)
+                    .map(|i| ftex_labels[i])
+            })
+            .collect();
+        write_fmat(
+            &mut writer,
+            &material.0,
+            &material.1,
+            index as u16,
+            label,
+            &ftex_targets,
+        );
+    }
+
+    write_fskl(
+        &mut writer,
+        &bones,
+        &inverse_bind_matrices,
+        &smooth_bones,
+        &rigid_bones,
+        &matrix_indices,
+        fskl_label,
+    );
+
+    for ((name, index), &label) in textures.iter().zip(ftex_labels.iter()) {
+        write_ftex(&mut writer, name, &template.textures[*index].1, label);
+    }
+
+    writer.align(4);
+    writer.place(string_table_label);
+    let (_, string_table_length) = writer.write_string_table();
+    writer.set_u32(0x18, string_table_length as u32);
+    let alignment = max(4, writer.write_deferred_data());
+    writer.align(4);
+    let file_length = writer.position();
+    writer.set_u32(0xC, file_length as u32);
+    writer.set_u32(0x10, alignment as u32);
+    Ok(writer.finish())
+}
+
+fn read_template(data: &[u8]) -> Result<Template, Box<Error>> {
+    let mut cursor = Cursor::new(data);
+    let fres = FRES::import(&mut cursor)?;
+    let mut materials = Vec::new();
+    if let Some(fmdl_index_group) = fres.sub_file_index_groups.model_data {
+        for fmdl_entry in fmdl_index_group.entries {
+            let fmdl = fmdl_entry.get_data(&mut cursor)?;
+            for fmat_entry in fmdl.fmat_index_group.entries {
+                let name = fmat_entry.get_name(&mut cursor)?;
+                let fmat = fmat_entry.get_data(&mut cursor)?;
+                materials.push((name, read_material(&fmat, &mut cursor)?));
+            }
+        }
+    }
+    let mut textures = Vec::new();
+    if let Some(ftex_index_group) = fres.sub_file_index_groups.texture_data {
+        for ftex_entry in ftex_index_group.entries {
+            let name = ftex_entry.get_name(&mut cursor)?;
+            let ftex: FTEX = ftex_entry.get_data(&mut cursor)?;
+            ftex_entry.data_pointer.seek_abs_pos(&mut cursor)?;
+            let mut header = vec![0u8; 0xA8];
+            cursor.read_exact(&mut header)?;
+            let path = if ftex.header.file_path_offset.points_to == 0 {
+                String::new()
+            } else {
+                ftex.header.file_path_offset.seek_abs_pos(&mut cursor)?;
+                read_text_entry(&mut cursor)?
+            };
+            let data = ftex.get_raw_data(&mut cursor)?;
+            let mut mipmaps = vec![0u8; ftex.header.mipmaps_data_length as usize];
+            if ftex.header.mipmap_offset.points_to != 0 {
+                ftex.header.mipmap_offset.seek_abs_pos(&mut cursor)?;
+                cursor.read_exact(&mut mipmaps)?;
+            }
+            textures.push((
+                name,
+                TextureTemplate {
+                    header,
+                    path,
+                    data,
+                    mipmaps,
+                    alignment: max(1, ftex.header.alignment as usize),
+                },
+            ));
+        }
+    }
+    Ok(Template {
+        version: fres.header.version,
+        materials,
+        textures,
+    })
+}
+
+fn read_material<R: Read + Seek>(
+    fmat: &FMAT,
+    reader: &mut R,
+) -> Result<MaterialTemplate, Box<Error>> {
+    let mut render_infos = Vec::new();
+    if let Some(index_group) = fmat.get_render_info_parameters(reader)? {
+        for entry in &index_group.entries {
+            let name = entry.get_name(reader)?;
+            let parameter = entry.get_data(reader)?;
+            let values = match parameter.values {
+                RenderInfoValues::Int(ref v) => RenderInfoTemplate::Int(v.clone()),
+                RenderInfoValues::Float(ref v) => RenderInfoTemplate::Float(v.clone()),
+                RenderInfoValues::Text(_) => RenderInfoTemplate::Text(parameter.get_texts(reader)?),
+            };
+            render_infos.push((name, values));
+        }
+    }
+    let render_state = if fmat.header.render_state_offset.points_to == 0 {
+        None
+    } else {
+        fmat.header.render_state_offset.seek_abs_pos(reader)?;
+        let mut data = vec![0u8; 0x30];
+        reader.read_exact(&mut data)?;
+        Some(data)
+    };
+    let shader_assign = match fmat.get_shader_assign(reader)? {
+        Some(s) => Some(ShaderAssignTemplate {
+            shader_archive_name: s.get_shader_archive_name(reader)?,
+            shading_model_name: s.get_shading_model_name(reader)?,
+            revision: s.revision,
+            attribute_assigns: s.get_attribute_assigns(reader)?,
+            sampler_assigns: s.get_sampler_assigns(reader)?,
+            shader_options: s.get_shader_options(reader)?,
+        }),
+        None => None,
+    };
+    let mut texture_names = Vec::new();
+    for entry in &fmat.get_texture_references(reader)?.entries {
+        texture_names.push(entry.get_data(reader)?.get_name(reader)?);
+    }
+    let mut samplers = Vec::new();
+    for entry in &fmat.get_texture_samplers(reader)?.entries {
+        let sampler = entry.get_data(reader)?;
+        samplers.push(SamplerTemplate {
+            gx2_sampler: sampler.gx2_sampler,
+            handle: sampler.handle,
+            name: sampler.get_name(reader)?,
+            index: sampler.index,
+        });
+    }
+    let mut parameters = Vec::new();
+    for entry in &fmat.get_material_parameters(reader)?.entries {
+        let parameter: MaterialParameter = entry.get_data(reader)?;
+        parameters.push((
+            parameter.get_name(reader)?,
+            ParameterTemplate {
+                parameter_type: parameter.parameter_type,
+                data_length: parameter.data_length,
+                data_offset: parameter.data_offset,
+                uniform_offset: parameter.uniform_offset,
+                callback_pointer: parameter.callback_pointer,
+                depended_index: parameter.depended_index,
+                depend_index: parameter.depend_index,
+            },
+        ));
+    }
+    Ok(MaterialTemplate {
+        flags: fmat.header.material_flags,
+        render_infos,
+        render_state,
+        shader_assign,
+        texture_names,
+        samplers,
+        parameters,
+        parameter_data: fmat.get_material_parameter_data(reader)?,
+        raw_parameter_data_length: fmat.header.raw_parameter_data_length,
+        volatile_parameter_count: fmat.header.volatile_parameter_count,
+        volatile_flags: fmat.get_volatile_flags_data(reader)?,
+    })
+}
+
+// A template material for each scene material, and the template textures they end up using, by
+// name in the new file
+fn pick_materials(
+    scene: &Scene,
+    template: &Template,
+    options: &BuildOptions,
+) -> Result<(Vec<NamedMaterial>, Vec<NamedTexture>), Box<Error>> {
+    let mut names = Vec::new();
+    let mut materials = Vec::new();
+    let mut textures: Vec<NamedTexture> = Vec::new();
+    for scene_material in &scene.materials {
+        let fallback = match options.template_material {
+            Some(ref n) => template.materials.iter().find(|m| &m.0 == n),
+            None => template.materials.first(),
+        };
+        let source = match template
+            .materials
+            .iter()
+            .find(|m| m.0 == scene_material.name)
+            .or(fallback)
+        {
+            Some(m) => m,
+            None => {
+                return Err(Box::new(MaterialNotFound {
+                    name: match options.template_material {
+                        Some(ref n) => n.clone(),
+                        None => scene_material.name.clone(),
+                    },
+                }))
+            }
+        };
+        let mut material = source.1.clone();
+        let original_names = material.texture_names.clone();
+        // The albedo texture follows the scene, the others stay as they are
+        if let Some(ref texture_name) = scene_material.texture_name {
+            if !material.texture_names.is_empty() {
+                let slot = match material.samplers.iter().position(|s| s.name == "_a0") {
+                    Some(i) if i < material.texture_names.len() => i,
+                    _ => 0,
+                };
+                material.texture_names[slot] = texture_name.clone();
+            }
+        }
+        // Renamed textures start as a copy of the one they replace, so they can be injected
+        for (name, original) in material.texture_names.iter().zip(original_names.iter()) {
+            if textures.iter().any(|t| &t.0 == name) {
+                continue;
+            }
+            let found = template
+                .textures
+                .iter()
+                .position(|t| &t.0 == name)
+                .or_else(|| template.textures.iter().position(|t| &t.0 == original));
+            if let Some(index) = found {
+                textures.push((name.clone(), index));
+            }
+        }
+        materials.push((make_unique(&mut names, &scene_material.name), material));
+    }
+    Ok((materials, textures))
+}
+
+fn get_bones(scene: &Scene, options: &BuildOptions) -> Vec<::scene::Bone> {
+    if scene.bones.is_empty() {
+        return vec![::scene::Bone::new(&options.name)];
+    }
+    let mut names = Vec::new();
+    scene
+        .bones
+        .iter()
+        .map(|b| ::scene::Bone {
+            name: make_unique(&mut names, &b.name),
+            parent_index: b.parent_index,
+            scale: b.scale,
+            rotation: b.rotation,
+            translation: b.translation,
+            inverse_bind_matrix: b.inverse_bind_matrix,
+        })
+        .collect()
+}
+
+fn make_unique(names: &mut Vec<String>, name: &str) -> String {
+    let mut unique = name.to_string();
+    let mut i = 1;
+    while names.contains(&unique) {
+        unique = format!("{}_{}", name, i);
+        i += 1;
+    }
+    names.push(unique.clone());
+    unique
+}
+
+// Most bones one vertex of the mesh uses
+fn get_skin_count(scene: &Scene, mesh: &::scene::Mesh) -> u8 {
+    if scene.bones.is_empty() || mesh.bone_indices.is_empty() {
+        return 0;
+    }
+    let count = mesh
+        .bone_weights
+        .iter()
+        .map(|w| w.iter().filter(|&&w| w > 0f32).count())
+        .max()
+        .unwrap_or(0);
+    max(1, count) as u8
+}
+
+fn make_shape(
+    scene: &Scene,
+    mesh: &::scene::Mesh,
+    name: String,
+    inverse_bind_matrices: &[Matrix],
+    matrix_indices: &[(Option<u16>, Option<u16>)],
+    options: &BuildOptions,
+) -> Result<Shape, Box<Error>> {
+    let skin_count = get_skin_count(scene, mesh);
+    let vertex_count = mesh.positions.len();
+    // Smooth skinned vertices stay in model space, the others go in the space of their bone
+    let mut positions = mesh.positions.clone();
+    let mut normals = mesh.normals.clone();
+    for i in 0..vertex_count {
+        let matrix = match skin_count {
+            0 => &inverse_bind_matrices[0],
+            1 => &inverse_bind_matrices[usize::from(mesh.bone_indices[i][0])],
+            _ => continue,
+        };
+        positions[i] = transform_point(matrix, &positions[i]);
+        if let Some(n) = normals.get_mut(i) {
+            *n = transform_normal(matrix, n);
+        }
+    }
+
+    let mut attributes = Vec::new();
+    let values: Vec<[f32; 4]> = positions.iter().map(|p| [p[0], p[1], p[2], 1f32]).collect();
+    attributes.push(make_attribute("_p0", options.position_format, &values));
+    if !normals.is_empty() {
+        let values: Vec<[f32; 4]> = normals.iter().map(|n| [n[0], n[1], n[2], 0f32]).collect();
+        attributes.push(make_attribute("_n0", options.normal_format, &values));
+    }
+    for (i, set) in mesh.texture_coordinates.iter().enumerate() {
+        let values: Vec<[f32; 4]> = set.iter().map(|t| [t[0], t[1], 0f32, 0f32]).collect();
+        attributes.push(make_attribute(
+            &format!("_u{}", i),
+            options.texture_coordinate_format,
+            &values,
+        ));
+    }
+    if !mesh.colors.is_empty() {
+        attributes.push(make_attribute("_c0", options.color_format, &mesh.colors));
+    }
+
+    let mut skin_bone_indices = Vec::new();
+    if skin_count > 0 {
+        let count = usize::from(skin_count);
+        let index_format = match skin_count {
+            1 => AttributesFormats::U8ToU32,
+            2 => AttributesFormats::TwoU8ToTwoU32,
+            _ => AttributesFormats::FourU8ToFourU32,
+        };
+        let mut values = Vec::with_capacity(vertex_count);
+        for indices in &mesh.bone_indices {
+            let mut value = [0f32; 4];
+            for (v, &i) in value.iter_mut().zip(indices.iter()).take(count) {
+                let entry = matrix_indices[usize::from(i)];
+                let matrix_index = if skin_count == 1 { entry.1 } else { entry.0 };
+                *v = f32::from(matrix_index.unwrap_or(0));
+                skin_bone_indices.push(i);
+            }
+            values.push(value);
+        }
+        attributes.push(make_attribute("_i0", index_format, &values));
+        if skin_count > 1 {
+            let weight_format = if skin_count == 2 {
+                AttributesFormats::TwoU8ToTwoF32
+            } else {
+                AttributesFormats::FourU8ToFourF32
+            };
+            let components = weight_format.get_component_count();
+            let mut data = Vec::with_capacity(vertex_count * components);
+            for weights in &mesh.bone_weights {
+                data.extend_from_slice(&quantize_weights(&weights[..components]));
+            }
+            attributes.push(("_w0".to_string(), weight_format, data));
+        }
+        skin_bone_indices.sort();
+        skin_bone_indices.dedup();
+    }
+
+    let index_format = match options.index_format {
+        Some(f) => f,
+        None if vertex_count <= 0x10000 => IndexFormat::U16BigEndian,
+        None => IndexFormat::U32BigEndian,
+    };
+    let maximum = match index_format {
+        IndexFormat::U16LittleEndian | IndexFormat::U16BigEndian => 0x10000,
+        IndexFormat::U32LittleEndian | IndexFormat::U32BigEndian => u32::MAX as usize,
+    };
+    if vertex_count > maximum {
+        return Err(Box::new(TooManyVertices {
+            count: vertex_count,
+            maximum,
+        }));
+    }
+    let indices = encode_indices(&mesh.indices, index_format);
+
+    let (center, extent, radius) = get_bounds(&positions);
+
+    Ok(Shape {
+        name,
+        material_index: mesh.material_index as u16,
+        bone_index: 0,
+        skin_count,
+        skin_bone_indices,
+        vertex_count: vertex_count as u32,
+        attributes,
+        index_format,
+        index_count: mesh.indices.len() as u32,
+        indices,
+        center,
+        extent,
+        radius,
+    })
+}
+
+// Bounds of the vertices as stored, like the game checks them: center, half of the size on each
+// axis and radius of the sphere around the center
+pub fn get_bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3], f32) {
+    match BoundingBox::from_points(positions) {
+        Some(b) => (b.center, b.extent, b.get_radius(positions)),
+        None => ([0f32; 3], [0f32; 3], 0f32),
+    }
+}
+
+pub fn encode_indices(indices: &[u32], format: IndexFormat) -> Vec<u8> {
+    let mut data = Vec::new();
+    for &i in indices {
+        match format {
+            IndexFormat::U16LittleEndian => data.extend_from_slice(&[i as u8, (i >> 8) as u8]),
+            IndexFormat::U16BigEndian => data.extend_from_slice(&[(i >> 8) as u8, i as u8]),
+            IndexFormat::U32LittleEndian => {
+                data.extend_from_slice(&[i as u8, (i >> 8) as u8, (i >> 16) as u8, (i >> 24) as u8])
+            }
+            IndexFormat::U32BigEndian => {
+                data.extend_from_slice(&[(i >> 24) as u8, (i >> 16) as u8, (i >> 8) as u8, i as u8])
+            }
+        }
+    }
+    data
+}
+
+fn make_attribute(
+    name: &str,
+    format: AttributesFormats,
+    values: &[[f32; 4]],
+) -> (String, AttributesFormats, Vec<u8>) {
+    (name.to_string(), format, encode_attribute(format, values))
+}
+
+// Weights as bytes which always add up to 255, the rounding error goes to the biggest one
+pub fn quantize_weights(weights: &[f32]) -> Vec<u8> {
+    let total: f32 = weights.iter().sum();
+    let mut bytes: Vec<u8> = weights
+        .iter()
+        .map(|&w| {
+            if total > 0f32 {
+                (w / total * 255f32).round() as u8
+            } else {
+                0
+            }
+        })
+        .collect();
+    let sum: i32 = bytes.iter().map(|&b| i32::from(b)).sum();
+    if total > 0f32 {
+        bytes[0] = (i32::from(bytes[0]) + 255 - sum) as u8;
+    }
+    bytes
+}
+
+struct FvtxLabels {
+    attribute_array: Label,
+    attribute_group: Label,
+    buffer_array: Label,
+}
+
+fn write_fvtx_header(writer: &mut Writer, shape: &Shape, index: u16, label: Label) -> FvtxLabels {
+    let labels = FvtxLabels {
+        attribute_array: writer.new_label(),
+        attribute_group: writer.new_label(),
+        buffer_array: writer.new_label(),
+    };
+    writer.place(label);
+    writer.write_bytes(b"FVTX");
+    writer.write_u8(shape.attributes.len() as u8);
+    writer.write_u8(shape.attributes.len() as u8);
+    writer.write_u16(index);
+    writer.write_u32(shape.vertex_count);
+    writer.write_u8(shape.skin_count);
+    writer.write_bytes(&[0u8; 3]);
+    writer.write_label_pointer(labels.attribute_array);
+    writer.write_label_pointer(labels.attribute_group);
+    writer.write_label_pointer(labels.buffer_array);
+    writer.write_u32(0);
+    labels
+}
+
+// One buffer per attribute
+fn write_fvtx_contents(writer: &mut Writer, shape: &Shape, labels: FvtxLabels) {
+    let attribute_labels: Vec<Label> = shape
+        .attributes
+        .iter()
+        .map(|_| writer.new_label())
+        .collect();
+    writer.place(labels.attribute_array);
+    for (i, (attribute, &label)) in shape
+        .attributes
+        .iter()
+        .zip(attribute_labels.iter())
+        .enumerate()
+    {
+        writer.place(label);
+        writer.write_text_pointer(&attribute.0);
+        writer.write_u8(i as u8);
+        writer.write_u8(0);
+        writer.write_u16(0);
+        writer.write_u32(attribute.1 as u32);
+    }
+    writer.place(labels.attribute_group);
+    writer.write_index_group(
+        shape
+            .attributes
+            .iter()
+            .zip(attribute_labels.iter())
+            .map(|(a, &l)| (a.0.clone(), Target::Label(l)))
+            .collect(),
+    );
+    writer.place(labels.buffer_array);
+    for attribute in &shape.attributes {
+        let data_label = writer.defer_data(attribute.2.clone(), BUFFER_ALIGNMENT);
+        write_buffer_info(
+            writer,
+            attribute.2.len(),
+            attribute.1.get_size(),
+            data_label,
+        );
+    }
+}
+
+pub fn write_buffer_info(writer: &mut Writer, size: usize, stride: u16, data: Label) {
+    writer.write_u32(0);
+    writer.write_u32(size as u32);
+    writer.write_u32(0);
+    writer.write_u16(stride);
+    writer.write_u16(1);
+    writer.write_u32(0);
+    writer.write_label_pointer(data);
+}
+
+fn write_fshp(
+    writer: &mut Writer,
+    shape: &Shape,
+    index: u16,
+    label: Label,
+    fvtx_label: Label,
+    has_radius_array: bool,
+) {
+    let radius_label = writer.new_label();
+    let lod_label = writer.new_label();
+    let skin_bones_label = writer.new_label();
+    let node_label = writer.new_label();
+    let bounding_label = writer.new_label();
+    let bounding_index_label = writer.new_label();
+    writer.place(label);
+    writer.write_bytes(b"FSHP");
+    writer.write_text_pointer(&shape.name);
+    writer.write_u32(2);
+    writer.write_u16(index);
+    writer.write_u16(shape.material_index);
+    writer.write_u16(shape.bone_index);
+    writer.write_u16(index);
+    writer.write_u16(shape.skin_bone_indices.len() as u16);
+    writer.write_u8(shape.skin_count);
+    writer.write_u8(1); // LOD models
+    writer.write_u8(0); // Key shapes
+    writer.write_u8(0); // Target attributes
+    writer.write_u16(1); // Bounding nodes
+                         // From v4.5, there is one radius per LOD model
+    if has_radius_array {
+        writer.write_label_pointer(radius_label);
+    } else {
+        writer.write_f32(shape.radius);
+    }
+    writer.write_label_pointer(fvtx_label);
+    writer.write_label_pointer(lod_label);
+    if shape.skin_bone_indices.is_empty() {
+        writer.write_pointer(Target::Null);
+    } else {
+        writer.write_label_pointer(skin_bones_label);
+    }
+    writer.write_pointer(Target::Null);
+    writer.write_label_pointer(node_label);
+    writer.write_label_pointer(bounding_label);
+    writer.write_label_pointer(bounding_index_label);
+    writer.write_u32(0);
+
+    write_lod_model(
+        writer,
+        lod_label,
+        shape.index_format,
+        shape.index_count,
+        shape.indices.clone(),
+    );
+
+    if has_radius_array {
+        writer.place(radius_label);
+        writer.write_f32(shape.radius);
+    }
+    if !shape.skin_bone_indices.is_empty() {
+        writer.place(skin_bones_label);
+        for &i in &shape.skin_bone_indices {
+            writer.write_u16(i);
+        }
+        writer.align(4);
+    }
+    write_bounding(
+        writer,
+        [node_label, bounding_label, bounding_index_label],
+        shape.center,
+        shape.extent,
+    );
+}
+
+// A single LOD model drawing every triangle
+pub fn write_lod_model(
+    writer: &mut Writer,
+    label: Label,
+    index_format: IndexFormat,
+    index_count: u32,
+    indices: Vec<u8>,
+) {
+    let submesh_label = writer.new_label();
+    let index_buffer_label = writer.new_label();
+    writer.place(label);
+    writer.write_u32(4); // Triangles
+    writer.write_u32(index_format as u32);
+    writer.write_u32(index_count);
+    writer.write_u16(1);
+    writer.write_u16(0);
+    writer.write_label_pointer(submesh_label);
+    writer.write_label_pointer(index_buffer_label);
+    writer.write_u32(0);
+    writer.place(submesh_label);
+    writer.write_u32(0);
+    writer.write_u32(index_count);
+    writer.place(index_buffer_label);
+    let index_size = index_format.get_size() as u16;
+    let length = indices.len();
+    let index_data = writer.defer_data(indices, BUFFER_ALIGNMENT);
+    write_buffer_info(writer, length, index_size, index_data);
+}
+
+// One bounding node holding the whole shape. Labels are for the nodes, the boxes and the indices.
+pub fn write_bounding(writer: &mut Writer, labels: [Label; 3], center: [f32; 3], extent: [f32; 3]) {
+    writer.place(labels[0]);
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(1);
+    writer.place(labels[1]);
+    for &v in center.iter().chain(extent.iter()) {
+        writer.write_f32(v);
+    }
+    writer.place(labels[2]);
+    writer.write_u16(0);
+    writer.align(4);
+}
+
+fn write_fmat(
+    writer: &mut Writer,
+    name: &str,
+    material: &MaterialTemplate,
+    index: u16,
+    label: Label,
+    ftex_targets: &[Option<Label>],
+) {
+    let render_info_group_label = writer.new_label();
+    let render_state_label = writer.new_label();
+    let shader_assign_label = writer.new_label();
+    let texture_array_label = writer.new_label();
+    let sampler_array_label = writer.new_label();
+    let sampler_group_label = writer.new_label();
+    let parameter_array_label = writer.new_label();
+    let parameter_group_label = writer.new_label();
+    let parameter_data_label = writer.new_label();
+    let volatile_flags_label = writer.new_label();
+    let optional = |present: bool, label: Label| {
+        if present {
+            Target::Label(label)
+        } else {
+            Target::Null
+        }
+    };
+    writer.place(label);
+    writer.write_bytes(b"FMAT");
+    writer.write_text_pointer(name);
+    writer.write_u32(material.flags);
+    writer.write_u16(index);
+    writer.write_u16(material.render_infos.len() as u16);
+    writer.write_u8(material.texture_names.len() as u8);
+    writer.write_u8(material.samplers.len() as u8);
+    writer.write_u16(material.parameters.len() as u16);
+    writer.write_u16(material.volatile_parameter_count);
+    writer.write_u16(material.parameter_data.len() as u16);
+    writer.write_u16(material.raw_parameter_data_length);
+    writer.write_u16(0); // User data
+    writer.write_pointer(optional(
+        !material.render_infos.is_empty(),
+        render_info_group_label,
+    ));
+    writer.write_pointer(optional(
+        material.render_state.is_some(),
+        render_state_label,
+    ));
+    writer.write_pointer(optional(
+        material.shader_assign.is_some(),
+        shader_assign_label,
+    ));
+    writer.write_pointer(optional(
+        !material.texture_names.is_empty(),
+        texture_array_label,
+    ));
+    writer.write_pointer(optional(!material.samplers.is_empty(), sampler_array_label));
+    writer.write_pointer(optional(!material.samplers.is_empty(), sampler_group_label));
+    writer.write_pointer(optional(
+        !material.parameters.is_empty(),
+        parameter_array_label,
+    ));
+    writer.write_pointer(optional(
+        !material.parameters.is_empty(),
+        parameter_group_label,
+    ));
+    writer.write_pointer(optional(
+        !material.parameter_data.is_empty(),
+        parameter_data_label,
+    ));
+    writer.write_pointer(Target::Null);
+    writer.write_pointer(optional(
+        material.volatile_parameter_count > 0,
+        volatile_flags_label,
+    ));
+    writer.write_u32(0);
+
+    if !material.render_infos.is_empty() {
+        let labels: Vec<Label> = material
+            .render_infos
+            .iter()
+            .map(|_| writer.new_label())
+            .collect();
+        writer.place(render_info_group_label);
+        writer.write_index_group(
+            material
+                .render_infos
+                .iter()
+                .zip(labels.iter())
+                .map(|(r, &l)| (r.0.clone(), Target::Label(l)))
+                .collect(),
+        );
+        for (render_info, &label) in material.render_infos.iter().zip(labels.iter()) {
+            writer.place(label);
+            match render_info.1 {
+                RenderInfoTemplate::Int(ref values) => {
+                    writer.write_u16(values.len() as u16);
+                    writer.write_u8(0);
+                    writer.write_u8(0);
+                    writer.write_text_pointer(&render_info.0);
+                    for &v in values {
+                        writer.write_i32(v);
+                    }
+                }
+                RenderInfoTemplate::Float(ref values) => {
+                    writer.write_u16(values.len() as u16);
+                    writer.write_u8(1);
+                    writer.write_u8(0);
+                    writer.write_text_pointer(&render_info.0);
+                    for &v in values {
+                        writer.write_f32(v);
+                    }
+                }
+                RenderInfoTemplate::Text(ref values) => {
+                    writer.write_u16(values.len() as u16);
+                    writer.write_u8(2);
+                    writer.write_u8(0);
+                    writer.write_text_pointer(&render_info.0);
+                    for v in values {
+                        writer.write_text_pointer(v);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(ref render_state) = material.render_state {
+        writer.place(render_state_label);
+        writer.write_bytes(render_state);
+    }
+
+    if let Some(ref shader_assign) = material.shader_assign {
+        let group_labels = [writer.new_label(), writer.new_label(), writer.new_label()];
+        let groups = [
+            &shader_assign.attribute_assigns,
+            &shader_assign.sampler_assigns,
+            &shader_assign.shader_options,
+        ];
+        writer.place(shader_assign_label);
+        writer.write_text_pointer(&shader_assign.shader_archive_name);
+        writer.write_text_pointer(&shader_assign.shading_model_name);
+        writer.write_u32(shader_assign.revision);
+        writer.write_u8(shader_assign.attribute_assigns.len() as u8);
+        writer.write_u8(shader_assign.sampler_assigns.len() as u8);
+        writer.write_u16(shader_assign.shader_options.len() as u16);
+        for (group, &label) in groups.iter().zip(group_labels.iter()) {
+            writer.write_pointer(optional(!group.is_empty(), label));
+        }
+        for (group, &label) in groups.iter().zip(group_labels.iter()) {
+            if !group.is_empty() {
+                writer.place(label);
+                writer.write_index_group(
+                    group
+                        .iter()
+                        .map(|a| (a.0.clone(), Target::Text(a.1.clone())))
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    if !material.texture_names.is_empty() {
+        writer.place(texture_array_label);
+        for (name, target) in material.texture_names.iter().zip(ftex_targets.iter()) {
+            writer.write_text_pointer(name);
+            match *target {
+                Some(l) => writer.write_label_pointer(l),
+                None => writer.write_pointer(Target::Null),
+            }
+        }
+    }
+
+    if !material.samplers.is_empty() {
+        let labels: Vec<Label> = material
+            .samplers
+            .iter()
+            .map(|_| writer.new_label())
+            .collect();
+        writer.place(sampler_array_label);
+        for (sampler, &label) in material.samplers.iter().zip(labels.iter()) {
+            writer.place(label);
+            for &v in &sampler.gx2_sampler {
+                writer.write_u32(v);
+            }
+            writer.write_u32(sampler.handle);
+            writer.write_text_pointer(&sampler.name);
+            writer.write_u8(sampler.index);
+            writer.write_bytes(&[0u8; 3]);
+        }
+        writer.place(sampler_group_label);
+        writer.write_index_group(
+            material
+                .samplers
+                .iter()
+                .zip(labels.iter())
+                .map(|(s, &l)| (s.name.clone(), Target::Label(l)))
+                .collect(),
+        );
+    }
+
+    if !material.parameters.is_empty() {
+        let labels: Vec<Label> = material
+            .parameters
+            .iter()
+            .map(|_| writer.new_label())
+            .collect();
+        writer.place(parameter_array_label);
+        for ((name, parameter), &label) in material.parameters.iter().zip(labels.iter()) {
+            writer.place(label);
+            writer.write_u8(parameter.parameter_type);
+            writer.write_u8(parameter.data_length);
+            writer.write_u16(parameter.data_offset);
+            writer.write_i32(parameter.uniform_offset);
+            writer.write_u32(parameter.callback_pointer);
+            writer.write_u16(parameter.depended_index);
+            writer.write_u16(parameter.depend_index);
+            writer.write_text_pointer(name);
+        }
+        writer.place(parameter_group_label);
+        writer.write_index_group(
+            material
+                .parameters
+                .iter()
+                .zip(labels.iter())
+                .map(|(p, &l)| (p.0.clone(), Target::Label(l)))
+                .collect(),
+        );
+    }
+
+    if !material.parameter_data.is_empty() {
+        writer.place(parameter_data_label);
+        writer.write_bytes(&material.parameter_data);
+        writer.align(4);
+    }
+
+    if material.volatile_parameter_count > 0 {
+        writer.place(volatile_flags_label);
+        writer.write_bytes(&material.volatile_flags);
+        writer.align(4);
+    }
+}
+
+fn write_fskl(
+    writer: &mut Writer,
+    bones: &[::scene::Bone],
+    inverse_bind_matrices: &[Matrix],
+    smooth_bones: &[usize],
+    rigid_bones: &[usize],
+    matrix_indices: &[(Option<u16>, Option<u16>)],
+    label: Label,
+) {
+    let bone_group_label = writer.new_label();
+    let bone_array_label = writer.new_label();
+    let matrix_bones_label = writer.new_label();
+    let inverse_matrices_label = writer.new_label();
+    let nb_matrices = smooth_bones.len() + rigid_bones.len();
+    writer.place(label);
+    writer.write_bytes(b"FSKL");
+    writer.write_u32(0x100); // Standard scaling, quaternion rotations
+    writer.write_u16(bones.len() as u16);
+    writer.write_u16(smooth_bones.len() as u16);
+    writer.write_u16(rigid_bones.len() as u16);
+    writer.write_u16(0);
+    writer.write_label_pointer(bone_group_label);
+    writer.write_label_pointer(bone_array_label);
+    if nb_matrices == 0 {
+        writer.write_pointer(Target::Null);
+    } else {
+        writer.write_label_pointer(matrix_bones_label);
+    }
+    if smooth_bones.is_empty() {
+        writer.write_pointer(Target::Null);
+    } else {
+        writer.write_label_pointer(inverse_matrices_label);
+    }
+    writer.write_u32(0);
+
+    let bone_labels: Vec<Label> = bones.iter().map(|_| writer.new_label()).collect();
+    writer.place(bone_group_label);
+    writer.write_index_group(
+        bones
+            .iter()
+            .zip(bone_labels.iter())
+            .map(|(b, &l)| (b.name.clone(), Target::Label(l)))
+            .collect(),
+    );
+    writer.place(bone_array_label);
+    let mut hierarchy_flags = Vec::with_capacity(bones.len());
+    for (index, (bone, &bone_label)) in bones.iter().zip(bone_labels.iter()).enumerate() {
+        let transform_flags = get_transform_flags(bone);
+        // Whatever holds for the bone and every one of its parents
+        let hierarchy = transform_flags >> 1
+            & match bone.parent_index {
+                Some(p) => hierarchy_flags[p],
+                None => 0xF,
+            };
+        hierarchy_flags.push(hierarchy);
+        writer.place(bone_label);
+        writer.write_text_pointer(&bone.name);
+        writer.write_u16(index as u16);
+        writer.write_u16(match bone.parent_index {
+            Some(p) => p as u16,
+            None => 0xFFFF,
+        });
+        writer.write_i16(matrix_indices[index].0.map_or(-1, |i| i as i16));
+        writer.write_i16(matrix_indices[index].1.map_or(-1, |i| i as i16));
+        writer.write_i16(-1); // Billboard
+        writer.write_u16(0); // User data
+        writer.write_u32(1 | transform_flags << 23 | hierarchy << 28);
+        for &v in bone
+            .scale
+            .iter()
+            .chain(bone.rotation.iter())
+            .chain(bone.translation.iter())
+        {
+            writer.write_f32(v);
+        }
+        writer.write_pointer(Target::Null);
+    }
+
+    if nb_matrices > 0 {
+        writer.place(matrix_bones_label);
+        for &b in smooth_bones.iter().chain(rigid_bones.iter()) {
+            writer.write_u16(b as u16);
+        }
+        writer.align(4);
+    }
+    // Three rows of four for each smooth matrix
+    if !smooth_bones.is_empty() {
+        writer.place(inverse_matrices_label);
+        for &b in smooth_bones {
+            let m = &inverse_bind_matrices[b];
+            for row in 0..3 {
+                for column in m {
+                    writer.write_f32(column[row]);
+                }
+            }
+        }
+    }
+}
+
+// Scale uniform, scale volume one, no rotation and no translation, above segment scale
+// compensation which is never used here
+fn get_transform_flags(bone: &::scene::Bone) -> u32 {
+    let s = bone.scale;
+    let mut flags = 0;
+    if s[0] == s[1] && s[1] == s[2] {
+        flags |= 0b00010;
+    }
+    if s[0] * s[1] * s[2] == 1f32 {
+        flags |= 0b00100;
+    }
+    if bone.rotation == [0f32, 0f32, 0f32, 1f32] {
+        flags |= 0b01000;
+    }
+    if bone.translation == [0f32; 3] {
+        flags |= 0b10000;
+    }
+    flags
+}
+
+fn write_ftex(writer: &mut Writer, name: &str, texture: &TextureTemplate, label: Label) {
+    let data_label = writer.defer_data(texture.data.clone(), texture.alignment);
+    writer.align(4);
+    writer.place(label);
+    writer.write_bytes(&texture.header);
+    writer.write_text_pointer(name);
+    writer.write_text_pointer(&texture.path);
+    writer.write_label_pointer(data_label);
+    if texture.mipmaps.is_empty() {
+        writer.write_pointer(Target::Null);
+    } else {
+        let mipmaps_label = writer.defer_data(texture.mipmaps.clone(), texture.alignment);
+        writer.write_label_pointer(mipmaps_label);
+    }
+    writer.write_pointer(Target::Null); // User data
+    writer.write_u16(0);
+    writer.write_u16(0);
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use fres::fmdl::FMDL;
+    use scene::{Bone, Mesh};
+
+    // A template without models nor textures, only there for its version
+    pub fn make_template() -> Vec<u8> {
+        let mut writer = Writer::new();
+        let string_table_label = writer.new_label();
+        writer.write_bytes(b"FRES");
+        writer.write_bytes(&[3, 4, 0, 4]);
+        writer.write_u16(0xFEFF);
+        writer.write_u16(0x10);
+        writer.write_u32(0); // File length
+        writer.write_u32(4); // Alignment
+        writer.write_text_pointer("template");
+        writer.write_u32(0); // String table length
+        writer.write_label_pointer(string_table_label);
+        for _ in 0..12 {
+            writer.write_pointer(Target::Null);
+        }
+        for _ in 0..12 {
+            writer.write_u16(0);
+        }
+        writer.write_u32(0); // User pointer
+        writer.place(string_table_label);
+        let (_, string_table_length) = writer.write_string_table();
+        writer.set_u32(0x18, string_table_length as u32);
+        let file_length = writer.position();
+        writer.set_u32(0xC, file_length as u32);
+        writer.finish()
+    }
+
+    pub fn make_mesh(name: &str, nb_quads: usize) -> Mesh {
+        let mut mesh = Mesh {
+            name: name.to_string(),
+            material_index: 0,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            texture_coordinates: vec![Vec::new()],
+            colors: Vec::new(),
+            bone_indices: Vec::new(),
+            bone_weights: Vec::new(),
+            indices: Vec::new(),
+        };
+        for i in 0..nb_quads {
+            let start = mesh.positions.len() as u32;
+            for &(x, y) in &[(0f32, 0f32), (1f32, 0f32), (1f32, 1f32), (0f32, 1f32)] {
+                mesh.positions.push([x + i as f32, y, 0f32]);
+                mesh.normals.push([0f32, 0f32, 1f32]);
+                mesh.texture_coordinates[0].push([x, y]);
+            }
+            for &j in &[0, 1, 2, 0, 2, 3] {
+                mesh.indices.push(start + j);
+            }
+        }
+        mesh
+    }
+
+    fn make_scene() -> Scene {
+        let mut empty = make_mesh("empty", 0);
+        empty.positions.push([0f32; 3]);
+        Scene {
+            meshes: vec![make_mesh("body", 3), make_mesh("body", 1), empty],
+            materials: Vec::new(),
+            bones: Vec::new(),
+        }
+    }
+
+    fn get_model(bfres: &[u8], name: &str) -> (FMDL, Cursor<Vec<u8>>) {
+        let mut cursor = Cursor::new(bfres.to_vec());
+        let fres = FRES::import(&mut cursor).unwrap();
+        let models = fres.sub_file_index_groups.model_data.unwrap();
+        let fmdl = models
+            .find(&mut cursor, name)
+            .unwrap()
+            .unwrap()
+            .get_data(&mut cursor)
+            .unwrap();
+        (fmdl, cursor)
+    }
+
+    #[test]
+    fn build_and_read_back() {
+        let bfres =
+            build_bfres(&make_scene(), &make_template(), &BuildOptions::new("Test")).unwrap();
+        let (fmdl, mut cursor) = get_model(&bfres, "Test");
+        assert!(fmdl
+            .fshp_index_group
+            .find(&mut cursor, "Tes")
+            .unwrap()
+            .is_none());
+        // The mesh without triangles is left out, and names stay unique
+        assert_eq!(fmdl.header.fshp_count, 2);
+        assert_eq!(fmdl.header.total_nb_vertices, 16);
+        assert!(fmdl
+            .fshp_index_group
+            .find(&mut cursor, "empty")
+            .unwrap()
+            .is_none());
+        for &(name, nb_quads) in &[("body", 3), ("body_1", 1)] {
+            let entry = fmdl.fshp_index_group.find(&mut cursor, name).unwrap();
+            let fshp = entry.unwrap().get_data(&mut cursor).unwrap();
+            assert_eq!(fshp.header.vertex_skin_count, 0);
+            let fvtx = fmdl.fvtx_array.entries[usize::from(fshp.header.fvtx_index)]
+                .get_data(&mut cursor)
+                .unwrap();
+            assert_eq!(fvtx.header.nb_vertices, nb_quads * 4);
+            let positions = fvtx
+                .get_attribute_values(&mut cursor, "_p0")
+                .unwrap()
+                .unwrap();
+            let mesh = make_mesh(name, nb_quads as usize);
+            for (read, original) in positions.iter().zip(mesh.positions.iter()) {
+                assert_eq!(&read[..3], &original[..]);
+            }
+            let lod_models = fshp.get_lod_models(&mut cursor).unwrap();
+            assert_eq!(lod_models.len(), 1);
+            assert_eq!(lod_models[0].nb_points, nb_quads * 6);
+            assert_eq!(
+                lod_models[0].get_indices(&mut cursor).unwrap(),
+                mesh.indices
+            );
+        }
+        // Without bones in the scene, the skeleton has one named after the model
+        assert!(fmdl.fskl.bones.find(&mut cursor, "Test").unwrap().is_some());
+    }
+
+    #[test]
+    fn build_skinned() {
+        let mut scene = make_scene();
+        scene.bones.push(Bone::new("root"));
+        let mut arm = Bone::new("arm");
+        arm.parent_index = Some(0);
+        arm.translation = [0f32, 2f32, 0f32];
+        scene.bones.push(arm);
+        scene.meshes[0].bone_indices = vec![[1, 0, 0, 0]; 12];
+        scene.meshes[0].bone_weights = vec![[1f32, 0f32, 0f32, 0f32]; 12];
+        scene.meshes[1].bone_indices = vec![[1, 0, 0, 0]; 4];
+        scene.meshes[1].bone_weights = vec![[0.75f32, 0.25f32, 0f32, 0f32]; 4];
+        let bfres = build_bfres(&scene, &make_template(), &BuildOptions::new("Test")).unwrap();
+        let (fmdl, mut cursor) = get_model(&bfres, "Test");
+        for &name in &["root", "arm"] {
+            assert!(fmdl.fskl.bones.find(&mut cursor, name).unwrap().is_some());
+        }
+        for &(name, skin_count) in &[("body", 1), ("body_1", 2)] {
+            let entry = fmdl.fshp_index_group.find(&mut cursor, name).unwrap();
+            let fshp = entry.unwrap().get_data(&mut cursor).unwrap();
+            assert_eq!(fshp.header.vertex_skin_count, skin_count);
+        }
+        // Rigid vertices are stored in the space of their bone
+        let fvtx = fmdl.fvtx_array.entries[0].get_data(&mut cursor).unwrap();
+        let positions = fvtx
+            .get_attribute_values(&mut cursor, "_p0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&positions[2][..3], &[1f32, -1f32, 0f32]);
+    }
+
+    #[test]
+    fn missing_template_material() {
+        let mut scene = make_scene();
+        scene.materials.push(::scene::Material {
+            name: "skin".to_string(),
+            texture_name: None,
+        });
+        assert!(build_bfres(&scene, &make_template(), &BuildOptions::new("Test")).is_err());
+    }
+}
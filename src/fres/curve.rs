@@ -0,0 +1,495 @@
+use error::UnrecognizedValue;
+use ez_io::ReadE;
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Seek};
+use util::{read_packed_bools, DataArray, Importable, Pointer};
+
+pub struct Curve {
+    pub frame_type: FrameType,
+    pub key_type: KeyType,
+    pub curve_type: CurveType,
+    pub nb_keys: u16,
+    pub target_offset: u32,
+    pub start_frame: f32,
+    pub end_frame: f32,
+    pub data_scale: f32,
+    pub data_offset: f32,
+    pub data_delta: f32,
+    pub frames_offset: Pointer,
+    pub keys_offset: Pointer,
+    pub frames: Vec<f32>,
+    pub keys: Keys,
+}
+
+#[derive(Copy, Clone)]
+pub enum FrameType {
+    F32 = 0,
+    Fixed16 = 1,
+    U8 = 2,
+}
+
+#[derive(Copy, Clone)]
+pub enum KeyType {
+    F32 = 0,
+    I16 = 1,
+    I8 = 2,
+}
+
+#[derive(Copy, Clone)]
+pub enum CurveType {
+    CubicFloat = 0,
+    LinearFloat = 1,
+    BakedFloat = 2,
+    StepInt = 4,
+    BakedInt = 5,
+    StepBool = 6,
+    BakedBool = 7,
+}
+
+// Keys are already scaled and offset
+pub enum Keys {
+    Cubic(Vec<[f32; 4]>),
+    Linear(Vec<[f32; 2]>),
+    Single(Vec<f32>),
+    Boolean(Vec<bool>),
+}
+
+impl Importable for Curve {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Curve, Box<Error>> {
+        let flags = reader.read_be_to_u16()?;
+        let frame_type = match flags & 0b11 {
+            0 => FrameType::F32,
+            1 => FrameType::Fixed16,
+            2 => FrameType::U8,
+            x => {
+                return Err(Box::new(UnrecognizedValue {
+                    value: x,
+                    enum_name: "FrameType".to_string(),
+                }))
+            }
+        };
+        let key_type = match (flags & 0b1100) >> 2 {
+            0 => KeyType::F32,
+            1 => KeyType::I16,
+            2 => KeyType::I8,
+            x => {
+                return Err(Box::new(UnrecognizedValue {
+                    value: x,
+                    enum_name: "KeyType".to_string(),
+                }))
+            }
+        };
+        let curve_type = match (flags & 0b111_0000) >> 4 {
+            0 => CurveType::CubicFloat,
+            1 => CurveType::LinearFloat,
+            2 => CurveType::BakedFloat,
+            4 => CurveType::StepInt,
+            5 => CurveType::BakedInt,
+            6 => CurveType::StepBool,
+            7 => CurveType::BakedBool,
+            x => {
+                return Err(Box::new(UnrecognizedValue {
+                    value: x,
+                    enum_name: "CurveType".to_string(),
+                }))
+            }
+        };
+        let nb_keys = reader.read_be_to_u16()?;
+        let target_offset = reader.read_be_to_u32()?;
+        let start_frame = reader.read_be_to_f32()?;
+        let end_frame = reader.read_be_to_f32()?;
+        // Scale and Offset are stored as integers for integer curves
+        let raw_data_scale = reader.read_be_to_u32()?;
+        let raw_data_offset = reader.read_be_to_u32()?;
+        let (data_scale, data_offset) = if curve_type.is_integer() {
+            (raw_data_scale as i32 as f32, raw_data_offset as i32 as f32)
+        } else {
+            (
+                f32::from_bits(raw_data_scale),
+                f32::from_bits(raw_data_offset),
+            )
+        };
+        let data_delta = reader.read_be_to_f32()?;
+        let frames_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let keys_offset = Pointer::read_new_rel_i32_be(reader)?;
+        // Frames
+        frames_offset.seek_abs_pos(reader)?;
+        let mut frames = Vec::with_capacity(usize::from(nb_keys));
+        for _ in 0..nb_keys {
+            frames.push(match frame_type {
+                FrameType::F32 => reader.read_be_to_f32()?,
+                FrameType::Fixed16 => f32::from(reader.read_be_to_i16()?) / 32f32,
+                FrameType::U8 => f32::from(reader.read_to_u8()?),
+            });
+        }
+        // Keys
+        keys_offset.seek_abs_pos(reader)?;
+        let keys = if curve_type.is_boolean() {
+            Keys::Boolean(read_packed_bools(reader, usize::from(nb_keys))?)
+        } else {
+            let read_key = |reader: &mut R| -> Result<f32, Box<Error>> {
+                Ok(match key_type {
+                    KeyType::F32 => reader.read_be_to_f32()?,
+                    KeyType::I16 => f32::from(reader.read_be_to_i16()?),
+                    KeyType::I8 => f32::from(reader.read_to_u8()? as i8),
+                })
+            };
+            match curve_type {
+                CurveType::CubicFloat => {
+                    let mut cubic = Vec::with_capacity(usize::from(nb_keys));
+                    for _ in 0..nb_keys {
+                        cubic.push([
+                            read_key(reader)? * data_scale + data_offset,
+                            read_key(reader)? * data_scale,
+                            read_key(reader)? * data_scale,
+                            read_key(reader)? * data_scale,
+                        ]);
+                    }
+                    Keys::Cubic(cubic)
+                }
+                CurveType::LinearFloat => {
+                    let mut linear = Vec::with_capacity(usize::from(nb_keys));
+                    for _ in 0..nb_keys {
+                        linear.push([
+                            read_key(reader)? * data_scale + data_offset,
+                            read_key(reader)? * data_scale,
+                        ]);
+                    }
+                    Keys::Linear(linear)
+                }
+                CurveType::BakedFloat => {
+                    let mut single = Vec::with_capacity(usize::from(nb_keys));
+                    for _ in 0..nb_keys {
+                        single.push(read_key(reader)? * data_scale + data_offset);
+                    }
+                    Keys::Single(single)
+                }
+                _ => {
+                    // Integer curves only get offset
+                    let mut single = Vec::with_capacity(usize::from(nb_keys));
+                    for _ in 0..nb_keys {
+                        single.push(read_key(reader)? + data_offset);
+                    }
+                    Keys::Single(single)
+                }
+            }
+        };
+        Ok(Curve {
+            frame_type,
+            key_type,
+            curve_type,
+            nb_keys,
+            target_offset,
+            start_frame,
+            end_frame,
+            data_scale,
+            data_offset,
+            data_delta,
+            frames_offset,
+            keys_offset,
+            frames,
+            keys,
+        })
+    }
+}
+
+impl Curve {
+    // Index of the key active at this frame, and how far we are towards the next one (0 to 1)
+    fn locate(&self, frame: f32) -> (usize, f32) {
+        if self.frames.is_empty() || frame <= self.frames[0] {
+            return (0, 0f32);
+        }
+        let last = self.frames.len() - 1;
+        if frame >= self.frames[last] {
+            return (last, 0f32);
+        }
+        let mut index = 0;
+        while (index < last) && (self.frames[index + 1] <= frame) {
+            index += 1;
+        }
+        let span = self.frames[index + 1] - self.frames[index];
+        let ratio = if span > 0f32 {
+            (frame - self.frames[index]) / span
+        } else {
+            0f32
+        };
+        (index, ratio)
+    }
+
+    // Boolean curves return 1 or 0
+    pub fn evaluate(&self, frame: f32) -> f32 {
+        let (index, t) = self.locate(frame);
+        match self.keys {
+            Keys::Cubic(ref keys) => match keys.get(index) {
+                Some(k) => k[0] + (k[1] * t) + (k[2] * t * t) + (k[3] * t * t * t),
+                None => 0f32,
+            },
+            Keys::Linear(ref keys) => match keys.get(index) {
+                Some(k) => k[0] + (k[1] * t),
+                None => 0f32,
+            },
+            Keys::Single(ref keys) => match keys.get(index) {
+                Some(k) => *k,
+                None => 0f32,
+            },
+            Keys::Boolean(ref keys) => match keys.get(index) {
+                Some(&true) => 1f32,
+                _ => 0f32,
+            },
+        }
+    }
+
+    pub fn evaluate_int(&self, frame: f32) -> i32 {
+        self.evaluate(frame).round() as i32
+    }
+
+    pub fn evaluate_bool(&self, frame: f32) -> bool {
+        let (index, _) = self.locate(frame);
+        match self.keys {
+            Keys::Boolean(ref keys) => keys.get(index).cloned().unwrap_or(false),
+            _ => self.evaluate(frame) != 0f32,
+        }
+    }
+}
+
+impl CurveType {
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            CurveType::StepInt | CurveType::BakedInt => true,
+            _ => false,
+        }
+    }
+    pub fn is_boolean(&self) -> bool {
+        match *self {
+            CurveType::StepBool | CurveType::BakedBool => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for CurveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            CurveType::CubicFloat => "Cubic Float",
+            CurveType::LinearFloat => "Linear Float",
+            CurveType::BakedFloat => "Baked Float",
+            CurveType::StepInt => "Step Integer",
+            CurveType::BakedInt => "Baked Integer",
+            CurveType::StepBool => "Step Boolean",
+            CurveType::BakedBool => "Baked Boolean",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+// Overwrites values of an animation data block with what its curves give at this frame, curves
+// target a byte offset in that block and every value is 4 bytes long
+pub fn animate_values<R: Read + Seek>(
+    reader: &mut R,
+    curve_array: &DataArray<Curve>,
+    frame: f32,
+    values: &mut [f32],
+) -> Result<(), Box<Error>> {
+    for curve_entry in &curve_array.entries {
+        let curve = curve_entry.get_data(reader)?;
+        if let Some(v) = values.get_mut((curve.target_offset / 4) as usize) {
+            *v = curve.evaluate(frame);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use fres::writer::{Target, Writer};
+    use std::io::Cursor;
+
+    pub struct TestCurve {
+        pub flags: u16,
+        pub target_offset: u32,
+        pub data_scale: u32,
+        pub data_offset: u32,
+        pub frames: Vec<u8>,
+        pub keys: Vec<u8>,
+        pub nb_keys: u16,
+    }
+
+    pub fn to_bytes(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| v.to_bits().to_be_bytes().to_vec())
+            .collect()
+    }
+
+    // Frames and keys are deferred, to go wherever the caller writes its deferred data
+    pub fn write_curves(writer: &mut Writer, curves: &[TestCurve]) {
+        for curve in curves {
+            writer.write_u16(curve.flags);
+            writer.write_u16(curve.nb_keys);
+            writer.write_u32(curve.target_offset);
+            writer.write_f32(0f32);
+            writer.write_f32(100f32);
+            writer.write_u32(curve.data_scale);
+            writer.write_u32(curve.data_offset);
+            writer.write_f32(0f32);
+            let frames = writer.defer_data(curve.frames.clone(), 4);
+            writer.write_pointer(Target::Label(frames));
+            let keys = writer.defer_data(curve.keys.clone(), 4);
+            writer.write_pointer(Target::Label(keys));
+        }
+    }
+
+    fn make_curves(curves: &[TestCurve]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        write_curves(&mut writer, curves);
+        writer.write_deferred_data();
+        writer.finish()
+    }
+
+    fn read_curve(curve: TestCurve) -> Curve {
+        Curve::import(&mut Cursor::new(make_curves(&[curve]))).unwrap()
+    }
+
+    // Frames 0, 10 and 20, integer keys 1, 5 and -2 moved up by 10
+    fn step_curve() -> TestCurve {
+        TestCurve {
+            flags: 0x44,
+            target_offset: 0x8,
+            data_scale: 1,
+            data_offset: 10,
+            frames: to_bytes(&[0f32, 10f32, 20f32]),
+            keys: vec![0x00, 0x01, 0x00, 0x05, 0xFF, 0xFE],
+            nb_keys: 3,
+        }
+    }
+
+    // Goes from 2 to 6 between frames 0 and 10, keys are stored halved and 1 lower
+    pub fn linear_curve() -> TestCurve {
+        TestCurve {
+            flags: 0x10,
+            target_offset: 0x4,
+            data_scale: 2f32.to_bits(),
+            data_offset: 1f32.to_bits(),
+            frames: to_bytes(&[0f32, 10f32]),
+            keys: to_bytes(&[0.5, 2f32, 2.5, 0f32]),
+            nb_keys: 2,
+        }
+    }
+
+    // Hermite segments stored as coefficients, frames as fixed point numbers
+    fn cubic_curve() -> TestCurve {
+        TestCurve {
+            flags: 0x01,
+            target_offset: 0x0,
+            data_scale: 1f32.to_bits(),
+            data_offset: 0,
+            frames: vec![0x00, 0x00, 0x01, 0x40, 0x02, 0x80],
+            keys: to_bytes(&[
+                1f32, 2f32, 3f32, -1f32, 5f32, -4f32, 0f32, 0f32, 1f32, 0f32, 0f32, 0f32,
+            ]),
+            nb_keys: 3,
+        }
+    }
+
+    // One key a frame, frames stored as bytes
+    fn baked_bool_curve() -> TestCurve {
+        TestCurve {
+            flags: 0x72,
+            target_offset: 0x10,
+            data_scale: 0,
+            data_offset: 0,
+            frames: vec![0, 1, 2, 3, 4, 0, 0, 0],
+            keys: vec![0x00, 0x00, 0x00, 0b0_1101],
+            nb_keys: 5,
+        }
+    }
+
+    #[test]
+    fn step_int() {
+        let curve = read_curve(step_curve());
+        assert!(curve.curve_type.is_integer());
+        for &(frame, value) in &[
+            (-5f32, 11),
+            (0f32, 11),
+            (9.9, 11),
+            (10f32, 15),
+            (15f32, 15),
+            (20f32, 8),
+            (30f32, 8),
+        ] {
+            assert_eq!(curve.evaluate_int(frame), value, "Frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn linear_float() {
+        let curve = read_curve(linear_curve());
+        for &(frame, value) in &[
+            (0f32, 2f32),
+            (2.5, 3f32),
+            (5f32, 4f32),
+            (10f32, 6f32),
+            (12f32, 6f32),
+        ] {
+            assert!(
+                (curve.evaluate(frame) - value).abs() < 1e-5,
+                "Frame {}",
+                frame
+            );
+        }
+    }
+
+    #[test]
+    fn cubic_float() {
+        let curve = read_curve(cubic_curve());
+        assert_eq!(curve.frames, vec![0f32, 10f32, 20f32]);
+        for &(frame, value) in &[
+            (0f32, 1f32),
+            (5f32, 2.625),
+            (10f32, 5f32),
+            (15f32, 3f32),
+            (20f32, 1f32),
+        ] {
+            assert!(
+                (curve.evaluate(frame) - value).abs() < 1e-5,
+                "Frame {}",
+                frame
+            );
+        }
+    }
+
+    #[test]
+    fn baked_bool() {
+        let curve = read_curve(baked_bool_curve());
+        assert!(curve.curve_type.is_boolean());
+        for &(frame, value) in &[
+            (0f32, true),
+            (1f32, false),
+            (1.5, false),
+            (2f32, true),
+            (3.5, true),
+            (4f32, false),
+            (10f32, false),
+        ] {
+            assert_eq!(curve.evaluate_bool(frame), value, "Frame {}", frame);
+            assert_eq!(curve.evaluate(frame), if value { 1f32 } else { 0f32 });
+        }
+    }
+
+    #[test]
+    fn animate_by_target_offset() {
+        let data = make_curves(&[
+            cubic_curve(),
+            linear_curve(),
+            step_curve(),
+            baked_bool_curve(),
+        ]);
+        let mut reader = Cursor::new(data);
+        let curves: DataArray<Curve> = DataArray::new(&mut reader, 0x24, 4).unwrap();
+        let mut values = [-1f32; 6];
+        animate_values(&mut reader, &curves, 5f32, &mut values).unwrap();
+        assert_eq!(values, [2.625, 4f32, 11f32, -1f32, 0f32, -1f32]);
+    }
+}
@@ -1,6 +1,8 @@
 use ez_io::ReadE;
 use std::error::Error;
+use std::fmt;
 use std::io::{Read, Seek};
+use std::str;
 use util::Importable;
 use util::Pointer;
 
@@ -9,6 +11,27 @@ pub struct Embedded {
     pub length: u32,
 }
 
+pub struct EmbeddedFile {
+    pub name: String,
+    pub kind: Kind,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Kind {
+    Yaz0,
+    SARC,
+    BFSHA,
+    BNSH,
+    SHARC,
+    SHARCFB,
+    BNTX,
+    JSON,
+    XML,
+    Text,
+    Unknown,
+}
+
 impl Importable for Embedded {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<Embedded, Box<Error>> {
         let offset = Pointer::read_new_rel_i32_be(reader)?;
@@ -16,3 +39,120 @@ impl Importable for Embedded {
         Ok(Embedded { offset, length })
     }
 }
+
+impl Embedded {
+    pub fn get_data<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, Box<Error>> {
+        self.offset.seek_abs_pos(reader)?;
+        let mut data = vec![0u8; self.length as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+    pub fn get_kind<R: Read + Seek>(&self, reader: &mut R) -> Result<Kind, Box<Error>> {
+        Ok(Kind::sniff(&self.get_data(reader)?))
+    }
+}
+
+impl Kind {
+    pub fn sniff(data: &[u8]) -> Kind {
+        if data.len() >= 4 {
+            match &data[0..4] {
+                b"Yaz0" => return Kind::Yaz0,
+                b"SARC" => return Kind::SARC,
+                b"FSHA" => return Kind::BFSHA,
+                b"BNSH" => return Kind::BNSH,
+                b"AAHS" => return Kind::SHARC,
+                b"BAHS" => return Kind::SHARCFB,
+                b"BNTX" => return Kind::BNTX,
+                _ => {}
+            }
+        }
+        // Everything else has to be valid UTF-8 text
+        let text = match str::from_utf8(data) {
+            Ok(t) => t,
+            Err(_) => return Kind::Unknown,
+        };
+        if text
+            .chars()
+            .any(|c| c.is_control() && !c.is_whitespace() && c != '\0')
+        {
+            return Kind::Unknown;
+        }
+        let trimmed = text
+            .trim_start_matches('\u{FEFF}')
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if trimmed.is_empty() {
+            Kind::Text
+        } else if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            Kind::JSON
+        } else if trimmed.starts_with('<') {
+            Kind::XML
+        } else {
+            Kind::Text
+        }
+    }
+    pub fn get_extension(&self) -> &'static str {
+        match *self {
+            Kind::Yaz0 => "szs",
+            Kind::SARC => "sarc",
+            Kind::BFSHA => "bfsha",
+            Kind::BNSH => "bnsh",
+            Kind::SHARC => "sharc",
+            Kind::SHARCFB => "sharcfb",
+            Kind::BNTX => "bntx",
+            Kind::JSON => "json",
+            Kind::XML => "xml",
+            Kind::Text => "txt",
+            Kind::Unknown => "bin",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Kind::Yaz0 => "Yaz0 compressed data",
+            Kind::SARC => "SARC archive",
+            Kind::BFSHA => "BFSHA shader archive",
+            Kind::BNSH => "BNSH shader binary",
+            Kind::SHARC => "SHARC shader archive",
+            Kind::SHARCFB => "SHARCFB shader binary",
+            Kind::BNTX => "BNTX texture container",
+            Kind::JSON => "JSON",
+            Kind::XML => "XML",
+            Kind::Text => "Text",
+            Kind::Unknown => "Unknown",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_magic_numbers() {
+        for &(data, kind) in &[
+            (&b"Yaz0\0\0\x01\0"[..], Kind::Yaz0),
+            (&b"SARC\0\x14\xFE\xFF"[..], Kind::SARC),
+            (&b"FSHA\0\0\0\x01"[..], Kind::BFSHA),
+            (&b"BNSH\0\0\0\0"[..], Kind::BNSH),
+            (&b"AAHS\0\0\0\x01"[..], Kind::SHARC),
+            (&b"BAHS\0\0\0\x01"[..], Kind::SHARCFB),
+            (&b"BNTX\0\0\0\0"[..], Kind::BNTX),
+        ] {
+            assert!(Kind::sniff(data) == kind, "Wrong kind for {:?}", data);
+        }
+    }
+
+    #[test]
+    fn sniff_text() {
+        assert!(Kind::sniff(b"\xEF\xBB\xBF  {\"a\": 1}") == Kind::JSON);
+        assert!(Kind::sniff(b"[1, 2]\0\0") == Kind::JSON);
+        assert!(Kind::sniff(b"<?xml version=\"1.0\"?><a/>") == Kind::XML);
+        assert!(Kind::sniff(b"key = value\r\n") == Kind::Text);
+        assert!(Kind::sniff(b"") == Kind::Text);
+        assert!(Kind::sniff(b"BFSH\x01\x02\x03\x04") == Kind::Unknown);
+        assert!(Kind::sniff(&[0xFF, 0xFE, 0x00, 0x41]) == Kind::Unknown);
+    }
+}
@@ -1 +1,46 @@
-pub struct MaterialParameter {}
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{read_text_entry, Importable, Pointer};
+
+// Describes a value in the material parameter data
+pub struct MaterialParameter {
+    pub parameter_type: u8,
+    pub data_length: u8,
+    pub data_offset: u16,
+    pub uniform_offset: i32,
+    pub callback_pointer: u32,
+    pub depended_index: u16,
+    pub depend_index: u16,
+    pub name_offset: Pointer,
+}
+
+impl Importable for MaterialParameter {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<MaterialParameter, Box<Error>> {
+        let parameter_type = reader.read_to_u8()?;
+        let data_length = reader.read_to_u8()?;
+        let data_offset = reader.read_be_to_u16()?;
+        let uniform_offset = reader.read_be_to_i32()?;
+        let callback_pointer = reader.read_be_to_u32()?;
+        let depended_index = reader.read_be_to_u16()?;
+        let depend_index = reader.read_be_to_u16()?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(MaterialParameter {
+            parameter_type,
+            data_length,
+            data_offset,
+            uniform_offset,
+            callback_pointer,
+            depended_index,
+            depend_index,
+            name_offset,
+        })
+    }
+}
+
+impl MaterialParameter {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
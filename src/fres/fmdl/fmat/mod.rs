@@ -2,18 +2,27 @@ pub mod material_parameter;
 pub mod render_info_parameter;
 pub mod render_state;
 pub mod shader_assign;
+pub mod texture_reference;
 pub mod texture_sampler;
 
+use self::material_parameter::MaterialParameter;
+use self::render_info_parameter::RenderInfoParameter;
+use self::render_state::RenderState;
+use self::shader_assign::ShaderAssign;
+use self::texture_reference::TextureReference;
+use self::texture_sampler::TextureSampler;
 use error::{check_magic_number, UserDataNotEmpty};
 use ez_io::ReadE;
 use std::error::Error;
 use std::io::{Read, Seek};
-use util::{Importable, Pointer};
+use util::{read_text_entry, DataArray, Importable, IndexGroup, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FMAT {
     pub header: Header,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub material_name_offset: Pointer,
     pub material_flags: u32,
@@ -47,6 +56,97 @@ impl Importable for FMAT {
     }
 }
 
+impl FMAT {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.header.material_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_render_info_parameters<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<IndexGroup<RenderInfoParameter>>, Box<Error>> {
+        if self.header.render_info_parameter_count == 0 {
+            return Ok(None);
+        }
+        self.header
+            .render_info_parameter_index_group_offset
+            .seek_abs_pos(reader)?;
+        Ok(Some(IndexGroup::import(reader)?))
+    }
+    pub fn get_render_state<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<RenderState, Box<Error>> {
+        self.header.render_state_offset.seek_abs_pos(reader)?;
+        Ok(RenderState::import(reader)?)
+    }
+    pub fn get_shader_assign<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<ShaderAssign>, Box<Error>> {
+        if self.header.shader_assign_offset.points_to == 0 {
+            return Ok(None);
+        }
+        self.header.shader_assign_offset.seek_abs_pos(reader)?;
+        Ok(Some(ShaderAssign::import(reader)?))
+    }
+    pub fn get_texture_references<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<TextureReference>, Box<Error>> {
+        self.header
+            .texture_reference_array_offset
+            .seek_abs_pos(reader)?;
+        DataArray::new(reader, 0x8, u32::from(self.header.texture_reference_count))
+    }
+    pub fn get_texture_samplers<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<TextureSampler>, Box<Error>> {
+        self.header.texture_sampler_offset.seek_abs_pos(reader)?;
+        DataArray::new(reader, 0x18, u32::from(self.header.texture_sampler_count))
+    }
+    pub fn get_material_parameters<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<MaterialParameter>, Box<Error>> {
+        self.header
+            .material_parameter_array_offset
+            .seek_abs_pos(reader)?;
+        DataArray::new(
+            reader,
+            0x14,
+            u32::from(self.header.material_parameter_count),
+        )
+    }
+    // What the material parameters describe, their offsets point in here
+    pub fn get_material_parameter_data<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        self.header
+            .material_parameter_data_offset
+            .seek_abs_pos(reader)?;
+        let mut data = vec![0u8; usize::from(self.header.material_parameter_data_length)];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+    // One bit per material parameter
+    pub fn get_volatile_flags_data<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        let mut data = vec![0u8; usize::from(self.header.material_parameter_count).div_ceil(8)];
+        if self.header.volatile_flags_data_offset.points_to != 0 {
+            self.header
+                .volatile_flags_data_offset
+                .seek_abs_pos(reader)?;
+            reader.read_exact(&mut data)?;
+        }
+        Ok(data)
+    }
+}
+
 impl Importable for Header {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
         let mut magic_number = [0u8; 4];
@@ -1 +1,76 @@
-pub struct RenderInfoParameter {}
+use error::UnrecognizedValue;
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, Importable, Pointer};
+
+pub struct RenderInfoParameter {
+    pub name_offset: Pointer,
+    pub values: RenderInfoValues,
+}
+
+pub enum RenderInfoValues {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Text(Vec<Pointer>),
+}
+
+impl Importable for RenderInfoParameter {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<RenderInfoParameter, Box<Error>> {
+        let count = reader.read_be_to_u16()?;
+        let kind = reader.read_to_u8()?;
+        reader.seek(SeekFrom::Current(1))?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let values = match kind {
+            0 => {
+                let mut values = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    values.push(reader.read_be_to_i32()?);
+                }
+                RenderInfoValues::Int(values)
+            }
+            1 => {
+                let mut values = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    values.push(reader.read_be_to_f32()?);
+                }
+                RenderInfoValues::Float(values)
+            }
+            2 => {
+                let mut values = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    values.push(Pointer::read_new_rel_i32_be(reader)?);
+                }
+                RenderInfoValues::Text(values)
+            }
+            x => {
+                return Err(Box::new(UnrecognizedValue {
+                    value: x,
+                    enum_name: "RenderInfoValues".to_string(),
+                }))
+            }
+        };
+        Ok(RenderInfoParameter {
+            name_offset,
+            values,
+        })
+    }
+}
+
+impl RenderInfoParameter {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    // Empty unless the values are text
+    pub fn get_texts<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<String>, Box<Error>> {
+        let mut texts = Vec::new();
+        if let RenderInfoValues::Text(ref pointers) = self.values {
+            for pointer in pointers {
+                pointer.seek_abs_pos(reader)?;
+                texts.push(read_text_entry(reader)?);
+            }
+        }
+        Ok(texts)
+    }
+}
@@ -1 +1,47 @@
-pub struct RenderState {}
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::Importable;
+
+// Raw GX2 register values, as the GPU takes them
+pub struct RenderState {
+    pub flags: u32,
+    pub polygon_control: u32,
+    pub depth_control: u32,
+    pub alpha_control: u32,
+    pub alpha_reference: f32,
+    pub color_control: u32,
+    pub blend_target: u32,
+    pub blend_control: u32,
+    pub blend_color: [f32; 4],
+}
+
+impl Importable for RenderState {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<RenderState, Box<Error>> {
+        let flags = reader.read_be_to_u32()?;
+        let polygon_control = reader.read_be_to_u32()?;
+        let depth_control = reader.read_be_to_u32()?;
+        let alpha_control = reader.read_be_to_u32()?;
+        let alpha_reference = reader.read_be_to_f32()?;
+        let color_control = reader.read_be_to_u32()?;
+        let blend_target = reader.read_be_to_u32()?;
+        let blend_control = reader.read_be_to_u32()?;
+        let blend_color = [
+            reader.read_be_to_f32()?,
+            reader.read_be_to_f32()?,
+            reader.read_be_to_f32()?,
+            reader.read_be_to_f32()?,
+        ];
+        Ok(RenderState {
+            flags,
+            polygon_control,
+            depth_control,
+            alpha_control,
+            alpha_reference,
+            color_control,
+            blend_target,
+            blend_control,
+            blend_color,
+        })
+    }
+}
@@ -1 +1,106 @@
-pub struct ShaderAssign {}
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{read_text_entry, Importable, IndexGroup, Pointer};
+
+pub struct ShaderAssign {
+    pub shader_archive_name_offset: Pointer,
+    pub shading_model_name_offset: Pointer,
+    pub revision: u32,
+    pub attribute_assign_count: u8,
+    pub sampler_assign_count: u8,
+    pub shader_option_count: u16,
+    pub attribute_assign_index_group_offset: Pointer,
+    pub sampler_assign_index_group_offset: Pointer,
+    pub shader_option_index_group_offset: Pointer,
+}
+
+impl Importable for ShaderAssign {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<ShaderAssign, Box<Error>> {
+        let shader_archive_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let shading_model_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let revision = reader.read_be_to_u32()?;
+        let attribute_assign_count = reader.read_to_u8()?;
+        let sampler_assign_count = reader.read_to_u8()?;
+        let shader_option_count = reader.read_be_to_u16()?;
+        let attribute_assign_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let sampler_assign_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let shader_option_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(ShaderAssign {
+            shader_archive_name_offset,
+            shading_model_name_offset,
+            revision,
+            attribute_assign_count,
+            sampler_assign_count,
+            shader_option_count,
+            attribute_assign_index_group_offset,
+            sampler_assign_index_group_offset,
+            shader_option_index_group_offset,
+        })
+    }
+}
+
+impl ShaderAssign {
+    pub fn get_shader_archive_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<String, Box<Error>> {
+        self.shader_archive_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_shading_model_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<String, Box<Error>> {
+        self.shading_model_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    // Each group maps a name used by the shader to one used by the material
+    pub fn get_attribute_assigns<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<(String, String)>, Box<Error>> {
+        read_assigns(
+            reader,
+            &self.attribute_assign_index_group_offset,
+            u32::from(self.attribute_assign_count),
+        )
+    }
+    pub fn get_sampler_assigns<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<(String, String)>, Box<Error>> {
+        read_assigns(
+            reader,
+            &self.sampler_assign_index_group_offset,
+            u32::from(self.sampler_assign_count),
+        )
+    }
+    pub fn get_shader_options<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<(String, String)>, Box<Error>> {
+        read_assigns(
+            reader,
+            &self.shader_option_index_group_offset,
+            u32::from(self.shader_option_count),
+        )
+    }
+}
+
+fn read_assigns<R: Read + Seek>(
+    reader: &mut R,
+    index_group_offset: &Pointer,
+    count: u32,
+) -> Result<Vec<(String, String)>, Box<Error>> {
+    let mut assigns = Vec::new();
+    if count == 0 {
+        return Ok(assigns);
+    }
+    index_group_offset.seek_abs_pos(reader)?;
+    let index_group: IndexGroup<String> = IndexGroup::import(reader)?;
+    for entry in &index_group.entries {
+        assigns.push((entry.get_name(reader)?, entry.get_data(reader)?));
+    }
+    Ok(assigns)
+}
@@ -0,0 +1,27 @@
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{read_text_entry, Importable, Pointer};
+
+// The FTEX is only set when the texture is in the same file
+pub struct TextureReference {
+    pub name_offset: Pointer,
+    pub ftex_offset: Pointer,
+}
+
+impl Importable for TextureReference {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<TextureReference, Box<Error>> {
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let ftex_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(TextureReference {
+            name_offset,
+            ftex_offset,
+        })
+    }
+}
+
+impl TextureReference {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
@@ -1 +1,38 @@
-pub struct TextureSampler {}
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, Importable, Pointer};
+
+pub struct TextureSampler {
+    pub gx2_sampler: [u32; 3],
+    pub handle: u32,
+    pub name_offset: Pointer,
+    pub index: u8,
+}
+
+impl Importable for TextureSampler {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<TextureSampler, Box<Error>> {
+        let gx2_sampler = [
+            reader.read_be_to_u32()?,
+            reader.read_be_to_u32()?,
+            reader.read_be_to_u32()?,
+        ];
+        let handle = reader.read_be_to_u32()?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let index = reader.read_to_u8()?;
+        reader.seek(SeekFrom::Current(3))?;
+        Ok(TextureSampler {
+            gx2_sampler,
+            handle,
+            name_offset,
+            index,
+        })
+    }
+}
+
+impl TextureSampler {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
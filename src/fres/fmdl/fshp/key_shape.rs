@@ -0,0 +1,98 @@
+use fres::fmdl::fvtx::attributes::Attributes;
+use fres::fmdl::fvtx::FVTX;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{Importable, IndexGroupEntry};
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct KeyShape {
+    pub target_attribute_indices: [u8; 20],
+    pub target_attribute_index_offsets: [u8; 4],
+}
+
+// The decoded values of one attribute of a key shape, its name tells which one it morphs
+pub struct KeyShapeTarget {
+    pub attribute_name: String,
+    pub values: Vec<[f32; 4]>,
+}
+
+impl Importable for KeyShape {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<KeyShape, Box<Error>> {
+        let mut target_attribute_indices = [0u8; 20];
+        reader.read_exact(&mut target_attribute_indices)?;
+        let mut target_attribute_index_offsets = [0u8; 4];
+        reader.read_exact(&mut target_attribute_index_offsets)?;
+        Ok(KeyShape {
+            target_attribute_indices,
+            target_attribute_index_offsets,
+        })
+    }
+}
+
+impl KeyShape {
+    // Indices of the FVTX attributes holding this target, FSHP tells how many are used
+    pub fn get_used_attribute_indices(&self, target_attribute_count: u8) -> Vec<u8> {
+        self.target_attribute_indices
+            .iter()
+            .take(usize::from(target_attribute_count))
+            .cloned()
+            .collect()
+    }
+    pub fn get_target_attributes<'a>(
+        &self,
+        fvtx: &'a FVTX,
+        target_attribute_count: u8,
+    ) -> Vec<&'a IndexGroupEntry<Attributes>> {
+        self.get_used_attribute_indices(target_attribute_count)
+            .into_iter()
+            .filter_map(|i| fvtx.attributes_index_group.entries.get(usize::from(i)))
+            .collect()
+    }
+    pub fn get_target_attribute_names<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        fvtx: &FVTX,
+        target_attribute_count: u8,
+    ) -> Result<Vec<String>, Box<Error>> {
+        let mut names = Vec::new();
+        for attribute_entry in self.get_target_attributes(fvtx, target_attribute_count) {
+            names.push(attribute_entry.get_name(reader)?);
+        }
+        Ok(names)
+    }
+    pub fn get_targets<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        fvtx: &FVTX,
+        target_attribute_count: u8,
+    ) -> Result<Vec<KeyShapeTarget>, Box<Error>> {
+        let mut targets = Vec::new();
+        for index in self.get_used_attribute_indices(target_attribute_count) {
+            let index = usize::from(index);
+            let attribute_name = match fvtx.attributes_index_group.entries.get(index) {
+                Some(e) => e.get_name(reader)?,
+                None => continue,
+            };
+            if let Some(values) = fvtx.get_attribute_values_at(reader, index)? {
+                targets.push(KeyShapeTarget {
+                    attribute_name,
+                    values,
+                });
+            }
+        }
+        Ok(targets)
+    }
+    // The morphed vertices positions, None when this key shape leaves them alone
+    pub fn get_target_positions<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        fvtx: &FVTX,
+        target_attribute_count: u8,
+    ) -> Result<Option<Vec<[f32; 3]>>, Box<Error>> {
+        Ok(self
+            .get_targets(reader, fvtx, target_attribute_count)?
+            .into_iter()
+            .find(|t| t.attribute_name.starts_with("_p"))
+            .map(|t| t.values.iter().map(|v| [v[0], v[1], v[2]]).collect()))
+    }
+}
@@ -1,10 +1,11 @@
 use super::visibility_group::VisibilityGroup;
-use error::UnrecognizedValue;
+use error::{UnrecognizedValue, UnsupportedPrimitive, VertexIndexOverflow};
 use ez_io::ReadE;
 use std::error::Error;
 use std::io::{Read, Seek, SeekFrom};
 use util::{BufferInfo, DataArray, Importable, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct LODModel {
     pub primitive_type: PrimitiveType,
     pub index_format: IndexFormat,
@@ -15,6 +16,8 @@ pub struct LODModel {
     pub skip_vertices: u32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum PrimitiveType {
     Points,
     Lines,
@@ -38,6 +41,8 @@ pub enum PrimitiveType {
     TessellateQuadStrip,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum IndexFormat {
     U16LittleEndian = 0,
     U32LittleEndian = 1,
@@ -111,7 +116,7 @@ impl LODModel {
         reader: &mut R,
     ) -> Result<DataArray<VisibilityGroup>, Box<Error>> {
         self.visibility_group_offset.seek_abs_pos(reader)?;
-        let array = DataArray::new(reader, 0x18, u32::from(self.nb_visibility_groups))?;
+        let array = DataArray::new(reader, 8, u32::from(self.nb_visibility_groups))?;
         Ok(array)
     }
     pub fn get_direct_buffer_info<R: Read + Seek>(
@@ -122,4 +127,127 @@ impl LODModel {
         let info = BufferInfo::import(reader)?;
         Ok(info)
     }
+    // Every index, as stored. Vertices they point to are counted from skip_vertices.
+    pub fn get_indices<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u32>, Box<Error>> {
+        let data = self.get_direct_buffer_info(reader)?.get_data(reader)?;
+        let indices = match self.index_format {
+            IndexFormat::U16LittleEndian => data
+                .chunks(2)
+                .filter(|c| c.len() == 2)
+                .map(|c| u32::from(c[0]) | u32::from(c[1]) << 8)
+                .collect(),
+            IndexFormat::U16BigEndian => data
+                .chunks(2)
+                .filter(|c| c.len() == 2)
+                .map(|c| u32::from(c[0]) << 8 | u32::from(c[1]))
+                .collect(),
+            IndexFormat::U32LittleEndian => data
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .map(|c| {
+                    u32::from(c[0])
+                        | u32::from(c[1]) << 8
+                        | u32::from(c[2]) << 16
+                        | u32::from(c[3]) << 24
+                })
+                .collect(),
+            IndexFormat::U32BigEndian => data
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .map(|c| {
+                    u32::from(c[0]) << 24
+                        | u32::from(c[1]) << 16
+                        | u32::from(c[2]) << 8
+                        | u32::from(c[3])
+                })
+                .collect(),
+        };
+        Ok(indices)
+    }
+    // How many triangles the points make once drawn, 0 for points and lines
+    pub fn get_nb_triangles(&self) -> u32 {
+        let n = self.nb_points;
+        match self.primitive_type {
+            PrimitiveType::Triangles | PrimitiveType::TessellateTriangles => n / 3,
+            PrimitiveType::TriangleFan
+            | PrimitiveType::TriangleStrip
+            | PrimitiveType::TessellateTriangleStrip => n.saturating_sub(2),
+            PrimitiveType::TrianglesAdjacency => n / 6,
+            PrimitiveType::TriangleStripAdjacency => n.saturating_sub(4) / 2,
+            // Three corners, the fourth one is guessed
+            PrimitiveType::Rectangles => n / 3 * 2,
+            PrimitiveType::Quads | PrimitiveType::TessellateQuads => n / 4 * 2,
+            PrimitiveType::QuadStrip | PrimitiveType::TessellateQuadStrip => {
+                n.saturating_sub(2) / 2 * 2
+            }
+            _ => 0,
+        }
+    }
+    // As a triangle list, with skip_vertices already added to the indices
+    pub fn get_triangles<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<[u32; 3]>, Box<Error>> {
+        let mut indices = Vec::with_capacity(self.nb_points as usize);
+        for &index in self
+            .get_indices(reader)?
+            .iter()
+            .take(self.nb_points as usize)
+        {
+            match index.checked_add(self.skip_vertices) {
+                Some(i) => indices.push(i),
+                None => {
+                    return Err(Box::new(VertexIndexOverflow {
+                        index,
+                        skip_vertices: self.skip_vertices,
+                    }))
+                }
+            }
+        }
+        let mut triangles = Vec::with_capacity(self.get_nb_triangles() as usize);
+        match self.primitive_type {
+            PrimitiveType::Triangles => {
+                for t in indices.chunks(3).filter(|t| t.len() == 3) {
+                    triangles.push([t[0], t[1], t[2]]);
+                }
+            }
+            PrimitiveType::TriangleStrip => {
+                for i in 0..indices.len().saturating_sub(2) {
+                    // Every other triangle is flipped to keep the same winding
+                    if i % 2 == 0 {
+                        triangles.push([indices[i], indices[i + 1], indices[i + 2]]);
+                    } else {
+                        triangles.push([indices[i + 1], indices[i], indices[i + 2]]);
+                    }
+                }
+            }
+            PrimitiveType::TriangleFan => {
+                for i in 1..indices.len().saturating_sub(1) {
+                    triangles.push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            PrimitiveType::Quads => {
+                for q in indices.chunks(4).filter(|q| q.len() == 4) {
+                    triangles.push([q[0], q[1], q[2]]);
+                    triangles.push([q[0], q[2], q[3]]);
+                }
+            }
+            p => {
+                return Err(Box::new(UnsupportedPrimitive {
+                    mode: format!("{:?}", p),
+                }))
+            }
+        }
+        Ok(triangles)
+    }
+}
+
+impl IndexFormat {
+    // Bytes taken by one index
+    pub fn get_size(&self) -> usize {
+        match *self {
+            IndexFormat::U16LittleEndian | IndexFormat::U16BigEndian => 2,
+            IndexFormat::U32LittleEndian | IndexFormat::U32BigEndian => 4,
+        }
+    }
 }
@@ -1,18 +1,33 @@
+pub mod key_shape;
 pub mod lod_model;
 pub mod visibility_group;
 
+use self::key_shape::KeyShape;
 use self::lod_model::LODModel;
-use error::check_magic_number;
+use self::visibility_group::{BoundingBox, TreeNode, VisibilityGroup};
+use error::{check_magic_number, AttributeNotFound};
 use ez_io::ReadE;
+use fres::fmdl::fvtx::FVTX;
+use fres::Version;
 use std::error::Error;
-use std::io::{Read, Seek};
-use util::{DataArray, Importable, Pointer};
+use std::io::{Read, Seek, SeekFrom};
+use util::{DataArray, Importable, IndexGroup, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FSHP {
     pub header: Header,
     pub lod_model_array: DataArray<LODModel>,
+    pub key_shape_index_group: Option<IndexGroup<KeyShape>>,
 }
 
+// Bounds computed from the vertices of a LOD model, and of each of its visibility groups
+pub struct LODBounds {
+    pub bounding_box: BoundingBox,
+    pub radius: f32,
+    pub visibility_groups: Vec<(BoundingBox, f32)>,
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub polygon_name_offset: Pointer,
     pub flags: u32,
@@ -26,24 +41,39 @@ pub struct Header {
     pub key_shape_count: u8,
     pub target_attribute_count: u8,
     pub visibility_group_tree_node_count: u16,
-    pub bounding_box_radius: u32,
+    pub bounding_radius: BoundingRadius,
     pub fvtx_offset: Pointer,
     pub lod_model_offset: Pointer,
     pub fskl_index_array_offset: Pointer,
     pub key_shape_index_group_offset: Pointer,
+    // Points to the bounding boxes instead when there are no tree nodes
     pub visibility_group_tree_nodes_offset: Pointer,
     pub visibility_group_tree_ranges_offset: Pointer,
     pub visibility_group_tree_indices_offset: Pointer,
 }
 
+// Before v4.5, the header holds the only radius. From v4.5, it points to one per LOD model.
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub enum BoundingRadius {
+    Radius(f32),
+    Offset(Pointer),
+}
+
 impl Importable for FSHP {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<FSHP, Box<Error>> {
         let header = Header::import(reader)?;
         header.lod_model_offset.seek_abs_pos(reader)?;
         let lod_model_array = DataArray::new(reader, 0x1C, u32::from(header.lod_model_count))?;
+        let key_shape_index_group = if header.key_shape_count > 0 {
+            header.key_shape_index_group_offset.seek_abs_pos(reader)?;
+            Some(IndexGroup::import(reader)?)
+        } else {
+            None
+        };
         Ok(FSHP {
             header,
             lod_model_array,
+            key_shape_index_group,
         })
     }
 }
@@ -65,7 +95,16 @@ impl Importable for Header {
         let key_shape_count = reader.read_to_u8()?;
         let target_attribute_count = reader.read_to_u8()?;
         let visibility_group_tree_node_count = reader.read_be_to_u16()?;
-        let bounding_box_radius = reader.read_be_to_u32()?;
+        let bounding_radius_offset = Pointer::read_new_rel_i32_be(reader)?;
+        // The version is in the FRES header, at the start of the file
+        let position = reader.seek(SeekFrom::Current(0))?;
+        reader.seek(SeekFrom::Start(4))?;
+        let bounding_radius = if Version::import(reader)?.has_radius_array() {
+            BoundingRadius::Offset(bounding_radius_offset)
+        } else {
+            BoundingRadius::Radius(f32::from_bits(bounding_radius_offset.points_to as u32))
+        };
+        reader.seek(SeekFrom::Start(position))?;
         let fvtx_offset = Pointer::read_new_rel_i32_be(reader)?;
         let lod_model_offset = Pointer::read_new_rel_i32_be(reader)?;
         let fskl_index_array_offset = Pointer::read_new_rel_i32_be(reader)?;
@@ -88,7 +127,7 @@ impl Importable for Header {
             key_shape_count,
             target_attribute_count,
             visibility_group_tree_node_count,
-            bounding_box_radius,
+            bounding_radius,
             fvtx_offset,
             lod_model_offset,
             fskl_index_array_offset,
@@ -99,3 +138,245 @@ impl Importable for Header {
         })
     }
 }
+
+impl FSHP {
+    // From the most detailed to the least
+    pub fn get_lod_models<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<LODModel>, Box<Error>> {
+        let mut lod_models = Vec::with_capacity(self.lod_model_array.entries.len());
+        for entry in &self.lod_model_array.entries {
+            lod_models.push(entry.get_data(reader)?);
+        }
+        Ok(lod_models)
+    }
+    // Every key shape with its name, in the order of the index group
+    pub fn get_key_shapes<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<(String, KeyShape)>, Box<Error>> {
+        let mut key_shapes = Vec::new();
+        if let Some(ref a) = self.key_shape_index_group {
+            for entry in &a.entries {
+                key_shapes.push((entry.get_name(reader)?, entry.get_data(reader)?));
+            }
+        }
+        Ok(key_shapes)
+    }
+    // One per LOD model, or a single one for the whole shape before v4.5
+    pub fn get_bounding_radii<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        match self.header.bounding_radius {
+            BoundingRadius::Radius(radius) => Ok(vec![radius]),
+            BoundingRadius::Offset(ref offset) => {
+                offset.seek_abs_pos(reader)?;
+                let mut radii = Vec::with_capacity(usize::from(self.header.lod_model_count));
+                for _ in 0..self.header.lod_model_count {
+                    radii.push(reader.read_be_to_f32()?);
+                }
+                Ok(radii)
+            }
+        }
+    }
+    pub fn get_tree_nodes<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<TreeNode>, Box<Error>> {
+        let count = self.header.visibility_group_tree_node_count;
+        let mut nodes = Vec::with_capacity(usize::from(count));
+        if count > 0 {
+            self.header
+                .visibility_group_tree_nodes_offset
+                .seek_abs_pos(reader)?;
+            for _ in 0..count {
+                nodes.push(TreeNode::import(reader)?);
+            }
+        }
+        Ok(nodes)
+    }
+    // One per tree node. Without nodes, the first offset points to one for each visibility group
+    // of the first LOD model followed by one for all of them.
+    pub fn get_bounding_boxes<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<BoundingBox>, Box<Error>> {
+        let (offset, count) = if self.header.visibility_group_tree_node_count > 0 {
+            (
+                &self.header.visibility_group_tree_ranges_offset,
+                usize::from(self.header.visibility_group_tree_node_count),
+            )
+        } else {
+            let count = match self.lod_model_array.entries.first() {
+                Some(entry) => usize::from(entry.get_data(reader)?.nb_visibility_groups) + 1,
+                None => 0,
+            };
+            (&self.header.visibility_group_tree_nodes_offset, count)
+        };
+        let mut boxes = Vec::with_capacity(count);
+        if offset.points_to != 0 {
+            offset.seek_abs_pos(reader)?;
+            for _ in 0..count {
+                boxes.push(BoundingBox::import(reader)?);
+            }
+        }
+        Ok(boxes)
+    }
+    pub fn get_tree_indices<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u16>, Box<Error>> {
+        let count = self.header.visibility_group_tree_node_count;
+        let mut indices = Vec::with_capacity(usize::from(count));
+        if count > 0 {
+            self.header
+                .visibility_group_tree_indices_offset
+                .seek_abs_pos(reader)?;
+            for _ in 0..count {
+                indices.push(reader.read_be_to_u16()?);
+            }
+        }
+        Ok(indices)
+    }
+    // What the bounds should be from the positions in the vertex buffer, for every LOD model
+    pub fn compute_bounds<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        fvtx: &FVTX,
+    ) -> Result<Vec<LODBounds>, Box<Error>> {
+        let positions: Vec<[f32; 3]> = match fvtx.get_attribute_values(reader, "_p0")? {
+            Some(values) => values.iter().map(|v| [v[0], v[1], v[2]]).collect(),
+            None => {
+                return Err(Box::new(AttributeNotFound {
+                    name: "_p0".to_string(),
+                }))
+            }
+        };
+        let mut bounds = Vec::with_capacity(self.lod_model_array.entries.len());
+        for lod_model in self.get_lod_models(reader)? {
+            let indices = lod_model.get_indices(reader)?;
+            let index_size = lod_model.index_format.get_size() as u32;
+            let get_points = |indices: &[u32]| -> Vec<[f32; 3]> {
+                indices
+                    .iter()
+                    .filter_map(|&i| {
+                        i.checked_add(lod_model.skip_vertices)
+                            .and_then(|i| positions.get(i as usize))
+                            .cloned()
+                    })
+                    .collect()
+            };
+            let mut visibility_groups = Vec::new();
+            for group_entry in &lod_model.get_visibility_groups(reader)?.entries {
+                let group: VisibilityGroup = group_entry.get_data(reader)?;
+                let start = (group.index_offset / index_size) as usize;
+                let end = start + group.nb_points as usize;
+                let points = if start <= end && end <= indices.len() {
+                    get_points(&indices[start..end])
+                } else {
+                    Vec::new()
+                };
+                visibility_groups.push(get_box_and_radius(&points));
+            }
+            let points = get_points(&indices);
+            let (bounding_box, radius) = get_box_and_radius(&points);
+            bounds.push(LODBounds {
+                bounding_box,
+                radius,
+                visibility_groups,
+            });
+        }
+        Ok(bounds)
+    }
+}
+
+fn get_box_and_radius(points: &[[f32; 3]]) -> (BoundingBox, f32) {
+    match BoundingBox::from_points(points) {
+        Some(b) => (b, b.get_radius(points)),
+        None => (
+            BoundingBox {
+                center: [0f32; 3],
+                extent: [0f32; 3],
+            },
+            0f32,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::build::tests::{make_mesh, make_template};
+    use fres::build::{build_bfres, BuildOptions};
+    use fres::fmdl::FMDL;
+    use fres::writer::{Target, Writer};
+    use fres::FRES;
+    use scene::Scene;
+    use std::io::Cursor;
+
+    fn make_bfres() -> Vec<u8> {
+        let scene = Scene {
+            meshes: vec![make_mesh("body", 3)],
+            materials: Vec::new(),
+            bones: Vec::new(),
+        };
+        build_bfres(&scene, &make_template(), &BuildOptions::new("Test")).unwrap()
+    }
+
+    // The shape with its vertex buffer, and where it starts in the file
+    fn get_shape(bfres: Vec<u8>) -> (FSHP, FVTX, usize, Cursor<Vec<u8>>) {
+        let mut cursor = Cursor::new(bfres);
+        let fres = FRES::import(&mut cursor).unwrap();
+        let fmdl: FMDL = fres.sub_file_index_groups.model_data.unwrap().entries[0]
+            .get_data(&mut cursor)
+            .unwrap();
+        let fshp_entry = &fmdl.fshp_index_group.entries[0];
+        let position = fshp_entry.data_pointer.get_abs_pos().unwrap() as usize;
+        let fshp = fshp_entry.get_data(&mut cursor).unwrap();
+        let fvtx = fmdl.fvtx_array.entries[usize::from(fshp.header.fvtx_index)]
+            .get_data(&mut cursor)
+            .unwrap();
+        (fshp, fvtx, position, cursor)
+    }
+
+    fn check_boxes(read: &[BoundingBox], computed: &[BoundingBox]) {
+        assert_eq!(read.len(), computed.len());
+        for (read, computed) in read.iter().zip(computed.iter()) {
+            assert_eq!(read.center, computed.center);
+            assert_eq!(read.extent, computed.extent);
+        }
+    }
+
+    #[test]
+    fn bounding_boxes_of_tree_nodes() {
+        let (fshp, fvtx, _, mut cursor) = get_shape(make_bfres());
+        assert_eq!(fshp.header.visibility_group_tree_node_count, 1);
+        let bounds = fshp.compute_bounds(&mut cursor, &fvtx).unwrap();
+        let boxes = fshp.get_bounding_boxes(&mut cursor).unwrap();
+        check_boxes(&boxes, &[bounds[0].bounding_box]);
+    }
+
+    // The built shape is changed to have no tree nodes, with the box of its visibility group
+    // and the one of the whole shape at the first offset
+    #[test]
+    fn bounding_boxes_without_tree_nodes() {
+        let (fshp, fvtx, position, mut cursor) = get_shape(make_bfres());
+        let bounds = fshp.compute_bounds(&mut cursor, &fvtx).unwrap();
+        let expected = [bounds[0].visibility_groups[0].0, bounds[0].bounding_box];
+        let mut writer = Writer::from_data(cursor.into_inner());
+        writer.set_u16(position + 0x1A, 0);
+        writer.set_pointer(position + 0x34, Target::Null);
+        writer.set_pointer(position + 0x38, Target::Null);
+        let label = writer.new_label();
+        writer.set_pointer(position + 0x30, Target::Label(label));
+        writer.place(label);
+        for bounding_box in &expected {
+            for &v in bounding_box.center.iter().chain(bounding_box.extent.iter()) {
+                writer.write_f32(v);
+            }
+        }
+        let (fshp, _, _, mut cursor) = get_shape(writer.finish());
+        assert_eq!(fshp.header.visibility_group_tree_node_count, 0);
+        let boxes = fshp.get_bounding_boxes(&mut cursor).unwrap();
+        check_boxes(&boxes, &expected);
+    }
+}
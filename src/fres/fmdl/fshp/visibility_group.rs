@@ -1,33 +1,104 @@
 use ez_io::ReadE;
 use std::error::Error;
 use std::io::{Read, Seek};
-use util::{BufferInfo, Importable, Pointer};
+use util::Importable;
 
+// A part of the index buffer of a LOD model, culled on its own
 pub struct VisibilityGroup {
-    pub buffer_info_offset: Pointer,
+    // In bytes, from the start of the index buffer
+    pub index_offset: u32,
     pub nb_points: u32,
 }
 
-pub struct Tree {}
+// Node of the tree used to cull visibility groups, every one of them has the bounding box of the
+// same index. The first node holds the whole shape.
+pub struct TreeNode {
+    pub left_index: u16,
+    pub right_index: u16,
+    pub unknown: u16,
+    pub next_sibling_index: u16,
+    pub visibility_group_index: u16,
+    pub nb_visibility_groups: u16,
+}
+
+// Axis aligned, extent is half of the size on each axis
+#[derive(Clone, Copy)]
+pub struct BoundingBox {
+    pub center: [f32; 3],
+    pub extent: [f32; 3],
+}
 
 impl Importable for VisibilityGroup {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<VisibilityGroup, Box<Error>> {
-        let buffer_info_offset = Pointer::read_new_rel_i32_be(reader)?; // Should be u32
+        let index_offset = reader.read_be_to_u32()?;
         let nb_points = reader.read_be_to_u32()?;
         Ok(VisibilityGroup {
-            buffer_info_offset,
+            index_offset,
             nb_points,
         })
     }
 }
 
-impl VisibilityGroup {
-    pub fn get_index_buffer<R: Read + Seek>(
-        &self,
-        reader: &mut R,
-    ) -> Result<BufferInfo, Box<Error>> {
-        self.buffer_info_offset.seek_abs_pos(reader)?;
-        let buffer_info = BufferInfo::import(reader)?;
-        Ok(buffer_info)
+impl Importable for TreeNode {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<TreeNode, Box<Error>> {
+        let left_index = reader.read_be_to_u16()?;
+        let right_index = reader.read_be_to_u16()?;
+        let unknown = reader.read_be_to_u16()?;
+        let next_sibling_index = reader.read_be_to_u16()?;
+        let visibility_group_index = reader.read_be_to_u16()?;
+        let nb_visibility_groups = reader.read_be_to_u16()?;
+        Ok(TreeNode {
+            left_index,
+            right_index,
+            unknown,
+            next_sibling_index,
+            visibility_group_index,
+            nb_visibility_groups,
+        })
+    }
+}
+
+impl Importable for BoundingBox {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<BoundingBox, Box<Error>> {
+        let mut values = [0f32; 6];
+        for v in &mut values {
+            *v = reader.read_be_to_f32()?;
+        }
+        Ok(BoundingBox {
+            center: [values[0], values[1], values[2]],
+            extent: [values[3], values[4], values[5]],
+        })
+    }
+}
+
+impl BoundingBox {
+    // The smallest box around every point, None when there is none
+    pub fn from_points(points: &[[f32; 3]]) -> Option<BoundingBox> {
+        if points.is_empty() {
+            return None;
+        }
+        let mut minimum = [f32::MAX; 3];
+        let mut maximum = [f32::MIN; 3];
+        for p in points {
+            for axis in 0..3 {
+                minimum[axis] = minimum[axis].min(p[axis]);
+                maximum[axis] = maximum[axis].max(p[axis]);
+            }
+        }
+        let mut center = [0f32; 3];
+        let mut extent = [0f32; 3];
+        for axis in 0..3 {
+            center[axis] = (minimum[axis] + maximum[axis]) / 2f32;
+            extent[axis] = (maximum[axis] - minimum[axis]) / 2f32;
+        }
+        Some(BoundingBox { center, extent })
+    }
+    // Of the sphere around the center of the box holding every point
+    pub fn get_radius(&self, points: &[[f32; 3]]) -> f32 {
+        let c = self.center;
+        points
+            .iter()
+            .map(|p| ((p[0] - c[0]).powi(2) + (p[1] - c[1]).powi(2) + (p[2] - c[2]).powi(2)).sqrt())
+            .fold(0f32, f32::max)
     }
 }
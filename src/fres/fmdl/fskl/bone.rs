@@ -4,6 +4,7 @@ use std::error::Error;
 use std::io::{Read, Seek};
 use util::{Importable, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Bone {
     pub name_offset: Pointer,
     pub bone_index: u16,
@@ -19,6 +20,7 @@ pub struct Bone {
     pub user_data_index_group_offset: Pointer,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Flags {
     pub visible: bool,
     pub rotation: RotationMode,
@@ -27,11 +29,13 @@ pub struct Flags {
     pub bone_hierarchy_flags: BoneHierarchyFlags,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum RotationMode {
     XYZEuler = 1,
     Quaternion = 0,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum BillboardBonesProjectionMode {
     None = 0,
     Child = 1,
@@ -43,6 +47,7 @@ pub enum BillboardBonesProjectionMode {
     YAxisViewPoint = 7,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct TransformationFlags {
     pub segment_scale_compensation: bool,
     pub scale_uniformly: bool,
@@ -51,6 +56,7 @@ pub struct TransformationFlags {
     pub no_translation: bool,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct BoneHierarchyFlags {
     pub scale_uniformly: bool,
     pub scale_volume_by_1: bool,
@@ -9,11 +9,13 @@ use std::error::Error;
 use std::io::{Read, Seek, SeekFrom};
 use util::{Importable, IndexGroup, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FSKL {
     pub header: Header,
     pub bones: IndexGroup<Bone>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub flags: u32,
     pub bone_array_count: u16,
@@ -67,3 +69,22 @@ impl Importable for Header {
         })
     }
 }
+
+impl FSKL {
+    // Bone index of every matrix vertices can be skinned to, the smooth ones come first
+    pub fn get_matrix_bone_indices<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u16>, Box<Error>> {
+        let count = usize::from(self.header.smooth_index_array_count)
+            + usize::from(self.header.rigid_index_array_count);
+        let mut indices = Vec::with_capacity(count);
+        if count > 0 {
+            self.header.smooth_index_array_offset.seek_abs_pos(reader)?;
+            for _ in 0..count {
+                indices.push(reader.read_be_to_u16()?);
+            }
+        }
+        Ok(indices)
+    }
+}
@@ -6,6 +6,7 @@ use std::io::SeekFrom;
 use std::io::{Read, Seek};
 use util::{Importable, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Attributes {
     pub attribute_name_offset: Pointer,
     pub buffer_info_index: u8,
@@ -13,6 +14,8 @@ pub struct Attributes {
     pub format: AttributesFormats,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum AttributesFormats {
     U8ToF32 = 0x000,
     TwoU8ToTwoF32 = 0x004,
@@ -81,6 +84,58 @@ impl Importable for Attributes {
     }
 }
 
+impl AttributesFormats {
+    // Bytes taken by one vertex
+    pub fn get_size(&self) -> u16 {
+        match *self {
+            AttributesFormats::U8ToF32
+            | AttributesFormats::U8ToU32
+            | AttributesFormats::I8ToF32
+            | AttributesFormats::I8 => 1,
+            AttributesFormats::TwoU8ToTwoF32
+            | AttributesFormats::TwoU8ToTwoU32
+            | AttributesFormats::TwoI8ToF32
+            | AttributesFormats::TwoI8 => 2,
+            AttributesFormats::TwoU16ToTwoF32
+            | AttributesFormats::FourU8ToFourF32
+            | AttributesFormats::FourU8ToFourU32
+            | AttributesFormats::TwoI16ToTwoF32
+            | AttributesFormats::FourI8ToFourF32
+            | AttributesFormats::ThreeI10toThreeF32
+            | AttributesFormats::FourI8
+            | AttributesFormats::F32
+            | AttributesFormats::TwoF16ToTwoF32 => 4,
+            AttributesFormats::TwoF32 | AttributesFormats::FourF16ToFourF32 => 8,
+            AttributesFormats::ThreeF32 => 12,
+            AttributesFormats::FourF32 => 16,
+        }
+    }
+    pub fn get_component_count(&self) -> usize {
+        match *self {
+            AttributesFormats::U8ToF32
+            | AttributesFormats::U8ToU32
+            | AttributesFormats::I8ToF32
+            | AttributesFormats::I8
+            | AttributesFormats::F32 => 1,
+            AttributesFormats::TwoU8ToTwoF32
+            | AttributesFormats::TwoU16ToTwoF32
+            | AttributesFormats::TwoU8ToTwoU32
+            | AttributesFormats::TwoI8ToF32
+            | AttributesFormats::TwoI16ToTwoF32
+            | AttributesFormats::TwoI8
+            | AttributesFormats::TwoF16ToTwoF32
+            | AttributesFormats::TwoF32 => 2,
+            AttributesFormats::ThreeI10toThreeF32 | AttributesFormats::ThreeF32 => 3,
+            AttributesFormats::FourU8ToFourF32
+            | AttributesFormats::FourU8ToFourU32
+            | AttributesFormats::FourI8ToFourF32
+            | AttributesFormats::FourI8
+            | AttributesFormats::FourF16ToFourF32
+            | AttributesFormats::FourF32 => 4,
+        }
+    }
+}
+
 impl fmt::Display for AttributesFormats {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let text = match *self {
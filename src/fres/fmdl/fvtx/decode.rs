@@ -0,0 +1,105 @@
+use fres::fmdl::fvtx::attributes::AttributesFormats;
+use half::f16;
+
+// One value per vertex, read every stride bytes from the offset. Components the format does not
+// have are left at 0, everything is big endian. Reading stops where the data does.
+pub fn decode_attribute(
+    format: AttributesFormats,
+    data: &[u8],
+    stride: usize,
+    offset: usize,
+    count: usize,
+) -> Vec<[f32; 4]> {
+    let size = usize::from(format.get_size());
+    let mut values = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = i * stride + offset;
+        if start + size > data.len() {
+            break;
+        }
+        values.push(decode_vertex(format, &data[start..start + size]));
+    }
+    values
+}
+
+fn decode_vertex(format: AttributesFormats, bytes: &[u8]) -> [f32; 4] {
+    let mut value = [0f32; 4];
+    match format {
+        AttributesFormats::U8ToF32
+        | AttributesFormats::TwoU8ToTwoF32
+        | AttributesFormats::FourU8ToFourF32 => {
+            for (v, &b) in value.iter_mut().zip(bytes.iter()) {
+                *v = f32::from(b) / 255f32;
+            }
+        }
+        AttributesFormats::TwoU16ToTwoF32 => {
+            for (v, c) in value.iter_mut().zip(bytes.chunks(2)) {
+                *v = read_u16(c) as f32 / 65_535f32;
+            }
+        }
+        AttributesFormats::U8ToU32
+        | AttributesFormats::TwoU8ToTwoU32
+        | AttributesFormats::FourU8ToFourU32 => {
+            for (v, &b) in value.iter_mut().zip(bytes.iter()) {
+                *v = f32::from(b);
+            }
+        }
+        AttributesFormats::I8ToF32
+        | AttributesFormats::TwoI8ToF32
+        | AttributesFormats::FourI8ToFourF32 => {
+            for (v, &b) in value.iter_mut().zip(bytes.iter()) {
+                *v = snorm(u32::from(b), 8);
+            }
+        }
+        AttributesFormats::TwoI16ToTwoF32 => {
+            for (v, c) in value.iter_mut().zip(bytes.chunks(2)) {
+                *v = snorm(read_u16(c), 16);
+            }
+        }
+        // X in the lowest bits
+        AttributesFormats::ThreeI10toThreeF32 => {
+            let packed = read_u32(bytes);
+            for (i, v) in value.iter_mut().take(3).enumerate() {
+                *v = snorm((packed >> (i * 10)) & 0x3FF, 10);
+            }
+        }
+        AttributesFormats::I8 | AttributesFormats::TwoI8 | AttributesFormats::FourI8 => {
+            for (v, &b) in value.iter_mut().zip(bytes.iter()) {
+                *v = f32::from(b as i8);
+            }
+        }
+        AttributesFormats::TwoF16ToTwoF32 | AttributesFormats::FourF16ToFourF32 => {
+            for (v, c) in value.iter_mut().zip(bytes.chunks(2)) {
+                *v = f32::from(f16::from_bits(read_u16(c) as u16));
+            }
+        }
+        AttributesFormats::F32
+        | AttributesFormats::TwoF32
+        | AttributesFormats::ThreeF32
+        | AttributesFormats::FourF32 => {
+            for (v, c) in value.iter_mut().zip(bytes.chunks(4)) {
+                *v = f32::from_bits(read_u32(c));
+            }
+        }
+    }
+    value
+}
+
+// The smallest value is the same as the one above it, -1
+fn snorm(raw: u32, bits: u32) -> f32 {
+    let shift = 32 - bits;
+    let signed = ((raw << shift) as i32) >> shift;
+    let max_value = ((1u64 << (bits - 1)) - 1) as f32;
+    (signed as f32 / max_value).max(-1f32)
+}
+
+fn read_u16(bytes: &[u8]) -> u32 {
+    u32::from(bytes[0]) << 8 | u32::from(bytes[1])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from(bytes[0]) << 24
+        | u32::from(bytes[1]) << 16
+        | u32::from(bytes[2]) << 8
+        | u32::from(bytes[3])
+}
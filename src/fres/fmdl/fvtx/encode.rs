@@ -0,0 +1,109 @@
+use fres::fmdl::fvtx::attributes::AttributesFormats;
+use half::f16;
+
+// One vertex per value, only the components the format has are used. Out of range values are
+// clamped, everything is big endian.
+pub fn encode_attribute(format: AttributesFormats, values: &[[f32; 4]]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * format.get_size() as usize);
+    for value in values {
+        encode_vertex(format, value, &mut out);
+    }
+    out
+}
+
+fn encode_vertex(format: AttributesFormats, value: &[f32; 4], out: &mut Vec<u8>) {
+    let components = &value[..format.get_component_count()];
+    match format {
+        AttributesFormats::U8ToF32
+        | AttributesFormats::TwoU8ToTwoF32
+        | AttributesFormats::FourU8ToFourF32 => {
+            for &c in components {
+                out.push(unorm(c, 8) as u8);
+            }
+        }
+        AttributesFormats::TwoU16ToTwoF32 => {
+            for &c in components {
+                push_u16(out, unorm(c, 16));
+            }
+        }
+        AttributesFormats::U8ToU32
+        | AttributesFormats::TwoU8ToTwoU32
+        | AttributesFormats::FourU8ToFourU32 => {
+            for &c in components {
+                out.push(uint(c, 8) as u8);
+            }
+        }
+        AttributesFormats::I8ToF32
+        | AttributesFormats::TwoI8ToF32
+        | AttributesFormats::FourI8ToFourF32 => {
+            for &c in components {
+                out.push(snorm(c, 8) as u8);
+            }
+        }
+        AttributesFormats::TwoI16ToTwoF32 => {
+            for &c in components {
+                push_u16(out, snorm(c, 16));
+            }
+        }
+        // X in the lowest bits, the last two bits are left empty
+        AttributesFormats::ThreeI10toThreeF32 => {
+            let packed = snorm(components[0], 10)
+                | (snorm(components[1], 10) << 10)
+                | (snorm(components[2], 10) << 20);
+            push_u32(out, packed);
+        }
+        AttributesFormats::I8 | AttributesFormats::TwoI8 | AttributesFormats::FourI8 => {
+            for &c in components {
+                out.push(sint(c, 8) as u8);
+            }
+        }
+        AttributesFormats::TwoF16ToTwoF32 | AttributesFormats::FourF16ToFourF32 => {
+            for &c in components {
+                push_u16(out, u32::from(f16::from_f32(c).to_bits()));
+            }
+        }
+        AttributesFormats::F32
+        | AttributesFormats::TwoF32
+        | AttributesFormats::ThreeF32
+        | AttributesFormats::FourF32 => {
+            for &c in components {
+                push_u32(out, c.to_bits());
+            }
+        }
+    }
+}
+
+fn unorm(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << bits) - 1) as f32;
+    (value.clamp(0f32, 1f32) * max_value).round() as u32
+}
+
+fn snorm(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << (bits - 1)) - 1) as f32;
+    let signed = (value.clamp(-1f32, 1f32) * max_value).round() as i32;
+    (signed as u32) & (((1u64 << bits) - 1) as u32)
+}
+
+fn uint(value: f32, bits: u32) -> u32 {
+    value.max(0f32).min(((1u64 << bits) - 1) as f32).round() as u32
+}
+
+fn sint(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << (bits - 1)) - 1) as f32;
+    let signed = value.max(-max_value - 1f32).min(max_value).round() as i32;
+    (signed as u32) & (((1u64 << bits) - 1) as u32)
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u32) {
+    out.push((value >> 8) as u8);
+    out.push(value as u8);
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&[
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ]);
+}
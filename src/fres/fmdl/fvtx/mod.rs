@@ -1,4 +1,5 @@
 use self::attributes::Attributes;
+use self::decode::decode_attribute;
 use error::{check_magic_number, UserDataNotEmpty};
 use ez_io::ReadE;
 use std::error::Error;
@@ -7,13 +8,17 @@ use std::io::{Read, Seek};
 use util::{BufferInfo, DataArray, Importable, IndexGroup, Pointer};
 
 pub mod attributes;
+pub mod decode;
+pub mod encode;
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FVTX {
     pub header: Header,
     pub attributes_index_group: IndexGroup<Attributes>,
     pub buffer_info_array: DataArray<BufferInfo>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub attribute_count: u8,
     pub buffer_info_count: u8,
@@ -75,3 +80,46 @@ impl Importable for Header {
         })
     }
 }
+
+impl FVTX {
+    // Every value of the attribute with this name, None when there is no such attribute
+    pub fn get_attribute_values<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        name: &str,
+    ) -> Result<Option<Vec<[f32; 4]>>, Box<Error>> {
+        for (index, entry) in self.attributes_index_group.entries.iter().enumerate() {
+            if entry.get_name(reader)? == name {
+                return self.get_attribute_values_at(reader, index);
+            }
+        }
+        Ok(None)
+    }
+    // Same, with the index of the attribute in the index group, as key shapes store them
+    pub fn get_attribute_values_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+    ) -> Result<Option<Vec<[f32; 4]>>, Box<Error>> {
+        let attribute = match self.attributes_index_group.entries.get(index) {
+            Some(e) => e.get_data(reader)?,
+            None => return Ok(None),
+        };
+        let buffer_info: BufferInfo = match self
+            .buffer_info_array
+            .entries
+            .get(usize::from(attribute.buffer_info_index))
+        {
+            Some(e) => e.get_data(reader)?,
+            None => return Ok(None),
+        };
+        let data = buffer_info.get_data(reader)?;
+        Ok(Some(decode_attribute(
+            attribute.format,
+            &data,
+            usize::from(buffer_info.stride),
+            usize::from(attribute.buffer_offset),
+            self.header.nb_vertices as usize,
+        )))
+    }
+}
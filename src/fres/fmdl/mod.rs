@@ -11,6 +11,7 @@ use std::error::Error;
 use std::io::{Read, Seek};
 use util::{DataArray, Importable, IndexGroup, Pointer};
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FMDL {
     pub header: Header,
     pub fvtx_array: DataArray<FVTX>,
@@ -19,6 +20,7 @@ pub struct FMDL {
     pub fshp_index_group: IndexGroup<FSHP>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub file_name_offset: Pointer,
     pub file_path_offset: Pointer,
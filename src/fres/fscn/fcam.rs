@@ -0,0 +1,182 @@
+use super::Flags;
+use error::check_magic_number;
+use ez_io::ReadE;
+use fres::curve::{animate_values, Curve};
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct FCAM {
+    pub header: Header,
+    pub curve_array: DataArray<Curve>,
+    pub base_data: CameraData,
+}
+
+pub struct Header {
+    pub flags: Flags,
+    pub rotation_mode: RotationMode,
+    pub projection_mode: ProjectionMode,
+    pub frame_count: i32,
+    pub curve_count: u8,
+    pub user_data_entry_count: u16,
+    pub baked_size: u32,
+    pub name_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_data_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+#[derive(Copy, Clone)]
+pub enum RotationMode {
+    Aim,
+    EulerZXY,
+}
+
+#[derive(Copy, Clone)]
+pub enum ProjectionMode {
+    Orthographic,
+    Perspective,
+}
+
+// With an orthographic projection, the field of view is the height of the view volume. With the
+// aim rotation mode, rotation is the position of the aimed point.
+pub struct CameraData {
+    pub clip_near: f32,
+    pub clip_far: f32,
+    pub aspect_ratio: f32,
+    pub field_of_view: f32,
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+    pub twist: f32,
+}
+
+impl Importable for FCAM {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FCAM, Box<Error>> {
+        let header = Header::import(reader)?;
+        header.curve_array_offset.seek_abs_pos(reader)?;
+        let curve_array = DataArray::new(reader, 0x24, u32::from(header.curve_count))?;
+        header.base_data_offset.seek_abs_pos(reader)?;
+        let base_data = CameraData::import(reader)?;
+        Ok(FCAM {
+            header,
+            curve_array,
+            base_data,
+        })
+    }
+}
+
+impl FCAM {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.header.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<CameraData, Box<Error>> {
+        let mut values = self.base_data.to_values();
+        animate_values(reader, &self.curve_array, frame, &mut values)?;
+        Ok(CameraData::from_values(&values))
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'C', b'A', b'M'])?;
+        let flags = Flags::import(reader)?;
+        let rotation_mode = match flags.raw_bits & 0b1_0000_0000 {
+            0 => RotationMode::Aim,
+            _ => RotationMode::EulerZXY,
+        };
+        let projection_mode = match flags.raw_bits & 0b100_0000_0000 {
+            0 => ProjectionMode::Orthographic,
+            _ => ProjectionMode::Perspective,
+        };
+        reader.seek(SeekFrom::Current(2))?;
+        let frame_count = reader.read_be_to_i32()?;
+        let curve_count = reader.read_to_u8()?;
+        reader.seek(SeekFrom::Current(1))?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let baked_size = reader.read_be_to_u32()?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_data_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            flags,
+            rotation_mode,
+            projection_mode,
+            frame_count,
+            curve_count,
+            user_data_entry_count,
+            baked_size,
+            name_offset,
+            curve_array_offset,
+            base_data_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for CameraData {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<CameraData, Box<Error>> {
+        let mut values = [0f32; 11];
+        for value in &mut values {
+            *value = reader.read_be_to_f32()?;
+        }
+        Ok(CameraData::from_values(&values))
+    }
+}
+
+impl CameraData {
+    pub fn from_values(values: &[f32]) -> CameraData {
+        CameraData {
+            clip_near: values[0],
+            clip_far: values[1],
+            aspect_ratio: values[2],
+            field_of_view: values[3],
+            position: [values[4], values[5], values[6]],
+            rotation: [values[7], values[8], values[9]],
+            twist: values[10],
+        }
+    }
+    pub fn to_values(&self) -> Vec<f32> {
+        vec![
+            self.clip_near,
+            self.clip_far,
+            self.aspect_ratio,
+            self.field_of_view,
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            self.rotation[0],
+            self.rotation[1],
+            self.rotation[2],
+            self.twist,
+        ]
+    }
+}
+
+impl fmt::Display for RotationMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            RotationMode::Aim => "Aim",
+            RotationMode::EulerZXY => "Euler ZXY",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl fmt::Display for ProjectionMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            ProjectionMode::Orthographic => "Orthographic",
+            ProjectionMode::Perspective => "Perspective",
+        };
+        write!(f, "{}", text)
+    }
+}
@@ -0,0 +1,136 @@
+use super::Flags;
+use error::check_magic_number;
+use ez_io::ReadE;
+use fres::curve::{animate_values, Curve};
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct FFOG {
+    pub header: Header,
+    pub curve_array: DataArray<Curve>,
+    pub base_data: FogData,
+}
+
+pub struct Header {
+    pub flags: Flags,
+    pub frame_count: i32,
+    pub curve_count: u8,
+    pub distance_attenuation_function_index: i8,
+    pub user_data_entry_count: u16,
+    pub baked_size: u32,
+    pub name_offset: Pointer,
+    pub distance_attenuation_function_name_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_data_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+pub struct FogData {
+    pub distance_attenuation: [f32; 2],
+    pub color: [f32; 3],
+}
+
+impl Importable for FFOG {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FFOG, Box<Error>> {
+        let header = Header::import(reader)?;
+        header.curve_array_offset.seek_abs_pos(reader)?;
+        let curve_array = DataArray::new(reader, 0x24, u32::from(header.curve_count))?;
+        header.base_data_offset.seek_abs_pos(reader)?;
+        let base_data = FogData::import(reader)?;
+        Ok(FFOG {
+            header,
+            curve_array,
+            base_data,
+        })
+    }
+}
+
+impl FFOG {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.header.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_distance_attenuation_function_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<String>, Box<Error>> {
+        if self.header.distance_attenuation_function_index < 0 {
+            return Ok(None);
+        }
+        self.header
+            .distance_attenuation_function_name_offset
+            .seek_abs_pos(reader)?;
+        Ok(Some(read_text_entry(reader)?))
+    }
+    pub fn sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<FogData, Box<Error>> {
+        let mut values = self.base_data.to_values();
+        animate_values(reader, &self.curve_array, frame, &mut values)?;
+        Ok(FogData::from_values(&values))
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'F', b'O', b'G'])?;
+        let flags = Flags::import(reader)?;
+        reader.seek(SeekFrom::Current(2))?;
+        let frame_count = reader.read_be_to_i32()?;
+        let curve_count = reader.read_to_u8()?;
+        let distance_attenuation_function_index = reader.read_to_u8()? as i8;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let baked_size = reader.read_be_to_u32()?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let distance_attenuation_function_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_data_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            flags,
+            frame_count,
+            curve_count,
+            distance_attenuation_function_index,
+            user_data_entry_count,
+            baked_size,
+            name_offset,
+            distance_attenuation_function_name_offset,
+            curve_array_offset,
+            base_data_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for FogData {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FogData, Box<Error>> {
+        let mut values = [0f32; 5];
+        for value in &mut values {
+            *value = reader.read_be_to_f32()?;
+        }
+        Ok(FogData::from_values(&values))
+    }
+}
+
+impl FogData {
+    pub fn from_values(values: &[f32]) -> FogData {
+        FogData {
+            distance_attenuation: [values[0], values[1]],
+            color: [values[2], values[3], values[4]],
+        }
+    }
+    pub fn to_values(&self) -> Vec<f32> {
+        vec![
+            self.distance_attenuation[0],
+            self.distance_attenuation[1],
+            self.color[0],
+            self.color[1],
+            self.color[2],
+        ]
+    }
+}
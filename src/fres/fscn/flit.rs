@@ -0,0 +1,314 @@
+use super::Flags;
+use error::check_magic_number;
+use ez_io::ReadE;
+use fres::curve::{animate_values, Curve};
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct FLIT {
+    pub header: Header,
+    pub curve_array: DataArray<Curve>,
+    pub base_data: LightData,
+}
+
+pub struct Header {
+    pub flags: Flags,
+    pub base_data_fields: BaseDataFields,
+    pub curve_enabled: bool,
+    pub user_data_entry_count: u16,
+    pub frame_count: i32,
+    pub curve_count: u8,
+    pub light_type_index: i8,
+    pub distance_attenuation_function_index: i8,
+    pub angle_attenuation_function_index: i8,
+    pub baked_size: u32,
+    pub name_offset: Pointer,
+    pub light_type_name_offset: Pointer,
+    pub distance_attenuation_function_name_offset: Pointer,
+    pub angle_attenuation_function_name_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_data_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+// Tells which fields are actually stored in the base data
+pub struct BaseDataFields {
+    pub enable: bool,
+    pub position: bool,
+    pub direction: bool,
+    pub distance_attenuation: bool,
+    pub angle_attenuation: bool,
+    pub color_0: bool,
+    pub color_1: bool,
+}
+
+pub struct LightData {
+    pub enable: bool,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub distance_attenuation: [f32; 2],
+    pub angle_attenuation: [f32; 2],
+    pub color_0: [f32; 3],
+    pub color_1: [f32; 3],
+}
+
+impl Importable for FLIT {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FLIT, Box<Error>> {
+        let header = Header::import(reader)?;
+        header.curve_array_offset.seek_abs_pos(reader)?;
+        let curve_array = DataArray::new(reader, 0x24, u32::from(header.curve_count))?;
+        header.base_data_offset.seek_abs_pos(reader)?;
+        let base_data = LightData::import_fields(reader, &header.base_data_fields)?;
+        Ok(FLIT {
+            header,
+            curve_array,
+            base_data,
+        })
+    }
+}
+
+impl FLIT {
+    pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.header.name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_light_type_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<String>, Box<Error>> {
+        if self.header.light_type_index < 0 {
+            return Ok(None);
+        }
+        self.header.light_type_name_offset.seek_abs_pos(reader)?;
+        Ok(Some(read_text_entry(reader)?))
+    }
+    pub fn get_distance_attenuation_function_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<String>, Box<Error>> {
+        if self.header.distance_attenuation_function_index < 0 {
+            return Ok(None);
+        }
+        self.header
+            .distance_attenuation_function_name_offset
+            .seek_abs_pos(reader)?;
+        Ok(Some(read_text_entry(reader)?))
+    }
+    pub fn get_angle_attenuation_function_name<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<String>, Box<Error>> {
+        if self.header.angle_attenuation_function_index < 0 {
+            return Ok(None);
+        }
+        self.header
+            .angle_attenuation_function_name_offset
+            .seek_abs_pos(reader)?;
+        Ok(Some(read_text_entry(reader)?))
+    }
+    pub fn sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<LightData, Box<Error>> {
+        let mut values = self.base_data.to_values();
+        let enable = values[0];
+        animate_values(reader, &self.curve_array, frame, &mut values)?;
+        // The flag only tells whether the light can be switched on and off by its curve
+        if !self.header.curve_enabled {
+            values[0] = enable;
+        }
+        Ok(LightData::from_values(&values))
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'L', b'I', b'T'])?;
+        let flags = Flags::import(reader)?;
+        let curve_enabled = flags.raw_bits & (1 << 8) != 0;
+        let base_data_fields = BaseDataFields {
+            enable: flags.raw_bits & (1 << 9) != 0,
+            position: flags.raw_bits & (1 << 10) != 0,
+            direction: flags.raw_bits & (1 << 11) != 0,
+            distance_attenuation: flags.raw_bits & (1 << 12) != 0,
+            angle_attenuation: flags.raw_bits & (1 << 13) != 0,
+            color_0: flags.raw_bits & (1 << 14) != 0,
+            color_1: flags.raw_bits & (1 << 15) != 0,
+        };
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let frame_count = reader.read_be_to_i32()?;
+        let curve_count = reader.read_to_u8()?;
+        let light_type_index = reader.read_to_u8()? as i8;
+        let distance_attenuation_function_index = reader.read_to_u8()? as i8;
+        let angle_attenuation_function_index = reader.read_to_u8()? as i8;
+        let baked_size = reader.read_be_to_u32()?;
+        let name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let light_type_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let distance_attenuation_function_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let angle_attenuation_function_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_data_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            flags,
+            base_data_fields,
+            curve_enabled,
+            user_data_entry_count,
+            frame_count,
+            curve_count,
+            light_type_index,
+            distance_attenuation_function_index,
+            angle_attenuation_function_index,
+            baked_size,
+            name_offset,
+            light_type_name_offset,
+            distance_attenuation_function_name_offset,
+            angle_attenuation_function_name_offset,
+            curve_array_offset,
+            base_data_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl LightData {
+    // Fields missing from the file keep their default value
+    fn import_fields<R: Read + Seek>(
+        reader: &mut R,
+        fields: &BaseDataFields,
+    ) -> Result<LightData, Box<Error>> {
+        let mut values = LightData::default().to_values();
+        if fields.enable {
+            values[0] = reader.read_be_to_i32()? as f32;
+        }
+        let present = [
+            (fields.position, 1, 3),
+            (fields.direction, 4, 3),
+            (fields.distance_attenuation, 7, 2),
+            (fields.angle_attenuation, 9, 2),
+            (fields.color_0, 11, 3),
+            (fields.color_1, 14, 3),
+        ];
+        for &(is_present, start, length) in &present {
+            if is_present {
+                for value in &mut values[start..start + length] {
+                    *value = reader.read_be_to_f32()?;
+                }
+            }
+        }
+        Ok(LightData::from_values(&values))
+    }
+    pub fn from_values(values: &[f32]) -> LightData {
+        LightData {
+            enable: values[0] != 0f32,
+            position: [values[1], values[2], values[3]],
+            direction: [values[4], values[5], values[6]],
+            distance_attenuation: [values[7], values[8]],
+            angle_attenuation: [values[9], values[10]],
+            color_0: [values[11], values[12], values[13]],
+            color_1: [values[14], values[15], values[16]],
+        }
+    }
+    pub fn to_values(&self) -> Vec<f32> {
+        vec![
+            if self.enable { 1f32 } else { 0f32 },
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            self.direction[0],
+            self.direction[1],
+            self.direction[2],
+            self.distance_attenuation[0],
+            self.distance_attenuation[1],
+            self.angle_attenuation[0],
+            self.angle_attenuation[1],
+            self.color_0[0],
+            self.color_0[1],
+            self.color_0[2],
+            self.color_1[0],
+            self.color_1[1],
+            self.color_1[2],
+        ]
+    }
+}
+
+impl Default for LightData {
+    fn default() -> LightData {
+        LightData {
+            enable: true,
+            position: [0f32; 3],
+            direction: [0f32, 0f32, -1f32],
+            distance_attenuation: [0f32; 2],
+            angle_attenuation: [0f32; 2],
+            color_0: [1f32; 3],
+            color_1: [1f32; 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::curve::tests::{linear_curve, to_bytes, write_curves, TestCurve};
+    use fres::writer::{Target, Writer};
+    use std::io::Cursor;
+
+    // A light switched off by its first curve and moved along X by the second one
+    fn make_flit(curve_enabled: bool) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_bytes(b"FLIT");
+        // Enable and position are in the base data
+        writer.write_u16(if curve_enabled { 0x0700 } else { 0x0600 });
+        writer.write_u16(0);
+        writer.write_i32(10);
+        writer.write_u8(2);
+        writer.write_bytes(&[0xFF, 0xFF, 0xFF]);
+        writer.write_u32(0);
+        for _ in 0..4 {
+            writer.write_pointer(Target::Null);
+        }
+        let curves = writer.new_label();
+        writer.write_pointer(Target::Label(curves));
+        let base_data = writer.new_label();
+        writer.write_pointer(Target::Label(base_data));
+        writer.write_pointer(Target::Null);
+        writer.place(curves);
+        let switch_off = TestCurve {
+            flags: 0x60,
+            target_offset: 0x0,
+            data_scale: 0,
+            data_offset: 0,
+            frames: to_bytes(&[0f32]),
+            keys: vec![0; 4],
+            nb_keys: 1,
+        };
+        let mut move_x = linear_curve();
+        move_x.target_offset = 0x4;
+        write_curves(&mut writer, &[switch_off, move_x]);
+        writer.place(base_data);
+        writer.write_i32(1);
+        for _ in 0..3 {
+            writer.write_f32(-3f32);
+        }
+        writer.write_deferred_data();
+        writer.finish()
+    }
+
+    #[test]
+    fn sample_curves() {
+        for &curve_enabled in &[false, true] {
+            let mut reader = Cursor::new(make_flit(curve_enabled));
+            let flit = FLIT::import(&mut reader).unwrap();
+            assert!(flit.base_data.enable);
+            assert_eq!(flit.base_data.position, [-3f32; 3]);
+            let light = flit.sample(&mut reader, 5f32).unwrap();
+            assert_eq!(light.enable, !curve_enabled);
+            assert_eq!(light.position, [4f32, -3f32, -3f32]);
+            assert_eq!(light.direction, [0f32, 0f32, -1f32]);
+        }
+    }
+}
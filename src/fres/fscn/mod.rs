@@ -0,0 +1,108 @@
+pub mod fcam;
+pub mod ffog;
+pub mod flit;
+
+use self::{fcam::FCAM, ffog::FFOG, flit::FLIT};
+use error::check_magic_number;
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{Importable, IndexGroup, Pointer};
+
+pub struct FSCN {
+    pub header: Header,
+    pub fcam_index_group: Option<IndexGroup<FCAM>>,
+    pub flit_index_group: Option<IndexGroup<FLIT>>,
+    pub ffog_index_group: Option<IndexGroup<FFOG>>,
+}
+
+pub struct Header {
+    pub file_name_offset: Pointer,
+    pub file_path_offset: Pointer,
+    pub user_data_entry_count: u16,
+    pub fcam_count: u16,
+    pub flit_count: u16,
+    pub ffog_count: u16,
+    pub fcam_index_group_offset: Pointer,
+    pub flit_index_group_offset: Pointer,
+    pub ffog_index_group_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+// Flags shared by the FCAM, FLIT and FFOG headers
+pub struct Flags {
+    pub baked_curve: bool,
+    pub looping: bool,
+    pub raw_bits: u16,
+}
+
+impl Importable for FSCN {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FSCN, Box<Error>> {
+        fn process_group<R: Read + Seek, I: Importable>(
+            count: u16,
+            index_group_pointer: &Pointer,
+            reader: &mut R,
+        ) -> Result<Option<IndexGroup<I>>, Box<Error>> {
+            Ok(if count > 0 {
+                index_group_pointer.seek_abs_pos(reader)?;
+                Some(IndexGroup::import(reader)?)
+            } else {
+                None
+            })
+        }
+        let header = Header::import(reader)?;
+        let fcam_index_group =
+            process_group(header.fcam_count, &header.fcam_index_group_offset, reader)?;
+        let flit_index_group =
+            process_group(header.flit_count, &header.flit_index_group_offset, reader)?;
+        let ffog_index_group =
+            process_group(header.ffog_count, &header.ffog_index_group_offset, reader)?;
+        Ok(FSCN {
+            header,
+            fcam_index_group,
+            flit_index_group,
+            ffog_index_group,
+        })
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'S', b'C', b'N'])?;
+        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let fcam_count = reader.read_be_to_u16()?;
+        let flit_count = reader.read_be_to_u16()?;
+        let ffog_count = reader.read_be_to_u16()?;
+        let fcam_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let flit_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let ffog_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            file_name_offset,
+            file_path_offset,
+            user_data_entry_count,
+            fcam_count,
+            flit_count,
+            ffog_count,
+            fcam_index_group_offset,
+            flit_index_group_offset,
+            ffog_index_group_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for Flags {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Flags, Box<Error>> {
+        let raw_bits = reader.read_be_to_u16()?;
+        Ok(Flags {
+            baked_curve: raw_bits & 0b001 != 0,
+            looping: raw_bits & 0b100 != 0,
+            raw_bits,
+        })
+    }
+}
@@ -0,0 +1,96 @@
+pub mod vertex_shape_animation;
+
+use self::vertex_shape_animation::VertexShapeAnimation;
+use error::check_magic_number;
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{DataArray, Importable, Pointer};
+
+pub struct FSHA {
+    pub header: Header,
+    pub vertex_shape_animation_array: DataArray<VertexShapeAnimation>,
+}
+
+pub struct Header {
+    pub file_name_offset: Pointer,
+    pub file_path_offset: Pointer,
+    pub flags: Flags,
+    pub user_data_entry_count: u16,
+    pub frame_count: i32,
+    pub vertex_shape_animation_count: u16,
+    pub key_shape_animation_info_count: u16,
+    pub curve_count: u16,
+    pub baked_size: u32,
+    pub fmdl_offset: Pointer,
+    pub bind_index_array_offset: Pointer,
+    pub vertex_shape_animation_array_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+pub struct Flags {
+    pub baked_curve: bool,
+    pub looping: bool,
+}
+
+impl Importable for FSHA {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FSHA, Box<Error>> {
+        let header = Header::import(reader)?;
+        header
+            .vertex_shape_animation_array_offset
+            .seek_abs_pos(reader)?;
+        let vertex_shape_animation_array =
+            DataArray::new(reader, 0x1C, u32::from(header.vertex_shape_animation_count))?;
+        Ok(FSHA {
+            header,
+            vertex_shape_animation_array,
+        })
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'S', b'H', b'A'])?;
+        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let flags = Flags::import(reader)?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let frame_count = reader.read_be_to_i32()?;
+        let vertex_shape_animation_count = reader.read_be_to_u16()?;
+        let key_shape_animation_info_count = reader.read_be_to_u16()?;
+        let curve_count = reader.read_be_to_u16()?;
+        reader.seek(SeekFrom::Current(2))?;
+        let baked_size = reader.read_be_to_u32()?;
+        let fmdl_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let bind_index_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let vertex_shape_animation_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            file_name_offset,
+            file_path_offset,
+            flags,
+            user_data_entry_count,
+            frame_count,
+            vertex_shape_animation_count,
+            key_shape_animation_info_count,
+            curve_count,
+            baked_size,
+            fmdl_offset,
+            bind_index_array_offset,
+            vertex_shape_animation_array_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for Flags {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Flags, Box<Error>> {
+        let raw_bits = reader.read_be_to_u16()?;
+        Ok(Flags {
+            baked_curve: raw_bits & 0b001 != 0,
+            looping: raw_bits & 0b100 != 0,
+        })
+    }
+}
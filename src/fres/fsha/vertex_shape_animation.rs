@@ -0,0 +1,131 @@
+use ez_io::ReadE;
+use fres::curve::Curve;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct VertexShapeAnimation {
+    pub curve_count: u16,
+    pub key_shape_animation_info_count: u16,
+    pub first_curve_index: i32,
+    pub first_key_shape_animation_info_index: i32,
+    pub shape_name_offset: Pointer,
+    pub key_shape_animation_info_array_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_value_array_offset: Pointer,
+}
+
+pub struct KeyShapeAnimationInfo {
+    pub curve_index: i8,
+    pub sub_bind_index: i8,
+    pub key_shape_name_offset: Pointer,
+}
+
+impl Importable for VertexShapeAnimation {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<VertexShapeAnimation, Box<Error>> {
+        let curve_count = reader.read_be_to_u16()?;
+        let key_shape_animation_info_count = reader.read_be_to_u16()?;
+        let first_curve_index = reader.read_be_to_i32()?;
+        let first_key_shape_animation_info_index = reader.read_be_to_i32()?;
+        let shape_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let key_shape_animation_info_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_value_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(VertexShapeAnimation {
+            curve_count,
+            key_shape_animation_info_count,
+            first_curve_index,
+            first_key_shape_animation_info_index,
+            shape_name_offset,
+            key_shape_animation_info_array_offset,
+            curve_array_offset,
+            base_value_array_offset,
+        })
+    }
+}
+
+impl VertexShapeAnimation {
+    pub fn get_shape_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.shape_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_key_shape_animation_infos<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<KeyShapeAnimationInfo>, Box<Error>> {
+        self.key_shape_animation_info_array_offset
+            .seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x8, u32::from(self.key_shape_animation_info_count))?;
+        Ok(array)
+    }
+    pub fn get_curves<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<Curve>, Box<Error>> {
+        self.curve_array_offset.seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x24, u32::from(self.curve_count))?;
+        Ok(array)
+    }
+    // The first Key Shape is the base shape and has no weight stored
+    pub fn get_base_values<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<f32>, Box<Error>> {
+        let count = self.key_shape_animation_info_count.saturating_sub(1);
+        let mut base_values = Vec::with_capacity(usize::from(count));
+        if count == 0 {
+            return Ok(base_values);
+        }
+        self.base_value_array_offset.seek_abs_pos(reader)?;
+        for _ in 0..count {
+            base_values.push(reader.read_be_to_f32()?);
+        }
+        Ok(base_values)
+    }
+    // Weight of every Key Shape at a given frame, base shape excluded
+    pub fn get_weights_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<Vec<(String, f32)>, Box<Error>> {
+        let base_values = self.get_base_values(reader)?;
+        let curves = self.get_curves(reader)?;
+        let mut weights = Vec::with_capacity(base_values.len());
+        for (i, info_entry) in self
+            .get_key_shape_animation_infos(reader)?
+            .entries
+            .iter()
+            .enumerate()
+            .skip(1)
+        {
+            let info = info_entry.get_data(reader)?;
+            let name = info.get_key_shape_name(reader)?;
+            let weight = match curves.entries.get(info.curve_index as usize) {
+                Some(curve_entry) if info.curve_index >= 0 => {
+                    curve_entry.get_data(reader)?.evaluate(frame)
+                }
+                _ => base_values.get(i - 1).cloned().unwrap_or(0f32),
+            };
+            weights.push((name, weight));
+        }
+        Ok(weights)
+    }
+}
+
+impl Importable for KeyShapeAnimationInfo {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<KeyShapeAnimationInfo, Box<Error>> {
+        let curve_index = reader.read_to_u8()? as i8;
+        let sub_bind_index = reader.read_to_u8()? as i8;
+        reader.seek(SeekFrom::Current(2))?;
+        let key_shape_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(KeyShapeAnimationInfo {
+            curve_index,
+            sub_bind_index,
+            key_shape_name_offset,
+        })
+    }
+}
+
+impl KeyShapeAnimationInfo {
+    pub fn get_key_shape_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.key_shape_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
@@ -0,0 +1,187 @@
+use ez_io::ReadE;
+use fres::curve::Curve;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct MaterialAnimation {
+    pub parameter_animation_info_count: u16,
+    pub curve_count: u16,
+    pub constant_count: u16,
+    pub first_curve_index: i32,
+    pub first_parameter_animation_info_index: i32,
+    pub material_name_offset: Pointer,
+    pub parameter_animation_info_array_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub constant_array_offset: Pointer,
+}
+
+pub struct ParameterAnimationInfo {
+    pub first_curve_index: u16,
+    pub float_curve_count: u16,
+    pub int_curve_count: u16,
+    pub first_constant_index: u16,
+    pub constant_count: u16,
+    pub sub_bind_index: u16,
+    pub parameter_name_offset: Pointer,
+}
+
+pub struct Constant {
+    pub target_offset: u32,
+    pub value: u32,
+}
+
+// Value of one animated shader parameter at a given frame, as (offset in the parameter, value)
+pub struct SampledParameter {
+    pub name: String,
+    pub values: Vec<(u32, f32)>,
+}
+
+impl Importable for MaterialAnimation {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<MaterialAnimation, Box<Error>> {
+        let parameter_animation_info_count = reader.read_be_to_u16()?;
+        let curve_count = reader.read_be_to_u16()?;
+        let constant_count = reader.read_be_to_u16()?;
+        reader.seek(SeekFrom::Current(2))?;
+        let first_curve_index = reader.read_be_to_i32()?;
+        let first_parameter_animation_info_index = reader.read_be_to_i32()?;
+        let material_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let parameter_animation_info_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let constant_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(MaterialAnimation {
+            parameter_animation_info_count,
+            curve_count,
+            constant_count,
+            first_curve_index,
+            first_parameter_animation_info_index,
+            material_name_offset,
+            parameter_animation_info_array_offset,
+            curve_array_offset,
+            constant_array_offset,
+        })
+    }
+}
+
+impl MaterialAnimation {
+    pub fn get_material_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.material_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_parameter_animation_infos<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<ParameterAnimationInfo>, Box<Error>> {
+        self.parameter_animation_info_array_offset
+            .seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x10, u32::from(self.parameter_animation_info_count))?;
+        Ok(array)
+    }
+    pub fn get_curves<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<Curve>, Box<Error>> {
+        self.curve_array_offset.seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x24, u32::from(self.curve_count))?;
+        Ok(array)
+    }
+    pub fn get_constants<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<Constant>, Box<Error>> {
+        let mut constants = Vec::with_capacity(usize::from(self.constant_count));
+        if self.constant_count == 0 {
+            return Ok(constants);
+        }
+        self.constant_array_offset.seek_abs_pos(reader)?;
+        for _ in 0..self.constant_count {
+            constants.push(Constant::import(reader)?);
+        }
+        Ok(constants)
+    }
+    // Evaluates every curve and constant of this material at a given frame
+    pub fn sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<Vec<SampledParameter>, Box<Error>> {
+        let mut curves = Vec::with_capacity(usize::from(self.curve_count));
+        for curve_entry in self.get_curves(reader)?.entries {
+            curves.push(curve_entry.get_data(reader)?);
+        }
+        let constants = self.get_constants(reader)?;
+        let mut sampled = Vec::with_capacity(usize::from(self.parameter_animation_info_count));
+        for info_entry in self.get_parameter_animation_infos(reader)?.entries {
+            let info = info_entry.get_data(reader)?;
+            let name = info.get_parameter_name(reader)?;
+            let mut values = Vec::new();
+            let first_constant = usize::from(info.first_constant_index);
+            let last_constant = first_constant + usize::from(info.constant_count);
+            if let Some(c) = constants.get(first_constant..last_constant) {
+                for constant in c {
+                    values.push((constant.target_offset, constant.as_f32()));
+                }
+            }
+            let first_curve = usize::from(info.first_curve_index);
+            let last_curve = first_curve
+                + usize::from(info.float_curve_count)
+                + usize::from(info.int_curve_count);
+            if let Some(c) = curves.get(first_curve..last_curve) {
+                for curve in c {
+                    values.push((curve.target_offset, curve.evaluate(frame)));
+                }
+            }
+            values.sort_by_key(|v| v.0);
+            sampled.push(SampledParameter { name, values });
+        }
+        Ok(sampled)
+    }
+}
+
+impl Importable for ParameterAnimationInfo {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<ParameterAnimationInfo, Box<Error>> {
+        let first_curve_index = reader.read_be_to_u16()?;
+        let float_curve_count = reader.read_be_to_u16()?;
+        let int_curve_count = reader.read_be_to_u16()?;
+        let first_constant_index = reader.read_be_to_u16()?;
+        let constant_count = reader.read_be_to_u16()?;
+        let sub_bind_index = reader.read_be_to_u16()?;
+        let parameter_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(ParameterAnimationInfo {
+            first_curve_index,
+            float_curve_count,
+            int_curve_count,
+            first_constant_index,
+            constant_count,
+            sub_bind_index,
+            parameter_name_offset,
+        })
+    }
+}
+
+impl ParameterAnimationInfo {
+    pub fn get_parameter_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.parameter_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
+
+impl Importable for Constant {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Constant, Box<Error>> {
+        let target_offset = reader.read_be_to_u32()?;
+        let value = reader.read_be_to_u32()?;
+        Ok(Constant {
+            target_offset,
+            value,
+        })
+    }
+}
+
+impl Constant {
+    pub fn as_f32(&self) -> f32 {
+        f32::from_bits(self.value)
+    }
+    pub fn as_i32(&self) -> i32 {
+        self.value as i32
+    }
+}
@@ -0,0 +1,115 @@
+pub mod material_animation;
+
+use self::material_animation::MaterialAnimation;
+use error::check_magic_number;
+use ez_io::ReadE;
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Seek};
+use util::{DataArray, Importable, Pointer};
+
+pub struct FSHU {
+    pub header: Header,
+    pub material_animation_array: DataArray<MaterialAnimation>,
+}
+
+pub struct Header {
+    pub file_name_offset: Pointer,
+    pub file_path_offset: Pointer,
+    pub flags: Flags,
+    pub frame_count: i32,
+    pub material_animation_count: u16,
+    pub user_data_entry_count: u16,
+    pub parameter_animation_info_count: i32,
+    pub curve_count: i32,
+    pub baked_size: u32,
+    pub fmdl_offset: Pointer,
+    pub bind_index_array_offset: Pointer,
+    pub material_animation_array_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+pub struct Flags {
+    pub baked_curve: bool,
+    pub looping: bool,
+}
+
+// The same FSHU structure is used by three different sub-file groups
+#[derive(Copy, Clone)]
+pub enum Kind {
+    ShaderParameters,
+    ColorAnimation,
+    TextureSRTAnimation,
+}
+
+impl Importable for FSHU {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FSHU, Box<Error>> {
+        let header = Header::import(reader)?;
+        header
+            .material_animation_array_offset
+            .seek_abs_pos(reader)?;
+        let material_animation_array =
+            DataArray::new(reader, 0x20, u32::from(header.material_animation_count))?;
+        Ok(FSHU {
+            header,
+            material_animation_array,
+        })
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'S', b'H', b'U'])?;
+        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let flags = Flags::import(reader)?;
+        let frame_count = reader.read_be_to_i32()?;
+        let material_animation_count = reader.read_be_to_u16()?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let parameter_animation_info_count = reader.read_be_to_i32()?;
+        let curve_count = reader.read_be_to_i32()?;
+        let baked_size = reader.read_be_to_u32()?;
+        let fmdl_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let bind_index_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let material_animation_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            file_name_offset,
+            file_path_offset,
+            flags,
+            frame_count,
+            material_animation_count,
+            user_data_entry_count,
+            parameter_animation_info_count,
+            curve_count,
+            baked_size,
+            fmdl_offset,
+            bind_index_array_offset,
+            material_animation_array_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for Flags {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Flags, Box<Error>> {
+        let raw_bits = reader.read_be_to_u32()?;
+        Ok(Flags {
+            baked_curve: raw_bits & 0b001 != 0,
+            looping: raw_bits & 0b100 != 0,
+        })
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            Kind::ShaderParameters => "Shader Parameters",
+            Kind::ColorAnimation => "Color Animation",
+            Kind::TextureSRTAnimation => "Texture SRT Animation",
+        };
+        write!(f, "{}", text)
+    }
+}
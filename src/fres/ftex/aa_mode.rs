@@ -6,6 +6,7 @@ use std::io::{Read, Seek};
 use util::Importable;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum AAMode {
     OneTime = 0x0,
     TwoTimes = 0x1,
@@ -44,3 +45,9 @@ impl fmt::Display for AAMode {
         )
     }
 }
+
+impl AAMode {
+    pub fn get_sample_count(&self) -> u32 {
+        1 << (*self as u32)
+    }
+}
@@ -0,0 +1,727 @@
+use error::UnsupportedFormat;
+use fres::ftex::format::Format;
+use std::cmp::min;
+use std::error::Error;
+
+// How hard the encoder looks for good endpoints
+#[derive(Copy, Clone, PartialEq)]
+pub enum Quality {
+    // Endpoints from the extent of the colors along their main axis
+    Fast,
+    // Like Fast, then refined with least squares, alpha and BC4/BC5 also try the other mode
+    Normal,
+    // Tries every ordering of the colors into the palette, and more endpoints for BC4/BC5
+    High,
+}
+
+type Color = [f32; 3];
+
+// data holds RGBA values like decode_rgba32f gives them, in [-1, 1] for the signed formats. sRGB
+// data is still encoded, but endpoints are fitted as the GPU interpolates them, in linear space.
+pub fn encode_bcn(
+    format: Format,
+    data: &[f32],
+    width: u32,
+    height: u32,
+    quality: Quality,
+) -> Result<Vec<u8>, Box<Error>> {
+    let srgb = format.is_srgb();
+    let signed = format.is_signed();
+    let (width, height) = (width as usize, height as usize);
+    let mut out = Vec::new();
+    for block_y in 0..height.div_ceil(4) {
+        for block_x in 0..width.div_ceil(4) {
+            // Pixels past the edge repeat the last row or column
+            let mut block = [[0f32; 4]; 16];
+            for (i, pixel) in block.iter_mut().enumerate() {
+                let x = min(block_x * 4 + i % 4, width - 1);
+                let y = min(block_y * 4 + i / 4, height - 1);
+                let pos = (y * width + x) * 4;
+                pixel.clone_from_slice(&data[pos..pos + 4]);
+            }
+            let channel = |c: usize| {
+                let mut values = [0f32; 16];
+                for (value, pixel) in values.iter_mut().zip(block.iter()) {
+                    *value = pixel[c];
+                }
+                values
+            };
+            match format {
+                Format::TBc1Unorm | Format::TBc1Srgb => {
+                    out.extend_from_slice(&encode_color_block(&block, srgb, true, quality));
+                }
+                Format::TBc2Unorm | Format::TBc2Srgb => {
+                    let mut alpha = 0u64;
+                    for (i, pixel) in block.iter().enumerate() {
+                        let value = (pixel[3].clamp(0f32, 1f32) * 15f32).round() as u64;
+                        alpha |= value << (4 * i);
+                    }
+                    for i in 0..8 {
+                        out.push((alpha >> (8 * i)) as u8);
+                    }
+                    out.extend_from_slice(&encode_color_block(&block, srgb, false, quality));
+                }
+                Format::TBc3Unorm | Format::TBc3Srgb => {
+                    out.extend_from_slice(&encode_bc4_block(&channel(3), false, quality));
+                    out.extend_from_slice(&encode_color_block(&block, srgb, false, quality));
+                }
+                Format::TBc4Unorm | Format::TBc4Snorm => {
+                    out.extend_from_slice(&encode_bc4_block(&channel(0), signed, quality));
+                }
+                Format::TBc5Unorm | Format::TBc5Snorm => {
+                    out.extend_from_slice(&encode_bc4_block(&channel(0), signed, quality));
+                    out.extend_from_slice(&encode_bc4_block(&channel(1), signed, quality));
+                }
+                _ => {
+                    return Err(Box::new(UnsupportedFormat {
+                        format: format as u32,
+                        container: "the BCn encoder".to_string(),
+                    }))
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1f32 / 2.4) - 0.055
+    }
+}
+
+fn add(a: Color, b: Color) -> Color {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Color, b: Color) -> Color {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Color, factor: f32) -> Color {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: Color, b: Color) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn clamp_color(a: Color) -> Color {
+    [
+        a[0].clamp(0f32, 1f32),
+        a[1].clamp(0f32, 1f32),
+        a[2].clamp(0f32, 1f32),
+    ]
+}
+
+// Colors are fitted and compared in the space the GPU interpolates in
+fn to_fitting_space(pixel: &[f32; 4], srgb: bool) -> Color {
+    let color = clamp_color([pixel[0], pixel[1], pixel[2]]);
+    if srgb {
+        [
+            srgb_to_linear(color[0]),
+            srgb_to_linear(color[1]),
+            srgb_to_linear(color[2]),
+        ]
+    } else {
+        color
+    }
+}
+
+fn quantize_565(color: Color, srgb: bool) -> u16 {
+    let color = clamp_color(color);
+    let encode = |value: f32| if srgb { linear_to_srgb(value) } else { value };
+    let r = (encode(color[0]) * 31f32).round() as u16;
+    let g = (encode(color[1]) * 63f32).round() as u16;
+    let b = (encode(color[2]) * 31f32).round() as u16;
+    (r << 11) | (g << 5) | b
+}
+
+fn expand_565(value: u16, srgb: bool) -> Color {
+    let (r, g, b) = (value >> 11, (value >> 5) & 0x3F, value & 0x1F);
+    let expand = |value: u16| {
+        let value = f32::from(value) / 255f32;
+        if srgb {
+            srgb_to_linear(value)
+        } else {
+            value
+        }
+    };
+    [
+        expand((r << 3) | (r >> 2)),
+        expand((g << 2) | (g >> 4)),
+        expand((b << 3) | (b >> 2)),
+    ]
+}
+
+// The fourth color only exists when the first endpoint is the greater one, otherwise it is
+// transparent black
+fn get_color_palette(color_0: u16, color_1: u16, srgb: bool) -> ([Color; 4], bool) {
+    let (a, b) = (expand_565(color_0, srgb), expand_565(color_1, srgb));
+    if color_0 > color_1 {
+        (
+            [
+                a,
+                b,
+                add(scale(a, 2f32 / 3f32), scale(b, 1f32 / 3f32)),
+                add(scale(a, 1f32 / 3f32), scale(b, 2f32 / 3f32)),
+            ],
+            true,
+        )
+    } else {
+        ([a, b, scale(add(a, b), 0.5), [0f32; 3]], false)
+    }
+}
+
+// Indices of every pixel and the total squared error
+fn get_color_indices(
+    colors: &[Color; 16],
+    transparent: &[bool; 16],
+    color_0: u16,
+    color_1: u16,
+    srgb: bool,
+) -> ([u8; 16], f32) {
+    let (palette, four_colors) = get_color_palette(color_0, color_1, srgb);
+    let nb_colors = if four_colors { 4 } else { 3 };
+    let mut indices = [0u8; 16];
+    let mut total_error = 0f32;
+    for i in 0..16 {
+        if transparent[i] {
+            indices[i] = 3;
+            continue;
+        }
+        let mut best_error = f32::MAX;
+        for (index, entry) in palette.iter().take(nb_colors).enumerate() {
+            let difference = sub(colors[i], *entry);
+            let error = dot(difference, difference);
+            if error < best_error {
+                best_error = error;
+                indices[i] = index as u8;
+            }
+        }
+        total_error += best_error;
+    }
+    (indices, total_error)
+}
+
+// Where each index sits between the first and the second endpoint, 1 being the first one
+fn get_index_weight(index: u8, four_colors: bool) -> f32 {
+    match (index, four_colors) {
+        (0, _) => 1f32,
+        (1, _) => 0f32,
+        (2, true) => 2f32 / 3f32,
+        (3, true) => 1f32 / 3f32,
+        _ => 0.5,
+    }
+}
+
+// Mean of the colors and the direction they spread the most along
+fn get_principal_axis(colors: &[Color]) -> (Color, Color) {
+    let count = colors.len() as f32;
+    let mut mean = [0f32; 3];
+    for color in colors {
+        mean = add(mean, scale(*color, 1f32 / count));
+    }
+    let mut covariance = [[0f32; 3]; 3];
+    for color in colors {
+        let difference = sub(*color, mean);
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += difference[i] * difference[j];
+            }
+        }
+    }
+    // Power iteration, starting from the row that spreads the most
+    let mut axis = covariance[0];
+    for row in covariance.iter() {
+        if dot(*row, *row) > dot(axis, axis) {
+            axis = *row;
+        }
+    }
+    for _ in 0..8 {
+        let length = dot(axis, axis).sqrt();
+        if length < 1e-9 {
+            return (mean, [0f32; 3]);
+        }
+        axis = scale(axis, 1f32 / length);
+        axis = [
+            dot(covariance[0], axis),
+            dot(covariance[1], axis),
+            dot(covariance[2], axis),
+        ];
+    }
+    let length = dot(axis, axis).sqrt();
+    if length < 1e-9 {
+        return (mean, [0f32; 3]);
+    }
+    (mean, scale(axis, 1f32 / length))
+}
+
+fn range_fit(colors: &[Color]) -> (Color, Color) {
+    let (mean, axis) = get_principal_axis(colors);
+    let mut lowest = 0f32;
+    let mut highest = 0f32;
+    for color in colors {
+        let projection = dot(sub(*color, mean), axis);
+        lowest = lowest.min(projection);
+        highest = highest.max(projection);
+    }
+    (
+        clamp_color(add(mean, scale(axis, highest))),
+        clamp_color(add(mean, scale(axis, lowest))),
+    )
+}
+
+// Best endpoints for colors that each sit at a given weight between them
+fn least_squares_fit(colors: &[Color], weights: &[f32]) -> Option<(Color, Color)> {
+    let (mut aa, mut ab, mut bb) = (0f32, 0f32, 0f32);
+    let (mut ax, mut bx) = ([0f32; 3], [0f32; 3]);
+    for (color, &weight) in colors.iter().zip(weights.iter()) {
+        let other = 1f32 - weight;
+        aa += weight * weight;
+        ab += weight * other;
+        bb += other * other;
+        ax = add(ax, scale(*color, weight));
+        bx = add(bx, scale(*color, other));
+    }
+    solve_endpoints(aa, ab, bb, ax, bx)
+}
+
+fn solve_endpoints(aa: f32, ab: f32, bb: f32, ax: Color, bx: Color) -> Option<(Color, Color)> {
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-8 {
+        return None;
+    }
+    let a = scale(sub(scale(ax, bb), scale(bx, ab)), 1f32 / determinant);
+    let b = scale(sub(scale(bx, aa), scale(ax, ab)), 1f32 / determinant);
+    Some((clamp_color(a), clamp_color(b)))
+}
+
+// Sorts the colors along the main axis, then tries every way of splitting them into consecutive
+// clusters, one per palette entry
+fn cluster_fit(colors: &[Color], four_colors: bool) -> (Color, Color) {
+    let (mean, axis) = get_principal_axis(colors);
+    let mut sorted = colors.to_vec();
+    sorted.sort_by(|a, b| {
+        dot(sub(*b, mean), axis)
+            .partial_cmp(&dot(sub(*a, mean), axis))
+            .unwrap()
+    });
+    let count = sorted.len();
+    let mut prefix = vec![[0f32; 3]; count + 1];
+    for i in 0..count {
+        prefix[i + 1] = add(prefix[i], sorted[i]);
+    }
+    let sum = |start: usize, end: usize| sub(prefix[end], prefix[start]);
+    let weights: &[f32] = if four_colors {
+        &[1f32, 2f32 / 3f32, 1f32 / 3f32, 0f32]
+    } else {
+        &[1f32, 0.5, 0f32]
+    };
+    let mut best = range_fit(colors);
+    let mut best_error = f32::MAX;
+    let mut try_split = |bounds: &[usize]| {
+        let (mut aa, mut ab, mut bb) = (0f32, 0f32, 0f32);
+        let (mut ax, mut bx) = ([0f32; 3], [0f32; 3]);
+        for (cluster, &weight) in weights.iter().enumerate() {
+            let (start, end) = (bounds[cluster], bounds[cluster + 1]);
+            let size = (end - start) as f32;
+            let other = 1f32 - weight;
+            aa += weight * weight * size;
+            ab += weight * other * size;
+            bb += other * other * size;
+            ax = add(ax, scale(sum(start, end), weight));
+            bx = add(bx, scale(sum(start, end), other));
+        }
+        if let Some((a, b)) = solve_endpoints(aa, ab, bb, ax, bx) {
+            // The squared error, minus what does not depend on the endpoints
+            let error = aa * dot(a, a) + 2f32 * ab * dot(a, b) + bb * dot(b, b)
+                - 2f32 * (dot(a, ax) + dot(b, bx));
+            if error < best_error {
+                best_error = error;
+                best = (a, b);
+            }
+        }
+    };
+    for i in 0..=count {
+        for j in i..=count {
+            if four_colors {
+                for k in j..=count {
+                    try_split(&[0, i, j, k, count]);
+                }
+            } else {
+                try_split(&[0, i, j, count]);
+            }
+        }
+    }
+    best
+}
+
+// Endpoints as they are stored, with their indices and error
+fn finish_color_block(
+    colors: &[Color; 16],
+    transparent: &[bool; 16],
+    endpoints: (Color, Color),
+    four_colors: bool,
+    srgb: bool,
+) -> (u16, u16, [u8; 16], f32) {
+    let mut color_0 = quantize_565(endpoints.0, srgb);
+    let mut color_1 = quantize_565(endpoints.1, srgb);
+    // The order of the endpoints picks the mode
+    if (four_colors && color_0 < color_1) || (!four_colors && color_0 > color_1) {
+        ::std::mem::swap(&mut color_0, &mut color_1);
+    }
+    let (indices, error) = get_color_indices(colors, transparent, color_0, color_1, srgb);
+    (color_0, color_1, indices, error)
+}
+
+// BC1 can use its three colors mode for transparent pixels, BC2 and BC3 always use four colors
+fn encode_color_block(
+    block: &[[f32; 4]; 16],
+    srgb: bool,
+    punch_through: bool,
+    quality: Quality,
+) -> [u8; 8] {
+    let mut colors = [[0f32; 3]; 16];
+    let mut transparent = [false; 16];
+    let mut opaque = Vec::new();
+    for i in 0..16 {
+        colors[i] = to_fitting_space(&block[i], srgb);
+        transparent[i] = punch_through && block[i][3] < 0.5;
+        if !transparent[i] {
+            opaque.push(colors[i]);
+        }
+    }
+    let (color_0, color_1, indices) = if opaque.is_empty() {
+        (0, 0, [3u8; 16])
+    } else {
+        let modes: &[bool] = if transparent.iter().any(|&t| t) {
+            &[false]
+        } else if punch_through && quality != Quality::Fast {
+            &[true, false]
+        } else {
+            &[true]
+        };
+        let mut best = (0, 0, [0u8; 16], f32::MAX);
+        for &four_colors in modes {
+            let endpoints = if quality == Quality::High {
+                cluster_fit(&opaque, four_colors)
+            } else {
+                range_fit(&opaque)
+            };
+            let mut result =
+                finish_color_block(&colors, &transparent, endpoints, four_colors, srgb);
+            let refinements = match quality {
+                Quality::Fast => 0,
+                Quality::Normal => 2,
+                Quality::High => 1,
+            };
+            for _ in 0..refinements {
+                let mut fitted = Vec::new();
+                let mut weights = Vec::new();
+                for i in 0..16 {
+                    if !transparent[i] {
+                        fitted.push(colors[i]);
+                        weights.push(get_index_weight(result.2[i], result.0 > result.1));
+                    }
+                }
+                if let Some(endpoints) = least_squares_fit(&fitted, &weights) {
+                    let refined =
+                        finish_color_block(&colors, &transparent, endpoints, four_colors, srgb);
+                    if refined.3 < result.3 {
+                        result = refined;
+                        continue;
+                    }
+                }
+                break;
+            }
+            if result.3 < best.3 {
+                best = result;
+            }
+        }
+        (best.0, best.1, best.2)
+    };
+    let mut bits = 0u32;
+    for (i, &index) in indices.iter().enumerate() {
+        bits |= u32::from(index) << (2 * i);
+    }
+    [
+        color_0 as u8,
+        (color_0 >> 8) as u8,
+        color_1 as u8,
+        (color_1 >> 8) as u8,
+        bits as u8,
+        (bits >> 8) as u8,
+        (bits >> 16) as u8,
+        (bits >> 24) as u8,
+    ]
+}
+
+fn quantize_bc4(value: f32, signed: bool) -> i32 {
+    if signed {
+        (value.clamp(-1f32, 1f32) * 127f32).round() as i32
+    } else {
+        (value.clamp(0f32, 1f32) * 255f32).round() as i32
+    }
+}
+
+// Same palette as the decoder, with endpoints as stored values (-127 to 127 when signed)
+fn get_bc4_palette(raw_0: i32, raw_1: i32, signed: bool) -> [f32; 8] {
+    let endpoint = |value: i32| {
+        if signed {
+            (value as f32 / 127f32).max(-1f32)
+        } else {
+            value as f32 / 255f32
+        }
+    };
+    let (value_0, value_1) = (endpoint(raw_0), endpoint(raw_1));
+    let mut palette = [0f32; 8];
+    palette[0] = value_0;
+    palette[1] = value_1;
+    if raw_0 > raw_1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i) as f32 * value_0 + i as f32 * value_1) / 7f32;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i) as f32 * value_0 + i as f32 * value_1) / 5f32;
+        }
+        palette[6] = if signed { -1f32 } else { 0f32 };
+        palette[7] = 1f32;
+    }
+    palette
+}
+
+fn get_bc4_indices(values: &[f32; 16], palette: &[f32; 8]) -> ([u8; 16], f32) {
+    let mut indices = [0u8; 16];
+    let mut total_error = 0f32;
+    for (i, &value) in values.iter().enumerate() {
+        let mut best_error = f32::MAX;
+        for (index, &entry) in palette.iter().enumerate() {
+            let error = (value - entry) * (value - entry);
+            if error < best_error {
+                best_error = error;
+                indices[i] = index as u8;
+            }
+        }
+        total_error += best_error;
+    }
+    (indices, total_error)
+}
+
+// Also used for the alpha of BC3. Eight values between the endpoints, or six plus both extremes.
+fn encode_bc4_block(values: &[f32; 16], signed: bool, quality: Quality) -> [u8; 8] {
+    let (lowest_possible, highest_possible) = if signed { (-1f32, 1f32) } else { (0f32, 1f32) };
+    let mut lowest = highest_possible;
+    let mut highest = lowest_possible;
+    // Without the values the extremes of the six values mode already cover
+    let mut inner_lowest = highest_possible;
+    let mut inner_highest = lowest_possible;
+    for &value in values.iter() {
+        let value = value.max(lowest_possible).min(highest_possible);
+        lowest = lowest.min(value);
+        highest = highest.max(value);
+        if value > lowest_possible && value < highest_possible {
+            inner_lowest = inner_lowest.min(value);
+            inner_highest = inner_highest.max(value);
+        }
+    }
+    let mut candidates = vec![(quantize_bc4(highest, signed), quantize_bc4(lowest, signed))];
+    if quality != Quality::Fast && inner_lowest <= inner_highest {
+        candidates.push((
+            quantize_bc4(inner_lowest, signed),
+            quantize_bc4(inner_highest, signed),
+        ));
+    }
+    // Nudge the endpoints around
+    let reach = match quality {
+        Quality::Fast => 0,
+        Quality::Normal => 1,
+        Quality::High => 4,
+    };
+    let (minimum, maximum) = if signed { (-127, 127) } else { (0, 255) };
+    let mut best = (0, 0, [0u8; 16], f32::MAX);
+    for &(base_0, base_1) in &candidates {
+        for offset_0 in -reach..=reach {
+            for offset_1 in -reach..=reach {
+                let raw_0 = (base_0 + offset_0).max(minimum).min(maximum);
+                let raw_1 = (base_1 + offset_1).max(minimum).min(maximum);
+                let palette = get_bc4_palette(raw_0, raw_1, signed);
+                let (indices, error) = get_bc4_indices(values, &palette);
+                if error < best.3 {
+                    best = (raw_0, raw_1, indices, error);
+                }
+            }
+        }
+    }
+    let mut bits = 0u64;
+    for (i, &index) in best.2.iter().enumerate() {
+        bits |= u64::from(index) << (3 * i);
+    }
+    let mut out = [0u8; 8];
+    out[0] = best.0 as u8;
+    out[1] = best.1 as u8;
+    for i in 0..6 {
+        out[i + 2] = (bits >> (8 * i)) as u8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::ftex::decode::{decode_rgba32f, get_image_size};
+
+    const FORMATS: [Format; 10] = [
+        Format::TBc1Unorm,
+        Format::TBc1Srgb,
+        Format::TBc2Unorm,
+        Format::TBc2Srgb,
+        Format::TBc3Unorm,
+        Format::TBc3Srgb,
+        Format::TBc4Unorm,
+        Format::TBc4Snorm,
+        Format::TBc5Unorm,
+        Format::TBc5Snorm,
+    ];
+    const QUALITIES: [Quality; 3] = [Quality::Fast, Quality::Normal, Quality::High];
+
+    // Smooth gradients, with a size that is not a multiple of the block size
+    fn make_image(width: u32, height: u32, signed: bool) -> Vec<f32> {
+        let mut data = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let (x, y) = (x as f32 / width as f32, y as f32 / height as f32);
+                let pixel = [x, y, (x + y) / 2f32, 1f32 - x * y];
+                for &value in &pixel {
+                    data.push(if signed { value * 2f32 - 1f32 } else { value });
+                }
+            }
+        }
+        data
+    }
+
+    fn get_nb_channels(format: Format) -> usize {
+        match format {
+            Format::TBc4Unorm | Format::TBc4Snorm => 1,
+            Format::TBc5Unorm | Format::TBc5Snorm => 2,
+            // BC1 alpha is only on or off
+            Format::TBc1Unorm | Format::TBc1Srgb => 3,
+            _ => 4,
+        }
+    }
+
+    // Root mean square error over the channels the format stores, in [0, 1] steps
+    fn get_error(format: Format, original: &[f32], decoded: &[f32]) -> f32 {
+        let nb_channels = get_nb_channels(format);
+        let range = if format.is_signed() { 2f32 } else { 1f32 };
+        let mut sum = 0f32;
+        let mut count = 0;
+        for (a, b) in original.chunks(4).zip(decoded.chunks(4)) {
+            // Transparent BC1 pixels are black
+            if nb_channels == 3 && a[3] < 0.5 {
+                continue;
+            }
+            for c in 0..nb_channels {
+                sum += ((a[c] - b[c]) / range).powi(2);
+            }
+            count += nb_channels;
+        }
+        (sum / count as f32).sqrt()
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let (width, height) = (22, 13);
+        for &format in &FORMATS {
+            let data = make_image(width, height, format.is_signed());
+            for &quality in &QUALITIES {
+                let encoded = encode_bcn(format, &data, width, height, quality).unwrap();
+                assert_eq!(encoded.len(), get_image_size(format, width, height));
+                let decoded = decode_rgba32f(format, &encoded, width, height).unwrap();
+                let error = get_error(format, &data, &decoded);
+                let max_error = if get_nb_channels(format) > 2 {
+                    0.05
+                } else {
+                    0.01
+                };
+                assert!(
+                    error < max_error,
+                    "Format 0x{:X}: error of {}",
+                    format as u32,
+                    error
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn better_quality_is_not_worse() {
+        let (width, height) = (16, 16);
+        for &format in &FORMATS {
+            let data = make_image(width, height, format.is_signed());
+            let errors: Vec<_> = QUALITIES
+                .iter()
+                .map(|&quality| {
+                    let encoded = encode_bcn(format, &data, width, height, quality).unwrap();
+                    let decoded = decode_rgba32f(format, &encoded, width, height).unwrap();
+                    get_error(format, &data, &decoded)
+                })
+                .collect();
+            assert!(
+                errors[1] <= errors[0] * 1.01 && errors[2] <= errors[1] * 1.01,
+                "Format 0x{:X}: errors of {:?}",
+                format as u32,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn bc1_keeps_transparent_pixels() {
+        let (width, height) = (8, 8);
+        let mut data = make_image(width, height, false);
+        for (i, pixel) in data.chunks_mut(4).enumerate() {
+            pixel[3] = if i % 3 == 0 { 0f32 } else { 1f32 };
+        }
+        for &quality in &QUALITIES {
+            let encoded = encode_bcn(Format::TBc1Unorm, &data, width, height, quality).unwrap();
+            let decoded = decode_rgba32f(Format::TBc1Unorm, &encoded, width, height).unwrap();
+            for (a, b) in data.chunks(4).zip(decoded.chunks(4)) {
+                assert_eq!(a[3], b[3]);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_blocks_are_exact() {
+        // Colors 565 and the 8 bits alpha can represent exactly
+        let pixel = [8f32 / 31f32, 20f32 / 63f32, 1f32, 204f32 / 255f32];
+        let data: Vec<f32> = (0..16).flat_map(|_| pixel.iter().cloned()).collect();
+        for &format in &[Format::TBc1Unorm, Format::TBc3Unorm, Format::TBc4Unorm] {
+            let encoded = encode_bcn(format, &data, 4, 4, Quality::Normal).unwrap();
+            let decoded = decode_rgba32f(format, &encoded, 4, 4).unwrap();
+            for chunk in decoded.chunks(4) {
+                for c in 0..get_nb_channels(format) {
+                    assert!((chunk[c] - pixel[c]).abs() < 0.5 / 255f32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let data = make_image(4, 4, false);
+        assert!(encode_bcn(Format::TcsR8G8B8A8Unorm, &data, 4, 4, Quality::Fast).is_err());
+    }
+}
@@ -5,11 +5,13 @@ use std::fmt;
 use std::io::{Read, Seek};
 use util::Importable;
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct ComponentSelector {
     composition: [Channel; 4],
 }
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum Channel {
     Red = 0,
     Green = 1,
@@ -32,6 +34,37 @@ impl Importable for ComponentSelector {
     }
 }
 
+impl ComponentSelector {
+    pub fn get_composition(&self) -> [Channel; 4] {
+        self.composition
+    }
+    // Gives what the GPU would sample, from RGBA pixels
+    pub fn apply_rgba8(&self, data: &mut [u8]) {
+        for pixel in data.chunks_mut(4) {
+            let source = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            for (value, channel) in pixel.iter_mut().zip(self.composition.iter()) {
+                *value = match *channel {
+                    Channel::Zero => 0,
+                    Channel::One => 0xFF,
+                    c => source[c as usize],
+                };
+            }
+        }
+    }
+    pub fn apply_rgba32f(&self, data: &mut [f32]) {
+        for pixel in data.chunks_mut(4) {
+            let source = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            for (value, channel) in pixel.iter_mut().zip(self.composition.iter()) {
+                *value = match *channel {
+                    Channel::Zero => 0f32,
+                    Channel::One => 1f32,
+                    c => source[c as usize],
+                };
+            }
+        }
+    }
+}
+
 impl fmt::Display for ComponentSelector {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -0,0 +1,591 @@
+use bcndecode::{decode, BcnDecoderFormat, BcnEncoding};
+use error::TextureDataTooShort;
+use fres::ftex::format::Format;
+use half::f16;
+use std::error::Error;
+
+// Integer formats give back their raw values, normalized formats are in [0, 1] or [-1, 1]. sRGB
+// data is left encoded.
+pub fn decode_rgba32f(
+    format: Format,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<f32>, Box<Error>> {
+    decode_texels(format, data, width, height, 1f32)
+}
+
+// Signed normalized values are remapped to [0, 255] and integer values are clamped, except for
+// 24 bits depth which is scaled
+pub fn decode_rgba8(
+    format: Format,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Box<Error>> {
+    match format {
+        Format::TcsR8G8B8A8Unorm | Format::TcsR8G8B8A8Srgb => {
+            check_length(format, data, width, height)?;
+            return Ok(data[..(width * height * 4) as usize].to_vec());
+        }
+        Format::TBc1Unorm
+        | Format::TBc1Srgb
+        | Format::TBc2Unorm
+        | Format::TBc2Srgb
+        | Format::TBc3Unorm
+        | Format::TBc3Srgb => return decode_bc1_to_3(format, data, width, height),
+        _ => {}
+    }
+    let integer = format.is_integer();
+    let signed = format.is_signed();
+    let mut texels = decode_texels(
+        format,
+        data,
+        width,
+        height,
+        if integer { 255f32 } else { 1f32 },
+    )?;
+    // Clamping would turn almost every depth into 255, keep its 8 most significant bits instead
+    if let Format::TX24G8Uint = format {
+        for texel in texels.chunks_mut(4) {
+            texel[0] = ((texel[0] as u32) >> 16) as f32;
+        }
+    }
+    Ok(texels
+        .iter()
+        .map(|&value| {
+            let value = if integer {
+                value
+            } else if signed {
+                (value * 0.5 + 0.5) * 255f32
+            } else {
+                value * 255f32
+            };
+            value.clamp(0f32, 255f32).round() as u8
+        })
+        .collect())
+}
+
+pub fn get_image_size(format: Format, width: u32, height: u32) -> usize {
+    let (width, height) = (width as usize, height as usize);
+    match format {
+        Format::TNv12Unorm => width * height + width.div_ceil(2) * height.div_ceil(2) * 2,
+        _ if format.is_block_compressed() => {
+            width.div_ceil(4) * height.div_ceil(4) * (format.get_bits_per_pixel() / 8) as usize
+        }
+        _ => width * height * (format.get_bits_per_pixel() / 8) as usize,
+    }
+}
+
+fn check_length(format: Format, data: &[u8], width: u32, height: u32) -> Result<(), Box<Error>> {
+    let expected = get_image_size(format, width, height);
+    if data.len() < expected {
+        return Err(Box::new(TextureDataTooShort {
+            expected,
+            got: data.len(),
+        }));
+    }
+    Ok(())
+}
+
+fn decode_texels(
+    format: Format,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    one: f32,
+) -> Result<Vec<f32>, Box<Error>> {
+    check_length(format, data, width, height)?;
+    let pixel_count = (width * height) as usize;
+    match format {
+        Format::TBc1Unorm
+        | Format::TBc1Srgb
+        | Format::TBc2Unorm
+        | Format::TBc2Srgb
+        | Format::TBc3Unorm
+        | Format::TBc3Srgb => {
+            return Ok(decode_bc1_to_3(format, data, width, height)?
+                .iter()
+                .map(|&value| f32::from(value) / 255f32)
+                .collect())
+        }
+        Format::TBc4Unorm | Format::TBc4Snorm | Format::TBc5Unorm | Format::TBc5Snorm => {
+            return Ok(decode_bc4_bc5(format, data, width, height))
+        }
+        Format::TNv12Unorm => return Ok(decode_nv12(data, width, height)),
+        _ => {}
+    }
+    let bytes_pp = (format.get_bits_per_pixel() / 8) as usize;
+    let mut out = Vec::with_capacity(pixel_count * 4);
+    for texel in data[..pixel_count * bytes_pp].chunks(bytes_pp) {
+        let rgba = decode_texel(format, texel, one);
+        out.extend_from_slice(&rgba);
+    }
+    Ok(out)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from(data[pos]) | (u16::from(data[pos + 1]) << 8)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from(read_u16(data, pos)) | (u32::from(read_u16(data, pos + 2)) << 16)
+}
+
+fn unorm(value: u32, bits: u32) -> f32 {
+    value as f32 / ((1u64 << bits) - 1) as f32
+}
+
+fn snorm(value: u32, bits: u32) -> f32 {
+    // Sign extend, then scale so that both -max and -max-1 give -1
+    let shift = 32 - bits;
+    let signed = ((value << shift) as i32) >> shift;
+    (signed as f32 / ((1u64 << (bits - 1)) - 1) as f32).max(-1f32)
+}
+
+fn sint(value: u32, bits: u32) -> f32 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as f32
+}
+
+// Unsigned floats with no sign bit and a 5 bits exponent, as used by R11G11B10
+fn ufloat(value: u32, mantissa_bits: u32) -> f32 {
+    let mantissa = value & ((1 << mantissa_bits) - 1);
+    let exponent = (value >> mantissa_bits) & 0x1F;
+    let mantissa_max = (1 << mantissa_bits) as f32;
+    match exponent {
+        0 => (mantissa as f32 / mantissa_max) * 2f32.powi(-14),
+        0x1F => {
+            if mantissa == 0 {
+                f32::INFINITY
+            } else {
+                f32::NAN
+            }
+        }
+        e => (1f32 + mantissa as f32 / mantissa_max) * 2f32.powi(e as i32 - 15),
+    }
+}
+
+// Channels are packed starting from the least significant bits
+fn decode_texel(format: Format, texel: &[u8], one: f32) -> [f32; 4] {
+    let byte = |i: usize| u32::from(texel[i]);
+    let bits = |value: u32, start: u32, count: u32| (value >> start) & ((1 << count) - 1);
+    match format {
+        Format::TcR8Unorm => [unorm(byte(0), 8), 0f32, 0f32, one],
+        Format::TcR8Uint => [byte(0) as f32, 0f32, 0f32, one],
+        Format::TcR8Snorm => [snorm(byte(0), 8), 0f32, 0f32, one],
+        Format::TcR8Sint => [sint(byte(0), 8), 0f32, 0f32, one],
+        Format::TR4G4Unorm => [
+            unorm(bits(byte(0), 0, 4), 4),
+            unorm(bits(byte(0), 4, 4), 4),
+            0f32,
+            one,
+        ],
+        Format::TcdR16Unorm => [unorm(u32::from(read_u16(texel, 0)), 16), 0f32, 0f32, one],
+        Format::TcR16Uint => [f32::from(read_u16(texel, 0)), 0f32, 0f32, one],
+        Format::TcR16Snorm => [snorm(u32::from(read_u16(texel, 0)), 16), 0f32, 0f32, one],
+        Format::TcR16Sint => [sint(u32::from(read_u16(texel, 0)), 16), 0f32, 0f32, one],
+        Format::TcR16Float => [f16::from_bits(read_u16(texel, 0)).to_f32(), 0f32, 0f32, one],
+        Format::TcR8G8Unorm => [unorm(byte(0), 8), unorm(byte(1), 8), 0f32, one],
+        Format::TcR8G8Uint => [byte(0) as f32, byte(1) as f32, 0f32, one],
+        Format::TcR8G8Snorm => [snorm(byte(0), 8), snorm(byte(1), 8), 0f32, one],
+        Format::TcR8G8Sint => [sint(byte(0), 8), sint(byte(1), 8), 0f32, one],
+        Format::TcsR5G6B5Unorm => {
+            let value = u32::from(read_u16(texel, 0));
+            [
+                unorm(bits(value, 0, 5), 5),
+                unorm(bits(value, 5, 6), 6),
+                unorm(bits(value, 11, 5), 5),
+                one,
+            ]
+        }
+        Format::TcR5G5B5A1Unorm => {
+            let value = u32::from(read_u16(texel, 0));
+            [
+                unorm(bits(value, 0, 5), 5),
+                unorm(bits(value, 5, 5), 5),
+                unorm(bits(value, 10, 5), 5),
+                unorm(bits(value, 15, 1), 1),
+            ]
+        }
+        Format::TcR4G4B4A4Unorm => {
+            let value = u32::from(read_u16(texel, 0));
+            [
+                unorm(bits(value, 0, 4), 4),
+                unorm(bits(value, 4, 4), 4),
+                unorm(bits(value, 8, 4), 4),
+                unorm(bits(value, 12, 4), 4),
+            ]
+        }
+        Format::TcA1B5G5R5Unorm => {
+            let value = u32::from(read_u16(texel, 0));
+            [
+                unorm(bits(value, 11, 5), 5),
+                unorm(bits(value, 6, 5), 5),
+                unorm(bits(value, 1, 5), 5),
+                unorm(bits(value, 0, 1), 1),
+            ]
+        }
+        Format::TcR32Uint => [read_u32(texel, 0) as f32, 0f32, 0f32, one],
+        Format::TcR32Sint => [read_u32(texel, 0) as i32 as f32, 0f32, 0f32, one],
+        Format::TcdR32Float => [f32::from_bits(read_u32(texel, 0)), 0f32, 0f32, one],
+        Format::TcR16G16Unorm => [
+            unorm(u32::from(read_u16(texel, 0)), 16),
+            unorm(u32::from(read_u16(texel, 2)), 16),
+            0f32,
+            one,
+        ],
+        Format::TcR16G16Uint => [
+            f32::from(read_u16(texel, 0)),
+            f32::from(read_u16(texel, 2)),
+            0f32,
+            one,
+        ],
+        Format::TcR16G16Snorm => [
+            snorm(u32::from(read_u16(texel, 0)), 16),
+            snorm(u32::from(read_u16(texel, 2)), 16),
+            0f32,
+            one,
+        ],
+        Format::TcR16G16Sint => [
+            sint(u32::from(read_u16(texel, 0)), 16),
+            sint(u32::from(read_u16(texel, 2)), 16),
+            0f32,
+            one,
+        ],
+        Format::TcR16G16Float => [
+            f16::from_bits(read_u16(texel, 0)).to_f32(),
+            f16::from_bits(read_u16(texel, 2)).to_f32(),
+            0f32,
+            one,
+        ],
+        // Depth goes to red, stencil to green
+        Format::TX24G8Uint => {
+            let value = read_u32(texel, 0);
+            [
+                bits(value, 0, 24) as f32,
+                bits(value, 24, 8) as f32,
+                0f32,
+                one,
+            ]
+        }
+        Format::DD24S8Float => {
+            let value = read_u32(texel, 0);
+            [
+                unorm(bits(value, 0, 24), 24),
+                unorm(bits(value, 24, 8), 8),
+                0f32,
+                one,
+            ]
+        }
+        Format::TcR11G11B10Float => {
+            let value = read_u32(texel, 0);
+            [
+                ufloat(bits(value, 0, 11), 6),
+                ufloat(bits(value, 11, 11), 6),
+                ufloat(bits(value, 22, 10), 5),
+                one,
+            ]
+        }
+        Format::TcsR10G10B10A2Unorm => {
+            let value = read_u32(texel, 0);
+            [
+                unorm(bits(value, 0, 10), 10),
+                unorm(bits(value, 10, 10), 10),
+                unorm(bits(value, 20, 10), 10),
+                unorm(bits(value, 30, 2), 2),
+            ]
+        }
+        Format::TcR10G10B10A2Uint => {
+            let value = read_u32(texel, 0);
+            [
+                bits(value, 0, 10) as f32,
+                bits(value, 10, 10) as f32,
+                bits(value, 20, 10) as f32,
+                bits(value, 30, 2) as f32,
+            ]
+        }
+        Format::TcR10G10B10A2Snorm => {
+            let value = read_u32(texel, 0);
+            [
+                snorm(bits(value, 0, 10), 10),
+                snorm(bits(value, 10, 10), 10),
+                snorm(bits(value, 20, 10), 10),
+                snorm(bits(value, 30, 2), 2),
+            ]
+        }
+        Format::TcR10G10B10A2Sint => {
+            let value = read_u32(texel, 0);
+            [
+                sint(bits(value, 0, 10), 10),
+                sint(bits(value, 10, 10), 10),
+                sint(bits(value, 20, 10), 10),
+                sint(bits(value, 30, 2), 2),
+            ]
+        }
+        Format::TcsR8G8B8A8Unorm | Format::TcsR8G8B8A8Srgb => [
+            unorm(byte(0), 8),
+            unorm(byte(1), 8),
+            unorm(byte(2), 8),
+            unorm(byte(3), 8),
+        ],
+        Format::TcR8G8B8A8Uint => [
+            byte(0) as f32,
+            byte(1) as f32,
+            byte(2) as f32,
+            byte(3) as f32,
+        ],
+        Format::TcR8G8B8A8Snorm => [
+            snorm(byte(0), 8),
+            snorm(byte(1), 8),
+            snorm(byte(2), 8),
+            snorm(byte(3), 8),
+        ],
+        Format::TcR8G8B8A8Sint => [
+            sint(byte(0), 8),
+            sint(byte(1), 8),
+            sint(byte(2), 8),
+            sint(byte(3), 8),
+        ],
+        Format::TcsA2B10G10R10Unorm => {
+            let value = read_u32(texel, 0);
+            [
+                unorm(bits(value, 22, 10), 10),
+                unorm(bits(value, 12, 10), 10),
+                unorm(bits(value, 2, 10), 10),
+                unorm(bits(value, 0, 2), 2),
+            ]
+        }
+        Format::TcA2B10G10R10Uint => {
+            let value = read_u32(texel, 0);
+            [
+                bits(value, 22, 10) as f32,
+                bits(value, 12, 10) as f32,
+                bits(value, 2, 10) as f32,
+                bits(value, 0, 2) as f32,
+            ]
+        }
+        Format::TX32G8UintX24 => [
+            f32::from_bits(read_u32(texel, 0)),
+            byte(4) as f32,
+            0f32,
+            one,
+        ],
+        Format::TcR32G32Uint => [
+            read_u32(texel, 0) as f32,
+            read_u32(texel, 4) as f32,
+            0f32,
+            one,
+        ],
+        Format::TcR32G32Sint => [
+            read_u32(texel, 0) as i32 as f32,
+            read_u32(texel, 4) as i32 as f32,
+            0f32,
+            one,
+        ],
+        Format::TcR32G32Float => [
+            f32::from_bits(read_u32(texel, 0)),
+            f32::from_bits(read_u32(texel, 4)),
+            0f32,
+            one,
+        ],
+        Format::TcR16G16B16A16Unorm => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = unorm(u32::from(read_u16(texel, i * 2)), 16);
+            }
+            rgba
+        }
+        Format::TcR16G16B16A16Uint => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = f32::from(read_u16(texel, i * 2));
+            }
+            rgba
+        }
+        Format::TcR16G16B16A16Snorm => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = snorm(u32::from(read_u16(texel, i * 2)), 16);
+            }
+            rgba
+        }
+        Format::TcR16G16B16A16Sint => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = sint(u32::from(read_u16(texel, i * 2)), 16);
+            }
+            rgba
+        }
+        Format::TcR16G16B16A16Float => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = f16::from_bits(read_u16(texel, i * 2)).to_f32();
+            }
+            rgba
+        }
+        Format::TcR32G32B32A32Uint => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = read_u32(texel, i * 4) as f32;
+            }
+            rgba
+        }
+        Format::TcR32G32B32A32Sint => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = read_u32(texel, i * 4) as i32 as f32;
+            }
+            rgba
+        }
+        Format::TcR32G32B32A32Float => {
+            let mut rgba = [0f32; 4];
+            for (i, value) in rgba.iter_mut().enumerate() {
+                *value = f32::from_bits(read_u32(texel, i * 4));
+            }
+            rgba
+        }
+        // Handled block by block or plane by plane
+        Format::TBc1Unorm
+        | Format::TBc1Srgb
+        | Format::TBc2Unorm
+        | Format::TBc2Srgb
+        | Format::TBc3Unorm
+        | Format::TBc3Srgb
+        | Format::TBc4Unorm
+        | Format::TBc4Snorm
+        | Format::TBc5Unorm
+        | Format::TBc5Snorm
+        | Format::TNv12Unorm => unreachable!(),
+    }
+}
+
+fn decode_bc1_to_3(
+    format: Format,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Box<Error>> {
+    check_length(format, data, width, height)?;
+    let encoding = match format {
+        Format::TBc1Unorm | Format::TBc1Srgb => BcnEncoding::Bc1,
+        Format::TBc2Unorm | Format::TBc2Srgb => BcnEncoding::Bc2,
+        _ => BcnEncoding::Bc3,
+    };
+    // The decoder works on whole blocks, crop the padding afterwards
+    let padded_width = (width.div_ceil(4) * 4) as usize;
+    let padded_height = (height.div_ceil(4) * 4) as usize;
+    let length = get_image_size(format, width, height);
+    let decoded = decode(
+        &data[..length],
+        padded_width,
+        padded_height,
+        encoding,
+        BcnDecoderFormat::RGBA,
+    )?;
+    let mut out = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height as usize {
+        let start = y * padded_width * 4;
+        out.extend_from_slice(&decoded[start..start + width as usize * 4]);
+    }
+    Ok(out)
+}
+
+// Same layout for BC4 and both halves of BC5: two endpoints then 16 3-bit indices
+fn decode_bc4_block(block: &[u8], signed: bool) -> [f32; 16] {
+    let endpoint = |value: u8| {
+        if signed {
+            (f32::from(value as i8) / 127f32).max(-1f32)
+        } else {
+            f32::from(value) / 255f32
+        }
+    };
+    let (raw_0, raw_1) = (block[0], block[1]);
+    let (value_0, value_1) = (endpoint(raw_0), endpoint(raw_1));
+    let six_steps = if signed {
+        (raw_0 as i8) > (raw_1 as i8)
+    } else {
+        raw_0 > raw_1
+    };
+    let mut palette = [0f32; 8];
+    palette[0] = value_0;
+    palette[1] = value_1;
+    if six_steps {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i) as f32 * value_0 + i as f32 * value_1) / 7f32;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i) as f32 * value_0 + i as f32 * value_1) / 5f32;
+        }
+        palette[6] = if signed { -1f32 } else { 0f32 };
+        palette[7] = 1f32;
+    }
+    let mut indices = 0u64;
+    for (i, &byte) in block[2..8].iter().enumerate() {
+        indices |= u64::from(byte) << (8 * i);
+    }
+    let mut out = [0f32; 16];
+    for (i, value) in out.iter_mut().enumerate() {
+        *value = palette[((indices >> (3 * i)) & 0b111) as usize];
+    }
+    out
+}
+
+fn decode_bc4_bc5(format: Format, data: &[u8], width: u32, height: u32) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let signed = format.is_signed();
+    let two_channels = match format {
+        Format::TBc5Unorm | Format::TBc5Snorm => true,
+        _ => false,
+    };
+    let block_size = if two_channels { 16 } else { 8 };
+    let blocks_per_row = width.div_ceil(4);
+    let mut out = vec![0f32; width * height * 4];
+    for (block_index, block) in data[..get_image_size(format, width as u32, height as u32)]
+        .chunks(block_size)
+        .enumerate()
+    {
+        let red = decode_bc4_block(&block[0..8], signed);
+        let green = if two_channels {
+            decode_bc4_block(&block[8..16], signed)
+        } else {
+            [0f32; 16]
+        };
+        let block_x = (block_index % blocks_per_row) * 4;
+        let block_y = (block_index / blocks_per_row) * 4;
+        for i in 0..16 {
+            let (x, y) = (block_x + i % 4, block_y + i / 4);
+            if x < width && y < height {
+                let pos = (y * width + x) * 4;
+                out[pos] = red[i];
+                out[pos + 1] = green[i];
+                out[pos + 3] = 1f32;
+            }
+        }
+    }
+    out
+}
+
+// Full-resolution luma plane followed by an interleaved half-resolution chroma plane
+fn decode_nv12(data: &[u8], width: u32, height: u32) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let chroma_start = width * height;
+    let chroma_width = width.div_ceil(2);
+    let mut out = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let luma = f32::from(data[y * width + x]);
+            let chroma_pos = chroma_start + ((y / 2) * chroma_width + x / 2) * 2;
+            let u = f32::from(data[chroma_pos]) - 128f32;
+            let v = f32::from(data[chroma_pos + 1]) - 128f32;
+            let luma = (luma - 16f32) * 1.164;
+            let clamp = |value: f32| (value / 255f32).clamp(0f32, 1f32);
+            out.push(clamp(luma + 1.596 * v));
+            out.push(clamp(luma - 0.392 * u - 0.813 * v));
+            out.push(clamp(luma + 2.017 * u));
+            out.push(1f32);
+        }
+    }
+    out
+}
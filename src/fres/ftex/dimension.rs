@@ -6,6 +6,7 @@ use std::io::{Read, Seek};
 use util::Importable;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum Dimension {
     OneD = 0x0,
     TwoD = 0x1,
@@ -0,0 +1,364 @@
+use error::{TextureDataTooShort, UnsupportedFormat};
+use fres::ftex::bcn::{encode_bcn, linear_to_srgb, srgb_to_linear, Quality};
+use fres::ftex::format::Format;
+use half::f16;
+use std::cmp::max;
+use std::error::Error;
+
+// The inverse of decode_rgba32f, values out of range are clamped. Quality only matters for block
+// compressed formats.
+pub fn encode_rgba32f(
+    format: Format,
+    data: &[f32],
+    width: u32,
+    height: u32,
+    quality: Quality,
+) -> Result<Vec<u8>, Box<Error>> {
+    let pixel_count = (width * height) as usize;
+    if data.len() < pixel_count * 4 {
+        return Err(Box::new(TextureDataTooShort {
+            expected: pixel_count * 4,
+            got: data.len(),
+        }));
+    }
+    if format.is_block_compressed() {
+        return encode_bcn(format, &data[..pixel_count * 4], width, height, quality);
+    }
+    let bytes_pp = (format.get_bits_per_pixel() / 8) as usize;
+    let mut out = Vec::with_capacity(pixel_count * bytes_pp);
+    for pixel in data[..pixel_count * 4].chunks(4) {
+        encode_texel(format, [pixel[0], pixel[1], pixel[2], pixel[3]], &mut out)?;
+    }
+    Ok(out)
+}
+
+// The inverse of decode_rgba8: signed normalized values come remapped from [0, 255], integer
+// values are taken as they are
+pub fn encode_rgba8(
+    format: Format,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    quality: Quality,
+) -> Result<Vec<u8>, Box<Error>> {
+    let pixel_count = (width * height) as usize;
+    if data.len() < pixel_count * 4 {
+        return Err(Box::new(TextureDataTooShort {
+            expected: pixel_count * 4,
+            got: data.len(),
+        }));
+    }
+    if let Format::TcsR8G8B8A8Unorm | Format::TcsR8G8B8A8Srgb = format {
+        return Ok(data[..pixel_count * 4].to_vec());
+    }
+    let integer = format.is_integer();
+    let signed = format.is_signed();
+    let mut texels: Vec<f32> = data[..pixel_count * 4]
+        .iter()
+        .map(|&value| {
+            let value = f32::from(value);
+            if integer {
+                value
+            } else if signed {
+                (value / 255f32) * 2f32 - 1f32
+            } else {
+                value / 255f32
+            }
+        })
+        .collect();
+    // Back to 24 bits of depth, 255 being the farthest
+    if let Format::TX24G8Uint = format {
+        for texel in texels.chunks_mut(4) {
+            texel[0] *= 65793f32;
+        }
+    }
+    encode_rgba32f(format, &texels, width, height, quality)
+}
+
+// Each level is half the size of the previous one, averaging 2x2 pixels. sRGB colors are averaged
+// in linear space.
+pub fn generate_mipmaps_rgba8(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    nb_mipmaps: u32,
+    srgb: bool,
+) -> Result<Vec<Vec<u8>>, Box<Error>> {
+    let length = (width * height * 4) as usize;
+    if data.len() < length {
+        return Err(Box::new(TextureDataTooShort {
+            expected: length,
+            got: data.len(),
+        }));
+    }
+    let mut levels = vec![data[..length].to_vec()];
+    let (mut width, mut height) = (width as usize, height as usize);
+    for _ in 1..nb_mipmaps {
+        let (new_width, new_height) = (max(1, width / 2), max(1, height / 2));
+        let mut level = Vec::with_capacity(new_width * new_height * 4);
+        {
+            let previous = &levels[levels.len() - 1];
+            for y in 0..new_height {
+                for x in 0..new_width {
+                    // Odd sizes repeat the last row or column
+                    let xs = [x * 2, (x * 2 + 1).min(width - 1)];
+                    let ys = [y * 2, (y * 2 + 1).min(height - 1)];
+                    for channel in 0..4 {
+                        let linear = srgb && channel < 3;
+                        let mut total = 0f32;
+                        for &sy in &ys {
+                            for &sx in &xs {
+                                let value =
+                                    f32::from(previous[(sy * width + sx) * 4 + channel]) / 255f32;
+                                total += if linear { srgb_to_linear(value) } else { value };
+                            }
+                        }
+                        let average = total / 4f32;
+                        let value = if linear {
+                            linear_to_srgb(average)
+                        } else {
+                            average
+                        };
+                        level.push((value * 255f32).clamp(0f32, 255f32).round() as u8);
+                    }
+                }
+            }
+        }
+        levels.push(level);
+        width = new_width;
+        height = new_height;
+    }
+    Ok(levels)
+}
+
+fn unorm(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << bits) - 1) as f32;
+    (value.clamp(0f32, 1f32) * max_value).round() as u32
+}
+
+fn snorm(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << (bits - 1)) - 1) as f32;
+    let signed = (value.clamp(-1f32, 1f32) * max_value).round() as i32;
+    (signed as u32) & (((1u64 << bits) - 1) as u32)
+}
+
+fn uint(value: f32, bits: u32) -> u32 {
+    value.max(0f32).min(((1u64 << bits) - 1) as f32).round() as u32
+}
+
+fn sint(value: f32, bits: u32) -> u32 {
+    let max_value = ((1u64 << (bits - 1)) - 1) as f32;
+    let signed = value.max(-max_value - 1f32).min(max_value).round() as i32;
+    (signed as u32) & (((1u64 << bits) - 1) as u32)
+}
+
+// Unsigned floats with no sign bit and a 5 bits exponent, as used by R11G11B10
+fn ufloat(value: f32, mantissa_bits: u32) -> u32 {
+    if value.is_nan() {
+        return (0x1F << mantissa_bits) | 1;
+    }
+    if value <= 0f32 {
+        return 0;
+    }
+    // Go through half floats, they share the exponent
+    let bits = u32::from(f16::from_f32(value).to_bits()) & 0x7FFF;
+    bits >> (10 - mantissa_bits)
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u32) {
+    out.push(value as u8);
+    out.push((value >> 8) as u8);
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    push_u16(out, value & 0xFFFF);
+    push_u16(out, value >> 16);
+}
+
+fn push_f16(out: &mut Vec<u8>, value: f32) {
+    push_u16(out, u32::from(f16::from_f32(value).to_bits()));
+}
+
+// Channels are packed starting from the least significant bits
+fn encode_texel(format: Format, rgba: [f32; 4], out: &mut Vec<u8>) -> Result<(), Box<Error>> {
+    let [r, g, b, a] = rgba;
+    match format {
+        Format::TcR8Unorm => out.push(unorm(r, 8) as u8),
+        Format::TcR8Uint => out.push(uint(r, 8) as u8),
+        Format::TcR8Snorm => out.push(snorm(r, 8) as u8),
+        Format::TcR8Sint => out.push(sint(r, 8) as u8),
+        Format::TR4G4Unorm => out.push((unorm(r, 4) | (unorm(g, 4) << 4)) as u8),
+        Format::TcdR16Unorm => push_u16(out, unorm(r, 16)),
+        Format::TcR16Uint => push_u16(out, uint(r, 16)),
+        Format::TcR16Snorm => push_u16(out, snorm(r, 16)),
+        Format::TcR16Sint => push_u16(out, sint(r, 16)),
+        Format::TcR16Float => push_f16(out, r),
+        Format::TcR8G8Unorm => out.extend_from_slice(&[unorm(r, 8) as u8, unorm(g, 8) as u8]),
+        Format::TcR8G8Uint => out.extend_from_slice(&[uint(r, 8) as u8, uint(g, 8) as u8]),
+        Format::TcR8G8Snorm => out.extend_from_slice(&[snorm(r, 8) as u8, snorm(g, 8) as u8]),
+        Format::TcR8G8Sint => out.extend_from_slice(&[sint(r, 8) as u8, sint(g, 8) as u8]),
+        Format::TcsR5G6B5Unorm => {
+            push_u16(out, unorm(r, 5) | (unorm(g, 6) << 5) | (unorm(b, 5) << 11))
+        }
+        Format::TcR5G5B5A1Unorm => push_u16(
+            out,
+            unorm(r, 5) | (unorm(g, 5) << 5) | (unorm(b, 5) << 10) | (unorm(a, 1) << 15),
+        ),
+        Format::TcR4G4B4A4Unorm => push_u16(
+            out,
+            unorm(r, 4) | (unorm(g, 4) << 4) | (unorm(b, 4) << 8) | (unorm(a, 4) << 12),
+        ),
+        Format::TcA1B5G5R5Unorm => push_u16(
+            out,
+            (unorm(r, 5) << 11) | (unorm(g, 5) << 6) | (unorm(b, 5) << 1) | unorm(a, 1),
+        ),
+        Format::TcR32Uint => push_u32(out, r.clamp(0f32, u32::MAX as f32) as u32),
+        Format::TcR32Sint => push_u32(out, r as i32 as u32),
+        Format::TcdR32Float => push_u32(out, r.to_bits()),
+        Format::TcR16G16Unorm => {
+            push_u16(out, unorm(r, 16));
+            push_u16(out, unorm(g, 16));
+        }
+        Format::TcR16G16Uint => {
+            push_u16(out, uint(r, 16));
+            push_u16(out, uint(g, 16));
+        }
+        Format::TcR16G16Snorm => {
+            push_u16(out, snorm(r, 16));
+            push_u16(out, snorm(g, 16));
+        }
+        Format::TcR16G16Sint => {
+            push_u16(out, sint(r, 16));
+            push_u16(out, sint(g, 16));
+        }
+        Format::TcR16G16Float => {
+            push_f16(out, r);
+            push_f16(out, g);
+        }
+        // Depth comes from red, stencil from green
+        Format::TX24G8Uint => push_u32(out, uint(r, 24) | (uint(g, 8) << 24)),
+        Format::DD24S8Float => push_u32(out, unorm(r, 24) | (unorm(g, 8) << 24)),
+        Format::TcR11G11B10Float => push_u32(
+            out,
+            ufloat(r, 6) | (ufloat(g, 6) << 11) | (ufloat(b, 5) << 22),
+        ),
+        Format::TcsR10G10B10A2Unorm => push_u32(
+            out,
+            unorm(r, 10) | (unorm(g, 10) << 10) | (unorm(b, 10) << 20) | (unorm(a, 2) << 30),
+        ),
+        Format::TcR10G10B10A2Uint => push_u32(
+            out,
+            uint(r, 10) | (uint(g, 10) << 10) | (uint(b, 10) << 20) | (uint(a, 2) << 30),
+        ),
+        Format::TcR10G10B10A2Snorm => push_u32(
+            out,
+            snorm(r, 10) | (snorm(g, 10) << 10) | (snorm(b, 10) << 20) | (snorm(a, 2) << 30),
+        ),
+        Format::TcR10G10B10A2Sint => push_u32(
+            out,
+            sint(r, 10) | (sint(g, 10) << 10) | (sint(b, 10) << 20) | (sint(a, 2) << 30),
+        ),
+        Format::TcsR8G8B8A8Unorm | Format::TcsR8G8B8A8Srgb => {
+            for &value in &rgba {
+                out.push(unorm(value, 8) as u8);
+            }
+        }
+        Format::TcR8G8B8A8Uint => {
+            for &value in &rgba {
+                out.push(uint(value, 8) as u8);
+            }
+        }
+        Format::TcR8G8B8A8Snorm => {
+            for &value in &rgba {
+                out.push(snorm(value, 8) as u8);
+            }
+        }
+        Format::TcR8G8B8A8Sint => {
+            for &value in &rgba {
+                out.push(sint(value, 8) as u8);
+            }
+        }
+        Format::TcsA2B10G10R10Unorm => push_u32(
+            out,
+            (unorm(r, 10) << 22) | (unorm(g, 10) << 12) | (unorm(b, 10) << 2) | unorm(a, 2),
+        ),
+        Format::TcA2B10G10R10Uint => push_u32(
+            out,
+            (uint(r, 10) << 22) | (uint(g, 10) << 12) | (uint(b, 10) << 2) | uint(a, 2),
+        ),
+        Format::TX32G8UintX24 => {
+            push_u32(out, r.to_bits());
+            push_u32(out, uint(g, 8));
+        }
+        Format::TcR32G32Uint => {
+            push_u32(out, r.clamp(0f32, u32::MAX as f32) as u32);
+            push_u32(out, g.clamp(0f32, u32::MAX as f32) as u32);
+        }
+        Format::TcR32G32Sint => {
+            push_u32(out, r as i32 as u32);
+            push_u32(out, g as i32 as u32);
+        }
+        Format::TcR32G32Float => {
+            push_u32(out, r.to_bits());
+            push_u32(out, g.to_bits());
+        }
+        Format::TcR16G16B16A16Unorm => {
+            for &value in &rgba {
+                push_u16(out, unorm(value, 16));
+            }
+        }
+        Format::TcR16G16B16A16Uint => {
+            for &value in &rgba {
+                push_u16(out, uint(value, 16));
+            }
+        }
+        Format::TcR16G16B16A16Snorm => {
+            for &value in &rgba {
+                push_u16(out, snorm(value, 16));
+            }
+        }
+        Format::TcR16G16B16A16Sint => {
+            for &value in &rgba {
+                push_u16(out, sint(value, 16));
+            }
+        }
+        Format::TcR16G16B16A16Float => {
+            for &value in &rgba {
+                push_f16(out, value);
+            }
+        }
+        Format::TcR32G32B32A32Uint => {
+            for &value in &rgba {
+                push_u32(out, value.clamp(0f32, u32::MAX as f32) as u32);
+            }
+        }
+        Format::TcR32G32B32A32Sint => {
+            for &value in &rgba {
+                push_u32(out, value as i32 as u32);
+            }
+        }
+        Format::TcR32G32B32A32Float => {
+            for &value in &rgba {
+                push_u32(out, value.to_bits());
+            }
+        }
+        // Block compressed formats go through the BCn encoder before getting here
+        Format::TBc1Unorm
+        | Format::TBc1Srgb
+        | Format::TBc2Unorm
+        | Format::TBc2Srgb
+        | Format::TBc3Unorm
+        | Format::TBc3Srgb
+        | Format::TBc4Unorm
+        | Format::TBc4Snorm
+        | Format::TBc5Unorm
+        | Format::TBc5Snorm
+        | Format::TNv12Unorm => {
+            return Err(Box::new(UnsupportedFormat {
+                format: format as u32,
+                container: "the encoder".to_string(),
+            }))
+        }
+    }
+    Ok(())
+}
@@ -6,6 +6,7 @@ use std::io::{Read, Seek};
 use util::Importable;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum Format {
     TcR8Unorm = 0x001,
     TcR8Uint = 0x101,
@@ -189,4 +190,91 @@ impl Format {
             _ => false,
         }
     }
+    // For block compressed formats, this is the size of a whole 4x4 block
+    pub fn get_bits_per_pixel(&self) -> u32 {
+        match *self {
+            Format::TcR8Unorm
+            | Format::TcR8Uint
+            | Format::TcR8Snorm
+            | Format::TcR8Sint
+            | Format::TR4G4Unorm => 8,
+            // A full size luma plane, then a quarter size plane of interleaved chroma
+            Format::TNv12Unorm => 12,
+            Format::TcdR16Unorm
+            | Format::TcR16Uint
+            | Format::TcR16Snorm
+            | Format::TcR16Sint
+            | Format::TcR16Float
+            | Format::TcR8G8Unorm
+            | Format::TcR8G8Uint
+            | Format::TcR8G8Snorm
+            | Format::TcR8G8Sint
+            | Format::TcsR5G6B5Unorm
+            | Format::TcR5G5B5A1Unorm
+            | Format::TcR4G4B4A4Unorm
+            | Format::TcA1B5G5R5Unorm => 16,
+            Format::TcR32Uint
+            | Format::TcR32Sint
+            | Format::TcdR32Float
+            | Format::TcR16G16Unorm
+            | Format::TcR16G16Uint
+            | Format::TcR16G16Snorm
+            | Format::TcR16G16Sint
+            | Format::TcR16G16Float
+            | Format::TX24G8Uint
+            | Format::DD24S8Float
+            | Format::TcR11G11B10Float
+            | Format::TcsR10G10B10A2Unorm
+            | Format::TcR10G10B10A2Uint
+            | Format::TcR10G10B10A2Snorm
+            | Format::TcR10G10B10A2Sint
+            | Format::TcsR8G8B8A8Unorm
+            | Format::TcR8G8B8A8Uint
+            | Format::TcR8G8B8A8Snorm
+            | Format::TcR8G8B8A8Sint
+            | Format::TcsR8G8B8A8Srgb
+            | Format::TcsA2B10G10R10Unorm
+            | Format::TcA2B10G10R10Uint => 32,
+            Format::TX32G8UintX24
+            | Format::TcR32G32Uint
+            | Format::TcR32G32Sint
+            | Format::TcR32G32Float
+            | Format::TcR16G16B16A16Unorm
+            | Format::TcR16G16B16A16Uint
+            | Format::TcR16G16B16A16Snorm
+            | Format::TcR16G16B16A16Sint
+            | Format::TcR16G16B16A16Float
+            | Format::TBc1Unorm
+            | Format::TBc1Srgb
+            | Format::TBc4Unorm
+            | Format::TBc4Snorm => 64,
+            Format::TcR32G32B32A32Uint
+            | Format::TcR32G32B32A32Sint
+            | Format::TcR32G32B32A32Float
+            | Format::TBc2Unorm
+            | Format::TBc2Srgb
+            | Format::TBc3Unorm
+            | Format::TBc3Srgb
+            | Format::TBc5Unorm
+            | Format::TBc5Snorm => 128,
+        }
+    }
+    pub fn is_signed(&self) -> bool {
+        match *self as u32 & 0xF00 {
+            0x200 | 0x300 => true,
+            _ => false,
+        }
+    }
+    pub fn is_srgb(&self) -> bool {
+        *self as u32 & 0xF00 == 0x400
+    }
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            Format::TX24G8Uint | Format::TX32G8UintX24 => true,
+            _ => match *self as u32 & 0xF00 {
+                0x100 | 0x300 => true,
+                _ => false,
+            },
+        }
+    }
 }
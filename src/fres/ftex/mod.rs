@@ -1,23 +1,36 @@
 pub mod aa_mode;
+pub mod bcn;
 pub mod component_selector;
+pub mod decode;
 pub mod dimension;
+pub mod encode;
 pub mod format;
 pub mod tile_mode;
 pub mod usage;
 
-use self::{aa_mode::AAMode, component_selector::ComponentSelector, dimension::Dimension,
-           format::Format, tile_mode::TileMode, usage::Usage};
-use error::{check_magic_number, UserDataNotEmpty};
+use self::{
+    aa_mode::AAMode, component_selector::ComponentSelector, dimension::Dimension, format::Format,
+    tile_mode::TileMode, usage::Usage,
+};
+use error::{
+    check_magic_number, MipLevelOutOfRange, SampleOutOfRange, SliceOutOfRange, UserDataNotEmpty,
+};
 use ez_io::ReadE;
 use std::error::Error;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
+use swizzle::{
+    deswizzle, deswizzle_mip_level, deswizzle_sample, deswizzle_slice, get_mip_level_dimensions,
+    get_mip_level_slice_count,
+};
 use util::Importable;
 use util::Pointer;
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FTEX {
     pub header: Header,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub dimension: Dimension,
     pub texture_width: u32,
@@ -54,11 +67,276 @@ impl Importable for FTEX {
     }
 }
 
-impl Importable for Header {
-    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
-        let mut magic_number = [0u8; 4];
-        reader.read_exact(&mut magic_number)?;
-        check_magic_number(magic_number, [b'F', b'T', b'E', b'X'])?;
+impl FTEX {
+    pub fn get_raw_data<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, Box<Error>> {
+        self.header.data_offset.seek_abs_pos(reader)?;
+        let mut data = vec![0u8; self.header.data_length as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+    pub fn get_deswizzled_data<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        deswizzle(&self.header, &self.get_raw_data(reader)?)
+    }
+    // Level 0 is the base image, the others come from the mipmap data
+    pub fn get_mip_level_raw_data<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        if level > 0 && level >= self.header.nb_mipmaps {
+            return Err(Box::new(MipLevelOutOfRange {
+                level,
+                nb_mipmaps: self.header.nb_mipmaps,
+            }));
+        }
+        if level == 0 {
+            return self.get_raw_data(reader);
+        }
+        let start = self.header.get_mipmap_start(level);
+        let end = if level + 1 < self.header.nb_mipmaps {
+            self.header.mipmap_offsets[level as usize]
+        } else {
+            self.header.mipmaps_data_length
+        };
+        self.header.mipmap_offset.seek_abs_pos(reader)?;
+        reader.seek(SeekFrom::Current(i64::from(start)))?;
+        let mut data = vec![0u8; end.saturating_sub(start) as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+    pub fn mip_level<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        deswizzle_mip_level(
+            &self.header,
+            level,
+            &self.get_mip_level_raw_data(reader, level)?,
+        )
+    }
+    // For arrays and cube maps, one slice is one layer or face
+    pub fn get_slice<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        let nb_slices = self.get_slice_count(level);
+        if slice >= nb_slices {
+            return Err(Box::new(SliceOutOfRange { slice, nb_slices }));
+        }
+        deswizzle_slice(
+            &self.header,
+            level,
+            slice,
+            &self.get_mip_level_raw_data(reader, level)?,
+        )
+    }
+    pub fn get_slices<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+    ) -> Result<Vec<Vec<u8>>, Box<Error>> {
+        let raw_data = self.get_mip_level_raw_data(reader, level)?;
+        let mut slices = Vec::new();
+        for slice in 0..self.get_slice_count(level) {
+            slices.push(deswizzle_slice(&self.header, level, slice, &raw_data)?);
+        }
+        Ok(slices)
+    }
+    // Multisampled textures keep every sample, get_slice only gives the first one
+    pub fn get_sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        sample: u32,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        let nb_slices = self.get_slice_count(level);
+        if slice >= nb_slices {
+            return Err(Box::new(SliceOutOfRange { slice, nb_slices }));
+        }
+        let nb_samples = self.get_sample_count();
+        if sample >= nb_samples {
+            return Err(Box::new(SampleOutOfRange { sample, nb_samples }));
+        }
+        deswizzle_sample(
+            &self.header,
+            level,
+            slice,
+            sample,
+            &self.get_mip_level_raw_data(reader, level)?,
+        )
+    }
+    pub fn get_sample_count(&self) -> u32 {
+        self.header.aa_mode.get_sample_count()
+    }
+    pub fn get_mip_level_dimensions(&self, level: u32) -> (u32, u32) {
+        get_mip_level_dimensions(&self.header, level)
+    }
+    pub fn get_slice_count(&self, level: u32) -> u32 {
+        get_mip_level_slice_count(&self.header, level)
+    }
+    // When apply_component_selector is false, channels are kept as they are stored
+    pub fn decode_rgba8<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        apply_component_selector: bool,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        self.decode_slice_rgba8(reader, 0, 0, apply_component_selector)
+    }
+    pub fn decode_rgba32f<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        apply_component_selector: bool,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        self.decode_slice_rgba32f(reader, 0, 0, apply_component_selector)
+    }
+    pub fn decode_mip_level_rgba8<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        self.decode_slice_rgba8(reader, level, 0, apply_component_selector)
+    }
+    pub fn decode_mip_level_rgba32f<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        self.decode_slice_rgba32f(reader, level, 0, apply_component_selector)
+    }
+    pub fn decode_slice_rgba8<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        let (width, height) = self.get_mip_level_dimensions(level);
+        let mut data = decode::decode_rgba8(
+            self.header.texture_format,
+            &self.get_slice(reader, level, slice)?,
+            width,
+            height,
+        )?;
+        if apply_component_selector {
+            self.header.component_selector.apply_rgba8(&mut data);
+        }
+        Ok(data)
+    }
+    pub fn decode_slice_rgba32f<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        let (width, height) = self.get_mip_level_dimensions(level);
+        let mut data = decode::decode_rgba32f(
+            self.header.texture_format,
+            &self.get_slice(reader, level, slice)?,
+            width,
+            height,
+        )?;
+        if apply_component_selector {
+            self.header.component_selector.apply_rgba32f(&mut data);
+        }
+        Ok(data)
+    }
+    pub fn decode_sample_rgba32f<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        sample: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        let (width, height) = self.get_mip_level_dimensions(level);
+        let mut data = decode::decode_rgba32f(
+            self.header.texture_format,
+            &self.get_sample(reader, level, slice, sample)?,
+            width,
+            height,
+        )?;
+        if apply_component_selector {
+            self.header.component_selector.apply_rgba32f(&mut data);
+        }
+        Ok(data)
+    }
+    // Averages all samples of each pixel, like the GPU does when resolving
+    pub fn resolve_rgba32f<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<f32>, Box<Error>> {
+        let nb_samples = self.get_sample_count();
+        let mut resolved =
+            self.decode_sample_rgba32f(reader, level, slice, 0, apply_component_selector)?;
+        for sample in 1..nb_samples {
+            let data =
+                self.decode_sample_rgba32f(reader, level, slice, sample, apply_component_selector)?;
+            for (total, value) in resolved.iter_mut().zip(data.iter()) {
+                *total += *value;
+            }
+        }
+        for value in &mut resolved {
+            *value /= nb_samples as f32;
+        }
+        Ok(resolved)
+    }
+    pub fn resolve_rgba8<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        level: u32,
+        slice: u32,
+        apply_component_selector: bool,
+    ) -> Result<Vec<u8>, Box<Error>> {
+        let nb_samples = self.get_sample_count();
+        let (width, height) = self.get_mip_level_dimensions(level);
+        let mut totals = vec![0u32; (width * height * 4) as usize];
+        for sample in 0..nb_samples {
+            let mut data = decode::decode_rgba8(
+                self.header.texture_format,
+                &self.get_sample(reader, level, slice, sample)?,
+                width,
+                height,
+            )?;
+            if apply_component_selector {
+                self.header.component_selector.apply_rgba8(&mut data);
+            }
+            for (total, value) in totals.iter_mut().zip(data.iter()) {
+                *total += u32::from(*value);
+            }
+        }
+        Ok(totals
+            .iter()
+            .map(|t| ((t + nb_samples / 2) / nb_samples) as u8)
+            .collect())
+    }
+}
+
+impl Header {
+    // Where a level starts in the mipmap data. The first offset counts from the start of the base
+    // image, and the base image is not in the mipmap data.
+    pub fn get_mipmap_start(&self, level: u32) -> u32 {
+        match level {
+            0 => 0,
+            1 => self.mipmap_offsets[0].saturating_sub(self.data_length),
+            _ => self.mipmap_offsets[level as usize - 1],
+        }
+    }
+    // The GX2Texture structure, which GTX files also use. Pointers are left empty, the file around
+    // it says where the data is.
+    pub fn import_gx2_texture<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
         let dimension = Dimension::import(reader)?;
         let texture_width = reader.read_be_to_u32()?;
         let texture_height = reader.read_be_to_u32()?;
@@ -105,20 +383,6 @@ impl Importable for Header {
         for data in &mut texture_registers {
             *data = reader.read_be_to_u32()?;
         }
-        let texture_handle = reader.read_be_to_u32()?;
-        if texture_handle != 0 {
-            return Err(Box::new(UserDataNotEmpty {
-                data: texture_handle,
-                data_desc: "Texture Handle".to_string(),
-            }));
-        }
-        let array_length = reader.read_be_to_u32()?;
-        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
-        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
-        let data_offset = Pointer::read_new_rel_i32_be(reader)?;
-        let mipmap_offset = Pointer::read_new_rel_i32_be(reader)?;
-        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
-        let user_data_entry_count = reader.read_be_to_u16()?;
         Ok(Header {
             dimension,
             texture_width,
@@ -139,13 +403,37 @@ impl Importable for Header {
             nb_slices,
             component_selector,
             texture_registers,
-            array_length,
-            file_name_offset,
-            file_path_offset,
-            data_offset,
-            mipmap_offset,
-            user_data_index_group_offset,
-            user_data_entry_count,
+            array_length: 0,
+            file_name_offset: Pointer::new_abs(0),
+            file_path_offset: Pointer::new_abs(0),
+            data_offset: Pointer::new_abs(0),
+            mipmap_offset: Pointer::new_abs(0),
+            user_data_index_group_offset: Pointer::new_abs(0),
+            user_data_entry_count: 0,
         })
     }
 }
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'T', b'E', b'X'])?;
+        let mut header = Header::import_gx2_texture(reader)?;
+        let texture_handle = reader.read_be_to_u32()?;
+        if texture_handle != 0 {
+            return Err(Box::new(UserDataNotEmpty {
+                data: texture_handle,
+                data_desc: "Texture Handle".to_string(),
+            }));
+        }
+        header.array_length = reader.read_be_to_u32()?;
+        header.file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        header.file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        header.data_offset = Pointer::read_new_rel_i32_be(reader)?;
+        header.mipmap_offset = Pointer::read_new_rel_i32_be(reader)?;
+        header.user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        header.user_data_entry_count = reader.read_be_to_u16()?;
+        Ok(header)
+    }
+}
@@ -6,6 +6,7 @@ use std::io::{Read, Seek};
 use util::Importable;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub enum TileMode {
     Default = 0x00,
     LinearSpecial = 0x10,
@@ -102,6 +103,22 @@ impl TileMode {
             _ => false,
         }
     }
+    pub fn is_linear(&self) -> bool {
+        match *self {
+            TileMode::Default | TileMode::LinearSpecial | TileMode::LinearAligned => true,
+            _ => false,
+        }
+    }
+    pub fn is_macro_tiled(&self) -> bool {
+        match *self {
+            TileMode::Default
+            | TileMode::LinearSpecial
+            | TileMode::LinearAligned
+            | TileMode::OneDTiledThin1
+            | TileMode::OneDTiledThick => false,
+            _ => true,
+        }
+    }
     pub fn get_aspect_ratio(&self) -> u8 {
         match *self {
             TileMode::TwoDTiledThin2 | TileMode::TwoBTiledThin2 => 2,
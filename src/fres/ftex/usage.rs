@@ -4,6 +4,7 @@ use std::fmt;
 use std::io::{Read, Seek};
 use util::Importable;
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Usage {
     texture: bool,
     color_buffer: bool,
@@ -0,0 +1,131 @@
+use ez_io::ReadE;
+use fres::curve::Curve;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use util::{read_text_entry, DataArray, Importable, Pointer};
+
+pub struct MaterialAnimation {
+    pub pattern_animation_info_count: u16,
+    pub curve_count: u16,
+    pub first_curve_index: i32,
+    pub first_pattern_animation_info_index: i32,
+    pub material_name_offset: Pointer,
+    pub pattern_animation_info_array_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_value_array_offset: Pointer,
+}
+
+pub struct PatternAnimationInfo {
+    pub curve_index: i8,
+    pub sub_bind_index: i8,
+    pub sampler_name_offset: Pointer,
+}
+
+impl Importable for MaterialAnimation {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<MaterialAnimation, Box<Error>> {
+        let pattern_animation_info_count = reader.read_be_to_u16()?;
+        let curve_count = reader.read_be_to_u16()?;
+        let first_curve_index = reader.read_be_to_i32()?;
+        let first_pattern_animation_info_index = reader.read_be_to_i32()?;
+        let material_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let pattern_animation_info_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_value_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(MaterialAnimation {
+            pattern_animation_info_count,
+            curve_count,
+            first_curve_index,
+            first_pattern_animation_info_index,
+            material_name_offset,
+            pattern_animation_info_array_offset,
+            curve_array_offset,
+            base_value_array_offset,
+        })
+    }
+}
+
+impl MaterialAnimation {
+    pub fn get_material_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.material_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+    pub fn get_pattern_animation_infos<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<PatternAnimationInfo>, Box<Error>> {
+        self.pattern_animation_info_array_offset
+            .seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x8, u32::from(self.pattern_animation_info_count))?;
+        Ok(array)
+    }
+    pub fn get_curves<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<DataArray<Curve>, Box<Error>> {
+        self.curve_array_offset.seek_abs_pos(reader)?;
+        let array = DataArray::new(reader, 0x24, u32::from(self.curve_count))?;
+        Ok(array)
+    }
+    // One texture index per Pattern Animation Info, used when it has no curve
+    pub fn get_base_values<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u16>, Box<Error>> {
+        let mut base_values = Vec::with_capacity(usize::from(self.pattern_animation_info_count));
+        if self.pattern_animation_info_count == 0 {
+            return Ok(base_values);
+        }
+        self.base_value_array_offset.seek_abs_pos(reader)?;
+        for _ in 0..self.pattern_animation_info_count {
+            base_values.push(reader.read_be_to_u16()?);
+        }
+        Ok(base_values)
+    }
+    // Index in the FTXP texture references of the texture shown by a sampler at a given frame
+    pub fn get_texture_index_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        sampler_name: &str,
+        frame: f32,
+    ) -> Result<Option<usize>, Box<Error>> {
+        let base_values = self.get_base_values(reader)?;
+        let curves = self.get_curves(reader)?;
+        for (i, info_entry) in self
+            .get_pattern_animation_infos(reader)?
+            .entries
+            .iter()
+            .enumerate()
+        {
+            let info = info_entry.get_data(reader)?;
+            if info.get_sampler_name(reader)? != sampler_name {
+                continue;
+            }
+            if info.curve_index >= 0 {
+                if let Some(curve_entry) = curves.entries.get(info.curve_index as usize) {
+                    let curve = curve_entry.get_data(reader)?;
+                    return Ok(Some(curve.evaluate_int(frame).max(0) as usize));
+                }
+            }
+            return Ok(base_values.get(i).map(|v| usize::from(*v)));
+        }
+        Ok(None)
+    }
+}
+
+impl Importable for PatternAnimationInfo {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<PatternAnimationInfo, Box<Error>> {
+        let curve_index = reader.read_to_u8()? as i8;
+        let sub_bind_index = reader.read_to_u8()? as i8;
+        reader.seek(SeekFrom::Current(2))?;
+        let sampler_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(PatternAnimationInfo {
+            curve_index,
+            sub_bind_index,
+            sampler_name_offset,
+        })
+    }
+}
+
+impl PatternAnimationInfo {
+    pub fn get_sampler_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
+        self.sampler_name_offset.seek_abs_pos(reader)?;
+        Ok(read_text_entry(reader)?)
+    }
+}
@@ -0,0 +1,159 @@
+pub mod material_animation;
+
+use self::material_animation::MaterialAnimation;
+use error::check_magic_number;
+use ez_io::ReadE;
+use std::error::Error;
+use std::io::{Read, Seek};
+use util::{DataArray, Importable, IndexGroup, Pointer};
+
+pub struct FTXP {
+    pub header: Header,
+    pub material_animation_array: DataArray<MaterialAnimation>,
+    pub texture_reference_index_group: IndexGroup<TextureReference>,
+}
+
+pub struct Header {
+    pub file_name_offset: Pointer,
+    pub file_path_offset: Pointer,
+    pub flags: Flags,
+    pub user_data_entry_count: u16,
+    pub frame_count: i32,
+    pub texture_reference_count: u16,
+    pub material_animation_count: u16,
+    pub pattern_animation_info_count: i32,
+    pub curve_count: i32,
+    pub baked_size: u32,
+    pub fmdl_offset: Pointer,
+    pub bind_index_array_offset: Pointer,
+    pub material_animation_array_offset: Pointer,
+    pub texture_reference_index_group_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+pub struct Flags {
+    pub baked_curve: bool,
+    pub looping: bool,
+}
+
+pub struct TextureReference {
+    pub texture_name_offset: Pointer,
+    pub ftex_offset: Pointer,
+}
+
+impl Importable for FTXP {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FTXP, Box<Error>> {
+        let header = Header::import(reader)?;
+        header
+            .material_animation_array_offset
+            .seek_abs_pos(reader)?;
+        let material_animation_array =
+            DataArray::new(reader, 0x1C, u32::from(header.material_animation_count))?;
+        header
+            .texture_reference_index_group_offset
+            .seek_abs_pos(reader)?;
+        let texture_reference_index_group = IndexGroup::import(reader)?;
+        Ok(FTXP {
+            header,
+            material_animation_array,
+            texture_reference_index_group,
+        })
+    }
+}
+
+impl FTXP {
+    pub fn get_texture_names<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<String>, Box<Error>> {
+        let mut names = Vec::with_capacity(self.texture_reference_index_group.entries.len());
+        for texture_reference_entry in &self.texture_reference_index_group.entries {
+            names.push(texture_reference_entry.get_name(reader)?);
+        }
+        Ok(names)
+    }
+    // Name of the texture shown by a sampler of a material at a given frame
+    pub fn get_texture_name_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        material_name: &str,
+        sampler_name: &str,
+        frame: f32,
+    ) -> Result<Option<String>, Box<Error>> {
+        let texture_names = self.get_texture_names(reader)?;
+        for material_animation_entry in &self.material_animation_array.entries {
+            let material_animation = material_animation_entry.get_data(reader)?;
+            if material_animation.get_material_name(reader)? != material_name {
+                continue;
+            }
+            return Ok(
+                match material_animation.get_texture_index_at(reader, sampler_name, frame)? {
+                    Some(i) => texture_names.get(i).cloned(),
+                    None => None,
+                },
+            );
+        }
+        Ok(None)
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'T', b'X', b'P'])?;
+        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let flags = Flags::import(reader)?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let frame_count = reader.read_be_to_i32()?;
+        let texture_reference_count = reader.read_be_to_u16()?;
+        let material_animation_count = reader.read_be_to_u16()?;
+        let pattern_animation_info_count = reader.read_be_to_i32()?;
+        let curve_count = reader.read_be_to_i32()?;
+        let baked_size = reader.read_be_to_u32()?;
+        let fmdl_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let bind_index_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let material_animation_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let texture_reference_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            file_name_offset,
+            file_path_offset,
+            flags,
+            user_data_entry_count,
+            frame_count,
+            texture_reference_count,
+            material_animation_count,
+            pattern_animation_info_count,
+            curve_count,
+            baked_size,
+            fmdl_offset,
+            bind_index_array_offset,
+            material_animation_array_offset,
+            texture_reference_index_group_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for Flags {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Flags, Box<Error>> {
+        let raw_bits = reader.read_be_to_u16()?;
+        Ok(Flags {
+            baked_curve: raw_bits & 0b001 != 0,
+            looping: raw_bits & 0b100 != 0,
+        })
+    }
+}
+
+impl Importable for TextureReference {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<TextureReference, Box<Error>> {
+        let texture_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let ftex_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(TextureReference {
+            texture_name_offset,
+            ftex_offset,
+        })
+    }
+}
@@ -1,11 +1,161 @@
+use error::check_magic_number;
+use ez_io::ReadE;
+use fres::curve::Curve;
 use std::error::Error;
+use std::fmt;
 use std::io::{Read, Seek};
-use util::Importable;
+use util::{read_packed_bools, read_text_entry, DataArray, Importable, Pointer};
 
-pub struct FVIS {}
+pub struct FVIS {
+    pub header: Header,
+    pub curve_array: DataArray<Curve>,
+}
+
+pub struct Header {
+    pub file_name_offset: Pointer,
+    pub file_path_offset: Pointer,
+    pub flags: Flags,
+    pub user_data_entry_count: u16,
+    pub frame_count: i32,
+    pub animation_count: u16,
+    pub curve_count: u16,
+    pub baked_size: u32,
+    pub fmdl_offset: Pointer,
+    pub bind_index_array_offset: Pointer,
+    pub name_array_offset: Pointer,
+    pub curve_array_offset: Pointer,
+    pub base_value_array_offset: Pointer,
+    pub user_data_index_group_offset: Pointer,
+}
+
+pub struct Flags {
+    pub baked_curve: bool,
+    pub looping: bool,
+    pub visibility_type: VisibilityType,
+}
+
+#[derive(Copy, Clone)]
+pub enum VisibilityType {
+    Bone = 0,
+    Material = 1,
+}
 
 impl Importable for FVIS {
-    fn import<R: Read + Seek>(_reader: &mut R) -> Result<FVIS, Box<Error>> {
-        Ok(FVIS {})
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<FVIS, Box<Error>> {
+        let header = Header::import(reader)?;
+        header.curve_array_offset.seek_abs_pos(reader)?;
+        let curve_array = DataArray::new(reader, 0x24, u32::from(header.curve_count))?;
+        Ok(FVIS {
+            header,
+            curve_array,
+        })
+    }
+}
+
+impl FVIS {
+    // Names of the animated Bones or Materials
+    pub fn get_names<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<String>, Box<Error>> {
+        let mut name_pointers = Vec::with_capacity(usize::from(self.header.animation_count));
+        if self.header.animation_count > 0 {
+            self.header.name_array_offset.seek_abs_pos(reader)?;
+            for _ in 0..self.header.animation_count {
+                name_pointers.push(Pointer::read_new_rel_i32_be(reader)?);
+            }
+        }
+        let mut names = Vec::with_capacity(name_pointers.len());
+        for pointer in name_pointers {
+            pointer.seek_abs_pos(reader)?;
+            names.push(read_text_entry(reader)?);
+        }
+        Ok(names)
+    }
+    // Visibility of every Bone or Material when no curve is applied
+    pub fn get_base_values<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<bool>, Box<Error>> {
+        if self.header.animation_count == 0 {
+            return Ok(Vec::new());
+        }
+        self.header.base_value_array_offset.seek_abs_pos(reader)?;
+        let base_values = read_packed_bools(reader, usize::from(self.header.animation_count))?;
+        Ok(base_values)
+    }
+    // Visibility of every animated Bone or Material at a given frame
+    pub fn get_visibility_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        frame: f32,
+    ) -> Result<Vec<(String, bool)>, Box<Error>> {
+        let names = self.get_names(reader)?;
+        let mut visibility = self.get_base_values(reader)?;
+        for curve_entry in &self.curve_array.entries {
+            let curve = curve_entry.get_data(reader)?;
+            // The target offset of a visibility curve is the index of the animated entry
+            if let Some(v) = visibility.get_mut(curve.target_offset as usize) {
+                *v = curve.evaluate_bool(frame);
+            }
+        }
+        Ok(names.into_iter().zip(visibility.into_iter()).collect())
+    }
+}
+
+impl Importable for Header {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'F', b'V', b'I', b'S'])?;
+        let file_name_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let file_path_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let flags = Flags::import(reader)?;
+        let user_data_entry_count = reader.read_be_to_u16()?;
+        let frame_count = reader.read_be_to_i32()?;
+        let animation_count = reader.read_be_to_u16()?;
+        let curve_count = reader.read_be_to_u16()?;
+        let baked_size = reader.read_be_to_u32()?;
+        let fmdl_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let bind_index_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let name_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let curve_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let base_value_array_offset = Pointer::read_new_rel_i32_be(reader)?;
+        let user_data_index_group_offset = Pointer::read_new_rel_i32_be(reader)?;
+        Ok(Header {
+            file_name_offset,
+            file_path_offset,
+            flags,
+            user_data_entry_count,
+            frame_count,
+            animation_count,
+            curve_count,
+            baked_size,
+            fmdl_offset,
+            bind_index_array_offset,
+            name_array_offset,
+            curve_array_offset,
+            base_value_array_offset,
+            user_data_index_group_offset,
+        })
+    }
+}
+
+impl Importable for Flags {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<Flags, Box<Error>> {
+        let raw_bits = reader.read_be_to_u16()?;
+        let visibility_type = match (raw_bits & 0b1_0000_0000) >> 8 {
+            0 => VisibilityType::Bone,
+            _ => VisibilityType::Material,
+        };
+        Ok(Flags {
+            baked_curve: raw_bits & 0b001 != 0,
+            looping: raw_bits & 0b100 != 0,
+            visibility_type,
+        })
+    }
+}
+
+impl fmt::Display for VisibilityType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            VisibilityType::Bone => "Bone",
+            VisibilityType::Material => "Material",
+        };
+        write!(f, "{}", text)
     }
 }
@@ -0,0 +1,445 @@
+use dds::DDS;
+use error::{FormatMismatch, SliceCountMismatch, TextureNotFound};
+use fres::ftex::bcn::Quality;
+use fres::ftex::encode::{encode_rgba8, generate_mipmaps_rgba8};
+use fres::ftex::{Header, FTEX};
+use fres::FRES;
+use gx2::surface::{compute_surface_info, Surface};
+use std::cmp::{max, min};
+use std::error::Error;
+use std::io::Cursor;
+use swizzle::swizzle_mip_level;
+use util::Importable;
+
+// A texture to put in place of an existing one
+pub enum NewTexture {
+    // A single image with channels in the order they are stored, mipmaps are generated
+    Rgba8 {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    // Used as it is, it needs the format and slice count of the texture it replaces
+    DDS(DDS),
+}
+
+// Gives back a new BFRES, decompressed, where the texture has been replaced. The texture keeps its
+// format, dimension and number of slices, everything else follows the new image. Quality is used
+// when a block compressed texture gets encoded from an image.
+pub fn replace_texture(
+    bfres: &[u8],
+    name: &str,
+    texture: &NewTexture,
+    quality: Quality,
+) -> Result<Vec<u8>, Box<Error>> {
+    let mut cursor = Cursor::new(bfres);
+    let fres = FRES::import(&mut cursor)?;
+    let mut found = None;
+    if let Some(ftex_index_group) = fres.sub_file_index_groups.texture_data {
+        for ftex_entry in ftex_index_group.entries {
+            if ftex_entry.get_name(&mut cursor)? == name {
+                found = Some(ftex_entry);
+                break;
+            }
+        }
+    }
+    let ftex_entry = match found {
+        Some(e) => e,
+        None => {
+            return Err(Box::new(TextureNotFound {
+                name: name.to_string(),
+            }))
+        }
+    };
+    let ftex_start = ftex_entry.data_pointer.get_abs_pos()? as usize;
+    let mut ftex: FTEX = ftex_entry.get_data(&mut cursor)?;
+    let old_data_start = ftex.header.data_offset.get_abs_pos()? as usize;
+    let old_data_length = ftex.header.data_length as usize;
+    let old_mipmaps_start = if ftex.header.mipmap_offset.points_to == 0 {
+        None
+    } else {
+        Some(ftex.header.mipmap_offset.get_abs_pos()? as usize)
+    };
+    let old_mipmaps_length = ftex.header.mipmaps_data_length as usize;
+
+    // Linear data of every level, all the slices one after the other
+    let levels = get_levels(&ftex, texture, quality)?;
+    let (width, height) = match *texture {
+        NewTexture::Rgba8 { width, height, .. } => (width, height),
+        NewTexture::DDS(ref dds) => (dds.width, dds.height),
+    };
+    {
+        let header = &mut ftex.header;
+        header.texture_width = width;
+        header.texture_height = height;
+        header.nb_mipmaps = levels.len() as u32;
+        let info = compute_surface_info(&Surface::from_header(header));
+        header.tile_mode = info.tile_mode;
+        header.pitch = info.pitch;
+        header.alignment = info.alignment;
+        header.data_length = info.data_length;
+        header.mipmaps_data_length = info.mipmaps_data_length;
+        header.mipmap_offsets = info.mipmap_offsets;
+        update_texture_registers(header);
+    }
+    let header = &ftex.header;
+
+    // Swizzle everything
+    let data = swizzle_mip_level(header, 0, &levels[0]);
+    let mut mipmaps_data = vec![0u8; header.mipmaps_data_length as usize];
+    for (level, linear) in levels.iter().enumerate().skip(1) {
+        let start = header.get_mipmap_start(level as u32) as usize;
+        let swizzled = swizzle_mip_level(header, level as u32, linear);
+        let end = min(mipmaps_data.len(), start + swizzled.len());
+        mipmaps_data[start..end].clone_from_slice(&swizzled[..end - start]);
+    }
+
+    // The data stays where it was if it still fits, or goes at the end of the file
+    let mut out = bfres.to_vec();
+    let alignment = max(1, header.alignment as usize);
+    let data_start = place_data(
+        &mut out,
+        Some(old_data_start),
+        old_data_length,
+        &data,
+        alignment,
+    );
+    write_pointer(&mut out, ftex_start + 0xB0, data_start);
+    if !mipmaps_data.is_empty() {
+        let mipmaps_start = place_data(
+            &mut out,
+            old_mipmaps_start,
+            old_mipmaps_length,
+            &mipmaps_data,
+            alignment,
+        );
+        write_pointer(&mut out, ftex_start + 0xB4, mipmaps_start);
+    } else {
+        write_u32(&mut out, ftex_start + 0xB4, 0);
+    }
+    write_header(&mut out, ftex_start, header);
+
+    // File length and alignment in the FRES header
+    let file_length = out.len() as u32;
+    write_u32(&mut out, 0x0C, file_length);
+    if fres.header.file_alignment < header.alignment {
+        write_u32(&mut out, 0x10, header.alignment);
+    }
+    Ok(out)
+}
+
+fn get_levels(
+    ftex: &FTEX,
+    texture: &NewTexture,
+    quality: Quality,
+) -> Result<Vec<Vec<u8>>, Box<Error>> {
+    let format = ftex.header.texture_format;
+    let nb_slices = ftex.get_slice_count(0);
+    match *texture {
+        NewTexture::Rgba8 {
+            width,
+            height,
+            ref data,
+        } => {
+            if nb_slices != 1 {
+                return Err(Box::new(SliceCountMismatch {
+                    expected: nb_slices,
+                    got: 1,
+                }));
+            }
+            // As many levels as before, as long as the new image is big enough for them
+            let max_levels = 32 - max(1, max(width, height)).leading_zeros();
+            let nb_mipmaps = min(min(max(1, ftex.header.nb_mipmaps), max_levels), 14);
+            let mut levels = Vec::new();
+            let images = generate_mipmaps_rgba8(data, width, height, nb_mipmaps, format.is_srgb())?;
+            for (level, image) in images.iter().enumerate() {
+                levels.push(encode_rgba8(
+                    format,
+                    image,
+                    max(1, width >> level),
+                    max(1, height >> level),
+                    quality,
+                )?);
+            }
+            Ok(levels)
+        }
+        NewTexture::DDS(ref dds) => {
+            // sRGB only changes how the data is read, not how it is stored
+            if (dds.format as u32 ^ format as u32) & !0x400 != 0 {
+                return Err(Box::new(FormatMismatch {
+                    expected: format as u32,
+                    got: dds.format as u32,
+                }));
+            }
+            if dds.depth != nb_slices {
+                return Err(Box::new(SliceCountMismatch {
+                    expected: nb_slices,
+                    got: dds.depth,
+                }));
+            }
+            Ok(dds
+                .mip_levels
+                .iter()
+                .take(14)
+                .map(|slices| slices.concat())
+                .collect())
+        }
+    }
+}
+
+// Size, tiling and number of levels, as the GPU reads them
+fn update_texture_registers(header: &mut Header) {
+    // The register wants the pitch in pixels
+    let pitch = if header.texture_format.is_block_compressed() {
+        header.pitch * 4
+    } else {
+        header.pitch
+    };
+    let tile_mode = header.tile_mode as u32;
+    let width = max(1, header.texture_width);
+    let height = max(1, header.texture_height);
+    let last_level = max(1, header.nb_mipmaps) - 1;
+    let registers = &mut header.texture_registers;
+    registers[0] = (registers[0] & 0x87)
+        | ((tile_mode & 0xF) << 3)
+        | (((max(8, pitch) / 8 - 1) & 0x7FF) << 8)
+        | (((width - 1) & 0x1FFF) << 19);
+    registers[1] = (registers[1] & !0x1FFF) | ((height - 1) & 0x1FFF);
+    registers[3] = (registers[3] & !0xF) | (last_level & 0xF);
+}
+
+// Overwrites the old data when possible, returns where the new data starts
+fn place_data(
+    out: &mut Vec<u8>,
+    old_start: Option<usize>,
+    old_length: usize,
+    data: &[u8],
+    alignment: usize,
+) -> usize {
+    if let Some(start) = old_start {
+        if data.len() <= old_length && start % alignment == 0 {
+            out[start..start + data.len()].clone_from_slice(data);
+            for byte in &mut out[start + data.len()..start + old_length] {
+                *byte = 0;
+            }
+            return start;
+        }
+    }
+    let start = out.len().div_ceil(alignment) * alignment;
+    out.resize(start, 0);
+    out.extend_from_slice(data);
+    start
+}
+
+fn write_u32(out: &mut [u8], position: usize, value: u32) {
+    out[position] = (value >> 24) as u8;
+    out[position + 1] = (value >> 16) as u8;
+    out[position + 2] = (value >> 8) as u8;
+    out[position + 3] = value as u8;
+}
+
+// Offsets in the file count from where they are written
+fn write_pointer(out: &mut [u8], position: usize, target: usize) {
+    write_u32(out, position, (target as i64 - position as i64) as u32);
+}
+
+fn write_header(out: &mut [u8], ftex_start: usize, header: &Header) {
+    write_u32(out, ftex_start + 0x08, header.texture_width);
+    write_u32(out, ftex_start + 0x0C, header.texture_height);
+    write_u32(out, ftex_start + 0x14, header.nb_mipmaps);
+    write_u32(out, ftex_start + 0x24, header.data_length);
+    write_u32(out, ftex_start + 0x2C, header.mipmaps_data_length);
+    write_u32(out, ftex_start + 0x34, header.tile_mode as u32);
+    write_u32(out, ftex_start + 0x3C, header.alignment);
+    write_u32(out, ftex_start + 0x40, header.pitch);
+    for (i, offset) in header.mipmap_offsets.iter().enumerate() {
+        write_u32(out, ftex_start + 0x44 + i * 4, *offset);
+    }
+    write_u32(out, ftex_start + 0x7C, header.nb_mipmaps);
+    for (i, register) in header.texture_registers.iter().enumerate() {
+        write_u32(out, ftex_start + 0x8C + i * 4, *register);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::ftex::{aa_mode::AAMode, dimension::Dimension, format::Format, tile_mode::TileMode};
+    use fres::writer::{Target, Writer};
+
+    // A BFRES holding a single texture, RGBA8 and empty
+    fn make_bfres(name: &str, width: u32, height: u32, nb_mipmaps: u32) -> Vec<u8> {
+        let info = compute_surface_info(&Surface {
+            format: Format::TcsR8G8B8A8Unorm,
+            dimension: Dimension::TwoD,
+            width,
+            height,
+            depth: 1,
+            nb_mipmaps,
+            aa_mode: AAMode::OneTime,
+            tile_mode: TileMode::Default,
+        });
+        let mut writer = Writer::new();
+        let texture_group_label = writer.new_label();
+        let ftex_label = writer.new_label();
+        let string_table_label = writer.new_label();
+
+        writer.write_bytes(b"FRES");
+        writer.write_bytes(&[3, 4, 0, 4]);
+        writer.write_u16(0xFEFF);
+        writer.write_u16(0x10);
+        writer.write_u32(0); // File length
+        writer.write_u32(0); // Alignment
+        writer.write_text_pointer("test");
+        writer.write_u32(0); // String table length
+        writer.write_label_pointer(string_table_label);
+        writer.write_pointer(Target::Null);
+        writer.write_label_pointer(texture_group_label);
+        for _ in 2..12 {
+            writer.write_pointer(Target::Null);
+        }
+        writer.write_u16(0);
+        writer.write_u16(1);
+        for _ in 2..12 {
+            writer.write_u16(0);
+        }
+        writer.write_u32(0); // User pointer
+        writer.place(texture_group_label);
+        writer.write_index_group(vec![(name.to_string(), Target::Label(ftex_label))]);
+
+        let alignment = info.alignment as usize;
+        let data_label = writer.defer_data(vec![0u8; info.data_length as usize], alignment);
+        writer.place(ftex_label);
+        writer.write_bytes(b"FTEX");
+        for &value in &[
+            1,
+            width,
+            height,
+            1,
+            nb_mipmaps,
+            0x01A,
+            0,
+            1,
+            info.data_length,
+            0,
+        ] {
+            writer.write_u32(value);
+        }
+        writer.write_u32(info.mipmaps_data_length);
+        writer.write_u32(0);
+        writer.write_u32(info.tile_mode as u32);
+        writer.write_u32(0); // Swizzle value
+        writer.write_u32(info.alignment);
+        writer.write_u32(info.pitch);
+        for &offset in &info.mipmap_offsets {
+            writer.write_u32(offset);
+        }
+        for &value in &[0, nb_mipmaps, 0, 1, 0x0001_0203, 0, 0, 0, 0, 0, 0, 1] {
+            writer.write_u32(value);
+        }
+        writer.write_text_pointer(name);
+        writer.write_pointer(Target::Null); // Path
+        writer.write_label_pointer(data_label);
+        if info.mipmaps_data_length == 0 {
+            writer.write_pointer(Target::Null);
+        } else {
+            let mipmaps = vec![0u8; info.mipmaps_data_length as usize];
+            let mipmaps_label = writer.defer_data(mipmaps, alignment);
+            writer.write_label_pointer(mipmaps_label);
+        }
+        writer.write_pointer(Target::Null); // User data
+        writer.write_u16(0);
+        writer.write_u16(0);
+
+        writer.align(4);
+        writer.place(string_table_label);
+        let (_, string_table_length) = writer.write_string_table();
+        writer.set_u32(0x18, string_table_length as u32);
+        let alignment = max(4, writer.write_deferred_data());
+        let file_length = writer.position();
+        writer.set_u32(0xC, file_length as u32);
+        writer.set_u32(0x10, alignment as u32);
+        writer.finish()
+    }
+
+    fn get_texture<'a>(bfres: &'a [u8], name: &str) -> (FTEX, Cursor<&'a [u8]>) {
+        let mut cursor = Cursor::new(bfres);
+        let fres = FRES::import(&mut cursor).unwrap();
+        for entry in fres.sub_file_index_groups.texture_data.unwrap().entries {
+            if entry.get_name(&mut cursor).unwrap() == name {
+                let ftex = entry.get_data(&mut cursor).unwrap();
+                return (ftex, cursor);
+            }
+        }
+        panic!("No texture named {}", name);
+    }
+
+    fn make_image(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height * 4)
+            .map(|i| (i * 7 + i / 13) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn replace_with_a_bigger_image() {
+        let bfres = make_bfres("tex", 16, 16, 3);
+        let image = make_image(64, 32);
+        let texture = NewTexture::Rgba8 {
+            width: 64,
+            height: 32,
+            data: image.clone(),
+        };
+        let out = replace_texture(&bfres, "tex", &texture, Quality::Normal).unwrap();
+        let (ftex, mut cursor) = get_texture(&out, "tex");
+        assert_eq!(ftex.header.texture_width, 64);
+        assert_eq!(ftex.header.texture_height, 32);
+        assert_eq!(ftex.header.nb_mipmaps, 3);
+        assert_eq!(ftex.decode_rgba8(&mut cursor, false).unwrap(), image);
+        let mipmaps = generate_mipmaps_rgba8(&image, 64, 32, 3, false).unwrap();
+        for level in 1..3 {
+            assert_eq!(
+                ftex.decode_slice_rgba8(&mut cursor, level, 0, false)
+                    .unwrap(),
+                mipmaps[level as usize]
+            );
+        }
+    }
+
+    #[test]
+    fn replace_in_place_with_a_smaller_image() {
+        let bfres = make_bfres("tex", 64, 64, 1);
+        let image = make_image(32, 32);
+        let texture = NewTexture::Rgba8 {
+            width: 32,
+            height: 32,
+            data: image.clone(),
+        };
+        let out = replace_texture(&bfres, "tex", &texture, Quality::Normal).unwrap();
+        assert_eq!(out.len(), bfres.len());
+        let (ftex, mut cursor) = get_texture(&out, "tex");
+        assert_eq!(ftex.header.nb_mipmaps, 1);
+        assert_eq!(ftex.decode_rgba8(&mut cursor, false).unwrap(), image);
+    }
+
+    #[test]
+    fn unknown_texture() {
+        let bfres = make_bfres("tex", 16, 16, 1);
+        let texture = NewTexture::Rgba8 {
+            width: 4,
+            height: 4,
+            data: make_image(4, 4),
+        };
+        assert!(replace_texture(&bfres, "other", &texture, Quality::Normal).is_err());
+    }
+
+    #[test]
+    fn image_data_too_short() {
+        let bfres = make_bfres("tex", 16, 16, 3);
+        let texture = NewTexture::Rgba8 {
+            width: 16,
+            height: 16,
+            data: make_image(16, 8),
+        };
+        assert!(replace_texture(&bfres, "tex", &texture, Quality::Normal).is_err());
+    }
+}
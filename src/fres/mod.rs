@@ -1,3 +1,5 @@
+pub mod build;
+pub mod curve;
 pub mod embedded;
 pub mod fmdl;
 pub mod fscn;
@@ -7,37 +9,49 @@ pub mod fska;
 pub mod ftex;
 pub mod ftxp;
 pub mod fvis;
+pub mod inject;
+pub mod replace;
+pub mod validate;
+pub mod writer;
 
-use self::embedded::Embedded;
+use self::embedded::{Embedded, EmbeddedFile, Kind as EmbeddedKind};
 use self::fmdl::FMDL;
 use self::fscn::FSCN;
 use self::fsha::FSHA;
-use self::fshu::FSHU;
+use self::fshu::{Kind as FSHUKind, FSHU};
 use self::fska::FSKA;
 use self::ftex::FTEX;
 use self::ftxp::FTXP;
 use self::fvis::FVIS;
+use error::check_magic_number;
 use error::IncorrectHeaderLength;
 use error::UserDataNotEmpty;
-use error::check_magic_number;
 use ez_io::ReadE;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FMTResult};
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Cursor;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
+use std::path::Path;
+use util::align_on_4_bytes;
 use util::Importable;
 use util::IndexGroup;
+use util::Opened;
 use util::Pointer;
-use util::align_on_4_bytes;
+use yaz0;
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct FRES {
     pub header: Header,
     pub string_table: StringTable,
     pub sub_file_index_groups: SubFileIndexGroups,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
     pub version: Version,
     pub file_length: u32,
@@ -49,14 +63,17 @@ pub struct Header {
     pub sub_file_index_groups_entry_counts: [u16; 12],
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Version {
     pub numbers: [u8; 4],
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct StringTable {
     pub map: HashMap<u64, String>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct SubFileIndexGroups {
     pub model_data: Option<IndexGroup<FMDL>>,
     pub texture_data: Option<IndexGroup<FTEX>>,
@@ -85,6 +102,19 @@ impl Importable for FRES {
     }
 }
 
+impl FRES {
+    // Sub-files are read from the returned cursor, which holds the whole decompressed file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Opened<FRES>, Box<Error>> {
+        FRES::from_reader(&mut BufReader::new(File::open(path)?))
+    }
+    // Works with both Yaz0 compressed and uncompressed files
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Opened<FRES>, Box<Error>> {
+        let mut cursor = Cursor::new(yaz0::read_all(reader)?);
+        let fres = FRES::import(&mut cursor)?;
+        Ok((fres, cursor))
+    }
+}
+
 impl Importable for Header {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<Header, Box<Error>> {
         // Magic Number
@@ -103,7 +133,7 @@ impl Importable for Header {
         let header_length = reader.read_be_to_u16()?;
         if header_length != 0x0010 {
             return Err(Box::new(IncorrectHeaderLength {
-                size: header_length,
+                size: u32::from(header_length),
             }));
         }
         // File Length
@@ -168,6 +198,13 @@ impl Importable for Version {
     }
 }
 
+impl Version {
+    // From v4.5, shapes have one bounding radius per LOD model instead of a single one
+    pub fn has_radius_array(&self) -> bool {
+        self.numbers[0] > 4 || (self.numbers[0] == 4 && self.numbers[1] >= 5)
+    }
+}
+
 impl Display for Version {
     fn fmt(&self, f: &mut Formatter) -> FMTResult {
         write!(
@@ -255,4 +292,32 @@ impl SubFileIndexGroups {
             embedded_file,
         })
     }
+    pub fn get_fshu_groups(&self) -> Vec<(FSHUKind, &IndexGroup<FSHU>)> {
+        let mut groups = Vec::new();
+        if let Some(ref a) = self.shader_parameters {
+            groups.push((FSHUKind::ShaderParameters, a));
+        }
+        if let Some(ref a) = self.color_animation {
+            groups.push((FSHUKind::ColorAnimation, a));
+        }
+        if let Some(ref a) = self.texture_srt_animation {
+            groups.push((FSHUKind::TextureSRTAnimation, a));
+        }
+        groups
+    }
+    pub fn get_embedded_files<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<EmbeddedFile>, Box<Error>> {
+        let mut files = Vec::new();
+        if let Some(ref a) = self.embedded_file {
+            for entry in &a.entries {
+                let name = entry.get_name(reader)?;
+                let data = entry.get_data(reader)?.get_data(reader)?;
+                let kind = EmbeddedKind::sniff(&data);
+                files.push(EmbeddedFile { name, kind, data });
+            }
+        }
+        Ok(files)
+    }
 }
@@ -0,0 +1,777 @@
+use error::{
+    BoneNotFound, BoneNotSkinned, BoneOutOfRange, MeshDataTooShort, MeshOutOfRange, ModelNotFound,
+    ShapeNotFound,
+};
+use fres::build::{
+    encode_indices, get_bounds, quantize_weights, write_bounding, write_buffer_info,
+    write_lod_model, BUFFER_ALIGNMENT,
+};
+use fres::fmdl::fshp::lod_model::{IndexFormat, LODModel};
+use fres::fmdl::fshp::FSHP;
+use fres::fmdl::fskl::bone::{Bone, RotationMode};
+use fres::fmdl::fvtx::attributes::{Attributes, AttributesFormats};
+use fres::fmdl::fvtx::encode::encode_attribute;
+use fres::fmdl::fvtx::FVTX;
+use fres::fmdl::FMDL;
+use fres::writer::{Target, Writer};
+use fres::FRES;
+use scene::{compose, invert, multiply, transform_normal, transform_point, Matrix, Mesh, Scene};
+use std::cmp::max;
+use std::error::Error;
+use std::io::Cursor;
+use util::{BufferInfo, Importable};
+
+// What is kept from the model, with where its parts are in the file
+struct Model {
+    fmdl_start: usize,
+    // For each vertex buffer, their sum is the vertex count of the model
+    nb_vertices: Vec<u32>,
+    shapes: Vec<ShapeInfo>,
+    bones: Vec<SkeletonBone>,
+    has_radius_array: bool,
+    file_alignment: u32,
+}
+
+struct ShapeInfo {
+    name: String,
+    fshp_start: usize,
+    fvtx_start: usize,
+    fvtx_index: usize,
+    bone_index: usize,
+    skin_count: u8,
+    index_format: IndexFormat,
+    attributes: Vec<AttributeInfo>,
+    strides: Vec<u16>,
+}
+
+struct AttributeInfo {
+    name: String,
+    format: AttributesFormats,
+    buffer_index: usize,
+    offset: usize,
+}
+
+struct SkeletonBone {
+    name: String,
+    smooth_matrix_index: i16,
+    rigid_matrix_index: i16,
+    world_matrix: Matrix,
+}
+
+// Geometry in the layout of the shape it replaces
+struct NewGeometry {
+    vertex_count: u32,
+    buffers: Vec<Vec<u8>>,
+    skin_bone_indices: Vec<u16>,
+    index_format: IndexFormat,
+    index_count: u32,
+    indices: Vec<u8>,
+    center: [f32; 3],
+    extent: [f32; 3],
+    radius: f32,
+}
+
+// Gives back a new BFRES, decompressed, where a shape has the geometry of a mesh of the scene. The
+// shape keeps its material, its bone, its skinning and the formats of its attributes. The first
+// model is used when no name is given.
+pub fn replace_shape(
+    bfres: &[u8],
+    model_name: Option<&str>,
+    shape_name: &str,
+    scene: &Scene,
+    mesh_index: usize,
+) -> Result<Vec<u8>, Box<Error>> {
+    let model = read_model(bfres, model_name)?;
+    let mesh = match scene.meshes.get(mesh_index) {
+        Some(m) => m,
+        None => {
+            return Err(Box::new(MeshOutOfRange {
+                index: mesh_index,
+                nb_meshes: scene.meshes.len(),
+            }))
+        }
+    };
+    let shape_index = match model.shapes.iter().position(|s| s.name == shape_name) {
+        Some(i) => i,
+        None => {
+            return Err(Box::new(ShapeNotFound {
+                name: shape_name.to_string(),
+            }))
+        }
+    };
+    write_geometry(bfres, &model, scene, &[(shape_index, Some(mesh))])
+}
+
+// Every shape of the model gets the geometry of the scene mesh with the same name, the ones no mesh
+// is named after are left with nothing to draw. Materials and skeleton stay as they are.
+pub fn replace_model(
+    bfres: &[u8],
+    model_name: Option<&str>,
+    scene: &Scene,
+) -> Result<Vec<u8>, Box<Error>> {
+    let model = read_model(bfres, model_name)?;
+    for mesh in &scene.meshes {
+        if !model.shapes.iter().any(|s| s.name == mesh.name) {
+            return Err(Box::new(ShapeNotFound {
+                name: mesh.name.clone(),
+            }));
+        }
+    }
+    let replacements: Vec<(usize, Option<&Mesh>)> = model
+        .shapes
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (i, scene.meshes.iter().find(|m| m.name == s.name)))
+        .collect();
+    write_geometry(bfres, &model, scene, &replacements)
+}
+
+fn read_model(bfres: &[u8], model_name: Option<&str>) -> Result<Model, Box<Error>> {
+    let mut cursor = Cursor::new(bfres);
+    let fres = FRES::import(&mut cursor)?;
+    let mut found = None;
+    if let Some(fmdl_index_group) = fres.sub_file_index_groups.model_data {
+        for fmdl_entry in fmdl_index_group.entries {
+            let is_wanted = match model_name {
+                Some(n) => fmdl_entry.get_name(&mut cursor)? == n,
+                None => true,
+            };
+            if is_wanted {
+                found = Some(fmdl_entry);
+                break;
+            }
+        }
+    }
+    let fmdl_entry = match found {
+        Some(e) => e,
+        None => {
+            return Err(Box::new(ModelNotFound {
+                name: model_name.unwrap_or("").to_string(),
+            }))
+        }
+    };
+    let fmdl_start = fmdl_entry.data_pointer.get_abs_pos()? as usize;
+    let fmdl: FMDL = fmdl_entry.get_data(&mut cursor)?;
+
+    let mut nb_vertices = Vec::with_capacity(fmdl.fvtx_array.entries.len());
+    for fvtx_entry in &fmdl.fvtx_array.entries {
+        let fvtx: FVTX = fvtx_entry.get_data(&mut cursor)?;
+        nb_vertices.push(fvtx.header.nb_vertices);
+    }
+
+    let mut bones: Vec<SkeletonBone> = Vec::new();
+    for bone_entry in &fmdl.fskl.bones.entries {
+        let name = bone_entry.get_name(&mut cursor)?;
+        let bone: Bone = bone_entry.get_data(&mut cursor)?;
+        let local = compose(
+            bone.scale_vectors,
+            get_quaternion(&bone),
+            bone.translation_vectors,
+        );
+        // Parents come first, the root has 0xFFFF
+        let world_matrix = match bones.get(usize::from(bone.parent_index)) {
+            Some(parent) => multiply(&parent.world_matrix, &local),
+            None => local,
+        };
+        bones.push(SkeletonBone {
+            name,
+            smooth_matrix_index: bone.smooth_matrix_index,
+            rigid_matrix_index: bone.rigid_matrix_index,
+            world_matrix,
+        });
+    }
+
+    let mut shapes = Vec::new();
+    for fshp_entry in &fmdl.fshp_index_group.entries {
+        let name = fshp_entry.get_name(&mut cursor)?;
+        let fshp_start = fshp_entry.data_pointer.get_abs_pos()? as usize;
+        let fshp: FSHP = fshp_entry.get_data(&mut cursor)?;
+        let index_format = match fshp.lod_model_array.entries.first() {
+            Some(e) => {
+                let lod_model: LODModel = e.get_data(&mut cursor)?;
+                lod_model.index_format
+            }
+            None => IndexFormat::U16BigEndian,
+        };
+        let fvtx_start = fshp.header.fvtx_offset.get_abs_pos()? as usize;
+        fshp.header.fvtx_offset.seek_abs_pos(&mut cursor)?;
+        let fvtx = FVTX::import(&mut cursor)?;
+        let mut attributes = Vec::new();
+        for attribute_entry in &fvtx.attributes_index_group.entries {
+            let attribute: Attributes = attribute_entry.get_data(&mut cursor)?;
+            attributes.push(AttributeInfo {
+                name: attribute_entry.get_name(&mut cursor)?,
+                format: attribute.format,
+                buffer_index: usize::from(attribute.buffer_info_index),
+                offset: usize::from(attribute.buffer_offset),
+            });
+        }
+        let mut strides = Vec::new();
+        for buffer_entry in &fvtx.buffer_info_array.entries {
+            let buffer_info: BufferInfo = buffer_entry.get_data(&mut cursor)?;
+            strides.push(buffer_info.stride);
+        }
+        shapes.push(ShapeInfo {
+            name,
+            fshp_start,
+            fvtx_start,
+            fvtx_index: usize::from(fshp.header.fvtx_index),
+            bone_index: usize::from(fshp.header.fskl_index),
+            skin_count: fshp.header.vertex_skin_count,
+            index_format,
+            attributes,
+            strides,
+        });
+    }
+
+    Ok(Model {
+        fmdl_start,
+        nb_vertices,
+        shapes,
+        bones,
+        has_radius_array: fres.header.version.has_radius_array(),
+        file_alignment: fres.header.file_alignment,
+    })
+}
+
+// Rotations can also be stored as angles around X, then Y, then Z
+fn get_quaternion(bone: &Bone) -> [f32; 4] {
+    let r = bone.rotation_vectors;
+    match bone.flags.rotation {
+        RotationMode::Quaternion => r,
+        RotationMode::XYZEuler => {
+            let (sx, cx) = (r[0] / 2f32).sin_cos();
+            let (sy, cy) = (r[1] / 2f32).sin_cos();
+            let (sz, cz) = (r[2] / 2f32).sin_cos();
+            [
+                sx * cy * cz - cx * sy * sz,
+                cx * sy * cz + sx * cy * sz,
+                cx * cy * sz - sx * sy * cz,
+                cx * cy * cz + sx * sy * sz,
+            ]
+        }
+    }
+}
+
+// The headers are changed where they are, everything they point to that changes size goes at the end
+// of the file. Shapes without a mesh get a single empty triangle.
+fn write_geometry(
+    bfres: &[u8],
+    model: &Model,
+    scene: &Scene,
+    replacements: &[(usize, Option<&Mesh>)],
+) -> Result<Vec<u8>, Box<Error>> {
+    let empty_mesh = Mesh {
+        name: String::new(),
+        material_index: 0,
+        positions: vec![[0f32; 3]],
+        normals: Vec::new(),
+        texture_coordinates: Vec::new(),
+        colors: Vec::new(),
+        bone_indices: Vec::new(),
+        bone_weights: Vec::new(),
+        indices: vec![0, 0, 0],
+    };
+    let mut writer = Writer::from_data(bfres.to_vec());
+    let mut nb_vertices = model.nb_vertices.clone();
+    for &(shape_index, mesh) in replacements {
+        let shape = &model.shapes[shape_index];
+        let geometry = make_geometry(scene, mesh.unwrap_or(&empty_mesh), shape, &model.bones)?;
+        if let Some(n) = nb_vertices.get_mut(shape.fvtx_index) {
+            *n = geometry.vertex_count;
+        }
+        write_shape(&mut writer, shape, geometry, model.has_radius_array);
+    }
+    writer.set_u32(model.fmdl_start + 0x28, nb_vertices.iter().sum());
+
+    let alignment = max(model.file_alignment as usize, writer.write_deferred_data());
+    writer.align(4);
+    let file_length = writer.position();
+    writer.set_u32(0xC, file_length as u32);
+    writer.set_u32(0x10, alignment as u32);
+    Ok(writer.finish())
+}
+
+fn make_geometry(
+    scene: &Scene,
+    mesh: &Mesh,
+    shape: &ShapeInfo,
+    bones: &[SkeletonBone],
+) -> Result<NewGeometry, Box<Error>> {
+    let vertex_count = mesh.positions.len();
+    let skin_count = shape.skin_count;
+    let is_skinned = !mesh.bone_indices.is_empty() && !scene.bones.is_empty();
+
+    // Every vertex needs its bones and weights
+    let lengths = [
+        ("bone indices", mesh.bone_indices.len()),
+        ("bone weights", mesh.bone_weights.len()),
+    ];
+    for &(data, length) in &lengths {
+        if is_skinned && skin_count > 0 && length < vertex_count {
+            return Err(Box::new(MeshDataTooShort {
+                name: mesh.name.clone(),
+                data: data.to_string(),
+                expected: vertex_count,
+                got: length,
+            }));
+        }
+    }
+
+    // Scene bones to bones of the skeleton, by name
+    let mut bone_map = vec![None; scene.bones.len()];
+    if is_skinned && skin_count > 0 {
+        for (indices, weights) in mesh.bone_indices.iter().zip(mesh.bone_weights.iter()) {
+            for (&i, &w) in indices.iter().zip(weights.iter()) {
+                let i = usize::from(i);
+                // Unused influences do not need to point at a bone
+                if w <= 0f32 {
+                    continue;
+                }
+                if i >= bone_map.len() {
+                    return Err(Box::new(BoneOutOfRange {
+                        index: i,
+                        nb_bones: bone_map.len(),
+                    }));
+                }
+                if bone_map[i].is_some() {
+                    continue;
+                }
+                let name = &scene.bones[i].name;
+                match bones.iter().position(|b| &b.name == name) {
+                    Some(b) => bone_map[i] = Some(b),
+                    None => return Err(Box::new(BoneNotFound { name: name.clone() })),
+                }
+            }
+        }
+    }
+
+    // Bones and weights each vertex ends up with, as many as the shape allows
+    let mut influences: Vec<Vec<(usize, f32)>> = Vec::with_capacity(vertex_count);
+    for i in 0..vertex_count {
+        let mut vertex: Vec<(usize, f32)> = if is_skinned && skin_count > 0 {
+            mesh.bone_indices[i]
+                .iter()
+                .zip(mesh.bone_weights[i].iter())
+                .filter(|&(_, &w)| w > 0f32)
+                .filter_map(|(&b, &w)| bone_map[usize::from(b)].map(|b| (b, w)))
+                .take(usize::from(skin_count))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if vertex.is_empty() {
+            vertex.push((shape.bone_index, 1f32));
+        }
+        let total: f32 = vertex.iter().map(|v| v.1).sum();
+        for v in &mut vertex {
+            v.1 /= total;
+        }
+        influences.push(vertex);
+    }
+
+    // Smooth skinned vertices stay in model space, the others go in the space of their bone. Rigid
+    // skinning falls back on smooth matrices, which already hold the inverse of the bind pose.
+    let mut positions = mesh.positions.clone();
+    let mut normals = mesh.normals.clone();
+    let mut matrix_indices: Vec<Vec<u16>> = Vec::with_capacity(vertex_count);
+    for i in 0..vertex_count {
+        let mut space = None;
+        let mut indices = Vec::new();
+        if skin_count == 0 {
+            space = Some(shape.bone_index);
+        } else {
+            for &(b, _) in &influences[i] {
+                let bone = &bones[b];
+                let index = if skin_count == 1 && bone.rigid_matrix_index >= 0 {
+                    space = Some(b);
+                    bone.rigid_matrix_index
+                } else if bone.smooth_matrix_index >= 0 {
+                    bone.smooth_matrix_index
+                } else {
+                    return Err(Box::new(BoneNotSkinned {
+                        name: bone.name.clone(),
+                        skin_count,
+                    }));
+                };
+                indices.push(index as u16);
+            }
+        }
+        if let Some(b) = space {
+            let matrix = invert(&bones[b].world_matrix);
+            positions[i] = transform_point(&matrix, &positions[i]);
+            if let Some(n) = normals.get_mut(i) {
+                *n = transform_normal(&matrix, n);
+            }
+        }
+        matrix_indices.push(indices);
+    }
+    let tangents = get_tangents(mesh, &positions, &normals);
+
+    let mut buffers: Vec<Vec<u8>> = shape
+        .strides
+        .iter()
+        .map(|&s| vec![0u8; usize::from(s) * vertex_count])
+        .collect();
+    for attribute in &shape.attributes {
+        let data = encode_vertices(
+            attribute,
+            mesh,
+            &positions,
+            &normals,
+            &tangents,
+            &influences,
+            &matrix_indices,
+        );
+        let size = usize::from(attribute.format.get_size());
+        let buffer = match buffers.get_mut(attribute.buffer_index) {
+            Some(b) => b,
+            None => continue,
+        };
+        let stride = buffer.len() / max(1, vertex_count);
+        if attribute.offset + size > stride {
+            continue;
+        }
+        for (i, value) in data.chunks(size).enumerate() {
+            let start = i * stride + attribute.offset;
+            buffer[start..start + size].clone_from_slice(value);
+        }
+    }
+
+    let mut skin_bone_indices: Vec<u16> = Vec::new();
+    if skin_count > 0 {
+        for vertex in &influences {
+            skin_bone_indices.extend(vertex.iter().map(|v| v.0 as u16));
+        }
+        skin_bone_indices.sort();
+        skin_bone_indices.dedup();
+    }
+
+    // Same kind of indices as before, bigger ones when they do not fit
+    let index_format = match shape.index_format {
+        IndexFormat::U16LittleEndian if vertex_count > 0x10000 => IndexFormat::U32LittleEndian,
+        IndexFormat::U16BigEndian if vertex_count > 0x10000 => IndexFormat::U32BigEndian,
+        f => f,
+    };
+    let (center, extent, radius) = get_bounds(&positions);
+    Ok(NewGeometry {
+        vertex_count: vertex_count as u32,
+        buffers,
+        skin_bone_indices,
+        index_format,
+        index_count: mesh.indices.len() as u32,
+        indices: encode_indices(&mesh.indices, index_format),
+        center,
+        extent,
+        radius,
+    })
+}
+
+// Values for an attribute from its name. Key shape positions and normals start as the base ones,
+// and what the mesh does not have gets something neutral.
+fn encode_vertices(
+    attribute: &AttributeInfo,
+    mesh: &Mesh,
+    positions: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    tangents: &[[f32; 4]],
+    influences: &[Vec<(usize, f32)>],
+    matrix_indices: &[Vec<u16>],
+) -> Vec<u8> {
+    let vertex_count = positions.len();
+    let name = attribute.name.as_str();
+    let (kind, set) = match name.len() {
+        3 if name.starts_with('_') => (&name[1..2], name[2..].parse().unwrap_or(0)),
+        _ => ("", 0),
+    };
+    let values: Vec<[f32; 4]> = match kind {
+        "p" => positions.iter().map(|p| [p[0], p[1], p[2], 1f32]).collect(),
+        "n" if !normals.is_empty() => normals.iter().map(|n| [n[0], n[1], n[2], 0f32]).collect(),
+        "n" => vec![[0f32, 0f32, 1f32, 0f32]; vertex_count],
+        "t" => tangents.to_vec(),
+        "b" => normals
+            .iter()
+            .zip(tangents.iter())
+            .map(|(n, t)| {
+                let b = cross(n, &[t[0], t[1], t[2]]);
+                [b[0] * t[3], b[1] * t[3], b[2] * t[3], 0f32]
+            })
+            .collect(),
+        "u" => match mesh.texture_coordinates.get(set) {
+            Some(s) => s.iter().map(|t| [t[0], t[1], 0f32, 0f32]).collect(),
+            None => vec![[0f32; 4]; vertex_count],
+        },
+        "c" if set == 0 && !mesh.colors.is_empty() => mesh.colors.clone(),
+        "c" => vec![[1f32; 4]; vertex_count],
+        // Four influences per attribute, the first ones in _i0 and _w0
+        "i" => matrix_indices
+            .iter()
+            .map(|indices| {
+                let mut value = [0f32; 4];
+                for (v, &i) in value.iter_mut().zip(indices.iter().skip(set * 4)) {
+                    *v = f32::from(i);
+                }
+                value
+            })
+            .collect(),
+        "w" => {
+            let weights: Vec<[f32; 4]> = influences
+                .iter()
+                .map(|vertex| {
+                    let mut value = [0f32; 4];
+                    for (v, i) in value.iter_mut().zip(vertex.iter().skip(set * 4)) {
+                        *v = i.1;
+                    }
+                    value
+                })
+                .collect();
+            // Bytes adding up to exactly 255
+            if set == 0 && is_unsigned_byte(attribute.format) {
+                let components = attribute.format.get_component_count();
+                return weights
+                    .iter()
+                    .flat_map(|w| quantize_weights(&w[..components]))
+                    .collect();
+            }
+            weights
+        }
+        _ => vec![[0f32; 4]; vertex_count],
+    };
+    encode_attribute(attribute.format, &values)
+}
+
+fn is_unsigned_byte(format: AttributesFormats) -> bool {
+    match format {
+        AttributesFormats::U8ToF32
+        | AttributesFormats::TwoU8ToTwoF32
+        | AttributesFormats::FourU8ToFourF32 => true,
+        _ => false,
+    }
+}
+
+fn cross(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+// From the first texture coordinates, W gives which way the binormal goes
+fn get_tangents(mesh: &Mesh, positions: &[[f32; 3]], normals: &[[f32; 3]]) -> Vec<[f32; 4]> {
+    let mut tangents = vec![[0f32; 3]; positions.len()];
+    let mut binormals = vec![[0f32; 3]; positions.len()];
+    if let Some(uvs) = mesh.texture_coordinates.first() {
+        for triangle in mesh.indices.chunks(3) {
+            if triangle.len() < 3 {
+                break;
+            }
+            let (a, b, c) = (
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            );
+            let mut e1 = [0f32; 3];
+            let mut e2 = [0f32; 3];
+            for axis in 0..3 {
+                e1[axis] = positions[b][axis] - positions[a][axis];
+                e2[axis] = positions[c][axis] - positions[a][axis];
+            }
+            let (u1, v1) = (uvs[b][0] - uvs[a][0], uvs[b][1] - uvs[a][1]);
+            let (u2, v2) = (uvs[c][0] - uvs[a][0], uvs[c][1] - uvs[a][1]);
+            let d = u1 * v2 - u2 * v1;
+            if d == 0f32 {
+                continue;
+            }
+            for &v in &[a, b, c] {
+                for axis in 0..3 {
+                    tangents[v][axis] += (e1[axis] * v2 - e2[axis] * v1) / d;
+                    binormals[v][axis] += (e2[axis] * u1 - e1[axis] * u2) / d;
+                }
+            }
+        }
+    }
+    tangents
+        .iter()
+        .zip(binormals.iter())
+        .enumerate()
+        .map(|(i, (t, b))| {
+            let n = normals.get(i).cloned().unwrap_or([0f32, 0f32, 1f32]);
+            // Made perpendicular to the normal
+            let dot = t[0] * n[0] + t[1] * n[1] + t[2] * n[2];
+            let mut tangent = [t[0] - n[0] * dot, t[1] - n[1] * dot, t[2] - n[2] * dot];
+            let length = tangent.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if length > 0f32 {
+                for v in &mut tangent {
+                    *v /= length;
+                }
+            } else {
+                tangent = if n[0].abs() < 0.9 {
+                    [1f32, 0f32, 0f32]
+                } else {
+                    [0f32, 1f32, 0f32]
+                };
+            }
+            let c = cross(&n, &tangent);
+            let w = if c[0] * b[0] + c[1] * b[1] + c[2] * b[2] < 0f32 {
+                -1f32
+            } else {
+                1f32
+            };
+            [tangent[0], tangent[1], tangent[2], w]
+        })
+        .collect()
+}
+
+fn write_shape(
+    writer: &mut Writer,
+    shape: &ShapeInfo,
+    geometry: NewGeometry,
+    has_radius_array: bool,
+) {
+    writer.align(4);
+
+    // FVTX, the attributes keep their buffers, offsets and formats
+    let buffer_array_label = writer.new_label();
+    writer.set_u32(shape.fvtx_start + 0x08, geometry.vertex_count);
+    writer.set_pointer(shape.fvtx_start + 0x18, Target::Label(buffer_array_label));
+    writer.place(buffer_array_label);
+    for (data, &stride) in geometry.buffers.into_iter().zip(shape.strides.iter()) {
+        let length = data.len();
+        let data_label = writer.defer_data(data, BUFFER_ALIGNMENT);
+        write_buffer_info(writer, length, stride, data_label);
+    }
+
+    // FSHP, other LOD models are dropped as they were made from the old vertices
+    let start = shape.fshp_start;
+    let lod_label = writer.new_label();
+    let skin_bones_label = writer.new_label();
+    let bounding_labels = [writer.new_label(), writer.new_label(), writer.new_label()];
+    writer.set_u16(start + 0x14, geometry.skin_bone_indices.len() as u16);
+    writer.data[start + 0x17] = 1; // LOD models
+    writer.set_u16(start + 0x1A, 1); // Bounding nodes
+    writer.set_pointer(start + 0x24, Target::Label(lod_label));
+    if geometry.skin_bone_indices.is_empty() {
+        writer.set_pointer(start + 0x28, Target::Null);
+    } else {
+        writer.set_pointer(start + 0x28, Target::Label(skin_bones_label));
+    }
+    for (i, &label) in bounding_labels.iter().enumerate() {
+        writer.set_pointer(start + 0x30 + i * 4, Target::Label(label));
+    }
+    if has_radius_array {
+        let radius_label = writer.new_label();
+        writer.set_pointer(start + 0x1C, Target::Label(radius_label));
+        writer.place(radius_label);
+        writer.write_f32(geometry.radius);
+    } else {
+        writer.set_u32(start + 0x1C, geometry.radius.to_bits());
+    }
+    write_lod_model(
+        writer,
+        lod_label,
+        geometry.index_format,
+        geometry.index_count,
+        geometry.indices,
+    );
+    if !geometry.skin_bone_indices.is_empty() {
+        writer.place(skin_bones_label);
+        for &i in &geometry.skin_bone_indices {
+            writer.write_u16(i);
+        }
+        writer.align(4);
+    }
+    write_bounding(writer, bounding_labels, geometry.center, geometry.extent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::build::tests::{make_mesh, make_template};
+    use fres::build::{build_bfres, BuildOptions};
+    use scene::Bone as SceneBone;
+
+    // A model with two shapes skinned to one bone, "body" and "body_1"
+    fn make_bfres() -> Vec<u8> {
+        let mut scene = Scene {
+            meshes: vec![make_mesh("body", 3), make_mesh("body", 1)],
+            materials: Vec::new(),
+            bones: vec![SceneBone::new("root")],
+        };
+        for mesh in &mut scene.meshes {
+            let nb_vertices = mesh.positions.len();
+            mesh.bone_indices = vec![[0; 4]; nb_vertices];
+            mesh.bone_weights = vec![[1f32, 0f32, 0f32, 0f32]; nb_vertices];
+        }
+        build_bfres(&scene, &make_template(), &BuildOptions::new("Test")).unwrap()
+    }
+
+    fn make_scene(mesh: Mesh) -> Scene {
+        Scene {
+            meshes: vec![mesh],
+            materials: Vec::new(),
+            bones: vec![SceneBone::new("root")],
+        }
+    }
+
+    fn get_nb_vertices(bfres: &[u8]) -> (u32, Vec<u32>) {
+        let mut cursor = Cursor::new(bfres);
+        let fres = FRES::import(&mut cursor).unwrap();
+        let models = fres.sub_file_index_groups.model_data.unwrap();
+        let fmdl = models.entries[0].get_data(&mut cursor).unwrap();
+        let mut nb_vertices = Vec::new();
+        for entry in &fmdl.fvtx_array.entries {
+            nb_vertices.push(entry.get_data(&mut cursor).unwrap().header.nb_vertices);
+        }
+        (fmdl.header.total_nb_vertices, nb_vertices)
+    }
+
+    #[test]
+    fn replace_one_shape() {
+        let bfres = make_bfres();
+        assert_eq!(get_nb_vertices(&bfres), (16, vec![12, 4]));
+        let scene = make_scene(make_mesh("new", 5));
+        let out = replace_shape(&bfres, None, "body_1", &scene, 0).unwrap();
+        assert_eq!(get_nb_vertices(&out), (32, vec![12, 20]));
+        let scene = make_scene(make_mesh("body_1", 1));
+        assert!(replace_model(&bfres, Some("Test"), &scene).is_ok());
+        assert!(replace_model(&bfres, Some("Test"), &make_scene(make_mesh("new", 1))).is_err());
+    }
+
+    #[test]
+    fn replace_the_whole_model() {
+        let bfres = make_bfres();
+        let mut scene = make_scene(make_mesh("body", 2));
+        scene.meshes[0].bone_weights = Vec::new();
+        let out = replace_model(&bfres, None, &scene).unwrap();
+        // The shape without a mesh gets one empty triangle
+        assert_eq!(get_nb_vertices(&out), (9, vec![8, 1]));
+    }
+
+    #[test]
+    fn invalid_replacements() {
+        let bfres = make_bfres();
+        let scene = make_scene(make_mesh("body", 1));
+        assert!(replace_shape(&bfres, None, "body", &scene, 1).is_err());
+        assert!(replace_shape(&bfres, None, "head", &scene, 0).is_err());
+        assert!(replace_shape(&bfres, Some("Other"), "body", &scene, 0).is_err());
+
+        let mut skinned = make_mesh("body", 1);
+        skinned.bone_indices = vec![[0; 4]; 4];
+        skinned.bone_weights = vec![[1f32, 0f32, 0f32, 0f32]; 3];
+        let scene = make_scene(skinned);
+        assert!(replace_shape(&bfres, None, "body", &scene, 0).is_err());
+
+        let mut skinned = make_mesh("body", 1);
+        skinned.bone_indices = vec![[3, 0, 0, 0]; 4];
+        skinned.bone_weights = vec![[1f32, 0f32, 0f32, 0f32]; 4];
+        let scene = make_scene(skinned);
+        assert!(replace_shape(&bfres, None, "body", &scene, 0).is_err());
+        // Unused influences can point anywhere
+        let mut skinned = make_mesh("body", 1);
+        skinned.bone_indices = vec![[0, 3, 0, 0]; 4];
+        skinned.bone_weights = vec![[1f32, 0f32, 0f32, 0f32]; 4];
+        let scene = make_scene(skinned);
+        assert!(replace_shape(&bfres, None, "body", &scene, 0).is_ok());
+    }
+}
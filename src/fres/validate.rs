@@ -0,0 +1,535 @@
+use fres::fmdl::fshp::lod_model::PrimitiveType;
+use fres::fmdl::fshp::FSHP;
+use fres::fmdl::fvtx::attributes::{Attributes, AttributesFormats};
+use fres::fmdl::fvtx::FVTX;
+use fres::fmdl::FMDL;
+use fres::FRES;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Cursor;
+use std::io::{Read, Seek};
+use util::Importable;
+
+// How far from 1 the length of a normal or the sum of the weights of a vertex can be, quantized
+// values are never exact
+const TOLERANCE: f32 = 0.01;
+// Places past these are only counted
+const MAX_PLACES: usize = 16;
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Report {
+    pub models: Vec<ModelReport>,
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct ModelReport {
+    pub name: String,
+    pub total_nb_vertices: u32,
+    // What total_nb_vertices should be
+    pub fvtx_nb_vertices: u32,
+    pub shapes: Vec<ShapeReport>,
+    pub problems: Vec<Problem>,
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct ShapeReport {
+    pub name: String,
+    pub nb_vertices: u32,
+    pub skin_count: u8,
+    pub attributes: Vec<AttributeStatistics>,
+    pub lod_models: Vec<LODStatistics>,
+    pub problems: Vec<Problem>,
+}
+
+// Of the decoded values, with one entry for each component of the format
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct AttributeStatistics {
+    pub name: String,
+    pub format: AttributesFormats,
+    pub minimum: Vec<f32>,
+    pub maximum: Vec<f32>,
+    pub mean: Vec<f32>,
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct LODStatistics {
+    pub primitive_type: PrimitiveType,
+    pub nb_points: u32,
+    pub nb_triangles: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum ProblemKind {
+    // Places are not used
+    TotalVerticesMismatch,
+    MissingPositions,
+    // Places are in the index buffer
+    IndexOutOfRange,
+    // Places are triangles
+    DegenerateTriangle,
+    // Places are vertices, or matrices for the ones of the skeleton
+    NonFinitePosition,
+    UnnormalizedNormal,
+    WeightSumNotOne,
+    BoneIndexOutOfRange,
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Problem {
+    pub kind: ProblemKind,
+    // For the ones found in the indices of a LOD model
+    pub lod_index: Option<usize>,
+    pub count: usize,
+    // Where the first ones are
+    pub places: Vec<u32>,
+}
+
+// Goes through every model of a decompressed BFRES, reading everything the GPU would
+pub fn validate(bfres: &[u8]) -> Result<Report, Box<Error>> {
+    let mut cursor = Cursor::new(bfres);
+    let fres = FRES::import(&mut cursor)?;
+    let mut models = Vec::new();
+    if let Some(fmdl_index_group) = fres.sub_file_index_groups.model_data {
+        for fmdl_entry in &fmdl_index_group.entries {
+            let name = fmdl_entry.get_name(&mut cursor)?;
+            let fmdl: FMDL = fmdl_entry.get_data(&mut cursor)?;
+            models.push(validate_model(&mut cursor, name, &fmdl)?);
+        }
+    }
+    Ok(Report { models })
+}
+
+fn validate_model<R: Read + Seek>(
+    reader: &mut R,
+    name: String,
+    fmdl: &FMDL,
+) -> Result<ModelReport, Box<Error>> {
+    let mut problems = Vec::new();
+    let mut fvtx_nb_vertices = 0;
+    for fvtx_entry in &fmdl.fvtx_array.entries {
+        let fvtx: FVTX = fvtx_entry.get_data(reader)?;
+        fvtx_nb_vertices += fvtx.header.nb_vertices;
+    }
+    if fvtx_nb_vertices != fmdl.header.total_nb_vertices {
+        let mut problem = Problem::new(ProblemKind::TotalVerticesMismatch, None);
+        problem.count = 1;
+        problems.push(problem);
+    }
+    let mut problem = Problem::new(ProblemKind::BoneIndexOutOfRange, None);
+    let nb_bones = fmdl.fskl.bones.entries.len();
+    for (i, &b) in fmdl
+        .fskl
+        .get_matrix_bone_indices(reader)?
+        .iter()
+        .enumerate()
+    {
+        if usize::from(b) >= nb_bones {
+            problem.add(i as u32);
+        }
+    }
+    problem.keep(&mut problems);
+    let mut shapes = Vec::new();
+    for fshp_entry in &fmdl.fshp_index_group.entries {
+        let name = fshp_entry.get_name(reader)?;
+        let fshp: FSHP = fshp_entry.get_data(reader)?;
+        shapes.push(validate_shape(reader, name, &fshp, fmdl)?);
+    }
+    Ok(ModelReport {
+        name,
+        total_nb_vertices: fmdl.header.total_nb_vertices,
+        fvtx_nb_vertices,
+        shapes,
+        problems,
+    })
+}
+
+fn validate_shape<R: Read + Seek>(
+    reader: &mut R,
+    name: String,
+    fshp: &FSHP,
+    fmdl: &FMDL,
+) -> Result<ShapeReport, Box<Error>> {
+    fshp.header.fvtx_offset.seek_abs_pos(reader)?;
+    let fvtx = FVTX::import(reader)?;
+    let nb_vertices = fvtx.header.nb_vertices;
+    let skin_count = fshp.header.vertex_skin_count;
+    let mut problems = Vec::new();
+
+    let mut values = HashMap::new();
+    let mut attributes = Vec::new();
+    for attribute_entry in &fvtx.attributes_index_group.entries {
+        let attribute_name = attribute_entry.get_name(reader)?;
+        let attribute: Attributes = attribute_entry.get_data(reader)?;
+        let decoded = fvtx
+            .get_attribute_values(reader, &attribute_name)?
+            .unwrap_or_default();
+        attributes.push(get_statistics(
+            attribute_name.clone(),
+            attribute.format,
+            &decoded,
+        ));
+        values.insert(attribute_name, decoded);
+    }
+
+    let positions = values.get("_p0");
+    match positions {
+        Some(positions) => {
+            let mut problem = Problem::new(ProblemKind::NonFinitePosition, None);
+            for (i, p) in positions.iter().enumerate() {
+                if !(p[0].is_finite() && p[1].is_finite() && p[2].is_finite()) {
+                    problem.add(i as u32);
+                }
+            }
+            problem.keep(&mut problems);
+        }
+        None => {
+            let mut problem = Problem::new(ProblemKind::MissingPositions, None);
+            problem.count = 1;
+            problems.push(problem);
+        }
+    }
+
+    if let Some(normals) = values.get("_n0") {
+        let mut problem = Problem::new(ProblemKind::UnnormalizedNormal, None);
+        for (i, n) in normals.iter().enumerate() {
+            let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            if length.is_nan() || (length - 1f32).abs() > TOLERANCE {
+                problem.add(i as u32);
+            }
+        }
+        problem.keep(&mut problems);
+    }
+
+    // Four influences in each attribute, the weights of rigid skinning can be left out
+    let skin_count = usize::from(skin_count);
+    let weights = get_influences(&values, "_w", skin_count);
+    let bone_indices = get_influences(&values, "_i", skin_count);
+    if let Some(ref weights) = weights {
+        if skin_count > 0 {
+            let mut problem = Problem::new(ProblemKind::WeightSumNotOne, None);
+            for (i, w) in weights.iter().enumerate() {
+                let sum: f32 = w.iter().sum();
+                if sum.is_nan() || (sum - 1f32).abs() > TOLERANCE {
+                    problem.add(i as u32);
+                }
+            }
+            problem.keep(&mut problems);
+        }
+    }
+    if let Some(ref bone_indices) = bone_indices {
+        // Rigid skinning can use both kinds of matrices, smooth skinning only the smooth ones
+        let header = &fmdl.fskl.header;
+        let nb_matrices = if skin_count == 1 {
+            u32::from(header.smooth_index_array_count) + u32::from(header.rigid_index_array_count)
+        } else {
+            u32::from(header.smooth_index_array_count)
+        };
+        let mut problem = Problem::new(ProblemKind::BoneIndexOutOfRange, None);
+        for (i, indices) in bone_indices.iter().enumerate() {
+            let is_wrong = indices.iter().enumerate().any(|(k, &b)| {
+                let is_used = match weights {
+                    Some(ref w) => w.get(i).is_none_or(|w| w[k] > 0f32),
+                    None => true,
+                };
+                is_used && b as u32 >= nb_matrices
+            });
+            if is_wrong {
+                problem.add(i as u32);
+            }
+        }
+        problem.keep(&mut problems);
+    }
+
+    let mut lod_models = Vec::new();
+    for (lod_index, lod_model) in fshp.get_lod_models(reader)?.iter().enumerate() {
+        let mut problem = Problem::new(ProblemKind::IndexOutOfRange, Some(lod_index));
+        let indices = lod_model.get_indices(reader)?;
+        for (i, &index) in indices
+            .iter()
+            .take(lod_model.nb_points as usize)
+            .enumerate()
+        {
+            if u64::from(index) + u64::from(lod_model.skip_vertices) >= u64::from(nb_vertices) {
+                problem.add(i as u32);
+            }
+        }
+        problem.keep(&mut problems);
+        // Points and lines have no triangles to check, nor do indices too big once skipped vertices
+        // are added, they were counted above
+        if let Ok(triangles) = lod_model.get_triangles(reader) {
+            let mut problem = Problem::new(ProblemKind::DegenerateTriangle, Some(lod_index));
+            for (i, t) in triangles.iter().enumerate() {
+                let is_degenerate = t[0] == t[1]
+                    || t[1] == t[2]
+                    || t[0] == t[2]
+                    || match positions {
+                        Some(p) => has_no_area(p, t),
+                        None => false,
+                    };
+                if is_degenerate {
+                    problem.add(i as u32);
+                }
+            }
+            problem.keep(&mut problems);
+        }
+        lod_models.push(LODStatistics {
+            primitive_type: lod_model.primitive_type,
+            nb_points: lod_model.nb_points,
+            nb_triangles: lod_model.get_nb_triangles(),
+        });
+    }
+
+    Ok(ShapeReport {
+        name,
+        nb_vertices,
+        skin_count: skin_count as u8,
+        attributes,
+        lod_models,
+        problems,
+    })
+}
+
+// Every value for each vertex, None when one of the attributes needed is missing
+fn get_influences(
+    values: &HashMap<String, Vec<[f32; 4]>>,
+    prefix: &str,
+    skin_count: usize,
+) -> Option<Vec<Vec<f32>>> {
+    let mut attributes = Vec::new();
+    for i in 0..skin_count.div_ceil(4) {
+        attributes.push(values.get(&format!("{}{}", prefix, i))?);
+    }
+    let nb_vertices = attributes.iter().map(|a| a.len()).min().unwrap_or(0);
+    Some(
+        (0..nb_vertices)
+            .map(|v| {
+                (0..skin_count)
+                    .map(|k| attributes[k / 4][v][k % 4])
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+// Corners that are not there count as fine, the indices get checked on their own
+fn has_no_area(positions: &[[f32; 4]], triangle: &[u32; 3]) -> bool {
+    let corners = match (
+        positions.get(triangle[0] as usize),
+        positions.get(triangle[1] as usize),
+        positions.get(triangle[2] as usize),
+    ) {
+        (Some(a), Some(b), Some(c)) => [a, b, c],
+        _ => return false,
+    };
+    let edge = |from: usize, to: usize| -> [f32; 3] {
+        [
+            corners[to][0] - corners[from][0],
+            corners[to][1] - corners[from][1],
+            corners[to][2] - corners[from][2],
+        ]
+    };
+    let (u, v, w) = (edge(0, 1), edge(0, 2), edge(1, 2));
+    let cross = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let area = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+    // Relative to the size of the triangle, so small models are not all flagged
+    let longest = [u, v, w]
+        .iter()
+        .map(|e| e[0] * e[0] + e[1] * e[1] + e[2] * e[2])
+        .fold(0f32, f32::max);
+    area <= longest * 1e-6
+}
+
+fn get_statistics(
+    name: String,
+    format: AttributesFormats,
+    values: &[[f32; 4]],
+) -> AttributeStatistics {
+    let nb_components = format.get_component_count();
+    let mut minimum = vec![f32::MAX; nb_components];
+    let mut maximum = vec![f32::MIN; nb_components];
+    let mut mean = vec![0f32; nb_components];
+    for value in values {
+        for c in 0..nb_components {
+            minimum[c] = minimum[c].min(value[c]);
+            maximum[c] = maximum[c].max(value[c]);
+            mean[c] += value[c];
+        }
+    }
+    if values.is_empty() {
+        minimum = vec![0f32; nb_components];
+        maximum = vec![0f32; nb_components];
+    } else {
+        for m in &mut mean {
+            *m /= values.len() as f32;
+        }
+    }
+    AttributeStatistics {
+        name,
+        format,
+        minimum,
+        maximum,
+        mean,
+    }
+}
+
+impl Problem {
+    fn new(kind: ProblemKind, lod_index: Option<usize>) -> Problem {
+        Problem {
+            kind,
+            lod_index,
+            count: 0,
+            places: Vec::new(),
+        }
+    }
+    fn add(&mut self, place: u32) {
+        self.count += 1;
+        if self.places.len() < MAX_PLACES {
+            self.places.push(place);
+        }
+    }
+    // Only when it happened somewhere
+    fn keep(self, problems: &mut Vec<Problem>) {
+        if self.count > 0 {
+            problems.push(self);
+        }
+    }
+}
+
+impl Report {
+    pub fn get_nb_problems(&self) -> usize {
+        self.models
+            .iter()
+            .map(|m| m.problems.len() + m.shapes.iter().map(|s| s.problems.len()).sum::<usize>())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::build::tests::{make_mesh, make_template};
+    use fres::build::{build_bfres, BuildOptions};
+    use fres::writer::Writer;
+    use scene::{Bone, Scene};
+
+    // Two quads skinned to two bones
+    fn make_bfres() -> Vec<u8> {
+        let mut mesh = make_mesh("body", 2);
+        mesh.bone_indices = vec![[1, 0, 0, 0]; 8];
+        mesh.bone_weights = vec![[0.75f32, 0.25f32, 0f32, 0f32]; 8];
+        let mut arm = Bone::new("arm");
+        arm.parent_index = Some(0);
+        let scene = Scene {
+            meshes: vec![mesh],
+            materials: Vec::new(),
+            bones: vec![Bone::new("root"), arm],
+        };
+        build_bfres(&scene, &make_template(), &BuildOptions::new("Test")).unwrap()
+    }
+
+    fn get_kinds(problems: &[Problem]) -> Vec<(ProblemKind, usize, Vec<u32>)> {
+        problems
+            .iter()
+            .map(|p| (p.kind, p.count, p.places.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_model() {
+        let report = validate(&make_bfres()).unwrap();
+        assert_eq!(report.models.len(), 1);
+        assert_eq!(report.models[0].shapes.len(), 1);
+        assert_eq!(report.models[0].shapes[0].skin_count, 2);
+        assert_eq!(report.get_nb_problems(), 0);
+    }
+
+    #[test]
+    fn broken_model() {
+        let bfres = make_bfres();
+        let mut cursor = Cursor::new(&bfres[..]);
+        let fres = FRES::import(&mut cursor).unwrap();
+        let fmdl_entry = &fres.sub_file_index_groups.model_data.unwrap().entries[0];
+        let fmdl_position = fmdl_entry.data_pointer.get_abs_pos().unwrap() as usize;
+        let fmdl: FMDL = fmdl_entry.get_data(&mut cursor).unwrap();
+        let fshp = fmdl.fshp_index_group.entries[0]
+            .get_data(&mut cursor)
+            .unwrap();
+        let fvtx = fmdl.fvtx_array.entries[0].get_data(&mut cursor).unwrap();
+        let lod_model = &fshp.get_lod_models(&mut cursor).unwrap()[0];
+        let index_buffer = lod_model.get_direct_buffer_info(&mut cursor).unwrap();
+        let weights = fvtx
+            .attributes_index_group
+            .find(&mut cursor, "_w0")
+            .unwrap()
+            .unwrap()
+            .get_data(&mut cursor)
+            .unwrap();
+        let weight_buffer = fvtx.buffer_info_array.entries[usize::from(weights.buffer_info_index)]
+            .get_data(&mut cursor)
+            .unwrap();
+
+        let mut writer = Writer::from_data(bfres.clone());
+        writer.set_u32(fmdl_position + 0x28, fmdl.header.total_nb_vertices + 1);
+        // The fifth index goes past the vertices
+        let index_size = lod_model.index_format.get_size();
+        let index_position = index_buffer.data_offset.get_abs_pos().unwrap() as usize;
+        for i in 0..index_size {
+            writer.data[index_position + 4 * index_size + i] = 0xFF;
+        }
+        // The weights of the third vertex only add up to 0.25
+        let weight_position = weight_buffer.data_offset.get_abs_pos().unwrap() as usize
+            + 2 * usize::from(weight_buffer.stride)
+            + usize::from(weights.buffer_offset);
+        writer.data[weight_position] = 0;
+
+        let report = validate(&writer.finish()).unwrap();
+        let model = &report.models[0];
+        assert_eq!(
+            get_kinds(&model.problems),
+            vec![(ProblemKind::TotalVerticesMismatch, 1, Vec::new())]
+        );
+        assert_eq!(model.total_nb_vertices, model.fvtx_nb_vertices + 1);
+        assert_eq!(
+            get_kinds(&model.shapes[0].problems),
+            vec![
+                (ProblemKind::WeightSumNotOne, 1, vec![2]),
+                (ProblemKind::IndexOutOfRange, 1, vec![4]),
+            ]
+        );
+        assert_eq!(report.get_nb_problems(), 3);
+    }
+
+    // Indices past u32 once skipped vertices are added are reported, not added up
+    #[test]
+    fn skipped_vertices_overflow() {
+        let bfres = make_bfres();
+        let mut cursor = Cursor::new(&bfres[..]);
+        let fres = FRES::import(&mut cursor).unwrap();
+        let fmdl: FMDL = fres.sub_file_index_groups.model_data.unwrap().entries[0]
+            .get_data(&mut cursor)
+            .unwrap();
+        let fshp = fmdl.fshp_index_group.entries[0]
+            .get_data(&mut cursor)
+            .unwrap();
+        let lod_entry = &fshp.lod_model_array.entries[0];
+        let lod_position = lod_entry.data_pointer.get_abs_pos().unwrap() as usize;
+        let mut writer = Writer::from_data(bfres.clone());
+        writer.set_u32(lod_position + 0x18, u32::MAX);
+        let bfres = writer.finish();
+        let report = validate(&bfres).unwrap();
+        assert_eq!(
+            get_kinds(&report.models[0].shapes[0].problems)[0],
+            (ProblemKind::IndexOutOfRange, 12, (0..12).collect())
+        );
+        let lod_model = lod_entry.get_data(&mut Cursor::new(&bfres[..])).unwrap();
+        assert!(lod_model
+            .get_triangles(&mut Cursor::new(&bfres[..]))
+            .is_err());
+    }
+}
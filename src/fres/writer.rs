@@ -0,0 +1,329 @@
+use std::cmp::max;
+use std::collections::BTreeMap;
+
+// A place in the file, known once the writer gets there
+#[derive(Clone, Copy, PartialEq)]
+pub struct Label(usize);
+
+pub enum Target {
+    Label(Label),
+    Text(String),
+    Null,
+}
+
+// Lays out a big endian file where pointers are relative to where they are written. Strings and
+// buffers are gathered to go at the end, like in files from Nintendo.
+#[derive(Default)]
+pub struct Writer {
+    pub data: Vec<u8>,
+    labels: Vec<Option<usize>>,
+    pointers: Vec<(usize, Label)>,
+    text_pointers: Vec<(usize, String)>,
+    strings: BTreeMap<String, usize>,
+    deferred: Vec<(Label, Vec<u8>, usize)>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer::default()
+    }
+    // Carries on after a file already there, to add to it
+    pub fn from_data(data: Vec<u8>) -> Writer {
+        let mut writer = Writer::new();
+        writer.data = data;
+        writer
+    }
+    pub fn position(&self) -> usize {
+        self.data.len()
+    }
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.push((value >> 8) as u8);
+        self.data.push(value as u8);
+    }
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&[
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ]);
+    }
+    pub fn write_i16(&mut self, value: i16) {
+        self.write_u16(value as u16);
+    }
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_u32(value as u32);
+    }
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+    pub fn align(&mut self, alignment: usize) {
+        while !self.data.len().is_multiple_of(alignment) {
+            self.data.push(0);
+        }
+    }
+    // Changes something already written
+    pub fn set_u32(&mut self, position: usize, value: u32) {
+        self.data[position..position + 4].clone_from_slice(&[
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ]);
+    }
+    pub fn set_u16(&mut self, position: usize, value: u16) {
+        self.data[position..position + 2].clone_from_slice(&[(value >> 8) as u8, value as u8]);
+    }
+    // Makes a pointer already written point somewhere else
+    pub fn set_pointer(&mut self, position: usize, target: Target) {
+        match target {
+            Target::Label(label) => self.pointers.push((position, label)),
+            Target::Text(text) => self.text_pointers.push((position, text)),
+            Target::Null => self.set_u32(position, 0),
+        }
+    }
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+    pub fn place(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.data.len());
+    }
+    pub fn write_pointer(&mut self, target: Target) {
+        let position = self.data.len();
+        match target {
+            Target::Label(label) => self.pointers.push((position, label)),
+            Target::Text(text) => self.text_pointers.push((position, text)),
+            Target::Null => {}
+        }
+        self.write_u32(0);
+    }
+    pub fn write_label_pointer(&mut self, label: Label) {
+        self.write_pointer(Target::Label(label));
+    }
+    pub fn write_text_pointer(&mut self, text: &str) {
+        self.write_pointer(Target::Text(text.to_string()));
+    }
+    // Entries stay in the given order, which is the one indices refer to
+    pub fn write_index_group(&mut self, entries: Vec<(String, Target)>) {
+        let nodes = {
+            let names: Vec<&[u8]> = entries.iter().map(|e| e.0.as_bytes()).collect();
+            build_search_tree(&names)
+        };
+        self.write_u32((8 + nodes.len() * 0x10) as u32);
+        self.write_i32(entries.len() as i32);
+        // The root has no name nor data
+        self.write_u32(u32::MAX);
+        self.write_u16(nodes[0].children[0] as u16);
+        self.write_u16(nodes[0].children[1] as u16);
+        self.write_pointer(Target::Null);
+        self.write_pointer(Target::Null);
+        for (node, (name, target)) in nodes[1..].iter().zip(entries) {
+            self.write_u32(node.bit as u32);
+            self.write_u16(node.children[0] as u16);
+            self.write_u16(node.children[1] as u16);
+            self.write_pointer(Target::Text(name));
+            self.write_pointer(target);
+        }
+    }
+    // Written at the end by write_deferred_data
+    pub fn defer_data(&mut self, data: Vec<u8>, alignment: usize) -> Label {
+        let label = self.new_label();
+        self.deferred.push((label, data, alignment));
+        label
+    }
+    // Every string pointed to so far, each after its length. Gives where the table starts and how
+    // long it is.
+    pub fn write_string_table(&mut self) -> (usize, usize) {
+        self.align(4);
+        let start = self.data.len();
+        let mut texts: Vec<String> = self.text_pointers.iter().map(|p| p.1.clone()).collect();
+        texts.sort();
+        texts.dedup();
+        for text in texts {
+            self.align(4);
+            self.write_u32(text.len() as u32);
+            let position = self.data.len();
+            self.data.extend_from_slice(text.as_bytes());
+            self.data.push(0);
+            self.strings.insert(text, position);
+        }
+        self.align(4);
+        (start, self.data.len() - start)
+    }
+    // Gives the biggest alignment used
+    pub fn write_deferred_data(&mut self) -> usize {
+        let mut max_alignment = 1;
+        let deferred: Vec<_> = self.deferred.drain(..).collect();
+        for (label, data, alignment) in deferred {
+            self.align(alignment);
+            self.place(label);
+            self.data.extend_from_slice(&data);
+            max_alignment = max(max_alignment, alignment);
+        }
+        max_alignment
+    }
+    // Fills in every pointer
+    pub fn finish(mut self) -> Vec<u8> {
+        let pointers: Vec<_> = self.pointers.drain(..).collect();
+        for (position, label) in pointers {
+            let target = self.labels[label.0].expect("Pointer to a place never written");
+            self.set_u32(position, (target as i64 - position as i64) as u32);
+        }
+        let text_pointers: Vec<_> = self.text_pointers.drain(..).collect();
+        for (position, text) in text_pointers {
+            let target = *self
+                .strings
+                .get(&text)
+                .expect("String pointed to after the string table");
+            self.set_u32(position, (target as i64 - position as i64) as u32);
+        }
+        self.data
+    }
+}
+
+// Index groups are searched as a Patricia tree: each node tests one bit of the name, counting from
+// the end of it, and bits tested get higher going down. The root is node 0 and tests nothing.
+struct Node {
+    bit: i32,
+    children: [usize; 2],
+}
+
+fn get_bit(name: &[u8], bit: i32) -> usize {
+    let character = (bit >> 3) as usize;
+    if character >= name.len() {
+        0
+    } else {
+        usize::from((name[name.len() - 1 - character] >> (bit & 7)) & 1)
+    }
+}
+
+fn first_different_bit(a: &[u8], b: &[u8]) -> i32 {
+    for bit in 0..(max(a.len(), b.len()) * 8) as i32 {
+        if get_bit(a, bit) != get_bit(b, bit) {
+            return bit;
+        }
+    }
+    // Only when both are the same
+    (max(a.len(), b.len()) * 8) as i32
+}
+
+fn build_search_tree(names: &[&[u8]]) -> Vec<Node> {
+    let mut nodes = vec![Node {
+        bit: -1,
+        children: [0, 0],
+    }];
+    for (index, name) in names.iter().enumerate() {
+        let new_node = index + 1;
+        // Closest name already in the tree
+        let mut parent = 0;
+        let mut child = nodes[0].children[0];
+        while nodes[child].bit > nodes[parent].bit {
+            parent = child;
+            child = nodes[child].children[get_bit(name, nodes[child].bit)];
+        }
+        let closest: &[u8] = if child == 0 { &[] } else { names[child - 1] };
+        let bit = first_different_bit(name, closest);
+        // Goes in the path right before the first node testing a later bit
+        parent = 0;
+        child = nodes[0].children[0];
+        while nodes[child].bit > nodes[parent].bit && nodes[child].bit < bit {
+            parent = child;
+            child = nodes[child].children[get_bit(name, nodes[child].bit)];
+        }
+        let direction = get_bit(name, bit);
+        let mut children = [0; 2];
+        children[direction] = new_node;
+        children[1 - direction] = child;
+        nodes.push(Node { bit, children });
+        if parent == 0 {
+            nodes[0].children[0] = new_node;
+        } else {
+            let parent_direction = get_bit(name, nodes[parent].bit);
+            nodes[parent].children[parent_direction] = new_node;
+        }
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use util::{Importable, IndexGroup};
+
+    fn read_u32(data: &[u8], position: usize) -> u32 {
+        data[position..position + 4]
+            .iter()
+            .fold(0, |value, &b| (value << 8) | u32::from(b))
+    }
+
+    #[test]
+    fn pointers_are_relative() {
+        let mut writer = Writer::new();
+        let label = writer.new_label();
+        writer.write_u32(0);
+        writer.write_label_pointer(label);
+        writer.write_text_pointer("bone");
+        writer.write_text_pointer("arm");
+        writer.write_text_pointer("bone");
+        writer.write_pointer(Target::Null);
+        let data_label = writer.defer_data(vec![1, 2, 3], 0x20);
+        writer.write_label_pointer(data_label);
+        writer.write_u8(7);
+        writer.place(label);
+        writer.write_u16(0xABCD);
+        let (start, length) = writer.write_string_table();
+        assert_eq!(writer.write_deferred_data(), 0x20);
+        let data = writer.finish();
+
+        assert_eq!(read_u32(&data, 4), 0x19);
+        assert_eq!(read_u32(&data, 0x14), 0);
+        // Strings are stored once, after their length
+        let bone = 8 + read_u32(&data, 8) as usize;
+        assert_eq!(bone, 0x10 + read_u32(&data, 0x10) as usize);
+        assert_eq!(read_u32(&data, bone - 4), 4);
+        assert_eq!(&data[bone..bone + 5], b"bone\0");
+        let arm = 0xC + read_u32(&data, 0xC) as usize;
+        assert_eq!(&data[arm..arm + 4], b"arm\0");
+        assert!(start <= arm && bone < start + length);
+        let buffer = 0x18 + read_u32(&data, 0x18) as usize;
+        assert_eq!(buffer % 0x20, 0);
+        assert_eq!(&data[buffer..], [1, 2, 3]);
+    }
+
+    #[test]
+    fn index_group_search() {
+        let mut names: Vec<String> = (0..40).map(|i| format!("Mat_{}", i * 7)).collect();
+        for name in &["a", "ab", "ba", "b\u{1}", "_a0", "_n0", "shape", "shapes"] {
+            names.push(name.to_string());
+        }
+        let mut writer = Writer::new();
+        writer.write_index_group(
+            names
+                .iter()
+                .map(|n| (n.clone(), Target::Text(n.clone())))
+                .collect(),
+        );
+        writer.write_string_table();
+        let data = writer.finish();
+
+        let mut cursor = Cursor::new(&data[..]);
+        let group: IndexGroup<String> = IndexGroup::import(&mut cursor).unwrap();
+        assert_eq!(group.entries.len(), names.len());
+        for (name, entry) in names.iter().zip(group.entries.iter()) {
+            assert_eq!(&entry.get_name(&mut cursor).unwrap(), name);
+            let found = group.find(&mut cursor, name).unwrap().unwrap();
+            assert_eq!(&found.get_data(&mut cursor).unwrap(), name);
+        }
+        for name in &["", "b", "Mat_1", "shape_", "Mat_280"] {
+            assert!(group.find(&mut cursor, name).unwrap().is_none());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use error::{check_magic_number, IncorrectHeaderLength};
+use ez_io::ReadE;
+use fres::ftex::{Header, FTEX};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use util::{Importable, Opened, Pointer};
+use yaz0;
+
+// A Gfx2 file, a list of blocks each with its own header
+pub struct GTX {
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub gpu_version: u32,
+    pub textures: Vec<FTEX>,
+}
+
+// What a block holds depends on its type, numbered differently before version 7
+#[derive(Copy, Clone, PartialEq)]
+pub enum BlockKind {
+    End,
+    SurfaceInfo,
+    ImageData,
+    MipmapData,
+    Other(u32),
+}
+
+impl BlockKind {
+    pub fn from_type(block_type: u32, major_version: u32) -> BlockKind {
+        let offset = if major_version >= 7 { 1 } else { 0 };
+        match block_type {
+            0x01 => BlockKind::End,
+            t if t == 0x0A + offset => BlockKind::SurfaceInfo,
+            t if t == 0x0B + offset => BlockKind::ImageData,
+            t if t == 0x0C + offset => BlockKind::MipmapData,
+            t => BlockKind::Other(t),
+        }
+    }
+}
+
+impl Importable for GTX {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<GTX, Box<Error>> {
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'G', b'f', b'x', b'2'])?;
+        let header_length = reader.read_be_to_u32()?;
+        if header_length != 0x20 {
+            return Err(Box::new(IncorrectHeaderLength {
+                size: header_length,
+            }));
+        }
+        let major_version = reader.read_be_to_u32()?;
+        let minor_version = reader.read_be_to_u32()?;
+        let gpu_version = reader.read_be_to_u32()?;
+        reader.seek(SeekFrom::Current(0xC))?;
+
+        let mut textures: Vec<FTEX> = Vec::new();
+        loop {
+            let block_start = reader.seek(SeekFrom::Current(0))?;
+            reader.read_exact(&mut magic_number)?;
+            check_magic_number(magic_number, [b'B', b'L', b'K', b'{'])?;
+            let block_header_length = reader.read_be_to_u32()?;
+            // Anything shorter would not move forward
+            if block_header_length < 0x20 {
+                return Err(Box::new(IncorrectHeaderLength {
+                    size: block_header_length,
+                }));
+            }
+            let _block_major_version = reader.read_be_to_u32()?;
+            let _block_minor_version = reader.read_be_to_u32()?;
+            let block_type = reader.read_be_to_u32()?;
+            let data_length = reader.read_be_to_u32()?;
+            let data_start = block_start + u64::from(block_header_length);
+            reader.seek(SeekFrom::Start(data_start))?;
+            // Image and mipmap data belong to the surface right before them
+            match BlockKind::from_type(block_type, major_version) {
+                BlockKind::End => break,
+                BlockKind::SurfaceInfo => {
+                    let header = Header::import_gx2_texture(reader)?;
+                    textures.push(FTEX { header });
+                }
+                BlockKind::ImageData => {
+                    if let Some(texture) = textures.last_mut() {
+                        texture.header.data_offset = Pointer::new_abs(data_start as i32);
+                        texture.header.data_length = data_length;
+                    }
+                }
+                BlockKind::MipmapData => {
+                    if let Some(texture) = textures.last_mut() {
+                        texture.header.mipmap_offset = Pointer::new_abs(data_start as i32);
+                        texture.header.mipmaps_data_length = data_length;
+                    }
+                }
+                BlockKind::Other(_) => {}
+            }
+            reader.seek(SeekFrom::Start(data_start + u64::from(data_length)))?;
+        }
+        Ok(GTX {
+            major_version,
+            minor_version,
+            gpu_version,
+            textures,
+        })
+    }
+}
+
+impl GTX {
+    // Textures are read from the returned cursor, like for FRES::open
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Opened<GTX>, Box<Error>> {
+        GTX::from_reader(&mut BufReader::new(File::open(path)?))
+    }
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Opened<GTX>, Box<Error>> {
+        let mut cursor = Cursor::new(yaz0::read_all(reader)?);
+        let gtx = GTX::import(&mut cursor)?;
+        Ok((gtx, cursor))
+    }
+}
+
+pub fn is_gtx(data: &[u8]) -> bool {
+    data.len() >= 4 && data[..4] == [b'G', b'f', b'x', b'2']
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fres::writer::Writer;
+
+    fn write_block(writer: &mut Writer, header_length: u32, block_type: u32, data: &[u8]) {
+        writer.write_bytes(b"BLK{");
+        writer.write_u32(header_length);
+        writer.write_u32(1);
+        writer.write_u32(0);
+        writer.write_u32(block_type);
+        writer.write_u32(data.len() as u32);
+        writer.write_u32(0); // Identifier
+        writer.write_u32(0); // Index
+        writer.write_bytes(data);
+    }
+
+    // A 8x8 RGBA8 surface with one mipmap, its image data and its mipmap data
+    fn make_gtx(header_length: u32) -> Vec<u8> {
+        let mut surface = Writer::new();
+        for &value in &[1, 8, 8, 1, 2, 0x01a, 0, 1, 256, 0, 64, 0, 2, 0, 256, 8] {
+            surface.write_u32(value);
+        }
+        surface.write_u32(256);
+        for _ in 1..13 {
+            surface.write_u32(0);
+        }
+        for &value in &[0, 2, 0, 1, 0x0001_0203, 0, 0, 0, 0, 0] {
+            surface.write_u32(value);
+        }
+        let mut writer = Writer::new();
+        writer.write_bytes(b"Gfx2");
+        for &value in &[0x20, 7, 1, 2, 0, 0, 0] {
+            writer.write_u32(value);
+        }
+        write_block(&mut writer, 0x20, 0x0B, &surface.finish());
+        write_block(&mut writer, header_length, 0x0C, &[0xAA; 256]);
+        write_block(&mut writer, 0x20, 0x0D, &[0x55; 64]);
+        write_block(&mut writer, 0x20, 0x01, &[]);
+        writer.finish()
+    }
+
+    #[test]
+    fn read_surface_blocks() {
+        let mut cursor = Cursor::new(make_gtx(0x20));
+        let gtx = GTX::import(&mut cursor).unwrap();
+        assert_eq!(gtx.major_version, 7);
+        assert_eq!(gtx.textures.len(), 1);
+        let texture = &gtx.textures[0];
+        assert_eq!(texture.header.texture_width, 8);
+        assert_eq!(texture.header.nb_mipmaps, 2);
+        assert_eq!(texture.get_raw_data(&mut cursor).unwrap(), vec![0xAA; 256]);
+        assert_eq!(
+            texture.get_mip_level_raw_data(&mut cursor, 1).unwrap(),
+            vec![0x55; 64]
+        );
+    }
+
+    #[test]
+    fn block_header_too_short() {
+        assert!(GTX::import(&mut Cursor::new(make_gtx(0))).is_err());
+        assert!(GTX::import(&mut Cursor::new(make_gtx(0x1C))).is_err());
+    }
+}
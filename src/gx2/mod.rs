@@ -0,0 +1,14 @@
+pub mod surface;
+
+// How the Wii U GPU is configured, AddrLib needs it for every computation
+pub const M_BANKS: i64 = 4;
+pub const M_BANKS_BIT_COUNT: i64 = 2;
+pub const M_PIPES: i64 = 2;
+pub const M_PIPES_BIT_COUNT: i64 = 1;
+pub const M_PIPE_INTERLEAVE_BYTES: i64 = 256;
+pub const M_PIPE_INTERLEAVE_BYTES_BIT_COUNT: i64 = 8;
+pub const M_ROW_SIZE: i64 = 2048;
+pub const M_SWAP_SIZE: i64 = 256;
+pub const M_SPLIT_SIZE: i64 = 2048;
+// pub const M_CHIP_FAMILY: i64 = 2;
+pub const MICRO_TILE_PIXELS: i64 = 8 * 8;
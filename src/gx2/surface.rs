@@ -0,0 +1,728 @@
+use fres::ftex::{
+    aa_mode::AAMode, dimension::Dimension, format::Format, tile_mode::TileMode, Header,
+};
+use gx2::{
+    MICRO_TILE_PIXELS, M_BANKS, M_PIPES, M_PIPE_INTERLEAVE_BYTES, M_ROW_SIZE, M_SPLIT_SIZE,
+    M_SWAP_SIZE,
+};
+use std::cmp::{max, min};
+
+// What a texture is made of, before GX2 lays it out
+#[derive(Copy, Clone)]
+pub struct Surface {
+    pub format: Format,
+    pub dimension: Dimension,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub nb_mipmaps: u32,
+    pub aa_mode: AAMode,
+    pub tile_mode: TileMode,
+}
+
+// How a mip level is laid out in memory. Pitch and height are in elements (blocks for block
+// compressed formats), height being aligned on the tiling, and depth is the number of slices once
+// padded. Size and alignment are in bytes.
+pub struct SurfaceLevel {
+    pub tile_mode: TileMode,
+    pub pitch: i64,
+    pub height: i64,
+    pub depth: i64,
+    pub nb_slices: u32,
+    pub nb_samples: i64,
+    pub pitch_alignment: i64,
+    pub height_alignment: i64,
+    pub size: i64,
+    pub alignment: i64,
+}
+
+// Everything the FTEX header needs to describe a surface
+pub struct SurfaceInfo {
+    pub tile_mode: TileMode,
+    pub pitch: u32,
+    pub height_alignment: u32,
+    pub alignment: u32,
+    pub data_length: u32,
+    pub mipmaps_data_length: u32,
+    pub mipmap_offsets: [u32; 13],
+    pub levels: Vec<SurfaceLevel>,
+}
+
+impl Surface {
+    pub fn from_header(header: &Header) -> Surface {
+        Surface {
+            format: header.texture_format,
+            dimension: header.dimension,
+            width: header.texture_width,
+            height: header.texture_height,
+            depth: header.texture_depth,
+            nb_mipmaps: header.nb_mipmaps,
+            aa_mode: header.aa_mode,
+            tile_mode: header.tile_mode,
+        }
+    }
+    // Arrays and cube maps keep all their slices on every level, 3D textures lose depth like the
+    // rest
+    pub fn get_mip_level_slice_count(&self, level: u32) -> u32 {
+        match self.dimension {
+            Dimension::ThreeD => max(1, self.depth >> level),
+            _ => max(1, self.depth),
+        }
+    }
+    // GX2 picks the tile mode itself when given the default one
+    fn get_requested_tile_mode(&self) -> TileMode {
+        match (self.tile_mode, self.dimension, self.aa_mode) {
+            (TileMode::Default, Dimension::OneD, AAMode::OneTime) => TileMode::LinearAligned,
+            (TileMode::Default, _, _) => TileMode::TwoDTiledThin1,
+            (t, _, _) => t,
+        }
+    }
+}
+
+// Lays out the whole texture like GX2CalcSurfaceSizeAndAlignment does. The first mipmap offset
+// counts from the start of the base image, the others from the start of the mipmap data.
+pub fn compute_surface_info(surface: &Surface) -> SurfaceInfo {
+    let mut surface = *surface;
+    surface.tile_mode = surface.get_requested_tile_mode();
+    let base_level = compute_mip_level_surface(&surface, 0);
+    surface.tile_mode = base_level.tile_mode;
+
+    let data_length = base_level.size as u32;
+    let mut mipmap_offsets = [0u32; 13];
+    let mut offset = 0u32;
+    let mut levels = vec![base_level];
+    for level in 1..surface.nb_mipmaps.clamp(1, 14) {
+        let mip_level = compute_mip_level_surface(&surface, level);
+        let alignment = mip_level.alignment as u32;
+        if level == 1 {
+            mipmap_offsets[0] = align(data_length, alignment);
+            offset = mipmap_offsets[0] - data_length;
+        } else {
+            offset = align(offset, alignment);
+            mipmap_offsets[level as usize - 1] = offset;
+        }
+        offset += mip_level.size as u32;
+        levels.push(mip_level);
+    }
+
+    SurfaceInfo {
+        tile_mode: levels[0].tile_mode,
+        pitch: levels[0].pitch as u32,
+        height_alignment: levels[0].height_alignment as u32,
+        alignment: levels[0].alignment as u32,
+        data_length,
+        mipmaps_data_length: offset,
+        mipmap_offsets,
+        levels,
+    }
+}
+
+// The getSurfaceInfo and computeSurfaceInfo part of AddrLib, for a single level
+pub fn compute_mip_level_surface(surface: &Surface, level: u32) -> SurfaceLevel {
+    let format = &surface.format;
+    let bits_pp = i64::from(format.get_bits_per_pixel());
+    let nb_samples = i64::from(surface.aa_mode.get_sample_count());
+    let level_height = max(1, surface.height >> level);
+    let (height, nb_slices) = match surface.dimension {
+        Dimension::OneD => (1, 1),
+        Dimension::TwoD | Dimension::TwoDMSAA => (level_height, 1),
+        Dimension::ThreeD => (level_height, max(1, surface.depth >> level)),
+        Dimension::Cube => (level_height, max(6, surface.depth)),
+        Dimension::OneDArray => (1, max(1, surface.depth)),
+        Dimension::TwoDArray | Dimension::TwoDMSAAArray => (level_height, max(1, surface.depth)),
+    };
+    let mut width = i64::from(max(1, surface.width >> level));
+    let mut height = i64::from(height);
+    let nb_slices = i64::from(nb_slices);
+    // Block compressed formats are laid out in blocks, mip levels padded in pixels first
+    if format.is_block_compressed() {
+        if level > 0 {
+            width = next_pow2(width);
+            height = next_pow2(height);
+        }
+        width = (width + 3) / 4;
+        height = (height + 3) / 4;
+    }
+
+    let mut surface_level = if let TileMode::LinearSpecial = surface.tile_mode {
+        // Not padded at all
+        SurfaceLevel {
+            tile_mode: TileMode::LinearSpecial,
+            pitch: width,
+            height,
+            depth: nb_slices,
+            nb_slices: 0,
+            nb_samples,
+            pitch_alignment: 1,
+            height_alignment: 1,
+            size: 0,
+            alignment: 1,
+        }
+    } else {
+        let is_cube = match surface.dimension {
+            Dimension::Cube => true,
+            _ => false,
+        };
+        let dimensions = (width, height, nb_slices);
+        let tile_mode = compute_surface_mip_level_tile_mode(
+            surface.tile_mode,
+            bits_pp,
+            level,
+            dimensions,
+            nb_samples,
+            false,
+        );
+        if tile_mode.is_linear() {
+            compute_surface_info_linear(tile_mode, bits_pp, nb_samples, dimensions, level, is_cube)
+        } else if tile_mode.is_macro_tiled() {
+            compute_surface_info_macro_tiled(
+                tile_mode,
+                surface.tile_mode,
+                bits_pp,
+                nb_samples,
+                dimensions,
+                level,
+                is_cube,
+            )
+        } else {
+            compute_surface_info_micro_tiled(
+                tile_mode, bits_pp, nb_samples, dimensions, level, is_cube,
+            )
+        }
+    };
+    surface_level.nb_slices = surface.get_mip_level_slice_count(level);
+    surface_level.size = get_surface_level_size(format, &surface_level);
+    surface_level
+}
+
+pub fn get_surface_level_size(format: &Format, surface_level: &SurfaceLevel) -> i64 {
+    let bits_pp = i64::from(format.get_bits_per_pixel());
+    (surface_level.pitch
+        * surface_level.height
+        * surface_level.depth
+        * bits_pp
+        * surface_level.nb_samples
+        + 7)
+        / 8
+}
+
+fn next_pow2(value: i64) -> i64 {
+    (max(1, value) as u64).next_power_of_two() as i64
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+fn align_i64(value: i64, alignment: i64) -> i64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+fn compute_surface_tile_slices(tile_mode: TileMode, bpp: i64, num_samples: i64) -> i64 {
+    let bytes_per_sample = ((bpp << 6) + 7) >> 3;
+    let num_samples = if tile_mode.get_surface_thickness() > 1 {
+        4
+    } else {
+        num_samples
+    };
+    let samples_per_tile = M_SPLIT_SIZE / bytes_per_sample;
+    if samples_per_tile != 0 {
+        max(1, num_samples / samples_per_tile)
+    } else {
+        1
+    }
+}
+
+fn convert_to_non_bank_swapped_mode(tile_mode: TileMode) -> TileMode {
+    match tile_mode {
+        TileMode::TwoBTiledThin1 => TileMode::TwoDTiledThin1,
+        TileMode::TwoBTiledThin2 => TileMode::TwoDTiledThin2,
+        TileMode::TwoBTiledThin4 => TileMode::TwoDTiledThin4,
+        TileMode::TwoBTiledThick => TileMode::TwoDTiledThick,
+        TileMode::ThreeBTiledThin1 => TileMode::ThreeDTiledThin1,
+        TileMode::ThreeBTiledThick => TileMode::ThreeDTiledThick,
+        t => t,
+    }
+}
+
+// Levels too small for their tiling fall back to a simpler one
+fn compute_surface_mip_level_tile_mode(
+    base_tile_mode: TileMode,
+    bpp: i64,
+    level: u32,
+    dimensions: (i64, i64, i64),
+    num_samples: i64,
+    no_recursive: bool,
+) -> TileMode {
+    let tile_slices = compute_surface_tile_slices(base_tile_mode, bpp, num_samples);
+    let mut tile_mode = base_tile_mode;
+    if num_samples > 1 || tile_slices > 1 {
+        tile_mode = match base_tile_mode {
+            TileMode::TwoDTiledThick => TileMode::TwoDTiledThin1,
+            TileMode::ThreeDTiledThick => TileMode::ThreeDTiledThin1,
+            TileMode::TwoBTiledThick => TileMode::TwoBTiledThin1,
+            TileMode::ThreeBTiledThick => TileMode::ThreeBTiledThin1,
+            t => t,
+        };
+    }
+    match base_tile_mode {
+        TileMode::OneDTiledThin1 if num_samples > 1 => tile_mode = TileMode::TwoDTiledThin1,
+        TileMode::OneDTiledThick if num_samples == 2 || num_samples == 4 => {
+            tile_mode = TileMode::TwoDTiledThick
+        }
+        TileMode::OneDTiledThick if num_samples > 1 => tile_mode = TileMode::OneDTiledThin1,
+        _ => {}
+    }
+    if no_recursive || level == 0 {
+        return tile_mode;
+    }
+
+    let (width, height, num_slices) = (
+        next_pow2(dimensions.0),
+        next_pow2(dimensions.1),
+        next_pow2(dimensions.2),
+    );
+    tile_mode = convert_to_non_bank_swapped_mode(tile_mode);
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    let micro_tile_bytes = (num_samples * bpp * (thickness * MICRO_TILE_PIXELS) + 7) >> 3;
+    let width_align_factor = if micro_tile_bytes < M_PIPE_INTERLEAVE_BYTES {
+        M_PIPE_INTERLEAVE_BYTES / micro_tile_bytes
+    } else {
+        1
+    };
+    let aspect_ratio = i64::from(tile_mode.get_aspect_ratio());
+    let macro_tile_width = (8 * M_BANKS) / aspect_ratio;
+    let macro_tile_height = (8 * M_PIPES) * aspect_ratio;
+    let too_small = width < width_align_factor * macro_tile_width || height < macro_tile_height;
+    tile_mode = match tile_mode {
+        TileMode::TwoDTiledThin1
+        | TileMode::TwoDTiledThin2
+        | TileMode::TwoDTiledThin4
+        | TileMode::ThreeDTiledThin1
+            if too_small =>
+        {
+            TileMode::OneDTiledThin1
+        }
+        TileMode::TwoDTiledThick | TileMode::ThreeDTiledThick if too_small => {
+            TileMode::OneDTiledThick
+        }
+        t => t,
+    };
+    if num_slices < 4 {
+        tile_mode = match tile_mode {
+            TileMode::OneDTiledThick => TileMode::OneDTiledThin1,
+            TileMode::TwoDTiledThick => TileMode::TwoDTiledThin1,
+            TileMode::ThreeDTiledThick => TileMode::ThreeDTiledThin1,
+            t => t,
+        };
+    }
+    compute_surface_mip_level_tile_mode(
+        tile_mode,
+        bpp,
+        level,
+        (width, height, num_slices),
+        num_samples,
+        true,
+    )
+}
+
+// Dimensions of mip levels get padded to a power of two, cube maps keep their slices as they are
+fn pad_mip_level(dimensions: (i64, i64, i64), level: u32, is_cube: bool) -> ((i64, i64, i64), u8) {
+    let (pitch, height, num_slices) = dimensions;
+    if level == 0 {
+        // Base levels of cube maps are not padded to a power of two slices
+        return (dimensions, if is_cube { 2 } else { 0 });
+    }
+    let pad_dims = if is_cube && num_slices <= 1 { 2 } else { 0 };
+    let num_slices = if is_cube {
+        num_slices
+    } else {
+        next_pow2(num_slices)
+    };
+    ((next_pow2(pitch), next_pow2(height), num_slices), pad_dims)
+}
+
+fn pad_dimensions(
+    tile_mode: TileMode,
+    pad_dims: u8,
+    is_cube: bool,
+    pitch_align: i64,
+    height_align: i64,
+    slice_align: i64,
+    dimensions: (i64, i64, i64),
+) -> (i64, i64, i64) {
+    let (mut pitch, mut height, mut num_slices) = dimensions;
+    let thickness = tile_mode.get_surface_thickness();
+    let pad_dims = if pad_dims == 0 { 3 } else { pad_dims };
+    pitch = align_i64(pitch, pitch_align);
+    if pad_dims > 1 {
+        height = align_i64(height, height_align);
+    }
+    if pad_dims > 2 || thickness > 1 {
+        if is_cube {
+            num_slices = next_pow2(num_slices);
+        }
+        if thickness > 1 {
+            num_slices = align_i64(num_slices, slice_align);
+        }
+    }
+    (pitch, height, num_slices)
+}
+
+fn make_surface_level(
+    tile_mode: TileMode,
+    dimensions: (i64, i64, i64),
+    num_samples: i64,
+    alignments: (i64, i64, i64),
+) -> SurfaceLevel {
+    SurfaceLevel {
+        tile_mode,
+        pitch: dimensions.0,
+        height: dimensions.1,
+        depth: dimensions.2,
+        nb_slices: 0,
+        nb_samples: num_samples,
+        pitch_alignment: alignments.0,
+        height_alignment: alignments.1,
+        size: 0,
+        alignment: alignments.2,
+    }
+}
+
+// Pitch, height and base alignments
+fn compute_surface_alignments_linear(tile_mode: TileMode, bpp: i64) -> (i64, i64, i64) {
+    match tile_mode {
+        TileMode::Default => (if bpp == 1 { 8 } else { 1 }, 1, 1),
+        TileMode::LinearAligned => (max(64, 2048 / bpp), 1, M_PIPE_INTERLEAVE_BYTES),
+        _ => (1, 1, 1),
+    }
+}
+
+fn compute_surface_alignments_micro_tiled(
+    tile_mode: TileMode,
+    bpp: i64,
+    num_samples: i64,
+) -> (i64, i64, i64) {
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    (
+        max(8, M_PIPE_INTERLEAVE_BYTES / bpp / num_samples / thickness),
+        8,
+        M_PIPE_INTERLEAVE_BYTES,
+    )
+}
+
+fn compute_surface_alignments_macro_tiled(
+    tile_mode: TileMode,
+    bpp: i64,
+    num_samples: i64,
+) -> (i64, i64, i64) {
+    let aspect_ratio = i64::from(tile_mode.get_aspect_ratio());
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    let macro_tile_width = (8 * M_BANKS) / aspect_ratio;
+    let macro_tile_height = (8 * M_PIPES) * aspect_ratio;
+    let pitch_align = max(
+        macro_tile_width,
+        macro_tile_width * (M_PIPE_INTERLEAVE_BYTES / bpp / (8 * thickness) / num_samples),
+    );
+    let height_align = macro_tile_height;
+    let macro_tile_bytes = num_samples * ((bpp * macro_tile_height * macro_tile_width + 7) >> 3);
+    let base_align = if thickness == 1 {
+        max(
+            macro_tile_bytes,
+            (num_samples * height_align * bpp * pitch_align + 7) >> 3,
+        )
+    } else {
+        max(
+            M_PIPE_INTERLEAVE_BYTES,
+            (4 * height_align * bpp * pitch_align + 7) >> 3,
+        )
+    };
+    let micro_tile_bytes = (thickness * num_samples * (bpp << 6) + 7) >> 3;
+    let slices_per_micro_tile = if micro_tile_bytes < M_SPLIT_SIZE {
+        1
+    } else {
+        micro_tile_bytes / M_SPLIT_SIZE
+    };
+    (
+        pitch_align,
+        height_align,
+        base_align / slices_per_micro_tile,
+    )
+}
+
+fn compute_surface_info_linear(
+    tile_mode: TileMode,
+    bpp: i64,
+    num_samples: i64,
+    dimensions: (i64, i64, i64),
+    level: u32,
+    is_cube: bool,
+) -> SurfaceLevel {
+    let (dimensions, pad_dims) = pad_mip_level(dimensions, level, is_cube);
+    let alignments = compute_surface_alignments_linear(tile_mode, bpp);
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    let dimensions = pad_dimensions(
+        tile_mode,
+        pad_dims,
+        is_cube,
+        alignments.0,
+        alignments.1,
+        thickness,
+        dimensions,
+    );
+    make_surface_level(tile_mode, dimensions, num_samples, alignments)
+}
+
+fn compute_surface_info_micro_tiled(
+    tile_mode: TileMode,
+    bpp: i64,
+    num_samples: i64,
+    dimensions: (i64, i64, i64),
+    level: u32,
+    is_cube: bool,
+) -> SurfaceLevel {
+    let (dimensions, pad_dims) = pad_mip_level(dimensions, level, is_cube);
+    let tile_mode = match tile_mode {
+        TileMode::OneDTiledThick if level > 0 && dimensions.2 < 4 => TileMode::OneDTiledThin1,
+        t => t,
+    };
+    let alignments = compute_surface_alignments_micro_tiled(tile_mode, bpp, num_samples);
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    let dimensions = pad_dimensions(
+        tile_mode,
+        pad_dims,
+        is_cube,
+        alignments.0,
+        alignments.1,
+        thickness,
+        dimensions,
+    );
+    make_surface_level(tile_mode, dimensions, num_samples, alignments)
+}
+
+fn compute_surface_info_macro_tiled(
+    tile_mode: TileMode,
+    base_tile_mode: TileMode,
+    bpp: i64,
+    num_samples: i64,
+    dimensions: (i64, i64, i64),
+    level: u32,
+    is_cube: bool,
+) -> SurfaceLevel {
+    let pitch = dimensions.0;
+    let (padded, pad_dims) = pad_mip_level(dimensions, level, is_cube);
+    let tile_mode = match tile_mode {
+        TileMode::TwoDTiledThick if level > 0 && padded.2 < 4 => TileMode::TwoDTiledThin1,
+        t => t,
+    };
+    // A thin level of a thick texture only stays macro tiled if it is big enough for the thick
+    // tiling
+    if level > 0 && base_tile_mode.is_thick() && !tile_mode.is_thick() {
+        let (pitch_align, height_align, _) =
+            compute_surface_alignments_macro_tiled(base_tile_mode, bpp, num_samples);
+        let pitch_align_factor = max(1, (M_PIPE_INTERLEAVE_BYTES >> 3) / bpp);
+        if padded.0 < pitch_align * pitch_align_factor || padded.1 < height_align {
+            return compute_surface_info_micro_tiled(
+                TileMode::OneDTiledThin1,
+                bpp,
+                num_samples,
+                dimensions,
+                level,
+                is_cube,
+            );
+        }
+    }
+    let mut alignments = compute_surface_alignments_macro_tiled(tile_mode, bpp, num_samples);
+    let bank_swapped_width =
+        compute_surface_bank_swapped_width(&tile_mode, bpp, pitch, num_samples);
+    alignments.0 = max(alignments.0, bank_swapped_width);
+    let thickness = i64::from(tile_mode.get_surface_thickness());
+    let padded = pad_dimensions(
+        tile_mode,
+        pad_dims,
+        is_cube,
+        alignments.0,
+        alignments.1,
+        thickness,
+        padded,
+    );
+    make_surface_level(tile_mode, padded, num_samples, alignments)
+}
+
+pub fn compute_surface_bank_swapped_width(
+    tile_mode: &TileMode,
+    bpp: i64,
+    pitch: i64,
+    num_samples: i64,
+) -> i64 {
+    if !tile_mode.is_bank_swapped() {
+        return 0;
+    }
+    let mut num_samples2 = num_samples;
+    let num_banks = M_BANKS;
+    let num_pipes = M_PIPES;
+    let swap_size = M_SWAP_SIZE;
+    let row_size = M_ROW_SIZE;
+    let split_size = M_SPLIT_SIZE;
+    let group_size = M_PIPE_INTERLEAVE_BYTES;
+    let bytes_per_sample = 8 * bpp;
+    let slices_per_tile = if bytes_per_sample != 0 {
+        max(1, num_samples2 / (split_size / bytes_per_sample))
+    } else {
+        1
+    };
+    if tile_mode.is_thick() {
+        num_samples2 = 4;
+    }
+    let bytes_per_tile_slice = num_samples2 * bytes_per_sample / slices_per_tile;
+    let factor = i64::from(tile_mode.get_aspect_ratio());
+    let swap_tiles = max(1, (swap_size >> 1) / bpp);
+    let swap_width = swap_tiles * 8 * num_banks;
+    let height_bytes = num_samples2 * factor * num_pipes * bpp / slices_per_tile;
+    let swap_max = num_pipes * num_banks * row_size / height_bytes;
+    let swap_min = group_size * 8 * num_banks / bytes_per_tile_slice;
+    let mut bank_swap_width = min(swap_max, max(swap_min, swap_width));
+    while bank_swap_width >= (2 * pitch) {
+        bank_swap_width >>= 1;
+    }
+    bank_swap_width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_surface(
+        format: Format,
+        dimension: Dimension,
+        tile_mode: TileMode,
+        (width, height, depth): (u32, u32, u32),
+        nb_mipmaps: u32,
+    ) -> Surface {
+        Surface {
+            format,
+            dimension,
+            width,
+            height,
+            depth,
+            nb_mipmaps,
+            aa_mode: AAMode::OneTime,
+            tile_mode,
+        }
+    }
+
+    fn check_surface_info(
+        surface: &Surface,
+        tile_mode: TileMode,
+        pitch: u32,
+        alignment: u32,
+        data_length: u32,
+        mipmaps_data_length: u32,
+        mipmap_offsets: &[u32],
+    ) {
+        let info = compute_surface_info(surface);
+        assert_eq!(info.tile_mode as u32, tile_mode as u32);
+        assert_eq!(info.pitch, pitch);
+        assert_eq!(info.alignment, alignment);
+        assert_eq!(info.data_length, data_length);
+        assert_eq!(info.mipmaps_data_length, mipmaps_data_length);
+        assert_eq!(&info.mipmap_offsets[..mipmap_offsets.len()], mipmap_offsets);
+        assert!(info.mipmap_offsets[mipmap_offsets.len()..]
+            .iter()
+            .all(|&o| o == 0));
+    }
+
+    // Levels from 16x16 blocks down are too small for the macro tiles and become 1D tiled
+    #[test]
+    fn bc1_two_d_tiled_with_mipmaps() {
+        let surface = make_surface(
+            Format::TBc1Unorm,
+            Dimension::TwoD,
+            TileMode::TwoDTiledThin1,
+            (256, 256, 1),
+            9,
+        );
+        check_surface_info(
+            &surface,
+            TileMode::TwoDTiledThin1,
+            64,
+            4096,
+            32768,
+            13312,
+            &[32768, 8192, 10240, 10752, 11264, 11776, 12288, 12800],
+        );
+        let info = compute_surface_info(&surface);
+        assert_eq!(info.height_alignment, 16);
+        assert_eq!(
+            info.levels[1].tile_mode as u32,
+            TileMode::TwoDTiledThin1 as u32
+        );
+        assert_eq!(
+            info.levels[2].tile_mode as u32,
+            TileMode::OneDTiledThin1 as u32
+        );
+    }
+
+    #[test]
+    fn rgba8_one_d_tiled() {
+        let surface = make_surface(
+            Format::TcsR8G8B8A8Unorm,
+            Dimension::TwoD,
+            TileMode::OneDTiledThin1,
+            (100, 60, 1),
+            3,
+        );
+        check_surface_info(
+            &surface,
+            TileMode::OneDTiledThin1,
+            104,
+            256,
+            26624,
+            10240,
+            &[26624, 8192],
+        );
+        assert_eq!(compute_surface_info(&surface).levels[0].height, 64);
+    }
+
+    #[test]
+    fn rgba8_three_d_thick() {
+        let surface = make_surface(
+            Format::TcsR8G8B8A8Unorm,
+            Dimension::ThreeD,
+            TileMode::ThreeDTiledThick,
+            (64, 64, 8),
+            2,
+        );
+        check_surface_info(
+            &surface,
+            TileMode::ThreeDTiledThick,
+            64,
+            8192,
+            131072,
+            16384,
+            &[131072],
+        );
+        let info = compute_surface_info(&surface);
+        assert_eq!(info.levels[0].depth, 8);
+        assert_eq!(info.levels[1].depth, 4);
+    }
+
+    // GX2 does not tile 1D textures when left to choose
+    #[test]
+    fn default_tile_mode() {
+        let mut surface = make_surface(
+            Format::TcsR8G8B8A8Unorm,
+            Dimension::OneD,
+            TileMode::Default,
+            (100, 1, 1),
+            1,
+        );
+        check_surface_info(&surface, TileMode::LinearAligned, 128, 256, 512, 0, &[]);
+        surface.dimension = Dimension::TwoD;
+        surface.height = 60;
+        assert_eq!(
+            compute_surface_info(&surface).tile_mode as u32,
+            TileMode::TwoDTiledThin1 as u32
+        );
+    }
+}
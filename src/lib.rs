@@ -1,6 +1,20 @@
+extern crate bcndecode;
 extern crate ez_io;
+extern crate gltf;
+extern crate half;
+#[cfg(feature = "json")]
+extern crate serde;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde_derive;
 
+pub mod dds;
 mod error;
 pub mod fres;
+pub mod gtx;
+pub mod gx2;
+pub mod sarc;
+pub mod scene;
 pub mod swizzle;
 pub mod util;
+pub mod yaz0;
@@ -0,0 +1,443 @@
+use error::{check_magic_number, FileNotFound, UnrecognizedValue};
+use fres::FRES;
+use std::cmp::max;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use util::{read_text_entry, Importable, Opened};
+use yaz0;
+
+pub const DEFAULT_HASH_KEY: u32 = 0x65;
+const HEADER_LENGTH: u32 = 0x14;
+const SFAT_HEADER_LENGTH: u32 = 0xC;
+const SFAT_NODE_LENGTH: u32 = 0x10;
+const SFNT_HEADER_LENGTH: u32 = 0x8;
+
+pub struct SARC {
+    pub big_endian: bool,
+    pub hash_key: u32,
+    pub files: Vec<SARCFile>,
+}
+
+// Files without a name are only known by their hash
+pub struct SARCFile {
+    pub name: Option<String>,
+    pub hash: u32,
+    pub data: Vec<u8>,
+}
+
+impl Importable for SARC {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<SARC, Box<Error>> {
+        let start = reader.seek(SeekFrom::Current(0))?;
+        // Header
+        let mut magic_number = [0u8; 4];
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'S', b'A', b'R', b'C'])?;
+        let mut header_length = [0u8; 2];
+        reader.read_exact(&mut header_length)?;
+        let mut bom = [0u8; 2];
+        reader.read_exact(&mut bom)?;
+        let big_endian = match bom {
+            [0xFE, 0xFF] => true,
+            [0xFF, 0xFE] => false,
+            _ => {
+                return Err(Box::new(UnrecognizedValue {
+                    enum_name: "Byte Order Mark".to_string(),
+                    value: bom,
+                }))
+            }
+        };
+        let _file_length = read_u32(reader, big_endian)?;
+        let data_offset = read_u32(reader, big_endian)?;
+        let _version = read_u16(reader, big_endian)?;
+        let _reserved = read_u16(reader, big_endian)?;
+        reader.seek(SeekFrom::Start(
+            start + u64::from(to_u16(header_length, big_endian)),
+        ))?;
+
+        // SFAT
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'S', b'F', b'A', b'T'])?;
+        let _sfat_header_length = read_u16(reader, big_endian)?;
+        let nb_nodes = read_u16(reader, big_endian)?;
+        let hash_key = read_u32(reader, big_endian)?;
+        let mut nodes = Vec::with_capacity(nb_nodes as usize);
+        for _ in 0..nb_nodes {
+            let hash = read_u32(reader, big_endian)?;
+            let attributes = read_u32(reader, big_endian)?;
+            let data_start = read_u32(reader, big_endian)?;
+            let data_end = read_u32(reader, big_endian)?;
+            nodes.push((hash, attributes, data_start, data_end));
+        }
+
+        // SFNT
+        reader.read_exact(&mut magic_number)?;
+        check_magic_number(magic_number, [b'S', b'F', b'N', b'T'])?;
+        let sfnt_header_length = read_u16(reader, big_endian)?;
+        let _reserved = read_u16(reader, big_endian)?;
+        let names_start = reader.seek(SeekFrom::Current(0))? - 8 + u64::from(sfnt_header_length);
+
+        // Files
+        let mut files = Vec::with_capacity(nodes.len());
+        for (hash, attributes, data_start, data_end) in nodes {
+            // The top byte is non zero when there is a name, the rest is its offset in words
+            let name = if attributes >> 24 != 0 {
+                reader.seek(SeekFrom::Start(
+                    names_start + u64::from(attributes & 0xFF_FFFF) * 4,
+                ))?;
+                Some(read_text_entry(reader)?)
+            } else {
+                None
+            };
+            reader.seek(SeekFrom::Start(
+                start + u64::from(data_offset) + u64::from(data_start),
+            ))?;
+            let mut data = vec![0u8; data_end.saturating_sub(data_start) as usize];
+            reader.read_exact(&mut data)?;
+            files.push(SARCFile { name, hash, data });
+        }
+        Ok(SARC {
+            big_endian,
+            hash_key,
+            files,
+        })
+    }
+}
+
+impl SARC {
+    // Wii U archives are big endian
+    pub fn new(big_endian: bool) -> SARC {
+        SARC {
+            big_endian,
+            hash_key: DEFAULT_HASH_KEY,
+            files: Vec::new(),
+        }
+    }
+    // .szs files are Yaz0 compressed, .sarc and .pack files are not
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SARC, Box<Error>> {
+        SARC::from_reader(&mut BufReader::new(File::open(path)?))
+    }
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<SARC, Box<Error>> {
+        SARC::import(&mut Cursor::new(yaz0::read_all(reader)?))
+    }
+    pub fn get_file(&self, name: &str) -> Option<&SARCFile> {
+        self.files.iter().find(|f| f.name.as_deref() == Some(name))
+    }
+    // Replaces the file if there already is one with this name
+    pub fn add_file(&mut self, name: &str, data: Vec<u8>) {
+        let hash = get_name_hash(name, self.hash_key);
+        match self
+            .files
+            .iter_mut()
+            .find(|f| f.name.as_deref() == Some(name))
+        {
+            Some(file) => file.data = data,
+            None => self.files.push(SARCFile {
+                name: Some(name.to_string()),
+                hash,
+                data,
+            }),
+        }
+    }
+    pub fn remove_file(&mut self, name: &str) -> Option<SARCFile> {
+        let index = self
+            .files
+            .iter()
+            .position(|f| f.name.as_deref() == Some(name))?;
+        Some(self.files.remove(index))
+    }
+    // The BFRES can be Yaz0 compressed inside the archive too
+    pub fn open_fres(&self, name: &str) -> Result<Opened<FRES>, Box<Error>> {
+        match self.get_file(name) {
+            Some(file) => FRES::from_reader(&mut Cursor::new(&file.data)),
+            None => Err(Box::new(FileNotFound {
+                name: name.to_string(),
+            })),
+        }
+    }
+    // Every file starts on a multiple of alignment, or more if its format needs it
+    pub fn write<W: Write>(&self, writer: &mut W, alignment: u32) -> Result<(), Box<Error>> {
+        let big_endian = self.big_endian;
+        // Nodes are sorted by hash, the game looks files up with a binary search
+        let mut files: Vec<(u32, &SARCFile)> = self
+            .files
+            .iter()
+            .map(|f| match f.name {
+                Some(ref name) => (get_name_hash(name, self.hash_key), f),
+                None => (f.hash, f),
+            })
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+        // Names, each padded to 4 bytes
+        let mut names = Vec::new();
+        let mut name_offsets = Vec::with_capacity(files.len());
+        for &(_, file) in &files {
+            match file.name {
+                Some(ref name) => {
+                    name_offsets.push(Some(names.len() as u32 / 4));
+                    names.extend_from_slice(name.as_bytes());
+                    names.push(0);
+                    while names.len() % 4 != 0 {
+                        names.push(0);
+                    }
+                }
+                None => name_offsets.push(None),
+            }
+        }
+
+        // Data, offsets count from the start of the data section
+        let mut data_alignment = max(1, alignment);
+        let mut data_ranges = Vec::with_capacity(files.len());
+        let mut data_length = 0u32;
+        for &(_, file) in &files {
+            let file_alignment = get_file_alignment(&file.data, alignment);
+            data_alignment = max(data_alignment, file_alignment);
+            data_length = align(data_length, file_alignment);
+            data_ranges.push((data_length, data_length + file.data.len() as u32));
+            data_length += file.data.len() as u32;
+        }
+        let tables_length = HEADER_LENGTH
+            + SFAT_HEADER_LENGTH
+            + SFAT_NODE_LENGTH * files.len() as u32
+            + SFNT_HEADER_LENGTH
+            + names.len() as u32;
+        let data_offset = align(tables_length, data_alignment);
+
+        // Header
+        writer.write_all(b"SARC")?;
+        write_u16(writer, HEADER_LENGTH as u16, big_endian)?;
+        write_u16(writer, 0xFEFF, big_endian)?;
+        write_u32(writer, data_offset + data_length, big_endian)?;
+        write_u32(writer, data_offset, big_endian)?;
+        write_u16(writer, 0x0100, big_endian)?;
+        write_u16(writer, 0, big_endian)?;
+        // SFAT
+        writer.write_all(b"SFAT")?;
+        write_u16(writer, SFAT_HEADER_LENGTH as u16, big_endian)?;
+        write_u16(writer, files.len() as u16, big_endian)?;
+        write_u32(writer, self.hash_key, big_endian)?;
+        // With a name, the top byte counts the names that share its hash so far, starting at 1
+        let mut collisions = 0;
+        for (i, &(hash, _)) in files.iter().enumerate() {
+            write_u32(writer, hash, big_endian)?;
+            if i == 0 || files[i - 1].0 != hash {
+                collisions = 0;
+            }
+            let attributes = match name_offsets[i] {
+                Some(offset) => {
+                    collisions += 1;
+                    (collisions << 24) | offset
+                }
+                None => 0,
+            };
+            write_u32(writer, attributes, big_endian)?;
+            write_u32(writer, data_ranges[i].0, big_endian)?;
+            write_u32(writer, data_ranges[i].1, big_endian)?;
+        }
+        // SFNT
+        writer.write_all(b"SFNT")?;
+        write_u16(writer, SFNT_HEADER_LENGTH as u16, big_endian)?;
+        write_u16(writer, 0, big_endian)?;
+        writer.write_all(&names)?;
+        // Data
+        let mut position = tables_length;
+        for (i, &(_, file)) in files.iter().enumerate() {
+            let start = data_offset + data_ranges[i].0;
+            writer.write_all(&vec![0u8; (start - position) as usize])?;
+            writer.write_all(&file.data)?;
+            position = start + file.data.len() as u32;
+        }
+        Ok(())
+    }
+}
+
+pub fn get_name_hash(name: &str, key: u32) -> u32 {
+    name.bytes().fold(0u32, |hash, c| {
+        // Characters are signed in the original implementation
+        hash.wrapping_mul(key).wrapping_add(c as i8 as i32 as u32)
+    })
+}
+
+// Some formats are read in place and need more than the default
+fn get_file_alignment(data: &[u8], alignment: u32) -> u32 {
+    let alignment = max(1, alignment);
+    if data.len() >= 0x14 && data[..4] == [b'F', b'R', b'E', b'S'] {
+        let file_alignment = to_u32([data[0x10], data[0x11], data[0x12], data[0x13]], true);
+        if file_alignment.is_power_of_two() {
+            return max(alignment, file_alignment);
+        }
+    } else if data.len() >= 4 && data[..4] == [b'G', b'f', b'x', b'2'] {
+        return max(alignment, 0x2000);
+    }
+    alignment
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+fn to_u16(bytes: [u8; 2], big_endian: bool) -> u16 {
+    if big_endian {
+        (u16::from(bytes[0]) << 8) | u16::from(bytes[1])
+    } else {
+        (u16::from(bytes[1]) << 8) | u16::from(bytes[0])
+    }
+}
+
+fn to_u32(bytes: [u8; 4], big_endian: bool) -> u32 {
+    let (high, low) = if big_endian {
+        ([bytes[0], bytes[1]], [bytes[2], bytes[3]])
+    } else {
+        ([bytes[3], bytes[2]], [bytes[1], bytes[0]])
+    };
+    (u32::from(to_u16(high, true)) << 16) | u32::from(to_u16(low, true))
+}
+
+fn read_u16<R: Read>(reader: &mut R, big_endian: bool) -> Result<u16, Box<Error>> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(to_u16(bytes, big_endian))
+}
+
+fn read_u32<R: Read>(reader: &mut R, big_endian: bool) -> Result<u32, Box<Error>> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(to_u32(bytes, big_endian))
+}
+
+fn write_u16<W: Write>(writer: &mut W, value: u16, big_endian: bool) -> Result<(), Box<Error>> {
+    let bytes = [(value >> 8) as u8, value as u8];
+    if big_endian {
+        writer.write_all(&bytes)?;
+    } else {
+        writer.write_all(&[bytes[1], bytes[0]])?;
+    }
+    Ok(())
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32, big_endian: bool) -> Result<(), Box<Error>> {
+    let bytes = [
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ];
+    if big_endian {
+        writer.write_all(&bytes)?;
+    } else {
+        writer.write_all(&[bytes[3], bytes[2], bytes[1], bytes[0]])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Both names hash to 0x80318F2B with the default key
+    const COLLIDING_NAMES: [&str; 2] = ["itpdcnfu.txt", "ntrintqi.txt"];
+
+    fn write(sarc: &SARC, alignment: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        sarc.write(&mut out, alignment).unwrap();
+        out
+    }
+
+    fn get_attributes(data: &[u8], big_endian: bool) -> Vec<u32> {
+        let nb_nodes = to_u16([data[0x1A], data[0x1B]], big_endian) as usize;
+        (0..nb_nodes)
+            .map(|i| {
+                let position = (HEADER_LENGTH + SFAT_HEADER_LENGTH) as usize + i * 0x10 + 4;
+                let bytes = [
+                    data[position],
+                    data[position + 1],
+                    data[position + 2],
+                    data[position + 3],
+                ];
+                to_u32(bytes, big_endian)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn name_hash() {
+        assert_eq!(
+            get_name_hash("Model/foo.bfres", DEFAULT_HASH_KEY),
+            0xEBCE_6370
+        );
+        for name in &COLLIDING_NAMES {
+            assert_eq!(get_name_hash(name, DEFAULT_HASH_KEY), 0x8031_8F2B);
+        }
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        for &big_endian in &[true, false] {
+            let mut sarc = SARC::new(big_endian);
+            sarc.add_file("b.txt", b"hello".to_vec());
+            sarc.add_file("a/c.bin", vec![1, 2, 3, 4, 5, 6, 7]);
+            let mut fres = vec![0u8; 0x40];
+            fres[..4].copy_from_slice(b"FRES");
+            fres[0x10..0x14].copy_from_slice(&[0, 0, 0x20, 0]);
+            sarc.add_file("x.bfres", fres.clone());
+            sarc.add_file("b.txt", b"hello again".to_vec());
+            sarc.files.push(SARCFile {
+                name: None,
+                hash: 0x1234,
+                data: vec![9; 3],
+            });
+            let out = write(&sarc, 4);
+
+            let read = SARC::import(&mut Cursor::new(&out)).unwrap();
+            assert_eq!(read.big_endian, big_endian);
+            assert_eq!(read.files.len(), 4);
+            assert_eq!(read.get_file("b.txt").unwrap().data, b"hello again");
+            assert_eq!(
+                read.get_file("a/c.bin").unwrap().data,
+                [1, 2, 3, 4, 5, 6, 7]
+            );
+            assert_eq!(read.get_file("x.bfres").unwrap().data, fres);
+            let unnamed = read.files.iter().find(|f| f.name.is_none()).unwrap();
+            assert_eq!((unnamed.hash, &unnamed.data[..]), (0x1234, &[9, 9, 9][..]));
+            // Sorted by hash, and the BFRES aligned like it asks
+            assert!(read.files.windows(2).all(|f| f[0].hash <= f[1].hash));
+            let fres_position = out.windows(4).position(|w| w == b"FRES").unwrap();
+            assert_eq!(fres_position % 0x2000, 0);
+            assert_eq!(write(&read, 4), out);
+
+            let compressed = yaz0::compress(&out, yaz0::MAX_WINDOW, 3);
+            let read = SARC::from_reader(&mut Cursor::new(compressed)).unwrap();
+            assert_eq!(read.files.len(), 4);
+        }
+    }
+
+    #[test]
+    fn colliding_names() {
+        let mut sarc = SARC::new(true);
+        sarc.add_file(COLLIDING_NAMES[1], b"second".to_vec());
+        sarc.add_file("b.txt", b"other".to_vec());
+        sarc.add_file(COLLIDING_NAMES[0], b"first".to_vec());
+        let out = write(&sarc, 4);
+        // b.txt hashes to 0x62BD5098, before the colliding pair
+        let attributes = get_attributes(&out, true);
+        assert_eq!(
+            attributes.iter().map(|a| a >> 24).collect::<Vec<_>>(),
+            [1, 1, 2]
+        );
+
+        let read = SARC::import(&mut Cursor::new(&out)).unwrap();
+        assert_eq!(read.get_file(COLLIDING_NAMES[0]).unwrap().data, b"first");
+        assert_eq!(read.get_file(COLLIDING_NAMES[1]).unwrap().data, b"second");
+        assert_eq!(read.get_file("b.txt").unwrap().data, b"other");
+        assert_eq!(write(&read, 4), out);
+    }
+
+    #[test]
+    fn missing_file() {
+        let sarc = SARC::new(false);
+        assert!(sarc.open_fres("nothing.bfres").is_err());
+        assert!(SARC::import(&mut Cursor::new(b"SARC\0\x14\xAB\xCD")).is_err());
+    }
+}
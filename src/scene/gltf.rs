@@ -0,0 +1,372 @@
+use error::{GltfBufferInvalid, UnsupportedPrimitive};
+use gltf::buffer::Source as BufferSource;
+use gltf::image::Source;
+use gltf::mesh::Mode;
+use gltf::{Document, Gltf, Node};
+use scene::{
+    decompose, determinant, invert, multiply, transform_normal, transform_point, Bone, Material,
+    Matrix, Mesh, Scene, IDENTITY,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+struct Loader<'a> {
+    buffers: &'a [Vec<u8>],
+    scene: Scene,
+    // Node index to bone index, and where each bone is
+    bones: HashMap<usize, usize>,
+    bone_world_matrices: Vec<Matrix>,
+    joints: HashSet<usize>,
+    inverse_bind_matrices: HashMap<usize, Matrix>,
+    default_material: Option<usize>,
+}
+
+// Every mesh of the default scene. Nodes used as joints by a skin become bones, the other
+// transforms are applied to the vertices.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, Box<Error>> {
+    let path = path.as_ref();
+    let Gltf { document, blob } = Gltf::from_reader(BufReader::new(File::open(path)?))?;
+    let buffers = read_buffers(&document, path, blob)?;
+    let mut loader = Loader {
+        buffers: &buffers,
+        scene: Scene {
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            bones: Vec::new(),
+        },
+        bones: HashMap::new(),
+        bone_world_matrices: Vec::new(),
+        joints: HashSet::new(),
+        inverse_bind_matrices: HashMap::new(),
+        default_material: None,
+    };
+    loader.load_materials(&document);
+    loader.load_joints(&document);
+    let roots: Vec<Node> = match document.default_scene() {
+        Some(s) => s.nodes().collect(),
+        None => match document.scenes().next() {
+            Some(s) => s.nodes().collect(),
+            None => Vec::new(),
+        },
+    };
+    // Bones first so skins can refer to any of them
+    for node in &roots {
+        loader.load_bones(node, &IDENTITY, None);
+    }
+    for node in &roots {
+        loader.load_meshes(node, &IDENTITY)?;
+    }
+    Ok(loader.scene)
+}
+
+impl<'a> Loader<'a> {
+    fn load_materials(&mut self, document: &Document) {
+        for (index, material) in document.materials().enumerate() {
+            let name = match material.name() {
+                Some(n) => n.to_string(),
+                None => format!("Material{}", index),
+            };
+            // Named after the image, like textures in a BFRES
+            let texture_name = material
+                .pbr_metallic_roughness()
+                .base_color_texture()
+                .map(|info| {
+                    let image = info.texture().source();
+                    let from_uri = match image.source() {
+                        Source::Uri { uri, .. } => Path::new(uri)
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().into_owned()),
+                        Source::View { .. } => None,
+                    };
+                    match (from_uri, image.name()) {
+                        (Some(n), _) => n,
+                        (None, Some(n)) => n.to_string(),
+                        (None, None) => format!("Texture{}", image.index()),
+                    }
+                });
+            self.scene.materials.push(Material { name, texture_name });
+        }
+    }
+    fn load_joints(&mut self, document: &Document) {
+        let buffers = self.buffers;
+        for skin in document.skins() {
+            self.joints.extend(skin.joints().map(|j| j.index()));
+            let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+            if let Some(matrices) = reader.read_inverse_bind_matrices() {
+                for (joint, matrix) in skin.joints().zip(matrices) {
+                    self.inverse_bind_matrices.insert(joint.index(), matrix);
+                }
+            }
+        }
+    }
+    fn load_bones(&mut self, node: &Node, parent_world: &Matrix, parent_bone: Option<usize>) {
+        let world = multiply(parent_world, &node.transform().matrix());
+        let mut bone = parent_bone;
+        if self.joints.contains(&node.index()) {
+            // Nodes between two bones end up in the transform of the lower one
+            let local = match parent_bone {
+                Some(p) => multiply(&invert(&self.bone_world_matrices[p]), &world),
+                None => world,
+            };
+            let (scale, rotation, translation) = decompose(&local);
+            let name = match node.name() {
+                Some(n) => n.to_string(),
+                None => format!("Bone{}", node.index()),
+            };
+            self.scene.bones.push(Bone {
+                name,
+                parent_index: parent_bone,
+                scale,
+                rotation,
+                translation,
+                inverse_bind_matrix: self.inverse_bind_matrices.get(&node.index()).cloned(),
+            });
+            self.bone_world_matrices.push(world);
+            bone = Some(self.scene.bones.len() - 1);
+            self.bones.insert(node.index(), self.scene.bones.len() - 1);
+        }
+        for child in node.children() {
+            self.load_bones(&child, &world, bone);
+        }
+    }
+    fn load_meshes(&mut self, node: &Node, parent_world: &Matrix) -> Result<(), Box<Error>> {
+        let world = multiply(parent_world, &node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            let base_name = match (node.name(), mesh.name()) {
+                (Some(n), _) | (None, Some(n)) => n.to_string(),
+                (None, None) => format!("Mesh{}", mesh.index()),
+            };
+            let nb_primitives = mesh.primitives().len();
+            // Joint numbers in this skin to bone indices
+            let joints: Option<Vec<u16>> = node.skin().map(|skin| {
+                skin.joints()
+                    .map(|j| self.bones.get(&j.index()).cloned().unwrap_or(0) as u16)
+                    .collect()
+            });
+            for (index, primitive) in mesh.primitives().enumerate() {
+                let name = if nb_primitives == 1 {
+                    base_name.clone()
+                } else {
+                    format!("{}_{}", base_name, index)
+                };
+                let material_index = match primitive.material().index() {
+                    Some(i) => i,
+                    None => self.get_default_material(),
+                };
+                let buffers = self.buffers;
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+                let mut positions: Vec<[f32; 3]> = match reader.read_positions() {
+                    Some(p) => p.collect(),
+                    None => continue,
+                };
+                let mut normals: Vec<[f32; 3]> = match reader.read_normals() {
+                    Some(n) => n.collect(),
+                    None => Vec::new(),
+                };
+                let mut texture_coordinates = Vec::new();
+                while let Some(t) = reader.read_tex_coords(texture_coordinates.len() as u32) {
+                    texture_coordinates.push(t.into_f32().collect());
+                }
+                let colors: Vec<[f32; 4]> = match reader.read_colors(0) {
+                    Some(c) => c.into_rgba_f32().collect(),
+                    None => Vec::new(),
+                };
+                let mut bone_indices = Vec::new();
+                let mut bone_weights = Vec::new();
+                if let (Some(ref joints), Some(read_joints), Some(read_weights)) =
+                    (&joints, reader.read_joints(0), reader.read_weights(0))
+                {
+                    for (j, w) in read_joints.into_u16().zip(read_weights.into_f32()) {
+                        let (indices, weights) = sort_influences(joints, j, w);
+                        bone_indices.push(indices);
+                        bone_weights.push(weights);
+                    }
+                }
+                let vertex_count = positions.len() as u32;
+                let raw_indices: Vec<u32> = match reader.read_indices() {
+                    Some(i) => i.into_u32().collect(),
+                    None => (0..vertex_count).collect(),
+                };
+                let mut indices = to_triangle_list(primitive.mode(), &raw_indices)?;
+                // Skinned meshes are already in model space, the others get moved there
+                if joints.is_none() {
+                    for p in &mut positions {
+                        *p = transform_point(&world, p);
+                    }
+                    for n in &mut normals {
+                        *n = transform_normal(&world, n);
+                    }
+                    if determinant(&world) < 0f32 {
+                        for triangle in indices.chunks_mut(3) {
+                            triangle.swap(1, 2);
+                        }
+                    }
+                }
+                self.scene.meshes.push(Mesh {
+                    name,
+                    material_index,
+                    positions,
+                    normals,
+                    texture_coordinates,
+                    colors,
+                    bone_indices,
+                    bone_weights,
+                    indices,
+                });
+            }
+        }
+        for child in node.children() {
+            self.load_meshes(&child, &world)?;
+        }
+        Ok(())
+    }
+    fn get_default_material(&mut self) -> usize {
+        if let Some(index) = self.default_material {
+            return index;
+        }
+        self.scene.materials.push(Material {
+            name: "default".to_string(),
+            texture_name: None,
+        });
+        self.default_material = Some(self.scene.materials.len() - 1);
+        self.scene.materials.len() - 1
+    }
+}
+
+// Images are not needed, only the buffers get loaded
+fn read_buffers(
+    document: &Document,
+    path: &Path,
+    mut blob: Option<Vec<u8>>,
+) -> Result<Vec<Vec<u8>>, Box<Error>> {
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            BufferSource::Bin => blob.take(),
+            BufferSource::Uri(uri) if uri.starts_with("data:") => match uri.find(";base64,") {
+                Some(i) => decode_base64(&uri[i + 8..]),
+                None => None,
+            },
+            BufferSource::Uri(uri) => {
+                let mut data = Vec::new();
+                File::open(path.with_file_name(uri))?.read_to_end(&mut data)?;
+                Some(data)
+            }
+        };
+        match data {
+            Some(d) if d.len() >= buffer.length() => buffers.push(d),
+            _ => {
+                return Err(Box::new(GltfBufferInvalid {
+                    index: buffer.index(),
+                }))
+            }
+        }
+    }
+    Ok(buffers)
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut nb_bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(value);
+        nb_bits += 6;
+        if nb_bits >= 8 {
+            nb_bits -= 8;
+            out.push((bits >> nb_bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// Biggest weights first, normalized so they add up to 1
+fn sort_influences(joints: &[u16], indices: [u16; 4], weights: [f32; 4]) -> ([u16; 4], [f32; 4]) {
+    let mut influences: Vec<(u16, f32)> = indices
+        .iter()
+        .zip(weights.iter())
+        .filter(|&(_, &w)| w > 0f32)
+        .map(|(&i, &w)| (joints.get(usize::from(i)).cloned().unwrap_or(0), w))
+        .collect();
+    influences.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(::std::cmp::Ordering::Equal));
+    let total: f32 = influences.iter().map(|i| i.1).sum();
+    let mut out_indices = [0u16; 4];
+    let mut out_weights = [0f32; 4];
+    for (i, &(index, weight)) in influences.iter().enumerate() {
+        out_indices[i] = index;
+        out_weights[i] = if total > 0f32 { weight / total } else { 0f32 };
+    }
+    (out_indices, out_weights)
+}
+
+fn to_triangle_list(mode: Mode, indices: &[u32]) -> Result<Vec<u32>, Box<Error>> {
+    let mut out = Vec::new();
+    match mode {
+        Mode::Triangles => out.extend_from_slice(&indices[..indices.len() / 3 * 3]),
+        Mode::TriangleStrip => {
+            for i in 0..indices.len().saturating_sub(2) {
+                // Every other triangle is flipped to keep the same winding
+                if i % 2 == 0 {
+                    out.extend_from_slice(&[indices[i], indices[i + 1], indices[i + 2]]);
+                } else {
+                    out.extend_from_slice(&[indices[i + 1], indices[i], indices[i + 2]]);
+                }
+            }
+        }
+        Mode::TriangleFan => {
+            for i in 1..indices.len().saturating_sub(1) {
+                out.extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+            }
+        }
+        m => {
+            return Err(Box::new(UnsupportedPrimitive {
+                mode: format!("{:?}", m),
+            }))
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        // From RFC 4648
+        let vectors = [
+            ("", ""),
+            ("Zg==", "f"),
+            ("Zm8=", "fo"),
+            ("Zm9v", "foo"),
+            ("Zm9vYg==", "foob"),
+            ("Zm9vYmE=", "fooba"),
+            ("Zm9vYmFy", "foobar"),
+        ];
+        for &(encoded, decoded) in &vectors {
+            assert_eq!(decode_base64(encoded).unwrap(), decoded.as_bytes());
+        }
+        // Padding is optional, and both alphabets work
+        assert_eq!(decode_base64("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(decode_base64("+/8=").unwrap(), [0xFB, 0xFF]);
+        assert_eq!(decode_base64("-_8").unwrap(), [0xFB, 0xFF]);
+        assert_eq!(
+            decode_base64("AAECAwQFBgc=").unwrap(),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert!(decode_base64("Zm9v YmFy").is_none());
+        assert!(decode_base64("Zm9v*").is_none());
+    }
+}
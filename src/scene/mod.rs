@@ -0,0 +1,264 @@
+pub mod gltf;
+pub mod obj;
+
+use std::error::Error;
+use std::path::Path;
+
+// Geometry from other formats, before it gets turned into a model
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub bones: Vec<Bone>,
+}
+
+// Triangles with every attribute per vertex, the ones a mesh does not have are left empty
+pub struct Mesh {
+    pub name: String,
+    pub material_index: usize,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub texture_coordinates: Vec<Vec<[f32; 2]>>,
+    pub colors: Vec<[f32; 4]>,
+    // Indices in the scene bones, the biggest weights first and unused ones at 0
+    pub bone_indices: Vec<[u16; 4]>,
+    pub bone_weights: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+pub struct Material {
+    pub name: String,
+    pub texture_name: Option<String>,
+}
+
+// Parents always come before their children
+pub struct Bone {
+    pub name: String,
+    pub parent_index: Option<usize>,
+    pub scale: [f32; 3],
+    pub rotation: [f32; 4],
+    pub translation: [f32; 3],
+    // From model space to the space of the bone, computed from the transforms when not given
+    pub inverse_bind_matrix: Option<Matrix>,
+}
+
+// Column major, like glTF
+pub type Matrix = [[f32; 4]; 4];
+
+pub const IDENTITY: Matrix = [
+    [1f32, 0f32, 0f32, 0f32],
+    [0f32, 1f32, 0f32, 0f32],
+    [0f32, 0f32, 1f32, 0f32],
+    [0f32, 0f32, 0f32, 1f32],
+];
+
+impl Scene {
+    // Wavefront OBJ when the extension says so, glTF otherwise
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Scene, Box<Error>> {
+        let is_obj = match path.as_ref().extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase() == "obj",
+            None => false,
+        };
+        if is_obj {
+            obj::load(path)
+        } else {
+            gltf::load(path)
+        }
+    }
+    // Where each bone is in model space
+    pub fn get_bone_world_matrices(&self) -> Vec<Matrix> {
+        let mut matrices: Vec<Matrix> = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let local = compose(bone.scale, bone.rotation, bone.translation);
+            let world = match bone.parent_index {
+                Some(parent) => multiply(&matrices[parent], &local),
+                None => local,
+            };
+            matrices.push(world);
+        }
+        matrices
+    }
+}
+
+impl Bone {
+    pub fn new(name: &str) -> Bone {
+        Bone {
+            name: name.to_string(),
+            parent_index: None,
+            scale: [1f32; 3],
+            rotation: [0f32, 0f32, 0f32, 1f32],
+            translation: [0f32; 3],
+            inverse_bind_matrix: None,
+        }
+    }
+}
+
+// Rotation is a quaternion, X Y Z then W
+pub fn compose(scale: [f32; 3], rotation: [f32; 4], translation: [f32; 3]) -> Matrix {
+    let [x, y, z, w] = rotation;
+    let rotation_columns = [
+        [
+            1f32 - 2f32 * (y * y + z * z),
+            2f32 * (x * y + z * w),
+            2f32 * (x * z - y * w),
+        ],
+        [
+            2f32 * (x * y - z * w),
+            1f32 - 2f32 * (x * x + z * z),
+            2f32 * (y * z + x * w),
+        ],
+        [
+            2f32 * (x * z + y * w),
+            2f32 * (y * z - x * w),
+            1f32 - 2f32 * (x * x + y * y),
+        ],
+    ];
+    let mut matrix = IDENTITY;
+    for column in 0..3 {
+        for row in 0..3 {
+            matrix[column][row] = rotation_columns[column][row] * scale[column];
+        }
+    }
+    matrix[3][..3].clone_from_slice(&translation);
+    matrix
+}
+
+// The inverse of compose, for matrices without shearing
+pub fn decompose(matrix: &Matrix) -> ([f32; 3], [f32; 4], [f32; 3]) {
+    let translation = [matrix[3][0], matrix[3][1], matrix[3][2]];
+    let mut scale = [0f32; 3];
+    for (column, s) in scale.iter_mut().enumerate() {
+        *s = length(&[matrix[column][0], matrix[column][1], matrix[column][2]]);
+    }
+    if determinant(matrix) < 0f32 {
+        scale[0] = -scale[0];
+    }
+    let mut r = [[0f32; 3]; 3];
+    for column in 0..3 {
+        for row in 0..3 {
+            r[column][row] = if scale[column] == 0f32 {
+                0f32
+            } else {
+                matrix[column][row] / scale[column]
+            };
+        }
+    }
+    // r[column][row]
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    let rotation = if trace > 0f32 {
+        let s = (trace + 1f32).sqrt() * 2f32;
+        [
+            (r[1][2] - r[2][1]) / s,
+            (r[2][0] - r[0][2]) / s,
+            (r[0][1] - r[1][0]) / s,
+            s / 4f32,
+        ]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let s = (1f32 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2f32;
+        [
+            s / 4f32,
+            (r[1][0] + r[0][1]) / s,
+            (r[2][0] + r[0][2]) / s,
+            (r[1][2] - r[2][1]) / s,
+        ]
+    } else if r[1][1] > r[2][2] {
+        let s = (1f32 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2f32;
+        [
+            (r[1][0] + r[0][1]) / s,
+            s / 4f32,
+            (r[2][1] + r[1][2]) / s,
+            (r[2][0] - r[0][2]) / s,
+        ]
+    } else {
+        let s = (1f32 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2f32;
+        [
+            (r[2][0] + r[0][2]) / s,
+            (r[2][1] + r[1][2]) / s,
+            s / 4f32,
+            (r[0][1] - r[1][0]) / s,
+        ]
+    };
+    (scale, rotation, translation)
+}
+
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0f32; 4]; 4];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[column][row] = (0..4).map(|i| a[i][row] * b[column][i]).sum();
+        }
+    }
+    out
+}
+
+// Only for transforms, the last row is taken as 0 0 0 1
+pub fn invert(matrix: &Matrix) -> Matrix {
+    let m = matrix;
+    let cofactors = [
+        [
+            m[1][1] * m[2][2] - m[2][1] * m[1][2],
+            m[2][1] * m[0][2] - m[0][1] * m[2][2],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+        ],
+        [
+            m[2][0] * m[1][2] - m[1][0] * m[2][2],
+            m[0][0] * m[2][2] - m[2][0] * m[0][2],
+            m[1][0] * m[0][2] - m[0][0] * m[1][2],
+        ],
+        [
+            m[1][0] * m[2][1] - m[2][0] * m[1][1],
+            m[2][0] * m[0][1] - m[0][0] * m[2][1],
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+        ],
+    ];
+    let det = determinant(m);
+    let inverse_det = if det == 0f32 { 0f32 } else { 1f32 / det };
+    let mut out = IDENTITY;
+    for column in 0..3 {
+        for row in 0..3 {
+            out[column][row] = cofactors[column][row] * inverse_det;
+        }
+    }
+    let rotation = out;
+    for (row, value) in out[3].iter_mut().take(3).enumerate() {
+        *value = -(0..3).map(|i| rotation[i][row] * m[3][i]).sum::<f32>();
+    }
+    out
+}
+
+pub fn determinant(m: &Matrix) -> f32 {
+    m[0][0] * (m[1][1] * m[2][2] - m[2][1] * m[1][2])
+        - m[1][0] * (m[0][1] * m[2][2] - m[2][1] * m[0][2])
+        + m[2][0] * (m[0][1] * m[1][2] - m[1][1] * m[0][2])
+}
+
+pub fn transform_point(matrix: &Matrix, point: &[f32; 3]) -> [f32; 3] {
+    let mut out = [0f32; 3];
+    for (row, o) in out.iter_mut().enumerate() {
+        *o = matrix[0][row] * point[0]
+            + matrix[1][row] * point[1]
+            + matrix[2][row] * point[2]
+            + matrix[3][row];
+    }
+    out
+}
+
+// Normals go through the inverse transpose so they stay perpendicular with non uniform scales
+pub fn transform_normal(matrix: &Matrix, normal: &[f32; 3]) -> [f32; 3] {
+    let inverse = invert(matrix);
+    let mut out = [0f32; 3];
+    for (row, o) in out.iter_mut().enumerate() {
+        *o =
+            inverse[row][0] * normal[0] + inverse[row][1] * normal[1] + inverse[row][2] * normal[2];
+    }
+    let l = length(&out);
+    if l > 0f32 {
+        for o in &mut out {
+            *o /= l;
+        }
+    }
+    out
+}
+
+fn length(v: &[f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
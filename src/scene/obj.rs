@@ -0,0 +1,293 @@
+use error::ObjDataInvalid;
+use scene::{Material, Mesh, Scene};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Position, texture coordinate and normal indices, starting at 0
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+    mesh: Mesh,
+    vertices: HashMap<VertexKey, u32>,
+    texture_coordinates: Vec<[f32; 2]>,
+    has_texture_coordinates: bool,
+    has_normals: bool,
+}
+
+struct Loader {
+    positions: Vec<[f32; 3]>,
+    texture_coordinates: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    materials: Vec<Material>,
+    object_name: String,
+    material_index: Option<usize>,
+    // One per object and material, in the order they first show up
+    meshes: Vec<(String, usize, MeshBuilder)>,
+}
+
+// One mesh per object and material, OBJ files have no bones
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, Box<Error>> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path)?);
+    let object_name = match path.file_stem() {
+        Some(s) => s.to_string_lossy().into_owned(),
+        None => "model".to_string(),
+    };
+    let mut loader = Loader {
+        positions: Vec::new(),
+        texture_coordinates: Vec::new(),
+        normals: Vec::new(),
+        materials: Vec::new(),
+        object_name,
+        material_index: None,
+        meshes: Vec::new(),
+    };
+    for (number, line) in file.lines().enumerate() {
+        let line = line?;
+        if loader.read_line(&line, path).is_none() {
+            return Err(Box::new(ObjDataInvalid { line: number + 1 }));
+        }
+    }
+    Ok(loader.into_scene())
+}
+
+fn parse_floats(words: &[&str], count: usize) -> Option<Vec<f32>> {
+    if words.len() < count {
+        return None;
+    }
+    words[..count].iter().map(|w| w.parse().ok()).collect()
+}
+
+// Negative indices count back from the last element read
+fn parse_index(word: &str, count: usize) -> Option<Option<usize>> {
+    if word.is_empty() {
+        return Some(None);
+    }
+    let index: i64 = word.parse().ok()?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        None
+    } else {
+        Some(Some(resolved as usize))
+    }
+}
+
+impl Loader {
+    // None when the line is wrong
+    fn read_line(&mut self, line: &str, path: &Path) -> Option<()> {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            return Some(());
+        }
+        let arguments = &words[1..];
+        match words[0] {
+            "v" => {
+                let v = parse_floats(arguments, 3)?;
+                self.positions.push([v[0], v[1], v[2]]);
+            }
+            // Images start at the top in a BFRES
+            "vt" => {
+                let v = parse_floats(arguments, 2)?;
+                self.texture_coordinates.push([v[0], 1f32 - v[1]]);
+            }
+            "vn" => {
+                let v = parse_floats(arguments, 3)?;
+                self.normals.push([v[0], v[1], v[2]]);
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return None;
+                }
+                let mut keys = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    keys.push(self.parse_vertex(argument)?);
+                }
+                self.add_face(&keys);
+            }
+            "o" | "g" if !arguments.is_empty() => self.object_name = arguments.join(" "),
+            "usemtl" => {
+                let name = arguments.join(" ");
+                self.material_index = Some(self.get_material(&name));
+            }
+            "mtllib" => {
+                let library = path.with_file_name(arguments.join(" "));
+                // Materials still work by name without their library
+                if let Ok(file) = File::open(library) {
+                    self.read_material_library(BufReader::new(file));
+                }
+            }
+            _ => {}
+        }
+        Some(())
+    }
+    fn parse_vertex(&self, word: &str) -> Option<VertexKey> {
+        let mut parts = word.split('/');
+        let position = parse_index(parts.next()?, self.positions.len())??;
+        let texture_coordinate = match parts.next() {
+            Some(p) => parse_index(p, self.texture_coordinates.len())?,
+            None => None,
+        };
+        let normal = match parts.next() {
+            Some(p) => parse_index(p, self.normals.len())?,
+            None => None,
+        };
+        Some((position, texture_coordinate, normal))
+    }
+    fn read_material_library<R: BufRead>(&mut self, reader: R) {
+        let mut current = None;
+        for line in reader.lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.len() < 2 {
+                continue;
+            }
+            match words[0] {
+                "newmtl" => current = Some(self.get_material(&words[1..].join(" "))),
+                "map_Kd" => {
+                    if let Some(index) = current {
+                        // Options come before the file name
+                        let file = words[words.len() - 1];
+                        self.materials[index].texture_name = Path::new(file)
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().into_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    fn get_material(&mut self, name: &str) -> usize {
+        match self.materials.iter().position(|m| m.name == name) {
+            Some(i) => i,
+            None => {
+                self.materials.push(Material {
+                    name: name.to_string(),
+                    texture_name: None,
+                });
+                self.materials.len() - 1
+            }
+        }
+    }
+    fn add_face(&mut self, keys: &[VertexKey]) {
+        let material_index = match self.material_index {
+            Some(i) => i,
+            None => {
+                let i = self.get_material("default");
+                self.material_index = Some(i);
+                i
+            }
+        };
+        let mesh_index = match self
+            .meshes
+            .iter()
+            .position(|m| m.0 == self.object_name && m.1 == material_index)
+        {
+            Some(i) => i,
+            None => {
+                self.meshes.push((
+                    self.object_name.clone(),
+                    material_index,
+                    MeshBuilder {
+                        mesh: Mesh {
+                            name: String::new(),
+                            material_index,
+                            positions: Vec::new(),
+                            normals: Vec::new(),
+                            texture_coordinates: Vec::new(),
+                            colors: Vec::new(),
+                            bone_indices: Vec::new(),
+                            bone_weights: Vec::new(),
+                            indices: Vec::new(),
+                        },
+                        vertices: HashMap::new(),
+                        texture_coordinates: Vec::new(),
+                        has_texture_coordinates: true,
+                        has_normals: true,
+                    },
+                ));
+                self.meshes.len() - 1
+            }
+        };
+        let mut indices = Vec::with_capacity(keys.len());
+        for key in keys {
+            let index = {
+                let builder = &mut self.meshes[mesh_index].2;
+                match builder.vertices.get(key) {
+                    Some(&i) => i,
+                    None => {
+                        let i = builder.mesh.positions.len() as u32;
+                        builder.mesh.positions.push(self.positions[key.0]);
+                        match key.1 {
+                            Some(t) => builder
+                                .texture_coordinates
+                                .push(self.texture_coordinates[t]),
+                            None => {
+                                builder.has_texture_coordinates = false;
+                                builder.texture_coordinates.push([0f32; 2])
+                            }
+                        }
+                        match key.2 {
+                            Some(n) => builder.mesh.normals.push(self.normals[n]),
+                            None => {
+                                builder.has_normals = false;
+                                builder.mesh.normals.push([0f32; 3])
+                            }
+                        }
+                        builder.vertices.insert(*key, i);
+                        i
+                    }
+                }
+            };
+            indices.push(index);
+        }
+        // Polygons are split as fans
+        let mesh = &mut self.meshes[mesh_index].2.mesh;
+        for i in 1..indices.len() - 1 {
+            mesh.indices
+                .extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+        }
+    }
+    // Meshes are named after their object, and their material when the object has several
+    fn into_scene(self) -> Scene {
+        let mut meshes = Vec::with_capacity(self.meshes.len());
+        let mut objects: HashMap<String, usize> = HashMap::new();
+        for (object_name, _, _) in &self.meshes {
+            *objects.entry(object_name.clone()).or_insert(0) += 1;
+        }
+        for (object_name, material_index, builder) in self.meshes {
+            let mut mesh = builder.mesh;
+            mesh.name = if objects[&object_name] > 1 {
+                format!("{}_{}", object_name, self.materials[material_index].name)
+            } else {
+                object_name
+            };
+            // Attributes only some faces have are dropped
+            if builder.has_texture_coordinates {
+                mesh.texture_coordinates.push(builder.texture_coordinates);
+            }
+            if !builder.has_normals {
+                mesh.normals.clear();
+            }
+            meshes.push(mesh);
+        }
+        Scene {
+            meshes,
+            materials: self.materials,
+            bones: Vec::new(),
+        }
+    }
+}
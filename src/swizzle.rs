@@ -1,66 +1,186 @@
-use fres::ftex::{format::Format, tile_mode::TileMode, FTEX};
-use std::cmp::{max, min};
+use fres::ftex::{format::Format, tile_mode::TileMode, Header};
+use gx2::surface::{
+    compute_mip_level_surface, compute_surface_bank_swapped_width, get_surface_level_size, Surface,
+    SurfaceLevel,
+};
+use gx2::{
+    MICRO_TILE_PIXELS, M_BANKS, M_BANKS_BIT_COUNT, M_PIPES, M_PIPES_BIT_COUNT,
+    M_PIPE_INTERLEAVE_BYTES_BIT_COUNT, M_SPLIT_SIZE,
+};
+use std::cmp::max;
 use std::error::Error;
 
-const M_BANKS: i64 = 4;
-const M_BANKS_BIT_COUNT: i64 = 2;
-const M_PIPES: i64 = 2;
-const M_PIPES_BIT_COUNT: i64 = 1;
-const M_PIPE_INTERLEAVE_BYTES: i64 = 256;
-const M_PIPE_INTERLEAVE_BYTES_BIT_COUNT: i64 = 8;
-const M_ROW_SIZE: i64 = 2048;
-const M_SWAP_SIZE: i64 = 256;
-const M_SPLIT_SIZE: i64 = 2048;
-// const M_CHIP_FAMILY: i64 = 2;
-const MICRO_TILE_PIXELS: i64 = 8 * 8;
-
-pub fn deswizzle(ftex: &FTEX, data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
-    let mut out = data.to_vec();
-
-    let dims = match ftex.header.texture_format.is_block_compressed() {
-        true => (
-            (i64::from(ftex.header.texture_width) + 3) / 4,
-            (i64::from(ftex.header.texture_height) + 3) / 4,
-        ),
-        false => (
-            i64::from(ftex.header.texture_width),
-            i64::from(ftex.header.texture_height),
+pub fn deswizzle(header: &Header, data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+    deswizzle_slice(header, 0, 0, data)
+}
+
+// Gives all the slices of the level, one after the other
+pub fn deswizzle_mip_level(
+    header: &Header,
+    level: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, Box<Error>> {
+    let surface = get_mip_level_surface(header, level);
+    let mut out = Vec::new();
+    for slice in 0..surface.nb_slices {
+        out.extend(deswizzle_slice(header, level, slice, data)?);
+    }
+    Ok(out)
+}
+
+pub fn deswizzle_slice(
+    header: &Header,
+    level: u32,
+    slice: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, Box<Error>> {
+    deswizzle_sample(header, level, slice, 0, data)
+}
+
+// Multisampled surfaces store every sample of a pixel, this only picks one of them
+pub fn deswizzle_sample(
+    header: &Header,
+    level: u32,
+    slice: u32,
+    sample: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, Box<Error>> {
+    // Only video textures use this, and the chroma plane is not tiled like the rest
+    if let Format::TNv12Unorm = header.texture_format {
+        return Ok(data.to_vec());
+    }
+    let (width, height) = get_mip_level_dimensions(header, level);
+    let surface = get_mip_level_surface(header, level);
+    Ok(deswizzle_surface(
+        &header.texture_format,
+        (width, height),
+        (i64::from(slice), i64::from(sample)),
+        &surface,
+        header.swizzle_value,
+        data,
+    ))
+}
+
+// The inverse of deswizzle, gives the base level as it is stored in the data of the FTEX
+pub fn swizzle(header: &Header, linear: &[u8]) -> Vec<u8> {
+    swizzle_mip_level(header, 0, linear)
+}
+
+// linear holds all the slices of the level one after the other, like deswizzle_mip_level gives
+// them. Multisampled surfaces get the same image in every sample.
+pub fn swizzle_mip_level(header: &Header, level: u32, linear: &[u8]) -> Vec<u8> {
+    if let Format::TNv12Unorm = header.texture_format {
+        return linear.to_vec();
+    }
+    let format = &header.texture_format;
+    let (width, height) = get_mip_level_dimensions(header, level);
+    let surface = get_mip_level_surface(header, level);
+    let slice_size = get_linear_slice_size(format, width, height);
+    let mut out = vec![0u8; surface.size as usize];
+    for (slice, data) in linear
+        .chunks(slice_size)
+        .take(surface.nb_slices as usize)
+        .enumerate()
+    {
+        for sample in 0..surface.nb_samples {
+            swizzle_surface(
+                format,
+                (width, height),
+                (slice as i64, sample),
+                &surface,
+                header.swizzle_value,
+                data,
+                &mut out,
+            );
+        }
+    }
+    out
+}
+
+pub fn get_mip_level_dimensions(header: &Header, level: u32) -> (u32, u32) {
+    (
+        max(1, header.texture_width >> level),
+        max(1, header.texture_height >> level),
+    )
+}
+
+pub fn get_mip_level_slice_count(header: &Header, level: u32) -> u32 {
+    Surface::from_header(header).get_mip_level_slice_count(level)
+}
+
+// Layout of a level of an existing texture, the base level keeps the pitch from the header
+pub fn get_mip_level_surface(header: &Header, level: u32) -> SurfaceLevel {
+    let mut surface = compute_mip_level_surface(&Surface::from_header(header), level);
+    if level == 0 && header.pitch != 0 {
+        surface.pitch = i64::from(header.pitch);
+        surface.size = get_surface_level_size(&header.texture_format, &surface);
+    }
+    surface
+}
+
+fn get_linear_slice_size(format: &Format, width: u32, height: u32) -> usize {
+    let dims = get_element_dimensions(format, width, height);
+    (dims.0 * dims.1 * (get_format_bits_per_pixel(format) / 8)) as usize
+}
+
+fn get_element_dimensions(format: &Format, width: u32, height: u32) -> (i64, i64) {
+    if format.is_block_compressed() {
+        ((i64::from(width) + 3) / 4, (i64::from(height) + 3) / 4)
+    } else {
+        (i64::from(width), i64::from(height))
+    }
+}
+
+fn compute_surface_address(
+    x: i64,
+    y: i64,
+    slice: i64,
+    sample: i64,
+    bits_pp: i64,
+    surface: &SurfaceLevel,
+    swizzle_value: u32,
+) -> i64 {
+    let pipe_swizzle = (i64::from(swizzle_value) >> 8) & 1;
+    let bank_swizzle = (i64::from(swizzle_value) >> 9) & 3;
+    match surface.tile_mode {
+        TileMode::Default | TileMode::LinearSpecial | TileMode::LinearAligned => {
+            compute_surface_address_linear(x, y, slice, bits_pp, surface.pitch, surface.height)
+        }
+        TileMode::OneDTiledThin1 | TileMode::OneDTiledThick => {
+            compute_surface_address_micro_tiled(x, y, slice, sample, bits_pp, surface)
+        }
+        _ => compute_surface_address_macro_tiled(
+            x,
+            y,
+            slice,
+            sample,
+            bits_pp,
+            surface,
+            (pipe_swizzle, bank_swizzle),
         ),
-    };
+    }
+}
 
-    let bits_pp = get_format_bits_per_pixel(&(ftex.header.texture_format));
+// Width and height are the ones of the mip level, slice and sample pick the image to read in it
+fn deswizzle_surface(
+    format: &Format,
+    (width, height): (u32, u32),
+    (slice, sample): (i64, i64),
+    surface: &SurfaceLevel,
+    swizzle_value: u32,
+    data: &[u8],
+) -> Vec<u8> {
+    let dims = get_element_dimensions(format, width, height);
+    let bits_pp = get_format_bits_per_pixel(format);
     let bytes_pp = bits_pp / 8;
-    let pipe_swizzle = (i64::from(ftex.header.swizzle_value) >> 8) & 1;
-    let bank_swizzle = (i64::from(ftex.header.swizzle_value) >> 9) & 3;
+
+    let mut out = vec![0u8; (dims.0 * dims.1 * bytes_pp) as usize];
 
     for y in 0..dims.1 {
         for x in 0..dims.0 {
-            let pos = match ftex.header.tile_mode {
-                TileMode::Default | TileMode::LinearAligned => {
-                    compute_surface_address_linear(x, y, bits_pp, i64::from(ftex.header.pitch))
-                }
-                TileMode::OneDTiledThin1 | TileMode::OneDTiledThick => {
-                    compute_surface_address_micro_tiled(
-                        x,
-                        y,
-                        bits_pp,
-                        i64::from(ftex.header.pitch),
-                        &ftex.header.tile_mode,
-                    )
-                }
-                _ => compute_surface_address_macro_tiled(
-                    x,
-                    y,
-                    bits_pp,
-                    i64::from(ftex.header.pitch),
-                    1,
-                    &ftex.header.tile_mode,
-                    pipe_swizzle,
-                    bank_swizzle,
-                ),
-            };
+            let pos = compute_surface_address(x, y, slice, sample, bits_pp, surface, swizzle_value);
             let pos2 = (y * dims.0 + x) * bytes_pp;
-            if (pos < (data.len() as i64)) & (pos2 < (data.len() as i64)) {
+            if pos + bytes_pp <= (data.len() as i64) {
                 // result[pos2:pos2 + bytes_pp] = data[pos:pos + bytes_pp]
                 out[pos2 as usize..(bytes_pp + pos2) as usize]
                     .clone_from_slice(&data[pos as usize..(bytes_pp + pos) as usize]);
@@ -68,86 +188,160 @@ pub fn deswizzle(ftex: &FTEX, data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
         }
     }
 
-    Ok(out)
+    out
 }
 
-fn get_format_bits_per_pixel(format: &Format) -> i64 {
-    match *format as u32 {
-        0x1A => 32,
-        0x31 | 0x431 | 0x34 | 0x234 => 64,
-        0x32 | 0x432 | 0x33 | 0x433 | 0x35 | 0x235 => 128,
-        _ => unimplemented!(),
+fn swizzle_surface(
+    format: &Format,
+    (width, height): (u32, u32),
+    (slice, sample): (i64, i64),
+    surface: &SurfaceLevel,
+    swizzle_value: u32,
+    data: &[u8],
+    out: &mut [u8],
+) {
+    let dims = get_element_dimensions(format, width, height);
+    let bits_pp = get_format_bits_per_pixel(format);
+    let bytes_pp = bits_pp / 8;
+
+    for y in 0..dims.1 {
+        for x in 0..dims.0 {
+            let pos = compute_surface_address(x, y, slice, sample, bits_pp, surface, swizzle_value);
+            let pos2 = (y * dims.0 + x) * bytes_pp;
+            if pos + bytes_pp <= (out.len() as i64) && pos2 + bytes_pp <= (data.len() as i64) {
+                out[pos as usize..(bytes_pp + pos) as usize]
+                    .clone_from_slice(&data[pos2 as usize..(bytes_pp + pos2) as usize]);
+            }
+        }
     }
 }
 
-fn compute_surface_address_linear(x: i64, y: i64, bpp: i64, pitch: i64) -> i64 {
-    (((y * pitch) + x) * bpp) / 8
+fn get_format_bits_per_pixel(format: &Format) -> i64 {
+    i64::from(format.get_bits_per_pixel())
 }
 
-fn compute_surface_address_micro_tiled(
+fn compute_surface_address_linear(
     x: i64,
     y: i64,
+    slice: i64,
     bpp: i64,
     pitch: i64,
-    tile_mode: &TileMode,
+    height: i64,
 ) -> i64 {
+    (((slice * height * pitch) + (y * pitch) + x) * bpp) / 8
+}
+
+fn compute_surface_address_micro_tiled(
+    x: i64,
+    y: i64,
+    slice: i64,
+    sample: i64,
+    bpp: i64,
+    surface: &SurfaceLevel,
+) -> i64 {
+    let tile_mode = &surface.tile_mode;
+    let num_samples = surface.nb_samples;
     let micro_tile_thickness = i64::from(tile_mode.get_surface_thickness());
-    let micro_tile_bytes = ((64 * micro_tile_thickness * bpp) + 7) / 8;
-    let micro_tiles_per_row = pitch >> 3;
-    let micro_tile_index = (x >> 3, y >> 3);
+    let micro_tile_bits = MICRO_TILE_PIXELS * micro_tile_thickness * bpp * num_samples;
+    let micro_tile_bytes = (micro_tile_bits + 7) / 8;
+    let micro_tiles_per_row = surface.pitch >> 3;
+    let micro_tile_index = (x >> 3, y >> 3, slice / micro_tile_thickness);
     let micro_tile_offset =
         micro_tile_bytes * (micro_tile_index.0 + micro_tile_index.1 * micro_tiles_per_row);
-    let pixel_index = compute_pixel_index_micro_tile(x, y, 0, bpp, tile_mode);
-    let pixel_offset = (bpp * pixel_index) >> 3;
-    pixel_offset + micro_tile_offset
+    let slice_bytes =
+        (surface.pitch * surface.height * micro_tile_thickness * bpp * num_samples + 7) / 8;
+    let slice_offset = micro_tile_index.2 * slice_bytes;
+    let pixel_index = compute_pixel_index_micro_tile(x, y, slice, bpp, tile_mode);
+    // Samples of the whole tile are stored one after the other
+    let sample_offset = sample * (micro_tile_bits / num_samples);
+    let pixel_offset = ((bpp * pixel_index) + sample_offset) >> 3;
+    pixel_offset + micro_tile_offset + slice_offset
+}
+
+fn compute_surface_rotation(tile_mode: &TileMode) -> i64 {
+    match *tile_mode {
+        TileMode::TwoDTiledThin1
+        | TileMode::TwoDTiledThin2
+        | TileMode::TwoDTiledThin4
+        | TileMode::TwoDTiledThick
+        | TileMode::TwoBTiledThin1
+        | TileMode::TwoBTiledThin2
+        | TileMode::TwoBTiledThin4
+        | TileMode::TwoBTiledThick => M_PIPES * ((M_BANKS >> 1) - 1),
+        TileMode::ThreeDTiledThin1
+        | TileMode::ThreeDTiledThick
+        | TileMode::ThreeBTiledThin1
+        | TileMode::ThreeBTiledThick => {
+            if M_PIPES >= 4 {
+                (M_PIPES >> 1) - 1
+            } else {
+                1
+            }
+        }
+        _ => 0,
+    }
 }
 
 fn compute_surface_address_macro_tiled(
     x: i64,
     y: i64,
+    slice: i64,
+    sample: i64,
     bpp: i64,
-    pitch: i64,
-    height: i64,
-    tile_mode: &TileMode,
-    pipe_swizzle: i64,
-    bank_swizzle: i64,
+    surface: &SurfaceLevel,
+    (pipe_swizzle, bank_swizzle): (i64, i64),
 ) -> i64 {
+    let tile_mode = &surface.tile_mode;
+    let pitch = surface.pitch;
+    let height = surface.height;
     let num_pipes = M_PIPES;
     let num_banks = M_BANKS;
     let num_group_bits = M_PIPE_INTERLEAVE_BYTES_BIT_COUNT;
     let num_pipe_bits = M_PIPES_BIT_COUNT;
     let num_bank_bits = M_BANKS_BIT_COUNT;
+    let mut num_samples = surface.nb_samples;
     let micro_tile_thickness = i64::from(tile_mode.get_surface_thickness());
-    let micro_tile_bits = bpp * (micro_tile_thickness * MICRO_TILE_PIXELS);
+    let micro_tile_bits = num_samples * bpp * (micro_tile_thickness * MICRO_TILE_PIXELS);
     let micro_tile_bytes = (micro_tile_bits + 7) / 8;
-    let pixel_index = compute_pixel_index_micro_tile(x, y, 0, bpp, tile_mode);
+    let pixel_index = compute_pixel_index_micro_tile(x, y, slice, bpp, tile_mode);
+    let bytes_per_sample = micro_tile_bytes / num_samples;
+    let sample_offset = sample * (micro_tile_bits / num_samples);
     let pixel_offset = bpp * pixel_index;
-    let mut element_offset = pixel_offset;
-    let bytes_per_sample = micro_tile_bytes;
-    let samples_per_slice = M_SPLIT_SIZE / bytes_per_sample;
-    let num_sample_splits = max(1, 1 / samples_per_slice);
-    let (num_samples, sample_slice) = if micro_tile_bytes <= M_SPLIT_SIZE {
+    let mut element_offset = pixel_offset + sample_offset;
+    // Tiles too big for a split are cut in several slices, each one holding some of the samples
+    let (num_sample_splits, sample_slice) = if num_samples <= 1 || micro_tile_bytes <= M_SPLIT_SIZE
+    {
         (1, 0)
     } else {
-        (
-            samples_per_slice,
-            element_offset / (micro_tile_bits / num_sample_splits),
-        )
+        let samples_per_slice = M_SPLIT_SIZE / bytes_per_sample;
+        let num_sample_splits = num_samples / samples_per_slice;
+        num_samples = samples_per_slice;
+        let tile_slice_bits = micro_tile_bits / num_sample_splits;
+        let sample_slice = element_offset / tile_slice_bits;
+        element_offset %= tile_slice_bits;
+        (num_sample_splits, sample_slice)
     };
-    if !(micro_tile_bytes <= M_SPLIT_SIZE) {
-        element_offset %= micro_tile_bits / num_sample_splits;
-    }
     element_offset = (element_offset + 7) / 8;
     let mut pipe = compute_pipe_from_coord_no_rotation(x, y);
     let mut bank = compute_bank_from_coord_no_rotation(x, y);
     let mut bank_pipe = pipe + (num_pipes * bank);
     let swizzle2 = pipe_swizzle + (num_pipes * bank_swizzle);
-    bank_pipe ^= num_pipes * sample_slice * ((num_banks >> 1) + 1) ^ swizzle2; // Pulled my hair off
+    // Thick tiles hold 4 slices, rotation only changes between them
+    let slice_in = if tile_mode.is_thick() {
+        slice >> 2
+    } else {
+        slice
+    };
+    let rotation = compute_surface_rotation(tile_mode);
+    // Pulled my hair off
+    bank_pipe ^=
+        num_pipes * sample_slice * ((num_banks >> 1) + 1) ^ (swizzle2 + slice_in * rotation);
     bank_pipe %= num_pipes * num_banks;
     pipe = bank_pipe % num_pipes;
     bank = bank_pipe / num_pipes;
     let slice_bytes = (height * pitch * micro_tile_thickness * bpp * num_samples + 7) / 8;
-    let slice_offset = slice_bytes * (sample_slice / micro_tile_thickness);
+    let slice_offset =
+        slice_bytes * ((sample_slice + num_sample_splits * slice) / micro_tile_thickness);
     let (macro_tile_pitch, macro_tile_height) = match tile_mode {
         TileMode::TwoDTiledThin2 | TileMode::TwoBTiledThin2 => {
             ((8 * M_BANKS) >> 1, (8 * M_PIPES) * 2)
@@ -171,7 +365,8 @@ fn compute_surface_address_macro_tiled(
         | TileMode::ThreeBTiledThin1
         | TileMode::ThreeBTiledThick => {
             let bank_swap_order = [0, 1, 3, 2, 6, 7, 5, 4, 0, 0];
-            let bank_swap_width = compute_surface_bank_swapped_width(tile_mode, bpp, pitch, 1);
+            let bank_swap_width =
+                compute_surface_bank_swapped_width(tile_mode, bpp, pitch, surface.nb_samples);
             let swap_index = macro_tile_pitch * macro_tile_index.0 / bank_swap_width;
             bank ^= bank_swap_order[(swap_index & (M_BANKS - 1)) as usize];
         }
@@ -264,8 +459,14 @@ fn compute_pixel_index_micro_tile(x: i64, y: i64, z: i64, bpp: i64, tile_mode: &
     if thickness == 8 {
         pixel_bits.8 = (z & 4) >> 2;
     }
-    ((pixel_bits.8 << 8) | (pixel_bits.7 << 7) | (pixel_bits.6 << 6) | 32 * pixel_bits.5
-        | 16 * pixel_bits.4 | 8 * pixel_bits.3 | 4 * pixel_bits.2 | pixel_bits.0
+    ((pixel_bits.8 << 8)
+        | (pixel_bits.7 << 7)
+        | (pixel_bits.6 << 6)
+        | 32 * pixel_bits.5
+        | 16 * pixel_bits.4
+        | 8 * pixel_bits.3
+        | 4 * pixel_bits.2
+        | pixel_bits.0
         | 2 * pixel_bits.1)
 }
 
@@ -282,48 +483,182 @@ fn compute_bank_from_coord_no_rotation(x: i64, y: i64) -> i64 {
         bank_bit_0 | 2 * (((y / (8 * num_pipes)) ^ (x >> 4)) & 1)
     } else if num_banks == 8 {
         let bank_bit_0 = ((y / (32 * num_pipes)) ^ (x >> 3)) & 1;
-        (bank_bit_0 | 2 * (((y / (32 * num_pipes)) ^ (y / (16 * num_pipes) ^ (x >> 4))) & 1)
+        (bank_bit_0
+            | 2 * (((y / (32 * num_pipes)) ^ (y / (16 * num_pipes) ^ (x >> 4))) & 1)
             | 4 * (((y / (8 * num_pipes)) ^ (x >> 5)) & 1))
     } else {
         0
     }
 }
 
-fn compute_surface_bank_swapped_width(
-    tile_mode: &TileMode,
-    bpp: i64,
-    pitch: i64,
-    num_samples: i64,
-) -> i64 {
-    if !tile_mode.is_bank_swapped() {
-        return 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gx2::surface::compute_surface_info;
+    use std::io::Cursor;
+
+    // A texture header read from GX2 values, laid out like GX2 would do it
+    fn make_header(
+        dimension: u32,
+        format: u32,
+        tile_mode: u32,
+        (width, height, depth): (u32, u32, u32),
+        nb_mipmaps: u32,
+        aa_mode: u32,
+    ) -> Header {
+        let mut values = vec![
+            dimension, width, height, depth, nb_mipmaps, format, aa_mode, 1,
+        ];
+        values.extend_from_slice(&[0, 0, 0, 0, tile_mode, 0x0D00, 0, 0]);
+        values.extend_from_slice(&[0; 13]);
+        values.extend_from_slice(&[0, nb_mipmaps, 0, depth, 0x0001_0203, 0, 0, 0, 0, 0]);
+        let mut data = Vec::new();
+        for value in values {
+            data.extend_from_slice(&[
+                (value >> 24) as u8,
+                (value >> 16) as u8,
+                (value >> 8) as u8,
+                value as u8,
+            ]);
+        }
+        let mut header = Header::import_gx2_texture(&mut Cursor::new(data)).unwrap();
+        let info = compute_surface_info(&Surface::from_header(&header));
+        header.tile_mode = info.tile_mode;
+        header.pitch = info.pitch;
+        header.alignment = info.alignment;
+        header.data_length = info.data_length;
+        header.mipmaps_data_length = info.mipmaps_data_length;
+        header.mipmap_offsets = info.mipmap_offsets;
+        header
     }
-    let mut num_samples2 = num_samples;
-    let num_banks = M_BANKS;
-    let num_pipes = M_PIPES;
-    let swap_size = M_SWAP_SIZE;
-    let row_size = M_ROW_SIZE;
-    let split_size = M_SPLIT_SIZE;
-    let group_size = M_PIPE_INTERLEAVE_BYTES;
-    let bytes_per_sample = 8 * bpp;
-    let slices_per_tile = if bytes_per_sample != 0 {
-        max(1, num_samples2 / (split_size / bytes_per_sample))
-    } else {
-        1
-    };
-    if tile_mode.is_thick() {
-        num_samples2 = 4;
+
+    fn make_data(length: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn check_round_trip(header: &Header) {
+        for level in 0..max(1, header.nb_mipmaps) {
+            let (width, height) = get_mip_level_dimensions(header, level);
+            let nb_slices = get_mip_level_slice_count(header, level) as usize;
+            let length = get_linear_slice_size(&header.texture_format, width, height) * nb_slices;
+            let linear = make_data(length, level + 1);
+            let swizzled = swizzle_mip_level(header, level, &linear);
+            if level == 0 && !header.tile_mode.is_linear() {
+                assert!(swizzled[..length] != linear[..]);
+            }
+            assert_eq!(
+                get_mip_level_surface(header, level).size as usize,
+                swizzled.len()
+            );
+            let deswizzled = deswizzle_mip_level(header, level, &swizzled).unwrap();
+            assert!(
+                deswizzled == linear,
+                "Level {} of tile mode 0x{:X} does not round trip",
+                level,
+                header.tile_mode as u32
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_every_tile_mode() {
+        for &tile_mode in &[0x01, 0x02, 0x04, 0x08, 0x0C, 0x0E] {
+            check_round_trip(&make_header(1, 0x01a, tile_mode, (64, 48, 1), 4, 0));
+        }
+    }
+
+    #[test]
+    fn round_trip_block_compressed() {
+        check_round_trip(&make_header(1, 0x031, 0x04, (128, 128, 1), 6, 0));
+        check_round_trip(&make_header(1, 0x033, 0x02, (20, 12, 1), 2, 0));
+    }
+
+    #[test]
+    fn round_trip_thick_and_arrays() {
+        check_round_trip(&make_header(2, 0x01a, 0x07, (32, 32, 8), 3, 0));
+        check_round_trip(&make_header(2, 0x01a, 0x03, (16, 16, 4), 1, 0));
+        check_round_trip(&make_header(3, 0x01a, 0x04, (32, 32, 6), 2, 0));
+        check_round_trip(&make_header(5, 0x007, 0x04, (64, 64, 3), 2, 0));
     }
-    let bytes_per_tile_slice = num_samples2 * bytes_per_sample / slices_per_tile;
-    let factor = i64::from(tile_mode.get_aspect_ratio());
-    let swap_tiles = max(1, (swap_size >> 1) / bpp);
-    let swap_width = swap_tiles * 8 * num_banks;
-    let height_bytes = num_samples2 * factor * num_pipes * bpp / slices_per_tile;
-    let swap_max = num_pipes * num_banks * row_size / height_bytes;
-    let swap_min = group_size * 8 * num_banks / bytes_per_tile_slice;
-    let mut bank_swap_width = min(swap_max, max(swap_min, swap_width));
-    while bank_swap_width >= (2 * pitch) {
-        bank_swap_width >>= 1;
+
+    // Every pixel of a RGBA8 surface holds its own coordinates
+    fn check_tiled_positions(header: &Header, positions: &[((u8, u8), usize)]) {
+        let (width, height) = (header.texture_width as u8, header.texture_height as u8);
+        let mut linear = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                linear.extend_from_slice(&[x, y, 0xAA, 0x55]);
+            }
+        }
+        let swizzled = swizzle(header, &linear);
+        for &((x, y), position) in positions {
+            assert_eq!(
+                &swizzled[position..position + 4],
+                &[x, y, 0xAA, 0x55],
+                "Pixel {}x{} is not at 0x{:X}",
+                x,
+                y,
+                position
+            );
+        }
+    }
+
+    // Micro tiles are 8x8 pixels, 32 bits pixels go x0 x1 y0 x2 y1 y2 from the lowest bit
+    #[test]
+    fn micro_tiled_positions() {
+        let header = make_header(1, 0x01a, 0x02, (16, 8, 1), 1, 0);
+        assert_eq!(header.pitch, 16);
+        check_tiled_positions(
+            &header,
+            &[
+                ((0, 0), 0x0),
+                ((1, 0), 0x4),
+                ((2, 0), 0x8),
+                ((0, 1), 0x10),
+                ((4, 0), 0x20),
+                ((0, 2), 0x40),
+                ((0, 4), 0x80),
+                ((7, 7), 0xFC),
+                ((8, 0), 0x100),
+            ],
+        );
+    }
+
+    // The 8 micro tiles of a macro tile go to their own pipe and bank, pipe at bit 8 and bank at
+    // bits 9 and 10 of the address
+    #[test]
+    fn macro_tiled_positions() {
+        let mut header = make_header(1, 0x01a, 0x04, (64, 32, 1), 1, 0);
+        assert_eq!(header.pitch, 64);
+        header.swizzle_value = 0;
+        check_tiled_positions(
+            &header,
+            &[
+                ((0, 0), 0x0),
+                ((1, 0), 0x4),
+                ((0, 1), 0x10),
+                ((0, 8), 0x100),
+                ((8, 0), 0x300),
+                ((16, 0), 0x400),
+                ((24, 8), 0x600),
+            ],
+        );
+        // Pipe swizzle 1 and bank swizzle 2 flip the pipe and the high bank bit
+        header.swizzle_value = 0x0D00;
+        check_tiled_positions(
+            &header,
+            &[((0, 0), 0x500), ((8, 0), 0x600), ((24, 8), 0x300)],
+        );
+    }
+
+    #[test]
+    fn round_trip_multisampled() {
+        check_round_trip(&make_header(6, 0x01a, 0x04, (64, 64, 1), 1, 2));
     }
-    bank_swap_width
 }
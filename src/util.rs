@@ -1,7 +1,7 @@
 use error::{IndexGroupTooLong, RelativePointerDataInvalid, UserDataNotEmpty};
 use ez_io::ReadE;
 use std::error::Error;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
 
 pub trait Importable
@@ -11,25 +11,45 @@ where
     fn import<R: Read + Seek>(reader: &mut R) -> Result<Self, Box<Error>>;
 }
 
+// An opened file, with the cursor over its decompressed data that the rest is read from
+pub type Opened<T> = (T, Cursor<Vec<u8>>);
+
+// Index groups whose entries point straight at a name, like shader assignments
+impl Importable for String {
+    fn import<R: Read + Seek>(reader: &mut R) -> Result<String, Box<Error>> {
+        read_text_entry(reader)
+    }
+}
+
+#[cfg_attr(feature = "json", derive(Serialize))]
+#[cfg_attr(feature = "json", serde(bound = ""))]
 pub struct IndexGroup<I: Importable> {
     pub entries: Vec<IndexGroupEntry<I>>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
+#[cfg_attr(feature = "json", serde(bound = ""))]
 pub struct IndexGroupEntry<I: Importable> {
     pub search_value: u32,
     pub left_index: u16,
     pub right_index: u16,
     pub name_pointer: Pointer,
     pub data_pointer: Pointer,
+    #[cfg_attr(feature = "json", serde(skip))]
     data_type: PhantomData<I>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
+#[cfg_attr(feature = "json", serde(bound = ""))]
 pub struct DataArray<I: Importable> {
     pub entries: Vec<DataArrayEntry<I>>,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
+#[cfg_attr(feature = "json", serde(bound = ""))]
 pub struct DataArrayEntry<I: Importable> {
     pub data_pointer: Pointer,
+    #[cfg_attr(feature = "json", serde(skip))]
     data_type: PhantomData<I>,
 }
 
@@ -72,6 +92,48 @@ impl<I: Importable> Importable for IndexGroupEntry<I> {
     }
 }
 
+impl<I: Importable> IndexGroup<I> {
+    // Searches the Patricia tree like the game does. The root is not kept, but it always leads to
+    // the node testing the lowest bit.
+    pub fn find<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        name: &str,
+    ) -> Result<Option<&IndexGroupEntry<I>>, Box<Error>> {
+        let name = name.as_bytes();
+        let mut index = match self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, e)| e.search_value)
+        {
+            Some((i, _)) => i + 1,
+            None => return Ok(None),
+        };
+        let mut last_bit = -1i64;
+        while let Some(entry) = self.entries.get(index.wrapping_sub(1)) {
+            let bit = i64::from(entry.search_value);
+            if bit <= last_bit {
+                break;
+            }
+            last_bit = bit;
+            // Bits count from the end of the name
+            let character = (bit >> 3) as usize;
+            let is_set =
+                character < name.len() && (name[name.len() - 1 - character] >> (bit & 7)) & 1 == 1;
+            index = usize::from(if is_set {
+                entry.right_index
+            } else {
+                entry.left_index
+            });
+        }
+        match self.entries.get(index.wrapping_sub(1)) {
+            Some(entry) if entry.get_name(reader)?.as_bytes() == name => Ok(Some(entry)),
+            _ => Ok(None),
+        }
+    }
+}
+
 impl<I: Importable> IndexGroupEntry<I> {
     pub fn get_name<R: Read + Seek>(&self, reader: &mut R) -> Result<String, Box<Error>> {
         self.name_pointer.seek_abs_pos(reader)?;
@@ -114,11 +176,13 @@ impl<I: Importable> DataArrayEntry<I> {
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Pointer {
     pub location: Option<u64>,
     pub points_to: i32,
 }
 
+#[cfg_attr(feature = "json", derive(Serialize))]
 pub struct BufferInfo {
     pub size: u32,
     pub stride: u16,
@@ -161,6 +225,15 @@ impl Pointer {
     }
 }
 
+impl BufferInfo {
+    pub fn get_data<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, Box<Error>> {
+        self.data_offset.seek_abs_pos(reader)?;
+        let mut data = vec![0u8; self.size as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
 impl Importable for BufferInfo {
     fn import<R: Read + Seek>(reader: &mut R) -> Result<BufferInfo, Box<Error>> {
         let data_pointer = reader.read_be_to_u32()?;
@@ -218,3 +291,16 @@ pub fn read_text_entry<R: Read + Seek>(reader: &mut R) -> Result<String, Box<Err
     }
     Ok(String::from_utf8(bytes)?)
 }
+
+pub fn read_packed_bools<R: Read>(reader: &mut R, count: usize) -> Result<Vec<bool>, Box<Error>> {
+    let mut bools = Vec::with_capacity(count);
+    for _ in 0..count.div_ceil(32) {
+        let word = reader.read_be_to_u32()?;
+        for bit in 0..32 {
+            if bools.len() < count {
+                bools.push(word & (1 << bit) != 0);
+            }
+        }
+    }
+    Ok(bools)
+}
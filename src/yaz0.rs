@@ -0,0 +1,304 @@
+use error::{check_magic_number, Yaz0DataInvalid};
+use std::cmp::min;
+use std::error::Error;
+use std::io::Read;
+
+// Back-references cannot reach further than this
+pub const MAX_WINDOW: usize = 0x1000;
+pub const MAX_LEVEL: u32 = 9;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0xFF + 0x12;
+const HASH_BITS: usize = 12;
+
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() >= 4 && data[..4] == [b'Y', b'a', b'z', b'0']
+}
+
+// Reads everything, decompressing it if needed
+pub fn read_all<R: Read>(reader: &mut R) -> Result<Vec<u8>, Box<Error>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if is_compressed(&data) {
+        decompress(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+    let byte = |position: usize| match data.get(position) {
+        Some(&b) => Ok(b),
+        None => Err(Box::new(Yaz0DataInvalid { position })),
+    };
+    let mut magic_number = [0u8; 4];
+    for (i, b) in magic_number.iter_mut().enumerate() {
+        *b = byte(i)?;
+    }
+    check_magic_number(magic_number, [b'Y', b'a', b'z', b'0'])?;
+    let mut size = 0usize;
+    for i in 4..8 {
+        size = (size << 8) | byte(i)? as usize;
+    }
+    let mut out = Vec::with_capacity(size);
+    // The rest of the header is padding, or the alignment on some platforms
+    let mut position = 0x10;
+    while out.len() < size {
+        let code = byte(position)?;
+        position += 1;
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+            if (code >> bit) & 1 == 1 {
+                out.push(byte(position)?);
+                position += 1;
+                continue;
+            }
+            let (first, second) = (byte(position)?, byte(position + 1)?);
+            let distance = ((usize::from(first & 0xF) << 8) | usize::from(second)) + 1;
+            // A length of zero means the real one is in a third byte
+            let (length, chunk_size) = if first >> 4 == 0 {
+                (usize::from(byte(position + 2)?) + 0x12, 3)
+            } else {
+                (usize::from(first >> 4) + 2, 2)
+            };
+            if distance > out.len() {
+                return Err(Box::new(Yaz0DataInvalid { position }));
+            }
+            position += chunk_size;
+            // Copies can overlap what they write
+            let start = out.len() - distance;
+            for i in start..start + length {
+                let value = out[i];
+                out.push(value);
+            }
+        }
+    }
+    out.truncate(size);
+    Ok(out)
+}
+
+// window is how far back matches are searched for, up to MAX_WINDOW. Level 0 only stores bytes as
+// they are, each level up to MAX_LEVEL searches twice as many matches and from 5, also checks if
+// waiting one byte gives a longer match.
+pub fn compress(data: &[u8], window: usize, level: u32) -> Vec<u8> {
+    let window = window.clamp(1, MAX_WINDOW);
+    let level = min(level, MAX_LEVEL);
+    let mut out = Vec::with_capacity(data.len() / 2 + 0x10);
+    out.extend_from_slice(b"Yaz0");
+    let size = data.len() as u32;
+    out.extend_from_slice(&[
+        (size >> 24) as u8,
+        (size >> 16) as u8,
+        (size >> 8) as u8,
+        size as u8,
+    ]);
+    out.extend_from_slice(&[0u8; 8]);
+
+    let mut matcher = Matcher::new(data, window, if level == 0 { 0 } else { 1 << level });
+    let mut group = Vec::with_capacity(24);
+    let mut code = 0u8;
+    let mut nb_chunks = 0;
+    let mut position = 0;
+    // Match found while checking if waiting was worth it
+    let mut next_match = None;
+    while position < data.len() {
+        let (length, distance) = match next_match.take() {
+            Some(m) => m,
+            None => matcher.find(position),
+        };
+        let mut literal = length < MIN_MATCH;
+        if !literal && level >= 5 && length < MAX_MATCH {
+            let next = matcher.find(position + 1);
+            if next.0 > length + 1 {
+                literal = true;
+                next_match = Some(next);
+            }
+        }
+        if literal {
+            code |= 0x80 >> nb_chunks;
+            group.push(data[position]);
+            matcher.skip_to(position + 1);
+            position += 1;
+        } else {
+            let distance = distance - 1;
+            if length >= 0x12 {
+                group.push((distance >> 8) as u8);
+                group.push(distance as u8);
+                group.push((length - 0x12) as u8);
+            } else {
+                group.push((((length - 2) << 4) | (distance >> 8)) as u8);
+                group.push(distance as u8);
+            }
+            matcher.skip_to(position + length);
+            position += length;
+        }
+        nb_chunks += 1;
+        if nb_chunks == 8 {
+            out.push(code);
+            out.extend_from_slice(&group);
+            group.clear();
+            code = 0;
+            nb_chunks = 0;
+        }
+    }
+    if nb_chunks > 0 {
+        out.push(code);
+        out.extend_from_slice(&group);
+    }
+    out
+}
+
+// Hash chains of every position with the same first three bytes, newest first
+struct Matcher<'a> {
+    data: &'a [u8],
+    window: usize,
+    max_chain: usize,
+    heads: Vec<usize>,
+    previous: Vec<usize>,
+    inserted: usize,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8], window: usize, max_chain: usize) -> Matcher<'a> {
+        Matcher {
+            data,
+            window,
+            max_chain,
+            heads: vec![usize::MAX; 1 << HASH_BITS],
+            previous: vec![usize::MAX; data.len()],
+            inserted: 0,
+        }
+    }
+    fn hash(&self, position: usize) -> usize {
+        let value = (usize::from(self.data[position]) << 16)
+            | (usize::from(self.data[position + 1]) << 8)
+            | usize::from(self.data[position + 2]);
+        (value.wrapping_mul(2_654_435_761) >> 8) & ((1 << HASH_BITS) - 1)
+    }
+    // Everything before position becomes searchable
+    fn skip_to(&mut self, position: usize) {
+        while self.inserted < position {
+            if self.inserted + MIN_MATCH <= self.data.len() {
+                let hash = self.hash(self.inserted);
+                self.previous[self.inserted] = self.heads[hash];
+                self.heads[hash] = self.inserted;
+            }
+            self.inserted += 1;
+        }
+    }
+    // Longest match as a length and a distance, the closest one when several are as long
+    fn find(&mut self, position: usize) -> (usize, usize) {
+        if self.max_chain == 0 || position + MIN_MATCH > self.data.len() {
+            return (0, 0);
+        }
+        self.skip_to(position);
+        let max_length = min(MAX_MATCH, self.data.len() - position);
+        let mut best = (0, 0);
+        let mut candidate = self.heads[self.hash(position)];
+        let mut steps = 0;
+        while candidate != usize::MAX && position - candidate <= self.window {
+            let mut length = 0;
+            while length < max_length
+                && self.data[candidate + length] == self.data[position + length]
+            {
+                length += 1;
+            }
+            if length > best.0 {
+                best = (length, position - candidate);
+                if length == max_length {
+                    break;
+                }
+            }
+            steps += 1;
+            if steps >= self.max_chain {
+                break;
+            }
+            candidate = self.previous[candidate];
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_inputs() -> Vec<Vec<u8>> {
+        let mut random = Vec::new();
+        let mut state = 0x1234_5678u32;
+        for _ in 0..0x3000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            random.push((state >> 16) as u8);
+        }
+        // Repeats from just inside and just outside the window
+        let mut far = random[..0x1200].to_vec();
+        far.extend_from_slice(&random[..0x100]);
+        let text = b"The quick brown fox jumps over the lazy dog. ".repeat(40);
+        vec![
+            Vec::new(),
+            vec![0x42],
+            b"abcabcabcabc".to_vec(),
+            vec![0; 0x1000],
+            random,
+            far,
+            text,
+        ]
+    }
+
+    #[test]
+    fn compress_decompress_round_trip() {
+        for data in &make_inputs() {
+            for level in 0..MAX_LEVEL + 1 {
+                for &window in &[1, 0x10, 0x800, MAX_WINDOW] {
+                    let compressed = compress(data, window, level);
+                    assert!(is_compressed(&compressed));
+                    assert_eq!(&decompress(&compressed).unwrap(), data);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn repetitive_data_gets_smaller() {
+        let data = vec![0; 0x1000];
+        assert!(compress(&data, MAX_WINDOW, 1).len() < 0x100);
+        let text = b"The quick brown fox jumps over the lazy dog. ".repeat(40);
+        let fast = compress(&text, MAX_WINDOW, 1).len();
+        let best = compress(&text, MAX_WINDOW, MAX_LEVEL).len();
+        assert!(best <= fast && fast < text.len() / 4);
+    }
+
+    #[test]
+    fn decompress_known_data() {
+        // "abc" as literals, then a copy of 6 bytes from 3 back, then 20 bytes from 6 back in the
+        // long form
+        let data = [
+            b'Y', b'a', b'z', b'0', 0, 0, 0, 0x1D, 0, 0, 0, 0, 0, 0, 0, 0, 0xE0, b'a', b'b', b'c',
+            0x40, 0x02, 0x00, 0x05, 0x02,
+        ];
+        assert_eq!(decompress(&data).unwrap(), &b"abc".repeat(10)[..0x1D]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        let compressed = compress(&b"abcabcabcabc".repeat(10), MAX_WINDOW, 5);
+        for length in 0..compressed.len() {
+            assert!(decompress(&compressed[..length]).is_err());
+        }
+        // A copy from before the start
+        let data = [
+            b'Y', b'a', b'z', b'0', 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, b'a', 0x10, 0x01,
+        ];
+        assert!(decompress(&data).is_err());
+        assert!(decompress(b"Yaz1\0\0\0\0\0\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn read_uncompressed_data() {
+        let data = b"SARC and some more".to_vec();
+        assert_eq!(read_all(&mut &data[..]).unwrap(), data);
+        let compressed = compress(&data, MAX_WINDOW, 3);
+        assert_eq!(read_all(&mut &compressed[..]).unwrap(), data);
+    }
+}